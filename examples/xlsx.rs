@@ -23,7 +23,7 @@ fn main() {
             // get cell values
             let cols: Vec<_> = rows
                 .into_iter()
-                .map(|cell| cell.value().unwrap_or_default())
+                .map(|cell| cell.to_string().unwrap_or_default())
                 .collect();
             println!("{}", itertools::join(&cols, ","));
         }
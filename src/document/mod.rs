@@ -5,4 +5,106 @@ mod presentation;
 mod spreadsheet;
 mod wordprocessing;
 
+pub use presentation::*;
 pub use spreadsheet::*;
+pub use wordprocessing::*;
+
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+
+use crate::error::Result;
+use crate::packaging::package::OpenXmlPackage;
+
+/// Any OpenXML document, dispatched by the content type of the main document part.
+///
+/// The main part is the target of the root `officeDocument` relationship, packages without
+/// it or with an unknown main part content type are kept as a generic [`OpenXmlPackage`].
+///
+/// ```rust
+/// use ooxml::document::Document;
+///
+/// let document = ooxml::open("examples/excel-demo/demo.xlsx").unwrap();
+/// assert!(matches!(document, Document::Spreadsheet(_)));
+/// ```
+#[derive(Debug)]
+pub enum Document {
+    Spreadsheet(SpreadsheetDocument),
+    Wordprocessing(WordprocessingDocument),
+    Presentation(PresentationDocument),
+    Package(OpenXmlPackage),
+}
+
+impl Document {
+    /// Open an OpenXML file path and detect its document kind.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let package = OpenXmlPackage::open(path)?;
+        Self::from_package(package)
+    }
+
+    /// Parse an OpenXML document from reader.
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self> {
+        let package = OpenXmlPackage::from_reader(reader)?;
+        Self::from_package(package)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::from_reader(Cursor::new(bytes))
+    }
+
+    /// Detect document kind of an opened package.
+    pub fn from_package(package: OpenXmlPackage) -> Result<Self> {
+        let content_type = match package
+            .main_part_uri()
+            .and_then(|uri| package.get_part_content_type(&uri).cloned())
+        {
+            Some(content_type) => content_type,
+            None => return Ok(Document::Package(package)),
+        };
+        if SpreadsheetDocumentType::try_from_content_type(content_type.as_str()).is_some() {
+            Ok(Document::Spreadsheet(SpreadsheetDocument::from_package(
+                package,
+            )?))
+        } else if let Some(document_type) =
+            WordprocessingDocumentType::try_from_content_type(content_type.as_str())
+        {
+            Ok(Document::Wordprocessing(
+                WordprocessingDocument::from_package(package, document_type)?,
+            ))
+        } else if let Some(document_type) =
+            PresentationDocumentType::try_from_content_type(content_type.as_str())
+        {
+            Ok(Document::Presentation(PresentationDocument::from_package(
+                package,
+                document_type,
+            )?))
+        } else {
+            Ok(Document::Package(package))
+        }
+    }
+}
+
+#[test]
+fn open_any() {
+    let document = Document::open("examples/excel-demo/demo.xlsx").unwrap();
+    match document {
+        Document::Spreadsheet(xlsx) => {
+            assert!(matches!(
+                xlsx.document_type(),
+                SpreadsheetDocumentType::Workbook
+            ));
+            assert_eq!(
+                xlsx.get_workbook().worksheet_names(),
+                vec!["Sheet1", "Sheet2"]
+            );
+        }
+        _ => panic!("expect spreadsheet document"),
+    }
+    let document = Document::open("examples/docx-demo/rust-docx-rs.docx").unwrap();
+    match document {
+        Document::Wordprocessing(docx) => assert!(matches!(
+            docx.document_type(),
+            WordprocessingDocumentType::Document
+        )),
+        _ => panic!("expect word processing document"),
+    }
+}
@@ -0,0 +1,66 @@
+#[derive(Debug, Clone, Copy, Default)]
+pub enum PresentationDocumentType {
+    /// PowerPoint Presentation (*.pptx).
+    #[default]
+    Presentation,
+    /// PowerPoint Template (*.potx).
+    Template,
+    /// PowerPoint Show (*.ppsx).
+    Slideshow,
+    /// PowerPoint Macro-Enabled Presentation (*.pptm).
+    MacroEnabledPresentation,
+    /// PowerPoint Macro-Enabled Template (*.potm).
+    MacroEnabledTemplate,
+    /// PowerPoint Macro-Enabled Show (*.ppsm).
+    MacroEnabledSlideshow,
+    /// PowerPoint Add-In (*.ppam).
+    AddIn,
+}
+
+const PRESENTATION_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation.main+xml";
+const TEMPLATE_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.presentationml.template.main+xml";
+const SLIDESHOW_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.presentationml.slideshow.main+xml";
+const MACRO_ENABLED_PRESENTATION_CONTENT_TYPE: &str =
+    "application/vnd.ms-powerpoint.presentation.macroEnabled.main+xml";
+const MACRO_ENABLED_TEMPLATE_CONTENT_TYPE: &str =
+    "application/vnd.ms-powerpoint.template.macroEnabled.main+xml";
+const MACRO_ENABLED_SLIDESHOW_CONTENT_TYPE: &str =
+    "application/vnd.ms-powerpoint.slideshow.macroEnabled.main+xml";
+const ADDIN_CONTENT_TYPE: &str = "application/vnd.ms-powerpoint.addin.macroEnabled.main+xml";
+impl PresentationDocumentType {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            PresentationDocumentType::Presentation => PRESENTATION_CONTENT_TYPE,
+            PresentationDocumentType::Template => TEMPLATE_CONTENT_TYPE,
+            PresentationDocumentType::Slideshow => SLIDESHOW_CONTENT_TYPE,
+            PresentationDocumentType::MacroEnabledPresentation => {
+                MACRO_ENABLED_PRESENTATION_CONTENT_TYPE
+            }
+            PresentationDocumentType::MacroEnabledTemplate => MACRO_ENABLED_TEMPLATE_CONTENT_TYPE,
+            PresentationDocumentType::MacroEnabledSlideshow => MACRO_ENABLED_SLIDESHOW_CONTENT_TYPE,
+            PresentationDocumentType::AddIn => ADDIN_CONTENT_TYPE,
+        }
+    }
+    /// Document type of the main part content type, `None` if it's not a presentation.
+    pub fn try_from_content_type(content_type: &str) -> Option<Self> {
+        match content_type {
+            PRESENTATION_CONTENT_TYPE => Some(PresentationDocumentType::Presentation),
+            TEMPLATE_CONTENT_TYPE => Some(PresentationDocumentType::Template),
+            SLIDESHOW_CONTENT_TYPE => Some(PresentationDocumentType::Slideshow),
+            MACRO_ENABLED_PRESENTATION_CONTENT_TYPE => {
+                Some(PresentationDocumentType::MacroEnabledPresentation)
+            }
+            MACRO_ENABLED_TEMPLATE_CONTENT_TYPE => {
+                Some(PresentationDocumentType::MacroEnabledTemplate)
+            }
+            MACRO_ENABLED_SLIDESHOW_CONTENT_TYPE => {
+                Some(PresentationDocumentType::MacroEnabledSlideshow)
+            }
+            ADDIN_CONTENT_TYPE => Some(PresentationDocumentType::AddIn),
+            _ => None,
+        }
+    }
+}
@@ -1 +1,71 @@
+//! PowerPoint file format .pptx document implementation.
 
+use std::{
+    io::{Cursor, Read, Seek},
+    path::Path,
+};
+
+use crate::{error::Result, packaging::package::OpenXmlPackage};
+
+mod document_type;
+
+pub use self::document_type::PresentationDocumentType;
+
+/// Presentation document, only the package level is supported for now.
+#[derive(Debug, Clone, Default)]
+pub struct PresentationDocument {
+    /// The OpenXML package itself.
+    package: OpenXmlPackage,
+    /// The document type, eg. .pptx, .pptm, etc.
+    document_type: PresentationDocumentType,
+}
+
+impl PresentationDocument {
+    pub(crate) fn from_package(
+        package: OpenXmlPackage,
+        document_type: PresentationDocumentType,
+    ) -> Result<Self> {
+        Ok(Self {
+            package,
+            document_type,
+        })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::from_reader(Cursor::new(bytes))
+    }
+
+    /// Open existing presentation reader and parse.
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self> {
+        let package = OpenXmlPackage::from_reader(reader)?;
+        let document_type = package
+            .main_part_uri()
+            .and_then(|uri| package.get_part_content_type(&uri).cloned())
+            .and_then(|content_type| {
+                PresentationDocumentType::try_from_content_type(content_type.as_str())
+            })
+            .unwrap_or_default();
+        Self::from_package(package, document_type)
+    }
+
+    /// Open existing presentation file and parse.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Self::from_reader(file)
+    }
+
+    /// Save as new file with `path`.
+    pub fn save_as<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.package.save_as(path)
+    }
+
+    /// The document type by main part content type.
+    pub fn document_type(&self) -> PresentationDocumentType {
+        self.document_type
+    }
+
+    /// The underlying OpenXML package.
+    pub fn package(&self) -> &OpenXmlPackage {
+        &self.package
+    }
+}
@@ -0,0 +1,1054 @@
+//! Worksheet functions.
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+use super::parser::Expr;
+use super::{broadcast, compare, number, Context, Value};
+use crate::document::spreadsheet::cell::CellError;
+use crate::document::spreadsheet::number_format::NumberFormatCode;
+use crate::document::spreadsheet::reference::{CellRange, CellRef};
+
+type Result<T> = std::result::Result<T, CellError>;
+
+/// An evaluated argument, values of references are aggregated like ranges.
+struct Arg {
+    value: Value,
+    range: bool,
+}
+
+fn arity<T>(args: &[T], min: usize, max: usize) -> Result<()> {
+    if (min..=max).contains(&args.len()) {
+        Ok(())
+    } else {
+        Err(CellError::Value)
+    }
+}
+
+/// Optional argument, omitted and empty arguments are `None`.
+fn optional(args: &[Value], index: usize) -> Option<&Value> {
+    args.get(index).filter(|v| **v != Value::Empty)
+}
+
+fn optional_number(args: &[Value], index: usize, default: f64) -> Result<f64> {
+    optional(args, index).map_or(Ok(default), Value::to_number)
+}
+
+/// Apply a scalar function, elementwise for array arguments.
+fn lift(args: &[Arg], f: impl Fn(&[Value]) -> Result<Value>) -> Value {
+    let values: Vec<Value> = args.iter().map(|arg| arg.value.clone()).collect();
+    broadcast(&values, |v| f(v).unwrap_or_else(Value::Error))
+}
+
+/// All values of an argument, arrays by rows.
+fn flatten(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(rows) => rows.iter().flatten().collect(),
+        value => vec![value],
+    }
+}
+
+/// Rows of an argument, scalars are single cell arrays.
+fn grid(value: &Value) -> Vec<Vec<Value>> {
+    match value {
+        Value::Array(rows) => rows.clone(),
+        value => vec![vec![value.clone()]],
+    }
+}
+
+/// Numbers of aggregate arguments, texts and booleans in ranges are skipped.
+fn numbers(args: &[Arg]) -> Result<Vec<f64>> {
+    let mut numbers = Vec::new();
+    for arg in args {
+        match &arg.value {
+            Value::Empty => {}
+            value if arg.range || matches!(value, Value::Array(_)) => {
+                for value in flatten(value) {
+                    match value {
+                        Value::Number(v) => numbers.push(*v),
+                        Value::Error(e) => return Err(*e),
+                        _ => {}
+                    }
+                }
+            }
+            value => numbers.push(value.to_number()?),
+        }
+    }
+    Ok(numbers)
+}
+
+/// Booleans of logical arguments, texts in ranges are skipped.
+fn booleans(args: &[Arg]) -> Result<Vec<bool>> {
+    let mut booleans = Vec::new();
+    for arg in args {
+        match &arg.value {
+            Value::Empty => {}
+            value if arg.range || matches!(value, Value::Array(_)) => {
+                for value in flatten(value) {
+                    match value {
+                        Value::Number(_) | Value::Bool(_) => booleans.push(value.to_bool()?),
+                        Value::Error(e) => return Err(*e),
+                        _ => {}
+                    }
+                }
+            }
+            value => booleans.push(value.to_bool()?),
+        }
+    }
+    if booleans.is_empty() {
+        return Err(CellError::Value);
+    }
+    Ok(booleans)
+}
+
+/// Round half away from zero at 15 significant digits first, as Excel does, so that
+/// `ROUND(2.675,2)` is `2.68`.
+fn round_with(v: f64, digits: f64, round: impl Fn(f64) -> f64) -> Result<Value> {
+    let factor = 10f64.powi(digits.trunc() as i32);
+    let scaled: f64 = format!("{:.14e}", v * factor)
+        .parse()
+        .map_err(|_| CellError::Num)?;
+    Ok(number(round(scaled) / factor))
+}
+
+/// Match text against a pattern with `?` and `*` wildcards and `~` escapes, case insensitive.
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    #[derive(PartialEq)]
+    enum Token {
+        Char(char),
+        One,
+        Any,
+    }
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars().flat_map(char::to_lowercase);
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '~' => match chars.next() {
+                Some(c) => Token::Char(c),
+                None => Token::Char('~'),
+            },
+            '?' => Token::One,
+            '*' => Token::Any,
+            c => Token::Char(c),
+        });
+    }
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    // matched[j] is whether the tokens so far match the first j chars.
+    let mut matched = vec![false; text.len() + 1];
+    matched[0] = true;
+    for token in &tokens {
+        let mut next = vec![false; text.len() + 1];
+        for j in 0..=text.len() {
+            next[j] = match token {
+                Token::Any => matched[j] || (j > 0 && next[j - 1]),
+                Token::One => j > 0 && matched[j - 1],
+                Token::Char(c) => j > 0 && matched[j - 1] && text[j - 1] == *c,
+            };
+        }
+        matched = next;
+    }
+    matched[text.len()]
+}
+
+fn has_wildcard(s: &str) -> bool {
+    s.contains(['*', '?', '~'])
+}
+
+/// Criteria of `COUNTIF` like functions: `">=5"`, `"<>done"`, `"app*"` or a value.
+fn criteria(criteria: &Value) -> impl Fn(&Value) -> bool {
+    let (op, operand) = match criteria {
+        Value::Text(s) => {
+            let op = ["<=", ">=", "<>", "<", ">", "="]
+                .iter()
+                .find(|op| s.starts_with(**op))
+                .copied()
+                .unwrap_or("");
+            let operand = &s[op.len()..];
+            let operand = match operand.trim().parse::<f64>() {
+                Ok(n) => Value::Number(n),
+                Err(_) if operand.eq_ignore_ascii_case("TRUE") => Value::Bool(true),
+                Err(_) if operand.eq_ignore_ascii_case("FALSE") => Value::Bool(false),
+                Err(_) => Value::Text(operand.to_string()),
+            };
+            (op, operand)
+        }
+        value => ("", value.clone()),
+    };
+    move |value: &Value| {
+        let same_kind = std::mem::discriminant(value) == std::mem::discriminant(&operand);
+        match (op, &operand) {
+            ("" | "=", Value::Text(t)) if t.is_empty() => {
+                matches!(value, Value::Empty) || *value == Value::Text(String::new())
+            }
+            ("<>", Value::Text(t)) if t.is_empty() => {
+                !matches!(value, Value::Empty) && *value != Value::Text(String::new())
+            }
+            ("" | "=", Value::Text(t)) => match value {
+                Value::Text(s) => wildcard_match(t, s),
+                _ => false,
+            },
+            ("<>", Value::Text(t)) => match value {
+                Value::Text(s) => !wildcard_match(t, s),
+                _ => true,
+            },
+            ("" | "=", operand) => same_kind && compare(value, operand) == Ordering::Equal,
+            ("<>", operand) => !same_kind || compare(value, operand) != Ordering::Equal,
+            (op, operand) => {
+                same_kind && {
+                    let ordering = compare(value, operand);
+                    match op {
+                        "<" => ordering == Ordering::Less,
+                        "<=" => ordering != Ordering::Greater,
+                        ">" => ordering == Ordering::Greater,
+                        _ => ordering != Ordering::Less,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Cells of ranges matching all criteria, by row and column of the first range.
+fn matching(ranges: &[(&Value, &Value)]) -> Vec<(usize, usize)> {
+    let (first, _) = ranges[0];
+    let first = grid(first);
+    let tests: Vec<(Vec<Vec<Value>>, _)> = ranges
+        .iter()
+        .map(|(range, criterion)| (grid(range), criteria(criterion)))
+        .collect();
+    let mut cells = Vec::new();
+    for (row, values) in first.iter().enumerate() {
+        for col in 0..values.len() {
+            let matched = tests.iter().all(|(range, test)| {
+                let value = range.get(row).and_then(|r| r.get(col));
+                value.is_some_and(test)
+            });
+            if matched {
+                cells.push((row, col));
+            }
+        }
+    }
+    cells
+}
+
+/// Numbers at cells of a range, cells out of the range and not numbers are skipped.
+fn numbers_at(range: &Value, cells: &[(usize, usize)]) -> Vec<f64> {
+    let range = grid(range);
+    cells
+        .iter()
+        .filter_map(|(row, col)| match range.get(*row)?.get(*col)? {
+            Value::Number(v) => Some(*v),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Position of a value in a list, `mode` is `0` for exact match, `1` for the largest value
+/// not greater than it in ascending lists and `-1` for the smallest value not less than it
+/// in descending lists.
+fn lookup(target: &Value, items: &[&Value], mode: i32) -> Option<usize> {
+    let same_kind = |value: &Value| std::mem::discriminant(value) == std::mem::discriminant(target);
+    match (mode, target) {
+        (0, Value::Text(pattern)) if has_wildcard(pattern) => items
+            .iter()
+            .position(|item| matches!(item, Value::Text(s) if wildcard_match(pattern, s))),
+        (0, target) => items
+            .iter()
+            .position(|item| same_kind(item) && compare(item, target) == Ordering::Equal),
+        (mode, target) => {
+            let mut found = None;
+            for (i, item) in items.iter().enumerate() {
+                if !same_kind(item) {
+                    continue;
+                }
+                let ordering = compare(item, target);
+                let accept = if mode > 0 {
+                    ordering != Ordering::Greater
+                } else {
+                    ordering != Ordering::Less
+                };
+                if !accept {
+                    break;
+                }
+                found = Some(i);
+            }
+            found
+        }
+    }
+}
+
+/// Values of lookup results, empty cells are `0`.
+fn lookup_result(value: Option<&Value>) -> Result<Value> {
+    match value {
+        Some(Value::Empty) => Ok(Value::Number(0.)),
+        Some(value) => Ok(value.clone()),
+        None => Err(CellError::Ref),
+    }
+}
+
+fn v_or_h_lookup(args: &[Value], vertical: bool) -> Result<Value> {
+    arity(args, 3, 4)?;
+    if let Value::Error(e) = args[0] {
+        return Err(e);
+    }
+    let mut table = grid(&args[1]);
+    if !vertical {
+        table = transpose(&table);
+    }
+    let index = args[2].to_number()?.trunc();
+    if index < 1. {
+        return Err(CellError::Value);
+    }
+    let approximate = optional(args, 3).map_or(Ok(true), Value::to_bool)?;
+    let keys: Vec<&Value> = table.iter().filter_map(|row| row.first()).collect();
+    let row = lookup(&args[0], &keys, approximate as i32).ok_or(CellError::NA)?;
+    lookup_result(table[row].get(index as usize - 1))
+}
+
+fn transpose(rows: &[Vec<Value>]) -> Vec<Vec<Value>> {
+    let cols = rows.first().map_or(0, Vec::len);
+    (0..cols)
+        .map(|col| {
+            rows.iter()
+                .map(|row| row.get(col).cloned().unwrap_or_default())
+                .collect()
+        })
+        .collect()
+}
+
+/// Row and column of `INDEX` arguments, `0` for the whole column or row.
+fn index_position(height: usize, width: usize, args: &[Value]) -> Result<(usize, usize)> {
+    let position = |v: Option<&Value>| -> Result<usize> {
+        let n = v.map_or(Ok(0.), Value::to_number)?.trunc();
+        if n < 0. {
+            return Err(CellError::Value);
+        }
+        Ok(n as usize)
+    };
+    let (row, col) = match (height, args.get(1)) {
+        // `INDEX(A1:C1,2)` indexes the only row.
+        (1, None) => (1, position(args.first())?),
+        _ => (position(args.first())?, position(args.get(1))?),
+    };
+    if row > height || col > width {
+        return Err(CellError::Ref);
+    }
+    Ok((row, col))
+}
+
+/// Reference of `INDEX` over a reference, for ranges like `A1:INDEX(A:A,3)`.
+pub(crate) fn index_reference(
+    ctx: &Context<'_>,
+    range: CellRange,
+    args: &[Expr],
+) -> Result<CellRange> {
+    let args: Vec<Value> = args.iter().map(|e| ctx.eval(e)).collect();
+    let (row, col) = index_position(range.height() as usize, range.width() as usize, &args)?;
+    let (first_row, last_row) = match row {
+        0 => (range.start.row, range.end.row),
+        row => (
+            range.start.row + row as u32 - 1,
+            range.start.row + row as u32 - 1,
+        ),
+    };
+    let (first_col, last_col) = match col {
+        0 if range.width() == 1 => (range.start.col, range.start.col),
+        0 => (range.start.col, range.end.col),
+        col => (
+            range.start.col + col as u32 - 1,
+            range.start.col + col as u32 - 1,
+        ),
+    };
+    Ok(CellRange::new(
+        CellRef::new(first_row, first_col),
+        CellRef::new(last_row, last_col),
+    ))
+}
+
+fn index(args: &[Value]) -> Result<Value> {
+    arity(args, 2, 3)?;
+    let rows = grid(&args[0]);
+    let width = rows.first().map_or(0, Vec::len);
+    let (row, col) = index_position(rows.len(), width, &args[1..])?;
+    let col = if col == 0 && width == 1 { 1 } else { col };
+    match (row, col) {
+        (0, 0) => Ok(args[0].clone()),
+        (0, col) => Ok(Value::Array(
+            rows.iter().map(|r| vec![r[col - 1].clone()]).collect(),
+        )),
+        (row, 0) => Ok(Value::Array(vec![rows[row - 1].clone()])),
+        (row, col) => lookup_result(rows[row - 1].get(col - 1)),
+    }
+}
+
+fn matches(args: &[Value]) -> Result<Value> {
+    arity(args, 2, 3)?;
+    if let Value::Error(e) = args[0] {
+        return Err(e);
+    }
+    let rows = grid(&args[1]);
+    if rows.len() > 1 && rows.first().map_or(0, Vec::len) > 1 {
+        return Err(CellError::NA);
+    }
+    let items: Vec<&Value> = rows.iter().flatten().collect();
+    let mode = match optional_number(args, 2, 1.)? {
+        mode if mode > 0. => 1,
+        mode if mode < 0. => -1,
+        _ => 0,
+    };
+    let position = lookup(&args[0], &items, mode).ok_or(CellError::NA)?;
+    Ok(Value::Number(position as f64 + 1.))
+}
+
+fn chars(v: &Value) -> Result<Vec<char>> {
+    Ok(v.to_text()?.chars().collect())
+}
+
+fn count_argument(v: Option<&Value>, default: f64) -> Result<usize> {
+    let n = v.map_or(Ok(default), Value::to_number)?.trunc();
+    if n < 0. {
+        return Err(CellError::Value);
+    }
+    Ok(n as usize)
+}
+
+/// Position of `find` in `text` from 1-based `start`, as `FIND` or `SEARCH`.
+fn find(args: &[Value], search: bool) -> Result<Value> {
+    arity(args, 2, 3)?;
+    let pattern = args[0].to_text()?;
+    let text = chars(&args[1])?;
+    let start = optional_number(args, 2, 1.)?.trunc();
+    if start < 1. || start as usize > text.len() + 1 {
+        return Err(CellError::Value);
+    }
+    let position = (start as usize - 1..=text.len()).find(|i| {
+        let rest: String = text[*i..].iter().collect();
+        if search {
+            wildcard_match(&format!("{}*", pattern), &rest)
+        } else {
+            rest.starts_with(&pattern)
+        }
+    });
+    position
+        .map(|i| Value::Number(i as f64 + 1.))
+        .ok_or(CellError::Value)
+}
+
+/// Longest text a cell can hold.
+const MAX_TEXT_LEN: usize = 32767;
+
+fn rept(args: &[Value]) -> Result<Value> {
+    let text = args[0].to_text()?;
+    let count = count_argument(Some(&args[1]), 0.)?;
+    match text.chars().count().checked_mul(count) {
+        Some(len) if len <= MAX_TEXT_LEN => Ok(Value::Text(text.repeat(count))),
+        _ => Err(CellError::Value),
+    }
+}
+
+fn substitute(args: &[Value]) -> Result<Value> {
+    arity(args, 3, 4)?;
+    let text = args[0].to_text()?;
+    let old = args[1].to_text()?;
+    let new = args[2].to_text()?;
+    if old.is_empty() {
+        return Ok(Value::Text(text));
+    }
+    let instance = match optional(args, 3) {
+        Some(v) => match v.to_number()?.trunc() {
+            n if n < 1. => return Err(CellError::Value),
+            n => n as usize,
+        },
+        None => return Ok(Value::Text(text.replace(&old, &new))),
+    };
+    match text.match_indices(&old).nth(instance - 1) {
+        Some((i, _)) => Ok(Value::Text(format!(
+            "{}{}{}",
+            &text[..i],
+            new,
+            &text[i + old.len()..]
+        ))),
+        None => Ok(Value::Text(text)),
+    }
+}
+
+fn text(ctx: &Context<'_>, args: &[Value]) -> Result<Value> {
+    let code = NumberFormatCode::parse(&args[1].to_text()?).with_date_system(ctx.date_system());
+    let text = match &args[0] {
+        Value::Text(s) => match s.trim().parse::<f64>() {
+            Ok(v) => code.format(v),
+            Err(_) => code.format_text(s).text,
+        },
+        v => code.format(v.to_number()?),
+    };
+    Ok(Value::Text(text))
+}
+
+fn value(args: &[Value]) -> Result<Value> {
+    let text = match &args[0] {
+        Value::Text(s) => s.trim().to_string(),
+        v => return Ok(Value::Number(v.to_number()?)),
+    };
+    let parsed = match text.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map(|v| v / 100.),
+        None => text.parse::<f64>(),
+    };
+    parsed.map(Value::Number).map_err(|_| CellError::Value)
+}
+
+/// Date of a serial number, the time part is dropped.
+fn date_of(ctx: &Context<'_>, v: &Value) -> Result<NaiveDate> {
+    let serial = v.to_number()?.floor();
+    ctx.date_system()
+        .serial_to_datetime(serial)
+        .map(|datetime| datetime.date())
+        .ok_or(CellError::Num)
+}
+
+fn serial_of(ctx: &Context<'_>, date: NaiveDate) -> Value {
+    Value::Number(ctx.date_system().date_to_serial(&date))
+}
+
+/// Add months to a date, days past the end of the month are clamped to it.
+fn add_months(date: NaiveDate, months: i64) -> Result<NaiveDate> {
+    let months = (date.year() as i64 * 12 + date.month0() as i64)
+        .checked_add(months)
+        .ok_or(CellError::Num)?;
+    let year = i32::try_from(months.div_euclid(12)).map_err(|_| CellError::Num)?;
+    let month = months.rem_euclid(12) as u32 + 1;
+    let first = NaiveDate::from_ymd_opt(year, month, 1).ok_or(CellError::Num)?;
+    let last = add_months_end(first)?;
+    Ok(first
+        .with_day(date.day().min(last.day()))
+        .expect("day in month"))
+}
+
+/// The last day of the month.
+fn add_months_end(first: NaiveDate) -> Result<NaiveDate> {
+    let next = match first.month() {
+        12 => NaiveDate::from_ymd_opt(first.year() + 1, 1, 1),
+        month => NaiveDate::from_ymd_opt(first.year(), month + 1, 1),
+    };
+    next.and_then(|next| next.pred_opt()).ok_or(CellError::Num)
+}
+
+fn date(ctx: &Context<'_>, args: &[Value]) -> Result<Value> {
+    let mut year = args[0].to_number()?.trunc() as i64;
+    let month = args[1].to_number()?.trunc() as i64;
+    let day = args[2].to_number()?.trunc() as i64;
+    if (0..1900).contains(&year) {
+        year += 1900;
+    }
+    if !(0..10000).contains(&year) {
+        return Err(CellError::Num);
+    }
+    let first = NaiveDate::from_ymd_opt(year as i32, 1, 1).ok_or(CellError::Num)?;
+    let days = day
+        .checked_sub(1)
+        .and_then(Duration::try_days)
+        .ok_or(CellError::Num)?;
+    let date = add_months(first, month.checked_sub(1).ok_or(CellError::Num)?)?
+        .checked_add_signed(days)
+        .ok_or(CellError::Num)?;
+    Ok(serial_of(ctx, date))
+}
+
+/// Hours, minutes and seconds of the time part of a serial number.
+fn time_of(v: &Value) -> Result<(u32, u32, u32)> {
+    let serial = v.to_number()?;
+    if serial < 0. {
+        return Err(CellError::Num);
+    }
+    let seconds = (serial.fract() * 86_400.).round() as u32 % 86_400;
+    Ok((seconds / 3600, seconds / 60 % 60, seconds % 60))
+}
+
+fn call_scalar(ctx: &Context<'_>, name: &str, a: &[Value]) -> Result<Value> {
+    let n = |i: usize| a[i].to_number();
+    let result = match name {
+        "ABS" => number(n(0)?.abs()),
+        "INT" => number(n(0)?.floor()),
+        "TRUNC" => round_with(n(0)?, optional_number(a, 1, 0.)?, f64::trunc)?,
+        "SIGN" => number(n(0)?.signum() * (n(0)? != 0.) as u8 as f64),
+        "SQRT" => match n(0)? {
+            v if v < 0. => return Err(CellError::Num),
+            v => number(v.sqrt()),
+        },
+        "EXP" => number(n(0)?.exp()),
+        "LN" | "LOG10" => match n(0)? {
+            v if v <= 0. => return Err(CellError::Num),
+            v if name == "LN" => number(v.ln()),
+            v => number(v.log10()),
+        },
+        "MOD" => {
+            let (v, d) = (n(0)?, n(1)?);
+            if d == 0. {
+                return Err(CellError::Div0);
+            }
+            number(v - d * (v / d).floor())
+        }
+        "POWER" => super::binary(super::BinaryOp::Pow, &a[0], &a[1]),
+        "ROUND" => round_with(n(0)?, n(1)?, f64::round)?,
+        "ROUNDUP" => round_with(n(0)?, n(1)?, |v| v.abs().ceil().copysign(v))?,
+        "ROUNDDOWN" => round_with(n(0)?, n(1)?, f64::trunc)?,
+        "NOT" => Value::Bool(!a[0].to_bool()?),
+        "LEN" => Value::Number(chars(&a[0])?.len() as f64),
+        "LEFT" => {
+            let count = count_argument(optional(a, 1), 1.)?;
+            Value::Text(chars(&a[0])?.iter().take(count).collect())
+        }
+        "RIGHT" => {
+            let text = chars(&a[0])?;
+            let count = count_argument(optional(a, 1), 1.)?.min(text.len());
+            Value::Text(text[text.len() - count..].iter().collect())
+        }
+        "MID" => {
+            let start = a[1].to_number()?.trunc();
+            if start < 1. {
+                return Err(CellError::Value);
+            }
+            let count = count_argument(Some(&a[2]), 0.)?;
+            let text = chars(&a[0])?;
+            Value::Text(text.iter().skip(start as usize - 1).take(count).collect())
+        }
+        "UPPER" => Value::Text(a[0].to_text()?.to_uppercase()),
+        "LOWER" => Value::Text(a[0].to_text()?.to_lowercase()),
+        "TRIM" => Value::Text(
+            a[0].to_text()?
+                .split(' ')
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        "CONCATENATE" => Value::Text(
+            a.iter()
+                .map(Value::to_text)
+                .collect::<Result<Vec<_>>>()?
+                .concat(),
+        ),
+        "REPT" => rept(a)?,
+        "EXACT" => Value::Bool(a[0].to_text()? == a[1].to_text()?),
+        "TEXT" => text(ctx, a)?,
+        "VALUE" => value(a)?,
+        "FIND" => find(a, false)?,
+        "SEARCH" => find(a, true)?,
+        "SUBSTITUTE" => substitute(a)?,
+        "DATE" => date(ctx, a)?,
+        "TIME" => {
+            let seconds = n(0)?.trunc() * 3600. + n(1)?.trunc() * 60. + n(2)?.trunc();
+            if seconds < 0. {
+                return Err(CellError::Num);
+            }
+            Value::Number(seconds.rem_euclid(86_400.) / 86_400.)
+        }
+        "YEAR" => Value::Number(date_of(ctx, &a[0])?.year() as f64),
+        "MONTH" => Value::Number(date_of(ctx, &a[0])?.month() as f64),
+        "DAY" => Value::Number(date_of(ctx, &a[0])?.day() as f64),
+        "HOUR" => Value::Number(time_of(&a[0])?.0 as f64),
+        "MINUTE" => Value::Number(time_of(&a[0])?.1 as f64),
+        "SECOND" => Value::Number(time_of(&a[0])?.2 as f64),
+        "WEEKDAY" => {
+            let weekday = date_of(ctx, &a[0])?.weekday();
+            let day = match optional_number(a, 1, 1.)? as i64 {
+                1 => weekday.num_days_from_sunday() + 1,
+                2 => weekday.number_from_monday(),
+                3 => weekday.num_days_from_monday(),
+                _ => return Err(CellError::Num),
+            };
+            Value::Number(day as f64)
+        }
+        "EDATE" => serial_of(ctx, add_months(date_of(ctx, &a[0])?, n(1)?.trunc() as i64)?),
+        "EOMONTH" => {
+            let date = add_months(date_of(ctx, &a[0])?, n(1)?.trunc() as i64)?;
+            let first = date.with_day(1).expect("first day");
+            serial_of(ctx, add_months_end(first)?)
+        }
+        "DAYS" => Value::Number(n(0)?.floor() - n(1)?.floor()),
+        "ISBLANK" => Value::Bool(a[0] == Value::Empty),
+        "ISNUMBER" => Value::Bool(matches!(a[0], Value::Number(_))),
+        "ISTEXT" => Value::Bool(matches!(a[0], Value::Text(_))),
+        "ISLOGICAL" => Value::Bool(matches!(a[0], Value::Bool(_))),
+        "ISERROR" => Value::Bool(a[0].is_error()),
+        "ISERR" => Value::Bool(a[0].is_error() && a[0] != Value::Error(CellError::NA)),
+        "ISNA" => Value::Bool(a[0] == Value::Error(CellError::NA)),
+        _ => return Err(CellError::Name),
+    };
+    Ok(result)
+}
+
+/// Argument count range of scalar functions.
+fn scalar_arity(name: &str) -> Option<(usize, usize)> {
+    let arity = match name {
+        "ABS" | "INT" | "SIGN" | "SQRT" | "EXP" | "LN" | "LOG10" | "NOT" | "LEN" | "UPPER"
+        | "LOWER" | "TRIM" | "VALUE" | "YEAR" | "MONTH" | "DAY" | "HOUR" | "MINUTE" | "SECOND"
+        | "ISBLANK" | "ISNUMBER" | "ISTEXT" | "ISLOGICAL" | "ISERROR" | "ISERR" | "ISNA" => (1, 1),
+        "TRUNC" | "LEFT" | "RIGHT" | "WEEKDAY" => (1, 2),
+        "MOD" | "POWER" | "ROUND" | "ROUNDUP" | "ROUNDDOWN" | "REPT" | "EXACT" | "TEXT"
+        | "EDATE" | "EOMONTH" | "DAYS" => (2, 2),
+        "FIND" | "SEARCH" => (2, 3),
+        "MID" | "DATE" | "TIME" => (3, 3),
+        "SUBSTITUTE" => (3, 4),
+        "CONCATENATE" => (1, 255),
+        _ => return None,
+    };
+    Some(arity)
+}
+
+/// Call a function, unknown functions are `#NAME?`.
+pub(crate) fn call(ctx: &Context<'_>, name: &str, exprs: &[Expr]) -> Value {
+    // functions that evaluate their arguments lazily or read references.
+    let result = match name {
+        "IF" => return if_(ctx, exprs),
+        "IFERROR" | "IFNA" => return if_error(ctx, exprs, name == "IFNA"),
+        "CHOOSE" => return choose(ctx, exprs),
+        "ROW" | "COLUMN" | "ROWS" | "COLUMNS" => shape(ctx, name, exprs),
+        _ => call_eager(ctx, name, exprs),
+    };
+    result.unwrap_or_else(Value::Error)
+}
+
+fn call_eager(ctx: &Context<'_>, name: &str, exprs: &[Expr]) -> Result<Value> {
+    let args: Vec<Arg> = exprs
+        .iter()
+        .map(|expr| Arg {
+            value: ctx.eval(expr),
+            range: ctx.reference(expr).is_some(),
+        })
+        .collect();
+    if let Some((min, max)) = scalar_arity(name) {
+        arity(&args, min, max)?;
+        return Ok(lift(&args, |a| call_scalar(ctx, name, a)));
+    }
+    let values: Vec<Value> = args.iter().map(|arg| arg.value.clone()).collect();
+    let result = match name {
+        "SUM" => number(numbers(&args)?.iter().sum()),
+        "PRODUCT" => number(numbers(&args)?.iter().product()),
+        "AVERAGE" => {
+            let numbers = numbers(&args)?;
+            if numbers.is_empty() {
+                return Err(CellError::Div0);
+            }
+            number(numbers.iter().sum::<f64>() / numbers.len() as f64)
+        }
+        "MIN" => number(numbers(&args)?.into_iter().reduce(f64::min).unwrap_or(0.)),
+        "MAX" => number(numbers(&args)?.into_iter().reduce(f64::max).unwrap_or(0.)),
+        "COUNT" => {
+            let count = args
+                .iter()
+                .map(|arg| match &arg.value {
+                    value if arg.range || matches!(value, Value::Array(_)) => flatten(value)
+                        .into_iter()
+                        .filter(|v| matches!(v, Value::Number(_)))
+                        .count(),
+                    value => value.to_number().is_ok() as usize,
+                })
+                .sum::<usize>();
+            Value::Number(count as f64)
+        }
+        "COUNTA" => Value::Number(
+            values
+                .iter()
+                .flat_map(flatten)
+                .filter(|v| **v != Value::Empty)
+                .count() as f64,
+        ),
+        "COUNTBLANK" => Value::Number(
+            values
+                .iter()
+                .flat_map(flatten)
+                .filter(|v| matches!(v, Value::Empty) || **v == Value::Text(String::new()))
+                .count() as f64,
+        ),
+        "SUMPRODUCT" => {
+            let arrays: Vec<Vec<Vec<Value>>> = values.iter().map(grid).collect();
+            let shape = |rows: &Vec<Vec<Value>>| (rows.len(), rows.first().map_or(0, Vec::len));
+            if arrays.is_empty() || arrays.iter().any(|a| shape(a) != shape(&arrays[0])) {
+                return Err(CellError::Value);
+            }
+            let mut sum = 0.;
+            for (row, cells) in arrays[0].iter().enumerate() {
+                for col in 0..cells.len() {
+                    let mut product = 1.;
+                    for array in &arrays {
+                        match &array[row][col] {
+                            Value::Number(v) => product *= v,
+                            Value::Error(e) => return Err(*e),
+                            _ => product = 0.,
+                        }
+                    }
+                    sum += product;
+                }
+            }
+            number(sum)
+        }
+        "SUMIF" | "AVERAGEIF" => {
+            arity(&values, 2, 3)?;
+            let cells = matching(&[(&values[0], &values[1])]);
+            let numbers = numbers_at(values.get(2).unwrap_or(&values[0]), &cells);
+            if name == "SUMIF" {
+                number(numbers.iter().sum())
+            } else if numbers.is_empty() {
+                return Err(CellError::Div0);
+            } else {
+                number(numbers.iter().sum::<f64>() / numbers.len() as f64)
+            }
+        }
+        "COUNTIF" => {
+            arity(&values, 2, 2)?;
+            Value::Number(matching(&[(&values[0], &values[1])]).len() as f64)
+        }
+        "SUMIFS" | "COUNTIFS" => {
+            let skip = (name == "SUMIFS") as usize;
+            if values.len() < skip + 2 || !(values.len() - skip).is_multiple_of(2) {
+                return Err(CellError::Value);
+            }
+            let pairs: Vec<(&Value, &Value)> = values[skip..]
+                .chunks(2)
+                .map(|pair| (&pair[0], &pair[1]))
+                .collect();
+            let cells = matching(&pairs);
+            match name {
+                "SUMIFS" => number(numbers_at(&values[0], &cells).iter().sum()),
+                _ => Value::Number(cells.len() as f64),
+            }
+        }
+        "AND" => Value::Bool(booleans(&args)?.iter().all(|v| *v)),
+        "OR" => Value::Bool(booleans(&args)?.iter().any(|v| *v)),
+        "TRUE" | "FALSE" => {
+            arity(&values, 0, 0)?;
+            Value::Bool(name == "TRUE")
+        }
+        "PI" => {
+            arity(&values, 0, 0)?;
+            Value::Number(std::f64::consts::PI)
+        }
+        "NA" => return Err(CellError::NA),
+        "VLOOKUP" => v_or_h_lookup(&values, true)?,
+        "HLOOKUP" => v_or_h_lookup(&values, false)?,
+        "INDEX" => index(&values)?,
+        "MATCH" => matches(&values)?,
+        "CONCAT" => {
+            let texts = values
+                .iter()
+                .flat_map(flatten)
+                .map(Value::to_text)
+                .collect::<Result<Vec<_>>>()?;
+            Value::Text(texts.concat())
+        }
+        "TEXTJOIN" => {
+            arity(&values, 3, 255)?;
+            let delimiter = values[0].to_text()?;
+            let ignore_empty = values[1].to_bool()?;
+            let texts = values[2..]
+                .iter()
+                .flat_map(flatten)
+                .map(Value::to_text)
+                .collect::<Result<Vec<_>>>()?;
+            let texts: Vec<String> = texts
+                .into_iter()
+                .filter(|s| !(ignore_empty && s.is_empty()))
+                .collect();
+            Value::Text(texts.join(&delimiter))
+        }
+        "TODAY" | "NOW" => {
+            arity(&values, 0, 0)?;
+            let now = chrono::Local::now().naive_local();
+            let serial = ctx.date_system().datetime_to_serial(&now);
+            Value::Number(if name == "TODAY" {
+                serial.floor()
+            } else {
+                serial
+            })
+        }
+        _ => return Err(CellError::Name),
+    };
+    Ok(result)
+}
+
+fn if_(ctx: &Context<'_>, exprs: &[Expr]) -> Value {
+    if arity(exprs, 2, 3).is_err() {
+        return Value::Error(CellError::Value);
+    }
+    let branch = |i: usize| match exprs.get(i) {
+        Some(expr) => ctx.eval(expr),
+        None => Value::Bool(false),
+    };
+    match ctx.eval(&exprs[0]) {
+        condition @ Value::Array(_) => {
+            let values = [condition, branch(1), branch(2)];
+            broadcast(&values, |v| match v[0].to_bool() {
+                Ok(true) => v[1].clone(),
+                Ok(false) => v[2].clone(),
+                Err(e) => Value::Error(e),
+            })
+        }
+        condition => match condition.to_bool() {
+            Ok(true) => branch(1),
+            Ok(false) => branch(2),
+            Err(e) => Value::Error(e),
+        },
+    }
+}
+
+fn if_error(ctx: &Context<'_>, exprs: &[Expr], na_only: bool) -> Value {
+    if arity(exprs, 2, 2).is_err() {
+        return Value::Error(CellError::Value);
+    }
+    let caught = |v: &Value| match v {
+        Value::Error(CellError::NA) => true,
+        Value::Error(_) => !na_only,
+        _ => false,
+    };
+    match ctx.eval(&exprs[0]) {
+        value @ Value::Array(_) => {
+            let fallback = ctx.eval(&exprs[1]);
+            broadcast(&[value, fallback], |v| {
+                if caught(&v[0]) {
+                    v[1].clone()
+                } else {
+                    v[0].clone()
+                }
+            })
+        }
+        value if caught(&value) => ctx.eval(&exprs[1]),
+        value => value,
+    }
+}
+
+fn choose(ctx: &Context<'_>, exprs: &[Expr]) -> Value {
+    if exprs.len() < 2 {
+        return Value::Error(CellError::Value);
+    }
+    let index = match ctx.eval(&exprs[0]).to_number() {
+        Ok(index) => index.trunc(),
+        Err(e) => return Value::Error(e),
+    };
+    if index < 1. || index as usize >= exprs.len() {
+        return Value::Error(CellError::Value);
+    }
+    ctx.eval(&exprs[index as usize])
+}
+
+fn shape(ctx: &Context<'_>, name: &str, exprs: &[Expr]) -> Result<Value> {
+    arity(exprs, 0, 1)?;
+    let reference = match exprs.first() {
+        Some(expr) => ctx.reference(expr).transpose()?.map(|(_, range)| range),
+        None if name == "ROW" || name == "COLUMN" => Some(CellRange::cell(ctx.cell())),
+        None => return Err(CellError::Value),
+    };
+    let result = match (name, reference) {
+        ("ROW", Some(range)) => range.start.row + 1,
+        ("COLUMN", Some(range)) => range.start.col + 1,
+        ("ROWS", Some(range)) => range.height(),
+        ("COLUMNS", Some(range)) => range.width(),
+        ("ROWS", None) => grid(&ctx.eval(&exprs[0])).len() as u32,
+        ("COLUMNS", None) => grid(&ctx.eval(&exprs[0])).first().map_or(0, Vec::len) as u32,
+        _ => return Err(CellError::Value),
+    };
+    Ok(Value::Number(result as f64))
+}
+
+#[test]
+fn functions() {
+    use super::Model;
+    use crate::document::spreadsheet::cell::DateSystem;
+    use crate::document::spreadsheet::formula::Formula;
+
+    let mut model = Model::new(DateSystem::default());
+    let sheet = model.add_sheet("Sheet1");
+    let rows = [
+        ("apple", 10., "2021-01-15"),
+        ("banana", 20., ""),
+        ("cherry", 30., ""),
+    ];
+    for (row, (name, amount, _)) in rows.iter().enumerate() {
+        model.set_value(sheet, CellRef::new(row as u32, 0), Value::from(*name));
+        model.set_value(sheet, CellRef::new(row as u32, 1), Value::from(*amount));
+    }
+    let cases = [
+        ("ROUND(2.675,2)", Value::Number(2.68)),
+        ("ROUNDDOWN(-2.5,0)*ROUNDUP(1.01,1)", Value::Number(-2.2)),
+        ("MOD(-7,3)", Value::Number(2.)),
+        ("AVERAGE(B1:B3,\"6\")", Value::Number(16.5)),
+        ("COUNT(A1:B3,1,\"x\")", Value::Number(4.)),
+        ("COUNTA(A:A)+COUNTBLANK(A1:C1)", Value::Number(4.)),
+        ("SUMIF(A1:A3,\"<>b*\",B1:B3)", Value::Number(40.)),
+        (
+            "SUMIFS(B1:B3,A1:A3,\"?a*\",B1:B3,\">15\")",
+            Value::Number(20.),
+        ),
+        ("COUNTIF(B:B,\">=20\")", Value::Number(2.)),
+        ("SUMPRODUCT(B1:B3,{1;2;3})", Value::Number(140.)),
+        ("AND(B1:B3)*OR(FALSE,0)", Value::Number(0.)),
+        ("HLOOKUP(2,{1,2,3;\"a\",\"b\",\"c\"},2)", Value::from("b")),
+        ("VLOOKUP(25,B1:B3,1)", Value::Number(20.)),
+        ("VLOOKUP(\"x\",A1:B3,2,FALSE)", Value::Error(CellError::NA)),
+        ("SUM(B1:INDEX(B:B,2))", Value::Number(30.)),
+        (
+            "MATCH(\"CH*\",A1:A3,0)+ROWS(A1:C2)*COLUMNS(A:C)+ROW()",
+            Value::Number(25.),
+        ),
+        ("CHOOSE(2,1/0,\"two\")", Value::from("two")),
+        ("IFNA(MATCH(0,B1:B3,0),\"none\")", Value::from("none")),
+        (
+            "MID(\"spreadsheet\",7,5)&RIGHT(\"ab\",5)&LEFT(\"xyz\")",
+            Value::from("sheetabx"),
+        ),
+        (
+            "TRIM(\"  a   b \")&LOWER(\"C\")&REPT(\"-\",2)",
+            Value::from("a bc--"),
+        ),
+        (
+            "FIND(\"b\",\"abcb\",3)+SEARCH(\"C?\",\"abcb\")",
+            Value::Number(7.),
+        ),
+        ("SUBSTITUTE(\"a-b-c\",\"-\",\"+\",2)", Value::from("a-b+c")),
+        ("LEN(REPT(\"a\",32767))", Value::Number(32767.)),
+        ("REPT(\"a\",32768)", Value::Error(CellError::Value)),
+        ("REPT(\"ab\",1E19)", Value::Error(CellError::Value)),
+        (
+            "CONCAT(A1:A2,1)&TEXTJOIN(\",\",TRUE,\"\",\"x\",\"y\")",
+            Value::from("applebanana1x,y"),
+        ),
+        (
+            "TEXT(0.256,\"0.0%\")&VALUE(\"50%\")",
+            Value::from("25.6%0.5"),
+        ),
+        ("EXACT(\"a\",\"A\")", Value::Bool(false)),
+        ("DATE(2021,14,1)-DATE(2022,1,31)", Value::Number(1.)),
+        (
+            "YEAR(44242)*10000+MONTH(44242)*100+DAY(44242)",
+            Value::Number(20210215.),
+        ),
+        (
+            "EOMONTH(DATE(2020,1,31),1)-EDATE(DATE(2020,1,31),1)",
+            Value::Number(0.),
+        ),
+        (
+            "WEEKDAY(DATE(2021,3,1),2)+HOUR(TIME(25,30,0))",
+            Value::Number(2.),
+        ),
+        ("DAYS(\"44300\",44200.9)", Value::Number(100.)),
+        (
+            "ISBLANK(C1)+ISNUMBER(B1)+ISERR(NA())+ISNA(NA())",
+            Value::Number(3.),
+        ),
+        ("EDATE(DATE(2020,1,31),1E300)", Value::Error(CellError::Num)),
+        (
+            "EOMONTH(DATE(2020,1,31),-1E300)",
+            Value::Error(CellError::Num),
+        ),
+        ("DATE(2020,-1E300,1E300)", Value::Error(CellError::Num)),
+        ("NOFUNC(1)", Value::Error(CellError::Name)),
+        ("SQRT(-1)", Value::Error(CellError::Num)),
+    ];
+    for (row, (formula, _)) in cases.iter().enumerate() {
+        model.set_formula(sheet, CellRef::new(row as u32, 4), &Formula::new(*formula));
+    }
+    let calculation = model.calculate();
+    for (row, (formula, expected)) in cases.iter().enumerate() {
+        let value = calculation.value("Sheet1", CellRef::new(row as u32, 4));
+        assert_eq!(value, Some(expected), "{}", formula);
+    }
+}
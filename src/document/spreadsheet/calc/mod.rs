@@ -0,0 +1,919 @@
+//! Formula calculation engine.
+//!
+//! Formulas are parsed into [`Expr`] trees by [`parse`] and evaluated over a [`Model`] of
+//! sheet values. Formula cells are calculated in dependency order, cells in circular
+//! references are reported in [`Calculation::cycles`] and evaluate to `0` as Excel does
+//! without iterative calculation.
+//!
+//! ```rust
+//! use ooxml::document::calc::{Model, Value};
+//! use ooxml::document::{DateSystem, Formula};
+//!
+//! let mut model = Model::new(DateSystem::default());
+//! let sheet = model.add_sheet("Sheet1");
+//! model.set_value(sheet, "A1".parse().unwrap(), Value::Number(2.));
+//! model.set_formula(sheet, "B1".parse().unwrap(), &Formula::new("A1*10+SUM(A1:A3)"));
+//! let calculation = model.calculate();
+//! assert_eq!(
+//!     calculation.value("Sheet1", "B1".parse().unwrap()),
+//!     Some(&Value::Number(22.))
+//! );
+//! ```
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use super::cell::{CellError, CellValue, DateSystem};
+use super::formula::{Formula, FormulaKind};
+use super::number_format;
+use super::reference::{CellRange, CellRef};
+
+mod functions;
+mod parser;
+
+pub use self::parser::{parse, BinaryOp, Expr, UnaryOp};
+
+/// A calculated value, ranges are evaluated to arrays by rows.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Value {
+    #[default]
+    Empty,
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Error(CellError),
+    Array(Vec<Vec<Value>>),
+}
+
+impl Value {
+    /// Value of a cell, date and time values are their serial numbers.
+    pub fn from_cell_value(value: &CellValue, date_system: DateSystem) -> Self {
+        match value {
+            CellValue::Null => Value::Empty,
+            CellValue::Bool(v) => Value::Bool(*v),
+            CellValue::String(s) | CellValue::Raw(s) => Value::Text(s.clone()),
+            CellValue::Error(e) => Value::Error(*e),
+            value => value
+                .to_serial(date_system)
+                .map_or(Value::Empty, Value::Number),
+        }
+    }
+
+    /// Number of the value, texts are parsed and booleans are `1` or `0`.
+    pub fn to_number(&self) -> Result<f64, CellError> {
+        match self {
+            Value::Empty => Ok(0.),
+            Value::Number(v) => Ok(*v),
+            Value::Bool(v) => Ok(*v as u8 as f64),
+            Value::Text(s) => s.trim().parse().map_err(|_| CellError::Value),
+            Value::Error(e) => Err(*e),
+            Value::Array(_) => self.scalar().to_number(),
+        }
+    }
+
+    /// Text of the value, numbers are in `General` format.
+    pub fn to_text(&self) -> Result<String, CellError> {
+        match self {
+            Value::Error(e) => Err(*e),
+            Value::Array(_) => self.scalar().to_text(),
+            value => Ok(value.to_string()),
+        }
+    }
+
+    /// Boolean of the value, numbers are `TRUE` if not zero.
+    pub fn to_bool(&self) -> Result<bool, CellError> {
+        match self {
+            Value::Empty => Ok(false),
+            Value::Number(v) => Ok(*v != 0.),
+            Value::Bool(v) => Ok(*v),
+            Value::Text(s) if s.eq_ignore_ascii_case("TRUE") => Ok(true),
+            Value::Text(s) if s.eq_ignore_ascii_case("FALSE") => Ok(false),
+            Value::Text(_) => Err(CellError::Value),
+            Value::Error(e) => Err(*e),
+            Value::Array(_) => self.scalar().to_bool(),
+        }
+    }
+
+    /// The top-left value of arrays, others as they are.
+    pub fn scalar(&self) -> Value {
+        match self {
+            Value::Array(rows) => rows
+                .first()
+                .and_then(|row| row.first())
+                .cloned()
+                .unwrap_or_default(),
+            value => value.clone(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Value::Error(_))
+    }
+
+    /// Rows and columns of arrays, `None` for scalars.
+    fn shape(&self) -> Option<(usize, usize)> {
+        match self {
+            Value::Array(rows) => Some((rows.len(), rows.first().map_or(0, Vec::len))),
+            _ => None,
+        }
+    }
+
+    /// Element of an array broadcast to a larger shape, single rows and columns are
+    /// repeated and positions out of the array are `#N/A`.
+    fn element(&self, row: usize, col: usize) -> Value {
+        match self {
+            Value::Array(rows) => {
+                let row = if rows.len() == 1 { 0 } else { row };
+                let cells = match rows.get(row) {
+                    Some(cells) => cells,
+                    None => return Value::Error(CellError::NA),
+                };
+                let col = if cells.len() == 1 { 0 } else { col };
+                cells
+                    .get(col)
+                    .cloned()
+                    .unwrap_or(Value::Error(CellError::NA))
+            }
+            value => value.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Empty => Ok(()),
+            Value::Number(v) => write!(f, "{}", number_format::format_general(*v)),
+            Value::Text(s) => write!(f, "{}", s),
+            Value::Bool(true) => write!(f, "TRUE"),
+            Value::Bool(false) => write!(f, "FALSE"),
+            Value::Error(e) => write!(f, "{}", e),
+            Value::Array(_) => write!(f, "{}", self.scalar()),
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::Number(v)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Bool(v)
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::Text(v)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::Text(v.to_string())
+    }
+}
+
+/// Apply `f` to scalars, or to each element of arrays broadcast to the largest shape.
+pub(crate) fn broadcast(values: &[Value], f: impl Fn(&[Value]) -> Value) -> Value {
+    let shape = values
+        .iter()
+        .filter_map(Value::shape)
+        .reduce(|(a, b), (c, d)| (a.max(c), b.max(d)));
+    let (rows, cols) = match shape {
+        Some(shape) => shape,
+        None => return f(values),
+    };
+    let rows = (0..rows)
+        .map(|row| {
+            (0..cols)
+                .map(|col| {
+                    let scalars: Vec<Value> = values.iter().map(|v| v.element(row, col)).collect();
+                    f(&scalars)
+                })
+                .collect()
+        })
+        .collect();
+    Value::Array(rows)
+}
+
+/// Compare values in Excel sort order: numbers, texts then booleans, texts are case
+/// insensitive and empty values equal to zero, empty text or `FALSE`.
+pub fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Number(_) => 0,
+            Value::Text(_) => 1,
+            Value::Bool(_) => 2,
+            _ => 3,
+        }
+    }
+    fn blank(other: &Value) -> Value {
+        match other {
+            Value::Text(_) => Value::Text(String::new()),
+            Value::Bool(_) => Value::Bool(false),
+            _ => Value::Number(0.),
+        }
+    }
+    match (a, b) {
+        (Value::Empty, Value::Empty) => Ordering::Equal,
+        (Value::Empty, b) => compare(&blank(b), b),
+        (a, Value::Empty) => compare(a, &blank(a)),
+        (Value::Number(a), Value::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (Value::Text(a), Value::Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (a, b) => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// A number result, infinite and NaN numbers are `#NUM!`.
+fn number(v: f64) -> Value {
+    if v.is_finite() {
+        Value::Number(v)
+    } else {
+        Value::Error(CellError::Num)
+    }
+}
+
+fn binary(op: BinaryOp, a: &Value, b: &Value) -> Value {
+    let numbers = || -> Result<(f64, f64), CellError> {
+        let a = a.to_number();
+        let b = b.to_number();
+        Ok((a?, b?))
+    };
+    let result = match op {
+        BinaryOp::Add => numbers().map(|(a, b)| number(a + b)),
+        BinaryOp::Sub => numbers().map(|(a, b)| number(a - b)),
+        BinaryOp::Mul => numbers().map(|(a, b)| number(a * b)),
+        BinaryOp::Div => numbers().and_then(|(a, b)| {
+            if b == 0. {
+                Err(CellError::Div0)
+            } else {
+                Ok(number(a / b))
+            }
+        }),
+        BinaryOp::Pow => numbers().and_then(|(a, b)| match (a, b) {
+            (a, b) if a == 0. && b == 0. => Err(CellError::Num),
+            (a, b) => Ok(number(a.powf(b))),
+        }),
+        BinaryOp::Concat => a.to_text().and_then(|a| Ok(Value::Text(a + &b.to_text()?))),
+        BinaryOp::Range => Err(CellError::Value),
+        op => {
+            if let Value::Error(e) = a {
+                return Value::Error(*e);
+            }
+            if let Value::Error(e) = b {
+                return Value::Error(*e);
+            }
+            let ordering = compare(a, b);
+            Ok(Value::Bool(match op {
+                BinaryOp::Eq => ordering == Ordering::Equal,
+                BinaryOp::Ne => ordering != Ordering::Equal,
+                BinaryOp::Lt => ordering == Ordering::Less,
+                BinaryOp::Le => ordering != Ordering::Greater,
+                BinaryOp::Gt => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            }))
+        }
+    };
+    result.unwrap_or_else(Value::Error)
+}
+
+#[derive(Debug, Clone)]
+struct CellFormula {
+    expr: Result<Expr, CellError>,
+    /// Cells of an array formula.
+    spill: Option<CellRange>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Sheet {
+    name: String,
+    values: BTreeMap<CellRef, Value>,
+    formulas: BTreeMap<CellRef, CellFormula>,
+}
+
+impl Sheet {
+    /// Bounds of all values and formula cells, whole row and column ranges are clamped to it.
+    fn bounds(&self) -> Option<CellRange> {
+        let cells = self
+            .values
+            .keys()
+            .copied()
+            .chain(self.formulas.iter().flat_map(|(cell, formula)| {
+                let spill = formula.spill.map(|range| range.end);
+                std::iter::once(*cell).chain(spill)
+            }));
+        cells.map(CellRange::cell).reduce(|a, b| bounding(&a, &b))
+    }
+}
+
+/// The smallest range covering both ranges.
+fn bounding(a: &CellRange, b: &CellRange) -> CellRange {
+    CellRange::new(
+        CellRef::new(a.start.row.min(b.start.row), a.start.col.min(b.start.col)),
+        CellRef::new(a.end.row.max(b.end.row), a.end.col.max(b.end.col)),
+    )
+}
+
+/// A defined name, scoped to a sheet or the workbook.
+#[derive(Debug, Clone)]
+struct Name {
+    name: String,
+    scope: Option<usize>,
+    expr: Result<Expr, CellError>,
+}
+
+/// Sheets of values and formulas to calculate.
+#[derive(Debug, Clone, Default)]
+pub struct Model {
+    date_system: DateSystem,
+    sheets: Vec<Sheet>,
+    names: Vec<Name>,
+}
+
+impl Model {
+    pub fn new(date_system: DateSystem) -> Self {
+        Self {
+            date_system,
+            sheets: Vec::new(),
+            names: Vec::new(),
+        }
+    }
+
+    pub fn date_system(&self) -> DateSystem {
+        self.date_system
+    }
+
+    /// Add a sheet and return its index.
+    pub fn add_sheet(&mut self, name: impl Into<String>) -> usize {
+        self.sheets.push(Sheet {
+            name: name.into(),
+            ..Default::default()
+        });
+        self.sheets.len() - 1
+    }
+
+    /// Index of a sheet by case insensitive name.
+    pub fn sheet_index(&self, name: &str) -> Option<usize> {
+        self.sheets
+            .iter()
+            .position(|sheet| sheet.name.to_lowercase() == name.to_lowercase())
+    }
+
+    /// Set a constant cell value.
+    pub fn set_value(&mut self, sheet: usize, cell: CellRef, value: Value) {
+        self.sheets[sheet].values.insert(cell.relative(), value);
+    }
+
+    /// Set a cell formula, formulas that fail to parse evaluate to `#NAME?`.
+    ///
+    /// Data tables and cells of shared groups without their concrete text are not calculated.
+    pub fn set_formula(&mut self, sheet: usize, cell: CellRef, formula: &Formula) {
+        if formula.kind == FormulaKind::DataTable || formula.text.is_empty() {
+            return;
+        }
+        let formula = CellFormula {
+            expr: parse(&formula.text).map_err(|_| CellError::Name),
+            spill: formula.range.filter(|_| formula.kind == FormulaKind::Array),
+        };
+        self.sheets[sheet].formulas.insert(cell.relative(), formula);
+    }
+
+    /// Define a name of the workbook, or of a sheet by its index, formulas that fail to parse
+    /// evaluate to `#NAME?`.
+    pub fn define_name(&mut self, name: impl Into<String>, scope: Option<usize>, formula: &str) {
+        self.names.push(Name {
+            name: name.into(),
+            scope,
+            expr: parse(formula).map_err(|_| CellError::Name),
+        });
+    }
+
+    /// The expression of a name used in a sheet, sheet scoped names come first.
+    fn name(&self, name: &str, sheet: usize) -> Option<&Result<Expr, CellError>> {
+        let names = || {
+            self.names
+                .iter()
+                .filter(move |n| n.name.eq_ignore_ascii_case(name))
+        };
+        names()
+            .find(|n| n.scope == Some(sheet))
+            .or_else(|| names().find(|n| n.scope.is_none()))
+            .map(|n| &n.expr)
+    }
+
+    /// Calculate all formulas.
+    pub fn calculate(&self) -> Calculation {
+        Engine::new(self).run()
+    }
+
+    /// Evaluate formulas as if they were in cells by sheet index, over the calculated model.
+    ///
+    /// It's for formulas out of cells, like the ones of data validation rules.
+    pub fn evaluate(&self, formulas: &[(usize, CellRef, &str)]) -> Vec<Value> {
+        let mut engine = Engine::new(self);
+        engine.calculate_all();
+        formulas
+            .iter()
+            .map(|(sheet, cell, formula)| match parse(formula) {
+                Ok(expr) => Context {
+                    engine: &engine,
+                    sheet: *sheet,
+                    cell: *cell,
+                    names: RefCell::new(Vec::new()),
+                }
+                .eval(&expr),
+                Err(_) => Value::Error(CellError::Name),
+            })
+            .collect()
+    }
+}
+
+/// Results of a calculation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Calculation {
+    /// Values of formula cells by sheet name, including all cells of array formulas.
+    pub values: BTreeMap<String, BTreeMap<CellRef, Value>>,
+    /// Formula cells in circular references.
+    pub cycles: Vec<(String, CellRef)>,
+}
+
+impl Calculation {
+    /// Calculated value of a formula cell.
+    pub fn value(&self, sheet: &str, cell: CellRef) -> Option<&Value> {
+        self.values.get(sheet)?.get(&cell.relative())
+    }
+
+    /// Calculated values of a sheet.
+    pub fn sheet(&self, sheet: &str) -> Option<&BTreeMap<CellRef, Value>> {
+        self.values.get(sheet)
+    }
+}
+
+/// Formula evaluation state of a calculation.
+struct Engine<'a> {
+    model: &'a Model,
+    bounds: Vec<Option<CellRange>>,
+    /// Formula cells by sheet and cell.
+    nodes: Vec<(usize, CellRef)>,
+    /// Formula cell index of each cell in formulas or array formula ranges, by sheet.
+    owners: Vec<BTreeMap<CellRef, usize>>,
+    results: HashMap<(usize, CellRef), Value>,
+}
+
+/// Evaluation context of a formula cell.
+pub(crate) struct Context<'a> {
+    engine: &'a Engine<'a>,
+    sheet: usize,
+    cell: CellRef,
+    /// Defined names being evaluated, to stop circular names.
+    names: RefCell<Vec<String>>,
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn date_system(&self) -> DateSystem {
+        self.engine.model.date_system
+    }
+
+    /// The calculating cell.
+    pub(crate) fn cell(&self) -> CellRef {
+        self.cell
+    }
+
+    fn sheet_index(&self, sheet: Option<&str>) -> Result<usize, CellError> {
+        match sheet {
+            Some(name) => self.engine.model.sheet_index(name).ok_or(CellError::Ref),
+            None => Ok(self.sheet),
+        }
+    }
+
+    /// Resolve reference expressions: references, ranges between references and `INDEX` of
+    /// references. `None` for other expressions.
+    pub(crate) fn reference(&self, expr: &Expr) -> Option<Result<(usize, CellRange), CellError>> {
+        match expr {
+            Expr::Reference { sheet, range } => Some(
+                self.sheet_index(sheet.as_deref())
+                    .map(|sheet| (sheet, *range)),
+            ),
+            Expr::Binary(BinaryOp::Range, a, b) => {
+                let result = match (self.reference(a)?, self.reference(b)?) {
+                    (Ok((sheet, a)), Ok((other, b))) if sheet == other => {
+                        Ok((sheet, bounding(&a, &b)))
+                    }
+                    (Err(e), _) | (_, Err(e)) => Err(e),
+                    _ => Err(CellError::Value),
+                };
+                Some(result)
+            }
+            Expr::Name(name) => match self.eval_name(name, |expr| self.reference(expr)) {
+                Ok(reference) => reference,
+                Err(e) => Some(Err(e)),
+            },
+            Expr::Call(name, args) if name == "INDEX" && !args.is_empty() => {
+                let (sheet, range) = match self.reference(&args[0])? {
+                    Ok(reference) => reference,
+                    Err(e) => return Some(Err(e)),
+                };
+                Some(functions::index_reference(self, range, &args[1..]).map(|r| (sheet, r)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluate the expression of a defined name with `f`, `#NAME?` for unknown or circular names.
+    fn eval_name<T>(&self, name: &str, f: impl FnOnce(&Expr) -> T) -> Result<T, CellError> {
+        let expr = match self.engine.model.name(name, self.sheet) {
+            Some(Ok(expr)) => expr,
+            Some(Err(e)) => return Err(*e),
+            None => return Err(CellError::Name),
+        };
+        let key = name.to_lowercase();
+        if self.names.borrow().contains(&key) {
+            return Err(CellError::Name);
+        }
+        self.names.borrow_mut().push(key);
+        let result = f(expr);
+        self.names.borrow_mut().pop();
+        Ok(result)
+    }
+
+    fn cell_value(&self, sheet: usize, cell: CellRef) -> Value {
+        let cell = cell.relative();
+        match self.engine.results.get(&(sheet, cell)) {
+            Some(value) => value.clone(),
+            None => self.engine.model.sheets[sheet]
+                .values
+                .get(&cell)
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Value of a reference, single cells are scalars and others are arrays.
+    pub(crate) fn range_value(&self, sheet: usize, range: CellRange) -> Value {
+        if range.is_single_cell() {
+            return self.cell_value(sheet, range.start);
+        }
+        let range = match self.engine.clamp(sheet, range) {
+            Some(range) => range,
+            None => return Value::Array(Vec::new()),
+        };
+        let rows = (range.start.row..=range.end.row)
+            .map(|row| {
+                (range.start.col..=range.end.col)
+                    .map(|col| self.cell_value(sheet, CellRef::new(row, col)))
+                    .collect()
+            })
+            .collect();
+        Value::Array(rows)
+    }
+
+    pub(crate) fn eval(&self, expr: &Expr) -> Value {
+        match expr {
+            Expr::Number(v) => Value::Number(*v),
+            Expr::Text(s) => Value::Text(s.clone()),
+            Expr::Bool(v) => Value::Bool(*v),
+            Expr::Error(e) => Value::Error(*e),
+            Expr::Missing => Value::Empty,
+            Expr::Name(name) => self
+                .eval_name(name, |expr| self.eval(expr))
+                .unwrap_or_else(Value::Error),
+            Expr::Reference { .. } | Expr::Binary(BinaryOp::Range, _, _) => {
+                match self.reference(expr) {
+                    Some(Ok((sheet, range))) => self.range_value(sheet, range),
+                    Some(Err(e)) => Value::Error(e),
+                    None => Value::Error(CellError::Value),
+                }
+            }
+            Expr::Array(rows) => Value::Array(
+                rows.iter()
+                    .map(|row| row.iter().map(|e| self.eval(e)).collect())
+                    .collect(),
+            ),
+            Expr::Unary(op, e) => {
+                let value = self.eval(e);
+                broadcast(&[value], |v| match (op, v[0].to_number()) {
+                    (UnaryOp::Plus, _) => v[0].clone(),
+                    (UnaryOp::Minus, Ok(n)) => number(-n),
+                    (UnaryOp::Percent, Ok(n)) => number(n / 100.),
+                    (_, Err(e)) => Value::Error(e),
+                })
+            }
+            Expr::Binary(op, a, b) => {
+                let values = [self.eval(a), self.eval(b)];
+                broadcast(&values, |v| binary(*op, &v[0], &v[1]))
+            }
+            Expr::Call(name, args) => functions::call(self, name, args),
+        }
+    }
+}
+
+impl<'a> Engine<'a> {
+    fn new(model: &'a Model) -> Self {
+        let mut nodes = Vec::new();
+        let mut owners = vec![BTreeMap::new(); model.sheets.len()];
+        for (index, sheet) in model.sheets.iter().enumerate() {
+            for (cell, formula) in &sheet.formulas {
+                let node = nodes.len();
+                nodes.push((index, *cell));
+                if let Some(spill) = formula.spill {
+                    for cell in spill.cells() {
+                        owners[index].entry(cell).or_insert(node);
+                    }
+                }
+                owners[index].insert(*cell, node);
+            }
+        }
+        Self {
+            model,
+            bounds: model.sheets.iter().map(Sheet::bounds).collect(),
+            nodes,
+            owners,
+            results: HashMap::new(),
+        }
+    }
+
+    /// Clamp a range to the sheet bounds, `None` if it's out of them.
+    fn clamp(&self, sheet: usize, range: CellRange) -> Option<CellRange> {
+        let bounds = self.bounds[sheet]?;
+        let end = CellRef::new(
+            range.end.row.min(bounds.end.row),
+            range.end.col.min(bounds.end.col),
+        );
+        if end.row < range.start.row || end.col < range.start.col {
+            return None;
+        }
+        Some(CellRange::new(range.start, end))
+    }
+
+    fn formula(&self, node: usize) -> &CellFormula {
+        let (sheet, cell) = self.nodes[node];
+        &self.model.sheets[sheet].formulas[&cell]
+    }
+
+    /// Formula cells each formula cell refers to, references of defined names included.
+    fn dependencies(&self) -> Vec<Vec<usize>> {
+        (0..self.nodes.len())
+            .map(|node| {
+                let (sheet, _) = self.nodes[node];
+                let mut dependencies = Vec::new();
+                if let Ok(expr) = &self.formula(node).expr {
+                    self.expr_dependencies(sheet, expr, &mut Vec::new(), &mut dependencies);
+                }
+                dependencies.sort_unstable();
+                dependencies.dedup();
+                dependencies
+            })
+            .collect()
+    }
+
+    /// Collect formula cells an expression of the sheet refers to.
+    fn expr_dependencies(
+        &self,
+        sheet: usize,
+        expr: &Expr,
+        names: &mut Vec<String>,
+        dependencies: &mut Vec<usize>,
+    ) {
+        expr.references(&mut |name, range| {
+            let sheet = match name {
+                Some(name) => match self.model.sheet_index(name) {
+                    Some(sheet) => sheet,
+                    None => return,
+                },
+                None => sheet,
+            };
+            let range = match self.clamp(sheet, *range) {
+                Some(range) => range,
+                None => return,
+            };
+            let first = CellRef::new(range.start.row, 0);
+            let last = CellRef::new(range.end.row, u32::MAX);
+            dependencies.extend(
+                self.owners[sheet]
+                    .range(first..=last)
+                    .filter(|(cell, _)| range.contains(cell))
+                    .map(|(_, node)| *node),
+            );
+        });
+        let mut used = Vec::new();
+        expr.names(&mut |name| used.push(name.to_lowercase()));
+        for name in used {
+            if names.contains(&name) {
+                continue;
+            }
+            if let Some(Ok(expr)) = self.model.name(&name, sheet) {
+                names.push(name);
+                self.expr_dependencies(sheet, expr, names, dependencies);
+            }
+        }
+    }
+
+    fn evaluate(&mut self, node: usize) {
+        let (sheet, cell) = self.nodes[node];
+        let formula = self.formula(node);
+        let value = match &formula.expr {
+            Ok(expr) => Context {
+                engine: self,
+                sheet,
+                cell,
+                names: RefCell::new(Vec::new()),
+            }
+            .eval(expr),
+            Err(e) => Value::Error(*e),
+        };
+        let result = |value: Value| match value {
+            Value::Empty => Value::Number(0.),
+            value => value,
+        };
+        match formula.spill {
+            Some(spill) => {
+                for target in spill.cells() {
+                    let element = value.element(
+                        (target.row - spill.start.row) as usize,
+                        (target.col - spill.start.col) as usize,
+                    );
+                    self.results.insert((sheet, target), result(element));
+                }
+            }
+            None => {
+                self.results.insert((sheet, cell), result(value.scalar()));
+            }
+        }
+    }
+
+    /// Evaluate all formula cells in dependency order, returns cells in circular references.
+    fn calculate_all(&mut self) -> Vec<(String, CellRef)> {
+        let mut cycles = Vec::new();
+        let dependencies = self.dependencies();
+        for component in strongly_connected(&dependencies) {
+            let cyclic = component.len() > 1 || dependencies[component[0]].contains(&component[0]);
+            if !cyclic {
+                self.evaluate(component[0]);
+                continue;
+            }
+            for node in component {
+                let (sheet, cell) = self.nodes[node];
+                let spill = self.formula(node).spill.unwrap_or(CellRange::cell(cell));
+                for target in spill.cells() {
+                    self.results.insert((sheet, target), Value::Number(0.));
+                }
+                cycles.push((self.model.sheets[sheet].name.clone(), cell));
+            }
+        }
+        cycles.sort();
+        cycles
+    }
+
+    fn run(mut self) -> Calculation {
+        let cycles = self.calculate_all();
+        let mut values: BTreeMap<String, BTreeMap<CellRef, Value>> = BTreeMap::new();
+        for ((sheet, cell), value) in self.results {
+            values
+                .entry(self.model.sheets[sheet].name.clone())
+                .or_default()
+                .insert(cell, value);
+        }
+        Calculation { values, cycles }
+    }
+}
+
+/// Strongly connected components of a graph by Tarjan's algorithm, dependencies first.
+fn strongly_connected(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let mut index = vec![UNVISITED; edges.len()];
+    let mut low = vec![0; edges.len()];
+    let mut on_stack = vec![false; edges.len()];
+    let mut stack = Vec::new();
+    let mut counter = 0;
+    let mut components = Vec::new();
+    for root in 0..edges.len() {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        // explicit call stack of nodes and their next edge, long chains overflow recursion.
+        let mut calls = vec![(root, 0)];
+        index[root] = counter;
+        low[root] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some(&(node, edge)) = calls.last() {
+            if let Some(&next) = edges[node].get(edge) {
+                calls.last_mut().expect("a call").1 += 1;
+                if index[next] == UNVISITED {
+                    index[next] = counter;
+                    low[next] = counter;
+                    counter += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    calls.push((next, 0));
+                } else if on_stack[next] {
+                    low[node] = low[node].min(index[next]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                low[parent] = low[parent].min(low[node]);
+            }
+            if low[node] == index[node] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+#[test]
+fn calculate() {
+    let cell = |s: &str| s.parse::<CellRef>().unwrap();
+    let mut model = Model::new(DateSystem::default());
+    let sheet = model.add_sheet("Sheet1");
+    let data = model.add_sheet("My Data");
+    for (row, (name, price)) in [("apple", 3.), ("pear", 4.5), ("plum", 2.)]
+        .iter()
+        .enumerate()
+    {
+        model.set_value(data, CellRef::new(row as u32, 0), Value::from(*name));
+        model.set_value(data, CellRef::new(row as u32, 1), Value::from(*price));
+    }
+    let formulas = [
+        ("A1", "B1*2"),
+        ("B1", "SUM('My Data'!B:B)"),
+        ("A2", "VLOOKUP(\"pear\",'My Data'!A1:B3,2,FALSE)"),
+        (
+            "A3",
+            "INDEX('My Data'!A1:A3,MATCH(2,'My Data'!B1:B3,0))&\"!\"",
+        ),
+        ("A4", "IF(A1>=19,\"big\",\"small\")"),
+        ("A5", "1/0"),
+        ("A6", "IFERROR(A5,-1)"),
+        ("A7", "TEXT(DATE(2021,1,31)+1,\"yyyy-mm-dd\")"),
+        ("A8", "SUM(A9:A10)"),
+        ("A9", "A8+1"),
+        ("A10", "LEN(UPPER(\"abc\"))+COUNTIF('My Data'!B1:B3,\">2\")"),
+        ("A11", "SUM(1,"),
+        ("A12", "SUM(Prices)*rate"),
+        ("A13", "Missing+1"),
+        ("A14", "Loop"),
+        ("A15", "Total*2"),
+        ("A16", "A1+1"),
+    ];
+    model.define_name("Prices", None, "'My Data'!$B$1:$B$3");
+    model.define_name("Rate", None, "2");
+    model.define_name("Rate", Some(sheet), "0.5");
+    model.define_name("Loop", None, "Loop+1");
+    model.define_name("Total", None, "Sheet1!$A$16");
+    for (reference, text) in formulas {
+        model.set_formula(sheet, cell(reference), &Formula::new(text));
+    }
+    model.set_formula(
+        sheet,
+        cell("C1"),
+        &Formula {
+            kind: FormulaKind::Array,
+            range: "C1:C3".parse().ok(),
+            ..Formula::new("'My Data'!B1:B3*10")
+        },
+    );
+    let calculation = model.calculate();
+    let value = |reference: &str| calculation.value("Sheet1", cell(reference)).cloned();
+    assert_eq!(value("B1"), Some(Value::Number(9.5)));
+    assert_eq!(value("A1"), Some(Value::Number(19.)));
+    assert_eq!(value("A2"), Some(Value::Number(4.5)));
+    assert_eq!(value("A3"), Some(Value::from("plum!")));
+    assert_eq!(value("A4"), Some(Value::from("big")));
+    assert_eq!(value("A5"), Some(Value::Error(CellError::Div0)));
+    assert_eq!(value("A6"), Some(Value::Number(-1.)));
+    assert_eq!(value("A7"), Some(Value::from("2021-02-01")));
+    assert_eq!(value("A8"), Some(Value::Number(0.)));
+    assert_eq!(value("A9"), Some(Value::Number(0.)));
+    assert_eq!(value("A10"), Some(Value::Number(5.)));
+    assert_eq!(value("A11"), Some(Value::Error(CellError::Name)));
+    assert_eq!(value("A12"), Some(Value::Number(4.75)));
+    assert_eq!(value("A13"), Some(Value::Error(CellError::Name)));
+    assert_eq!(value("A14"), Some(Value::Error(CellError::Name)));
+    assert_eq!(value("A15"), Some(Value::Number(40.)));
+    assert_eq!(value("C3"), Some(Value::Number(20.)));
+    assert_eq!(
+        calculation.cycles,
+        vec![
+            ("Sheet1".to_string(), cell("A8")),
+            ("Sheet1".to_string(), cell("A9"))
+        ]
+    );
+}
@@ -0,0 +1,646 @@
+//! Formula tokenizer and parser of the Excel grammar.
+use std::fmt;
+
+use crate::document::spreadsheet::cell::CellError;
+use crate::document::spreadsheet::reference::{split_a1, CellRange};
+use crate::error::OoxmlError;
+
+fn invalid(formula: &str, reason: &str) -> OoxmlError {
+    OoxmlError::FormulaError(format!("{}: {}", reason, formula))
+}
+
+const ERRORS: [CellError; 10] = [
+    CellError::Null,
+    CellError::Div0,
+    CellError::Value,
+    CellError::Ref,
+    CellError::Name,
+    CellError::Num,
+    CellError::NA,
+    CellError::GettingData,
+    CellError::Spill,
+    CellError::Calc,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Plus,
+    Minus,
+    Percent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Concat,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `:` between references that are not plain ranges, like `A1:INDEX(B:B,3)`.
+    Range,
+}
+
+impl BinaryOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Pow => "^",
+            BinaryOp::Concat => "&",
+            BinaryOp::Eq => "=",
+            BinaryOp::Ne => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Range => ":",
+        }
+    }
+}
+
+/// Formula syntax tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Error(CellError),
+    /// A cell or range reference, with the sheet name for cross sheet references.
+    Reference {
+        sheet: Option<String>,
+        range: CellRange,
+    },
+    /// A defined name.
+    Name(String),
+    /// Array constant like `{1,2;3,4}`, by rows.
+    Array(Vec<Vec<Expr>>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// Function call with upper case name, `_xlfn.` prefixes are removed.
+    Call(String, Vec<Expr>),
+    /// Omitted argument like the second one of `IF(A1,,1)`.
+    Missing,
+}
+
+impl Expr {
+    /// Visit all references in the expression.
+    pub fn references(&self, visit: &mut impl FnMut(Option<&str>, &CellRange)) {
+        match self {
+            Expr::Reference { sheet, range } => visit(sheet.as_deref(), range),
+            Expr::Array(rows) => rows.iter().flatten().for_each(|e| e.references(visit)),
+            Expr::Unary(_, e) => e.references(visit),
+            Expr::Binary(_, a, b) => {
+                a.references(visit);
+                b.references(visit);
+            }
+            Expr::Call(_, args) => args.iter().for_each(|e| e.references(visit)),
+            _ => {}
+        }
+    }
+
+    /// Visit all defined names in the expression.
+    pub fn names(&self, visit: &mut impl FnMut(&str)) {
+        match self {
+            Expr::Name(name) => visit(name),
+            Expr::Array(rows) => rows.iter().flatten().for_each(|e| e.names(visit)),
+            Expr::Unary(_, e) => e.names(visit),
+            Expr::Binary(_, a, b) => {
+                a.names(visit);
+                b.names(visit);
+            }
+            Expr::Call(_, args) => args.iter().for_each(|e| e.names(visit)),
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(v) => write!(f, "{}", v),
+            Expr::Text(s) => write!(f, "\"{}\"", s.replace('"', "\"\"")),
+            Expr::Bool(v) => write!(f, "{}", if *v { "TRUE" } else { "FALSE" }),
+            Expr::Error(e) => write!(f, "{}", e),
+            Expr::Reference { sheet, range } => {
+                if let Some(sheet) = sheet {
+                    write!(
+                        f,
+                        "{}!",
+                        crate::document::spreadsheet::reference::quote_sheet_name(sheet)
+                    )?;
+                }
+                write!(f, "{}", range)
+            }
+            Expr::Name(name) => write!(f, "{}", name),
+            Expr::Array(rows) => {
+                let rows: Vec<String> = rows
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|e| e.to_string())
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    })
+                    .collect();
+                write!(f, "{{{}}}", rows.join(";"))
+            }
+            Expr::Unary(UnaryOp::Percent, e) => write!(f, "{}%", e),
+            Expr::Unary(UnaryOp::Plus, e) => write!(f, "+{}", e),
+            Expr::Unary(UnaryOp::Minus, e) => write!(f, "-{}", e),
+            Expr::Binary(op, a, b) => write!(f, "({}{}{})", a, op.as_str(), b),
+            Expr::Call(name, args) => {
+                let args: Vec<String> = args.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}({})", name, args.join(","))
+            }
+            Expr::Missing => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Error(CellError),
+    Reference(Option<String>, CellRange),
+    Name(String),
+    Function(String),
+    Op(&'static str),
+    Open,
+    Close,
+    Comma,
+    Semicolon,
+    OpenBrace,
+    CloseBrace,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '$' || c == '\\'
+}
+
+/// Parse a reference word or a `word:word` range, `None` if it's not a reference.
+fn parse_range(first: &str, second: Option<&str>) -> Option<CellRange> {
+    let (a_col, a_row) = split_a1(first)?;
+    let second = match second {
+        Some(second) => second,
+        None if a_col.is_some() && a_row.is_some() => return first.parse().ok(),
+        None => return None,
+    };
+    let (b_col, b_row) = split_a1(second)?;
+    if a_col.is_some() != b_col.is_some() || a_row.is_some() != b_row.is_some() {
+        return None;
+    }
+    format!("{}:{}", first, second).parse().ok()
+}
+
+fn tokenize(formula: &str) -> Result<Vec<Token>, OoxmlError> {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let word_end = |start: usize| {
+        let mut end = start;
+        while end < chars.len() && is_word_char(chars[end]) {
+            end += 1;
+        }
+        end
+    };
+    // a quoted string with doubled quotes as escapes, returns the text and the end index.
+    let quoted = |start: usize, quote: char| -> Option<(String, usize)> {
+        let mut text = String::new();
+        let mut i = start + 1;
+        while i < chars.len() {
+            if chars[i] == quote {
+                if chars.get(i + 1) == Some(&quote) {
+                    text.push(quote);
+                    i += 2;
+                    continue;
+                }
+                return Some((text, i + 1));
+            }
+            text.push(chars[i]);
+            i += 1;
+        }
+        None
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' | '\r' | '\n' => i += 1,
+            '"' => {
+                let (text, end) =
+                    quoted(i, '"').ok_or_else(|| invalid(formula, "unclosed string"))?;
+                tokens.push(Token::Text(text));
+                i = end;
+            }
+            '#' => {
+                let rest: String = chars[i..].iter().collect();
+                let error = ERRORS
+                    .iter()
+                    .find(|e| rest.to_ascii_uppercase().starts_with(e.as_str()))
+                    .ok_or_else(|| invalid(formula, "unknown error literal"))?;
+                tokens.push(Token::Error(*error));
+                i += error.as_str().chars().count();
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            ';' => {
+                tokens.push(Token::Semicolon);
+                i += 1;
+            }
+            '{' => {
+                tokens.push(Token::OpenBrace);
+                i += 1;
+            }
+            '}' => {
+                tokens.push(Token::CloseBrace);
+                i += 1;
+            }
+            '<' | '>' => {
+                let op = match (c, chars.get(i + 1)) {
+                    ('<', Some('=')) => "<=",
+                    ('<', Some('>')) => "<>",
+                    ('>', Some('=')) => ">=",
+                    ('<', _) => "<",
+                    _ => ">",
+                };
+                tokens.push(Token::Op(op));
+                i += op.len();
+            }
+            '+' | '-' | '*' | '/' | '^' | '&' | '=' | '%' | ':' => {
+                let op = match c {
+                    '+' => "+",
+                    '-' => "-",
+                    '*' => "*",
+                    '/' => "/",
+                    '^' => "^",
+                    '&' => "&",
+                    '=' => "=",
+                    '%' => "%",
+                    _ => ":",
+                };
+                tokens.push(Token::Op(op));
+                i += 1;
+            }
+            '\'' => {
+                let (sheet, end) =
+                    quoted(i, '\'').ok_or_else(|| invalid(formula, "unclosed sheet name"))?;
+                if chars.get(end) != Some(&'!') {
+                    return Err(invalid(formula, "sheet name without reference"));
+                }
+                i = reference(&chars, end + 1, Some(sheet), &mut tokens, formula)?;
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                // row ranges like `1:3` start with digits.
+                let end = word_end(i);
+                if chars.get(end) == Some(&':') {
+                    let second_end = word_end(end + 1);
+                    let first: String = chars[i..end].iter().collect();
+                    let second: String = chars[end + 1..second_end].iter().collect();
+                    if let Some(range) = parse_range(&first, Some(&second)) {
+                        tokens.push(Token::Reference(None, range));
+                        i = second_end;
+                        continue;
+                    }
+                }
+                let mut end = i;
+                while end < chars.len() && (chars[end].is_ascii_digit() || chars[end] == '.') {
+                    end += 1;
+                }
+                if matches!(chars.get(end), Some('e') | Some('E')) {
+                    let mut exp = end + 1;
+                    if matches!(chars.get(exp), Some('+') | Some('-')) {
+                        exp += 1;
+                    }
+                    if chars.get(exp).is_some_and(|c| c.is_ascii_digit()) {
+                        end = exp;
+                        while end < chars.len() && chars[end].is_ascii_digit() {
+                            end += 1;
+                        }
+                    }
+                }
+                let text: String = chars[i..end].iter().collect();
+                let number = text
+                    .parse()
+                    .map_err(|_| invalid(formula, "invalid number"))?;
+                tokens.push(Token::Number(number));
+                i = end;
+            }
+            c if is_word_char(c) => {
+                let end = word_end(i);
+                let word: String = chars[i..end].iter().collect();
+                match chars.get(end) {
+                    Some('!') => i = reference(&chars, end + 1, Some(word), &mut tokens, formula)?,
+                    Some('(') => {
+                        let name = word.to_ascii_uppercase();
+                        let name = name
+                            .strip_prefix("_XLFN.")
+                            .or_else(|| name.strip_prefix("_XLWS."))
+                            .unwrap_or(&name)
+                            .to_string();
+                        tokens.push(Token::Function(name));
+                        i = end;
+                    }
+                    _ => i = reference(&chars, i, None, &mut tokens, formula)?,
+                }
+            }
+            c => return Err(invalid(formula, &format!("unexpected character `{}`", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Tokenize a reference, a name or a boolean at `start`, returns the end index.
+fn reference(
+    chars: &[char],
+    start: usize,
+    sheet: Option<String>,
+    tokens: &mut Vec<Token>,
+    formula: &str,
+) -> Result<usize, OoxmlError> {
+    let word_end = |start: usize| {
+        let mut end = start;
+        while end < chars.len() && is_word_char(chars[end]) {
+            end += 1;
+        }
+        end
+    };
+    let end = word_end(start);
+    let word: String = chars[start..end].iter().collect();
+    if chars.get(end) == Some(&':') {
+        let second_end = word_end(end + 1);
+        let second: String = chars[end + 1..second_end].iter().collect();
+        if let Some(range) = parse_range(&word, Some(&second)) {
+            tokens.push(Token::Reference(sheet, range));
+            return Ok(second_end);
+        }
+    }
+    if let Some(range) = parse_range(&word, None) {
+        tokens.push(Token::Reference(sheet, range));
+        return Ok(end);
+    }
+    if sheet.is_some() {
+        if word.eq_ignore_ascii_case("#REF") || chars.get(start) == Some(&'#') {
+            tokens.push(Token::Error(CellError::Ref));
+            return Ok(end);
+        }
+        return Err(invalid(formula, "invalid sheet reference"));
+    }
+    match word.to_ascii_uppercase().as_str() {
+        "TRUE" => tokens.push(Token::Bool(true)),
+        "FALSE" => tokens.push(Token::Bool(false)),
+        "" => return Err(invalid(formula, "empty name")),
+        _ => tokens.push(Token::Name(word)),
+    }
+    Ok(end)
+}
+
+struct Parser<'a> {
+    formula: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn error(&self, reason: &str) -> OoxmlError {
+        invalid(self.formula, reason)
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[&'static str],
+        next: fn(&mut Self) -> Result<Expr, OoxmlError>,
+    ) -> Result<Expr, OoxmlError> {
+        let mut left = next(self)?;
+        while let Some(op) = self.eat_op(ops) {
+            let right = next(self)?;
+            let op = match op {
+                "+" => BinaryOp::Add,
+                "-" => BinaryOp::Sub,
+                "*" => BinaryOp::Mul,
+                "/" => BinaryOp::Div,
+                "^" => BinaryOp::Pow,
+                "&" => BinaryOp::Concat,
+                "=" => BinaryOp::Eq,
+                "<>" => BinaryOp::Ne,
+                "<" => BinaryOp::Lt,
+                "<=" => BinaryOp::Le,
+                ">" => BinaryOp::Gt,
+                ">=" => BinaryOp::Ge,
+                _ => BinaryOp::Range,
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, OoxmlError> {
+        self.binary(&["=", "<>", "<", "<=", ">", ">="], Self::concat)
+    }
+
+    fn concat(&mut self) -> Result<Expr, OoxmlError> {
+        self.binary(&["&"], Self::additive)
+    }
+
+    fn additive(&mut self) -> Result<Expr, OoxmlError> {
+        self.binary(&["+", "-"], Self::multiplicative)
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, OoxmlError> {
+        self.binary(&["*", "/"], Self::power)
+    }
+
+    fn power(&mut self) -> Result<Expr, OoxmlError> {
+        self.binary(&["^"], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, OoxmlError> {
+        match self.eat_op(&["-", "+"]) {
+            Some("-") => Ok(Expr::Unary(UnaryOp::Minus, Box::new(self.unary()?))),
+            Some(_) => Ok(Expr::Unary(UnaryOp::Plus, Box::new(self.unary()?))),
+            None => self.percent(),
+        }
+    }
+
+    fn percent(&mut self) -> Result<Expr, OoxmlError> {
+        let mut expr = self.range()?;
+        while self.eat_op(&["%"]).is_some() {
+            expr = Expr::Unary(UnaryOp::Percent, Box::new(expr));
+        }
+        Ok(expr)
+    }
+
+    fn range(&mut self) -> Result<Expr, OoxmlError> {
+        let mut left = self.primary()?;
+        while self.eat_op(&[":"]).is_some() {
+            let right = self.primary()?;
+            left = match (left, right) {
+                (
+                    Expr::Reference {
+                        sheet,
+                        range: first,
+                    },
+                    Expr::Reference {
+                        sheet: None,
+                        range: second,
+                    },
+                ) => Expr::Reference {
+                    sheet,
+                    range: CellRange::new(first.start, second.end),
+                },
+                (left, right) => Expr::Binary(BinaryOp::Range, Box::new(left), Box::new(right)),
+            };
+        }
+        Ok(left)
+    }
+
+    fn arguments(&mut self, close: Token) -> Result<Vec<Expr>, OoxmlError> {
+        let mut args = Vec::new();
+        if self.peek() == Some(&close) {
+            self.pos += 1;
+            return Ok(args);
+        }
+        loop {
+            let arg = match self.peek() {
+                Some(Token::Comma) => Expr::Missing,
+                Some(token) if *token == close => Expr::Missing,
+                _ => self.comparison()?,
+            };
+            args.push(arg);
+            match self.next() {
+                Some(Token::Comma) => continue,
+                Some(token) if token == close => return Ok(args),
+                _ => return Err(self.error("expect `,` or `)`")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Expr, OoxmlError> {
+        let mut rows = vec![Vec::new()];
+        loop {
+            let negative = self.eat_op(&["-"]).is_some();
+            let item = match (self.next(), negative) {
+                (Some(Token::Number(v)), true) => Expr::Number(-v),
+                (Some(Token::Number(v)), false) => Expr::Number(v),
+                (Some(Token::Text(s)), false) => Expr::Text(s),
+                (Some(Token::Bool(v)), false) => Expr::Bool(v),
+                (Some(Token::Error(e)), false) => Expr::Error(e),
+                _ => return Err(self.error("invalid array constant")),
+            };
+            rows.last_mut().expect("a row").push(item);
+            match self.next() {
+                Some(Token::Comma) => {}
+                Some(Token::Semicolon) => rows.push(Vec::new()),
+                Some(Token::CloseBrace) => return Ok(Expr::Array(rows)),
+                _ => return Err(self.error("unclosed array constant")),
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, OoxmlError> {
+        let expr = match self.next() {
+            Some(Token::Number(v)) => Expr::Number(v),
+            Some(Token::Text(s)) => Expr::Text(s),
+            Some(Token::Bool(v)) => Expr::Bool(v),
+            Some(Token::Error(e)) => Expr::Error(e),
+            Some(Token::Reference(sheet, range)) => Expr::Reference { sheet, range },
+            Some(Token::Name(name)) => Expr::Name(name),
+            Some(Token::Function(name)) => {
+                self.next();
+                Expr::Call(name, self.arguments(Token::Close)?)
+            }
+            Some(Token::Open) => {
+                let expr = self.comparison()?;
+                if self.next() != Some(Token::Close) {
+                    return Err(self.error("expect `)`"));
+                }
+                expr
+            }
+            Some(Token::OpenBrace) => self.array()?,
+            _ => return Err(self.error("unexpected end or token")),
+        };
+        Ok(expr)
+    }
+}
+
+/// Parse a formula, with or without the leading `=`.
+pub fn parse(formula: &str) -> Result<Expr, OoxmlError> {
+    let text = formula.trim();
+    let text = text.strip_prefix('=').unwrap_or(text);
+    let mut parser = Parser {
+        formula,
+        tokens: tokenize(text)?,
+        pos: 0,
+    };
+    let expr = parser.comparison()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(parser.error("unexpected trailing tokens"));
+    }
+    Ok(expr)
+}
+
+#[test]
+fn parse_formulas() {
+    let cases = [
+        ("=1+2*3", "(1+(2*3))"),
+        ("-2^2", "(-2^2)"),
+        ("A1&\" \"&B1", "((A1&\" \")&B1)"),
+        (
+            "SUM(A1:B2,Sheet2!C3,'My Sheet'!D:D)",
+            "SUM(A1:B2,Sheet2!C3,'My Sheet'!D:D)",
+        ),
+        ("IF(A1>=10%,\"big\",)", "IF((A1>=10%),\"big\",)"),
+        ("_xlfn.CONCAT(1,TRUE,#N/A)", "CONCAT(1,TRUE,#N/A)"),
+        ("{1,2;3,-4}", "{1,2;3,-4}"),
+        ("1.5E+3<>$A$1", "(1500<>$A$1)"),
+        ("SUM(1:3)", "SUM(1:3)"),
+        ("A1:INDEX(B:B,2)", "(A1:INDEX(B:B,2))"),
+        ("TaxRate*2", "(TaxRate*2)"),
+    ];
+    for (formula, expected) in cases {
+        assert_eq!(parse(formula).unwrap().to_string(), expected, "{}", formula);
+    }
+    assert!(parse("SUM(1,").is_err());
+    assert!(parse("\"open").is_err());
+    assert!(parse("1+").is_err());
+}
@@ -1,16 +1,99 @@
 use std::fmt::Display;
+use std::str::FromStr;
 
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
 use serde::{Deserialize, Serialize};
 
+use super::number_format::DateTimeKind;
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum CellType {
     Empty,
     Raw,
     Number,
     StyledNumber(usize),
+    /// ISO 8601 date, strict only.
+    Date,
+    StyledDate(usize),
     Shared(usize),
     Styled(usize),
+    /// `t="b"`, `0` or `1`.
+    Bool,
+    /// `t="e"`, an error like `#N/A`.
+    Error,
+    /// `t="str"`, string result of a formula.
+    FormulaString,
+    /// `t="inlineStr"`, string in the `<is>` element.
+    InlineString,
+}
+
+/// Cell error values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum CellError {
+    /// `#NULL!`
+    Null,
+    /// `#DIV/0!`
+    Div0,
+    /// `#VALUE!`
+    Value,
+    /// `#REF!`
+    Ref,
+    /// `#NAME?`
+    Name,
+    /// `#NUM!`
+    Num,
+    /// `#N/A`
+    NA,
+    /// `#GETTING_DATA`
+    GettingData,
+    /// `#SPILL!`
+    Spill,
+    /// `#CALC!`
+    Calc,
+}
+
+impl CellError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CellError::Null => "#NULL!",
+            CellError::Div0 => "#DIV/0!",
+            CellError::Value => "#VALUE!",
+            CellError::Ref => "#REF!",
+            CellError::Name => "#NAME?",
+            CellError::Num => "#NUM!",
+            CellError::NA => "#N/A",
+            CellError::GettingData => "#GETTING_DATA",
+            CellError::Spill => "#SPILL!",
+            CellError::Calc => "#CALC!",
+        }
+    }
+}
+
+impl Display for CellError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CellError {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = match s.trim().to_ascii_uppercase().as_str() {
+            "#NULL!" => CellError::Null,
+            "#DIV/0!" => CellError::Div0,
+            "#VALUE!" => CellError::Value,
+            "#REF!" => CellError::Ref,
+            "#NAME?" => CellError::Name,
+            "#NUM!" => CellError::Num,
+            "#N/A" => CellError::NA,
+            "#GETTING_DATA" => CellError::GettingData,
+            "#SPILL!" => CellError::Spill,
+            "#CALC!" => CellError::Calc,
+            _ => return Err(()),
+        };
+        Ok(error)
+    }
 }
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum CellValue {
@@ -22,7 +105,85 @@ pub enum CellValue {
     String(String),
     //DateTime(DateTime<Local>),
     DateTime(NaiveDateTime, String),
+    /// Date of a date only number format.
+    Date(NaiveDate),
+    /// Time of a time only number format.
+    Time(NaiveTime),
+    /// Elapsed time of formats like `[h]:mm:ss`.
+    Duration(Duration),
     Raw(String),
+    Error(CellError),
+}
+
+impl CellValue {
+    /// Number value of a raw number string, integers are `Int`.
+    ///
+    /// Strings that are not numbers are kept as `String`.
+    pub fn from_number_str(raw: &str) -> Self {
+        let raw = raw.trim();
+        if let Ok(v) = raw.parse::<i64>() {
+            CellValue::Int(v)
+        } else if let Ok(v) = raw.parse::<f64>() {
+            CellValue::Double(v)
+        } else {
+            CellValue::String(raw.to_string())
+        }
+    }
+
+    /// Boolean value of `t="b"` cells.
+    pub fn from_bool_str(raw: &str) -> Self {
+        match raw.trim() {
+            "1" => CellValue::Bool(true),
+            "0" => CellValue::Bool(false),
+            s if s.eq_ignore_ascii_case("true") => CellValue::Bool(true),
+            s if s.eq_ignore_ascii_case("false") => CellValue::Bool(false),
+            s => CellValue::String(s.to_string()),
+        }
+    }
+
+    /// Error value of `t="e"` cells, unknown errors are kept as `String`.
+    pub fn from_error_str(raw: &str) -> Self {
+        match raw.parse() {
+            Ok(error) => CellValue::Error(error),
+            Err(_) => CellValue::String(raw.to_string()),
+        }
+    }
+
+    /// Date or time value of a serial as the number format implies.
+    ///
+    /// Date only formats give `Date` for whole serials and time only formats give `Time` for
+    /// serials in a day, others are `DateTime`. Serials out of the date system are numbers.
+    pub fn from_serial(serial: f64, kind: DateTimeKind, date_system: DateSystem) -> Self {
+        let value = match kind {
+            DateTimeKind::Duration => serial_to_duration(serial).map(CellValue::Duration),
+            DateTimeKind::Time if (0. ..1.).contains(&serial) => date_system
+                .serial_to_datetime(serial)
+                .map(|datetime| CellValue::Time(datetime.time())),
+            DateTimeKind::Date if serial.fract() == 0. => date_system
+                .serial_to_datetime(serial)
+                .map(|datetime| CellValue::Date(datetime.date())),
+            _ => date_system
+                .serial_to_datetime(serial)
+                .map(ToCellValue::to_cell_value),
+        };
+        value.unwrap_or(CellValue::Double(serial))
+    }
+
+    /// Serial number of numbers, booleans and date or time values to write into cells.
+    pub fn to_serial(&self, date_system: DateSystem) -> Option<f64> {
+        let serial = match self {
+            CellValue::Bool(v) => *v as u8 as f64,
+            CellValue::Int(v) => *v as f64,
+            CellValue::Byte(v) => *v as f64,
+            CellValue::Double(v) => *v,
+            CellValue::DateTime(datetime, _) => date_system.datetime_to_serial(datetime),
+            CellValue::Date(date) => date_system.date_to_serial(date),
+            CellValue::Time(time) => time_to_serial(time),
+            CellValue::Duration(duration) => duration_to_serial(duration),
+            _ => return None,
+        };
+        Some(serial)
+    }
 }
 
 impl Display for CellValue {
@@ -31,10 +192,28 @@ impl Display for CellValue {
             CellValue::Null => write!(f, "{}", ""),
             CellValue::String(v) => write!(f, "{}", v),
             CellValue::Raw(v) => write!(f, "{}", v),
-            CellValue::Bool(_v) => panic!("unsupported cell type: bool"),
+            CellValue::Bool(true) => write!(f, "TRUE"),
+            CellValue::Bool(false) => write!(f, "FALSE"),
+            CellValue::Int(v) => write!(f, "{}", v),
+            CellValue::Byte(v) => write!(f, "{}", v),
             CellValue::Double(v) => write!(f, "{}", v),
             CellValue::DateTime(datetime, format) => write!(f, "{}", datetime.format(&format)),
-            _ => unimplemented!(),
+            CellValue::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+            CellValue::Time(time) => write!(f, "{}", time.format("%H:%M:%S%.f")),
+            CellValue::Duration(duration) => {
+                let seconds = duration.num_seconds();
+                let sign = if seconds < 0 { "-" } else { "" };
+                let seconds = seconds.abs();
+                write!(
+                    f,
+                    "{}{}:{:02}:{:02}",
+                    sign,
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60
+                )
+            }
+            CellValue::Error(error) => write!(f, "{}", error),
         }
     }
 }
@@ -82,3 +261,199 @@ impl_to_cell_value!(bool, Bool);
 impl_to_cell_value!(f32, Double);
 impl_to_cell_value!(f64, Double);
 impl_to_cell_value!(String, String);
+
+impl ToCellValue for NaiveDateTime {
+    fn to_cell_value(self) -> CellValue {
+        CellValue::DateTime(self, "%Y-%m-%d %H:%M:%S".into())
+    }
+}
+impl ToCellValue for NaiveDate {
+    fn to_cell_value(self) -> CellValue {
+        CellValue::Date(self)
+    }
+}
+impl ToCellValue for NaiveTime {
+    fn to_cell_value(self) -> CellValue {
+        CellValue::Time(self)
+    }
+}
+impl ToCellValue for Duration {
+    fn to_cell_value(self) -> CellValue {
+        CellValue::Duration(self)
+    }
+}
+
+/// Date system of a workbook, see `workbookPr@date1904`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateSystem {
+    /// Serial `1` is 1900-01-01, with the Lotus 1-2-3 bug that 1900 is a leap year.
+    #[default]
+    Date1900,
+    /// Serial `0` is 1904-01-01.
+    Date1904,
+}
+
+impl DateSystem {
+    pub fn from_date1904(date1904: bool) -> Self {
+        if date1904 {
+            DateSystem::Date1904
+        } else {
+            DateSystem::Date1900
+        }
+    }
+
+    /// Day zero of serials, in 1900 system it's for serials after the fictional 1900-02-29.
+    fn epoch(&self) -> NaiveDateTime {
+        let date = match self {
+            DateSystem::Date1900 => NaiveDate::from_ymd_opt(1899, 12, 30),
+            DateSystem::Date1904 => NaiveDate::from_ymd_opt(1904, 1, 1),
+        };
+        date.and_then(|d| d.and_hms_opt(0, 0, 0))
+            .expect("valid epoch")
+    }
+
+    /// Convert a serial date number to datetime, rounded to milliseconds.
+    ///
+    /// Negative serials and the fictional 1900-02-29 (serial `60`) have no datetime.
+    pub fn serial_to_datetime(&self, serial: f64) -> Option<NaiveDateTime> {
+        if !serial.is_finite() || serial < 0. {
+            return None;
+        }
+        let mut millis = (serial * 86_400_000.).round() as i64;
+        if *self == DateSystem::Date1900 && millis < 61 * 86_400_000 {
+            if millis >= 60 * 86_400_000 {
+                return None;
+            }
+            // serials before the leap day count from 1899-12-31.
+            millis += 86_400_000;
+        }
+        self.epoch()
+            .checked_add_signed(chrono::Duration::milliseconds(millis))
+    }
+
+    /// Convert a datetime to serial date number.
+    pub fn datetime_to_serial(&self, datetime: &NaiveDateTime) -> f64 {
+        let mut millis = (*datetime - self.epoch()).num_milliseconds();
+        if *self == DateSystem::Date1900 && millis < 61 * 86_400_000 {
+            millis -= 86_400_000;
+        }
+        millis as f64 / 86_400_000.
+    }
+
+    /// Convert a date to serial date number.
+    pub fn date_to_serial(&self, date: &NaiveDate) -> f64 {
+        self.datetime_to_serial(&date.and_time(NaiveTime::MIN))
+    }
+}
+
+/// Convert a time to the fraction of a day.
+pub fn time_to_serial(time: &NaiveTime) -> f64 {
+    (*time - NaiveTime::MIN).num_milliseconds() as f64 / 86_400_000.
+}
+
+/// Convert a duration to days.
+pub fn duration_to_serial(duration: &Duration) -> f64 {
+    duration.num_milliseconds() as f64 / 86_400_000.
+}
+
+/// Convert a serial to duration in days, rounded to milliseconds.
+pub fn serial_to_duration(serial: f64) -> Option<Duration> {
+    if !serial.is_finite() {
+        return None;
+    }
+    Some(Duration::milliseconds((serial * 86_400_000.).round() as i64))
+}
+
+/// Parse ISO 8601 date or datetime of strict `t="d"` cells, eg. `2021-03-04T12:30:00`.
+pub fn parse_iso_datetime(s: &str) -> Option<NaiveDateTime> {
+    let s = s.trim().trim_end_matches('Z');
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Some(datetime);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+    NaiveTime::parse_from_str(s, "%H:%M:%S%.f")
+        .ok()
+        .map(|time| {
+            DateSystem::Date1900
+                .serial_to_datetime(0.)
+                .expect("day zero")
+                .date()
+                .and_time(time)
+        })
+}
+
+/// Format datetime in ISO 8601 for strict `t="d"` cells.
+pub fn format_iso_datetime(datetime: &NaiveDateTime) -> String {
+    datetime.format("%Y-%m-%dT%H:%M:%S%.f").to_string()
+}
+
+#[test]
+fn serial_datetime() {
+    let system = DateSystem::Date1900;
+    let datetime = system.serial_to_datetime(44259.5).unwrap();
+    assert_eq!(format_iso_datetime(&datetime), "2021-03-04T12:00:00");
+    assert_eq!(system.datetime_to_serial(&datetime), 44259.5);
+    assert_eq!(parse_iso_datetime("2021-03-04T12:00:00Z"), Some(datetime));
+    assert_eq!(
+        parse_iso_datetime("2021-03-04").map(|d| system.datetime_to_serial(&d)),
+        Some(44259.)
+    );
+}
+
+#[test]
+fn date_systems() {
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    let system = DateSystem::Date1900;
+    let serial = |serial| system.serial_to_datetime(serial).map(|d| d.date());
+    assert_eq!(serial(1.), Some(date(1900, 1, 1)));
+    assert_eq!(serial(59.), Some(date(1900, 2, 28)));
+    assert_eq!(serial(60.), None);
+    assert_eq!(serial(61.), Some(date(1900, 3, 1)));
+    assert_eq!(serial(-1.), None);
+    assert_eq!(system.date_to_serial(&date(1900, 1, 1)), 1.);
+    assert_eq!(system.date_to_serial(&date(1900, 3, 1)), 61.);
+
+    let system = DateSystem::Date1904;
+    assert_eq!(
+        system.serial_to_datetime(0.).map(|d| d.date()),
+        Some(date(1904, 1, 1))
+    );
+    assert_eq!(system.date_to_serial(&date(2021, 3, 4)), 44259. - 1462.);
+
+    let time = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+    assert_eq!(
+        CellValue::from_serial(0.75, DateTimeKind::Time, DateSystem::Date1900),
+        CellValue::Time(time)
+    );
+    assert_eq!(
+        CellValue::from_serial(44259., DateTimeKind::Date, DateSystem::Date1900),
+        CellValue::Date(date(2021, 3, 4))
+    );
+    let duration = CellValue::from_serial(1.5, DateTimeKind::Duration, DateSystem::Date1900);
+    assert_eq!(duration, CellValue::Duration(Duration::hours(36)));
+    assert_eq!(duration.to_string(), "36:00:00");
+    assert_eq!(duration.to_serial(DateSystem::Date1900), Some(1.5));
+    assert_eq!(
+        CellValue::from(date(2021, 3, 4)).to_serial(DateSystem::Date1904),
+        Some(44259. - 1462.)
+    );
+}
+
+#[test]
+fn cell_value_display() {
+    assert_eq!(CellValue::from_number_str("42"), CellValue::Int(42));
+    assert_eq!(
+        CellValue::from_number_str("1.5E-3"),
+        CellValue::Double(0.0015)
+    );
+    assert_eq!(CellValue::from_bool_str("1").to_string(), "TRUE");
+    assert_eq!(CellValue::from_bool_str("0").to_string(), "FALSE");
+    assert_eq!(
+        CellValue::from_error_str("#DIV/0!"),
+        CellValue::Error(CellError::Div0)
+    );
+    assert_eq!(CellValue::from_error_str("#N/A").to_string(), "#N/A");
+    assert_eq!(CellValue::Byte(7).to_string(), "7");
+}
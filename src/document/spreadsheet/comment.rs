@@ -0,0 +1,476 @@
+//! Cell comments, the notes of Excel, see ECMA-376 Part 1, 18.7.
+//!
+//! Comments of a worksheet live in a comments part like `xl/comments1.xml`, the note boxes are
+//! shapes of the worksheet legacy VML drawing like `xl/drawings/vmlDrawing1.vml`.
+use std::fmt;
+use std::io::prelude::*;
+
+use crate::error::OoxmlError;
+use crate::packaging::element::*;
+
+use super::reference::CellRef;
+use super::shared_string::{read_rich_text, write_rich_text, SharedString, TextRun};
+
+const SPREADSHEET_NAMESPACE: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const MARKUP_COMPATIBILITY_NAMESPACE: &str =
+    "http://schemas.openxmlformats.org/markup-compatibility/2006";
+const REVISION_NAMESPACE: &str = "http://schemas.microsoft.com/office/spreadsheetml/2014/revision";
+
+/// A cell comment with its author.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub reference: CellRef,
+    pub author: String,
+    /// Comment text, plain or rich text runs.
+    pub text: SharedString,
+}
+
+impl Comment {
+    pub fn new(reference: CellRef, author: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            reference,
+            author: author.into(),
+            text: SharedString::new(text),
+        }
+    }
+
+    /// A comment of rich text runs.
+    pub fn from_runs(reference: CellRef, author: impl Into<String>, runs: Vec<TextRun>) -> Self {
+        Self {
+            reference,
+            author: author.into(),
+            text: SharedString::from_runs(runs),
+        }
+    }
+
+    /// Plain text of the comment.
+    pub fn text(&self) -> &str {
+        self.text.as_str()
+    }
+}
+
+/// A `<comment>` element.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CommentItem {
+    pub reference: String,
+    pub author_id: usize,
+    /// Revision id `xr:uid`, the id of the thread for threaded comments.
+    pub uid: Option<String>,
+    pub shape_id: Option<String>,
+    pub text: SharedString,
+}
+
+/// Comments part of a worksheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommentsPart {
+    /// Attributes of the root element, namespace declarations included.
+    attributes: Vec<(String, String)>,
+    pub authors: Vec<String>,
+    pub comments: Vec<CommentItem>,
+}
+
+impl Default for CommentsPart {
+    fn default() -> Self {
+        Self {
+            attributes: vec![("xmlns".into(), SPREADSHEET_NAMESPACE.into())],
+            authors: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+}
+
+impl CommentsPart {
+    /// Comments with resolved authors, comments of invalid references are skipped.
+    pub fn to_comments(&self) -> Vec<Comment> {
+        self.comments
+            .iter()
+            .filter_map(|item| {
+                Some(Comment {
+                    reference: item.reference.parse().ok()?,
+                    author: self
+                        .authors
+                        .get(item.author_id)
+                        .cloned()
+                        .unwrap_or_default(),
+                    text: item.text.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Index of an author, added if not exists.
+    pub fn author_id(&mut self, author: &str) -> usize {
+        match self.authors.iter().position(|a| a == author) {
+            Some(idx) => idx,
+            None => {
+                self.authors.push(author.to_string());
+                self.authors.len() - 1
+            }
+        }
+    }
+
+    /// Set the comment of a cell, an existing one of the cell is replaced.
+    pub fn set_comment(&mut self, comment: Comment) {
+        self.set_item(
+            &comment.author,
+            CommentItem {
+                reference: comment.reference.to_string(),
+                text: comment.text,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Set a comment item of the author, an existing one of the cell is replaced.
+    pub(crate) fn set_item(&mut self, author: &str, mut item: CommentItem) {
+        item.author_id = self.author_id(author);
+        if item.uid.is_some() {
+            self.ignorable_namespace("xr", REVISION_NAMESPACE);
+        }
+        self.insert(item);
+    }
+
+    /// Declare an ignorable namespace of markup compatibility like the `xr` of `xr:uid`.
+    fn ignorable_namespace(&mut self, prefix: &str, uri: &str) {
+        let mut declare = |key: String, value: &str| {
+            if !self.attributes.iter().any(|(k, _)| *k == key) {
+                self.attributes.push((key, value.to_string()));
+            }
+        };
+        declare("xmlns:mc".into(), MARKUP_COMPATIBILITY_NAMESPACE);
+        declare(format!("xmlns:{}", prefix), uri);
+        match self
+            .attributes
+            .iter_mut()
+            .find(|(k, _)| k == "mc:Ignorable")
+        {
+            Some((_, value)) => {
+                if !value.split_whitespace().any(|p| p == prefix) {
+                    value.push(' ');
+                    value.push_str(prefix);
+                }
+            }
+            None => self
+                .attributes
+                .push(("mc:Ignorable".into(), prefix.to_string())),
+        }
+    }
+
+    /// Insert a comment item in the row-major order of cells, replacing the one of the same cell.
+    fn insert(&mut self, item: CommentItem) {
+        let key = |item: &CommentItem| {
+            item.reference
+                .parse::<CellRef>()
+                .map(|r| (r.row, r.col))
+                .unwrap_or((u32::MAX, u32::MAX))
+        };
+        let cell = key(&item);
+        match self
+            .comments
+            .iter()
+            .position(|c| c.reference == item.reference)
+        {
+            Some(idx) => self.comments[idx] = item,
+            None => {
+                let idx = self.comments.partition_point(|c| key(c) < cell);
+                self.comments.insert(idx, item);
+            }
+        }
+    }
+
+    /// Write to an writer
+    pub fn write<W: std::io::Write>(&self, writer: W) -> Result<(), OoxmlError> {
+        use quick_xml::events::*;
+
+        let mut xml = quick_xml::Writer::new(writer);
+        xml.write_event(Event::Decl(BytesDecl::new(
+            b"1.0",
+            Some(b"UTF-8"),
+            Some(b"yes"),
+        )))?;
+        let mut elem = BytesStart::borrowed_name(b"comments");
+        for (key, value) in &self.attributes {
+            elem.push_attribute((key.as_str(), value.as_str()));
+        }
+        xml.write_event(Event::Start(elem))?;
+        xml.write_event(Event::Start(BytesStart::borrowed_name(b"authors")))?;
+        for author in &self.authors {
+            xml.write_event(Event::Start(BytesStart::borrowed_name(b"author")))?;
+            xml.write_event(Event::Text(BytesText::from_plain_str(author)))?;
+            xml.write_event(Event::End(BytesEnd::borrowed(b"author")))?;
+        }
+        xml.write_event(Event::End(BytesEnd::borrowed(b"authors")))?;
+        xml.write_event(Event::Start(BytesStart::borrowed_name(b"commentList")))?;
+        for item in &self.comments {
+            let mut elem = BytesStart::borrowed_name(b"comment");
+            elem.push_attribute(("ref", item.reference.as_str()));
+            elem.push_attribute(("authorId", item.author_id.to_string().as_str()));
+            if let Some(shape_id) = &item.shape_id {
+                elem.push_attribute(("shapeId", shape_id.as_str()));
+            }
+            if let Some(uid) = &item.uid {
+                elem.push_attribute(("xr:uid", uid.as_str()));
+            }
+            xml.write_event(Event::Start(elem))?;
+            xml.write_event(Event::Start(BytesStart::borrowed_name(b"text")))?;
+            write_rich_text(&mut xml, &item.text)?;
+            xml.write_event(Event::End(BytesEnd::borrowed(b"text")))?;
+            xml.write_event(Event::End(BytesEnd::borrowed(b"comment")))?;
+        }
+        xml.write_event(Event::End(BytesEnd::borrowed(b"commentList")))?;
+        xml.write_event(Event::End(BytesEnd::borrowed(b"comments")))?;
+        Ok(())
+    }
+}
+
+impl OpenXmlElementInfo for CommentsPart {
+    fn tag_name() -> &'static str {
+        "comments"
+    }
+
+    fn element_type() -> OpenXmlElementType {
+        OpenXmlElementType::Root
+    }
+}
+
+impl OpenXmlDeserialize for CommentsPart {
+    fn from_xml_reader<R: BufRead>(reader: R) -> Result<Self, OoxmlError> {
+        use quick_xml::events::Event;
+
+        let mut reader = quick_xml::Reader::from_reader(reader);
+        reader.trim_text(false);
+        let mut part = CommentsPart {
+            attributes: Vec::new(),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        let mut author: Option<String> = None;
+        let mut comment: Option<CommentItem> = None;
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Start(ref e) | Event::Empty(ref e) if e.local_name() == b"comments" => {
+                    for attr in e.attributes() {
+                        let attr = attr?;
+                        part.attributes.push((
+                            String::from_utf8_lossy(attr.key).into_owned(),
+                            attr.unescape_and_decode_value(&reader)?,
+                        ));
+                    }
+                }
+                Event::Start(ref e) if e.local_name() == b"author" => {
+                    author = Some(String::new());
+                }
+                Event::Empty(ref e) if e.local_name() == b"author" => {
+                    part.authors.push(String::new());
+                }
+                Event::Text(ref e) => {
+                    if let Some(author) = author.as_mut() {
+                        author.push_str(&e.unescape_and_decode(&reader)?);
+                    }
+                }
+                Event::End(ref e) if e.local_name() == b"author" => {
+                    part.authors.extend(author.take());
+                }
+                Event::Start(ref e) if e.local_name() == b"comment" => {
+                    let mut item = CommentItem::default();
+                    for attr in e.attributes() {
+                        let attr = attr?;
+                        let value = attr.unescape_and_decode_value(&reader)?;
+                        match attr.key {
+                            b"ref" => item.reference = value,
+                            b"authorId" => item.author_id = value.parse().unwrap_or_default(),
+                            b"shapeId" => item.shape_id = Some(value),
+                            key if key.ends_with(b":uid") => item.uid = Some(value),
+                            _ => {}
+                        }
+                    }
+                    comment = Some(item);
+                }
+                Event::Start(ref e) if e.local_name() == b"text" => {
+                    let text = read_rich_text(&mut reader)?;
+                    if let Some(comment) = comment.as_mut() {
+                        comment.text = text;
+                    }
+                }
+                Event::End(ref e) if e.local_name() == b"comment" => {
+                    part.comments.extend(comment.take());
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+        Ok(part)
+    }
+}
+
+impl fmt::Display for CommentsPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut container = Vec::new();
+        self.write(&mut container).map_err(|_| fmt::Error)?;
+        f.write_str(&String::from_utf8_lossy(&container))
+    }
+}
+
+const VML_HEADER: &str = r#"<xml xmlns:v="urn:schemas-microsoft-com:vml" xmlns:o="urn:schemas-microsoft-com:office:office" xmlns:x="urn:schemas-microsoft-com:office:excel">"#;
+
+/// Shape type of the note boxes.
+const VML_NOTE_SHAPETYPE: &str = r#"<v:shapetype id="_x0000_t202" coordsize="21600,21600" o:spt="202" path="m,l,21600r21600,l21600,xe"><v:stroke joinstyle="miter"/><v:path gradientshapeok="t" o:connecttype="rect"/></v:shapetype>"#;
+
+/// A new legacy VML drawing for notes, shape ids start from `1024 * idmap + 1`.
+pub(crate) fn new_vml_drawing(idmap: usize) -> String {
+    format!(
+        r#"{}<o:shapelayout v:ext="edit"><o:idmap v:ext="edit" data="{}"/></o:shapelayout>{}</xml>"#,
+        VML_HEADER, idmap, VML_NOTE_SHAPETYPE
+    )
+}
+
+/// Hidden note box of a cell, anchored to the right of the cell like Excel does.
+fn vml_note_shape(id: usize, cell: CellRef) -> String {
+    let top = cell.row.saturating_sub(1);
+    format!(
+        concat!(
+            r##"<v:shape id="_x0000_s{id}" type="#_x0000_t202" style="position:absolute;margin-left:59.25pt;margin-top:1.5pt;width:108pt;height:59.25pt;z-index:{z};visibility:hidden" fillcolor="#ffffe1" o:insetmode="auto">"##,
+            r##"<v:fill color2="#ffffe1"/><v:shadow on="t" color="black" obscured="t"/><v:path o:connecttype="none"/>"##,
+            r##"<v:textbox style="mso-direction-alt:auto"><div style="text-align:left"></div></v:textbox>"##,
+            r##"<x:ClientData ObjectType="Note"><x:MoveWithCells/><x:SizeWithCells/>"##,
+            r##"<x:Anchor>{left}, 15, {top}, 10, {right}, 15, {bottom}, 4</x:Anchor>"##,
+            r##"<x:AutoFill>False</x:AutoFill><x:Row>{row}</x:Row><x:Column>{col}</x:Column></x:ClientData></v:shape>"##,
+        ),
+        id = id,
+        z = id % 1024,
+        left = cell.col + 1,
+        top = top,
+        right = cell.col + 3,
+        bottom = top + 4,
+        row = cell.row,
+        col = cell.col,
+    )
+}
+
+/// Cells of the note shapes, and the max shape id number of a VML drawing.
+fn vml_notes(data: &[u8]) -> Result<(Vec<CellRef>, Option<usize>, usize), OoxmlError> {
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_reader(data);
+    let mut buf = Vec::new();
+    let mut cells = Vec::new();
+    let mut max_id = None;
+    let mut idmap = 1;
+    let mut note: Option<(Option<u32>, Option<u32>)> = None;
+    let mut field: Option<Vec<u8>> = None;
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                for attr in e.attributes().flatten() {
+                    let value = attr.unescape_and_decode_value(&reader)?;
+                    match (e.name(), attr.key) {
+                        (b"o:idmap", b"data") => {
+                            idmap = value
+                                .split(',')
+                                .next()
+                                .and_then(|v| v.trim().parse().ok())
+                                .unwrap_or(idmap)
+                        }
+                        (_, b"id") | (_, b"o:spid") => {
+                            if let Some(id) = value
+                                .strip_prefix("_x0000_s")
+                                .and_then(|id| id.parse::<usize>().ok())
+                            {
+                                max_id = max_id.max(Some(id));
+                            }
+                        }
+                        (b"x:ClientData", b"ObjectType") if value == "Note" => {
+                            note = Some((None, None))
+                        }
+                        _ => {}
+                    }
+                }
+                field = Some(e.name().to_vec());
+            }
+            Event::Text(ref e) => {
+                let text = e.unescape_and_decode(&reader)?;
+                if let Some((row, col)) = note.as_mut() {
+                    match field.as_deref() {
+                        Some(b"x:Row") => *row = text.trim().parse().ok(),
+                        Some(b"x:Column") => *col = text.trim().parse().ok(),
+                        _ => {}
+                    }
+                }
+            }
+            Event::End(ref e) => {
+                field = None;
+                if e.name() == b"x:ClientData" {
+                    if let Some((Some(row), Some(col))) = note.take() {
+                        cells.push(CellRef::new(row, col));
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok((cells, max_id, idmap))
+}
+
+/// Add a note shape of the cell to a legacy VML drawing if the cell has none.
+pub(crate) fn add_vml_note(data: &[u8], cell: CellRef) -> Result<Vec<u8>, OoxmlError> {
+    let (cells, max_id, idmap) = vml_notes(data)?;
+    if cells.contains(&cell) {
+        return Ok(data.to_vec());
+    }
+    let id = max_id.map_or(idmap * 1024 + 1, |id| id + 1);
+    let mut insert = String::new();
+    if !String::from_utf8_lossy(data).contains("_x0000_t202") {
+        insert.push_str(VML_NOTE_SHAPETYPE);
+    }
+    insert.push_str(&vml_note_shape(id, cell));
+    let end = data
+        .windows(b"</xml>".len())
+        .rposition(|w| w == b"</xml>")
+        .ok_or_else(|| quick_xml::Error::UnexpectedEof("VML drawing".into()))?;
+    let mut result = data[..end].to_vec();
+    result.extend_from_slice(insert.as_bytes());
+    result.extend_from_slice(&data[end..]);
+    Ok(result)
+}
+
+#[test]
+fn comments() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<comments xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006" mc:Ignorable="xr" xmlns:xr="http://schemas.microsoft.com/office/spreadsheetml/2014/revision"><authors><author>Reviewer</author><author>Bob &amp; Co</author></authors><commentList><comment ref="A1" authorId="0"><text><t>plain</t></text></comment><comment ref="B2" authorId="1" shapeId="0" xr:uid="{00000000-0006-0000-0000-000001000000}"><text><r><rPr><b/><sz val="9"/><rFont val="Tahoma"/></rPr><t>Bob:</t></r><r><t xml:space="preserve">
+Check this</t></r></text></comment></commentList></comments>"#;
+    let mut part = CommentsPart::from_xml_str(xml).unwrap();
+    let comments = part.to_comments();
+    assert_eq!(comments.len(), 2);
+    assert_eq!(comments[1].author, "Bob & Co");
+    assert_eq!(comments[1].reference, CellRef::new(1, 1));
+    assert_eq!(comments[1].text(), "Bob:\nCheck this");
+    assert_eq!(
+        comments[1].text.runs()[0].properties.as_ref().unwrap().bold,
+        Some(true)
+    );
+    assert_eq!(comments[0].text(), "plain");
+    assert_eq!(
+        part.comments[1].uid.as_deref(),
+        Some("{00000000-0006-0000-0000-000001000000}")
+    );
+    assert_eq!(CommentsPart::from_xml_str(&part.to_string()).unwrap(), part);
+
+    part.set_comment(Comment::new(CellRef::new(0, 0), "Reviewer", "replaced"));
+    part.set_comment(Comment::new(CellRef::new(0, 3), "New", "added"));
+    let comments = part.to_comments();
+    let cells: Vec<String> = comments.iter().map(|c| c.reference.to_string()).collect();
+    assert_eq!(cells, ["A1", "D1", "B2"]);
+    assert_eq!(comments[0].text(), "replaced");
+    assert_eq!(part.authors, ["Reviewer", "Bob & Co", "New"]);
+
+    let vml = new_vml_drawing(1);
+    let vml = add_vml_note(vml.as_bytes(), CellRef::new(0, 0)).unwrap();
+    let vml = add_vml_note(&vml, CellRef::new(2, 1)).unwrap();
+    assert_eq!(add_vml_note(&vml, CellRef::new(2, 1)).unwrap(), vml);
+    let (cells, max_id, _) = vml_notes(&vml).unwrap();
+    assert_eq!(cells, [CellRef::new(0, 0), CellRef::new(2, 1)]);
+    assert_eq!(max_id, Some(1026));
+}
@@ -0,0 +1,589 @@
+//! Conditional formatting `<conditionalFormatting>` of worksheets, see ECMA-376 Part 1,
+//! 18.3.1.18.
+//!
+//! Each block applies its rules `<cfRule>` to ranges by priority, formats of rules are
+//! differential formats of the style sheet referred by `dxfId`.
+use serde::{Deserialize, Serialize};
+
+use super::reference::{CellRange, CellRef};
+use super::style::Color;
+
+/// Type of a conditional format rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConditionalFormatType {
+    /// Cells making a formula true.
+    #[default]
+    Expression,
+    /// Cell values compared with formulas.
+    CellIs,
+    ColorScale,
+    DataBar,
+    IconSet,
+    Top10,
+    UniqueValues,
+    DuplicateValues,
+    ContainsText,
+    NotContainsText,
+    BeginsWith,
+    EndsWith,
+    ContainsBlanks,
+    NotContainsBlanks,
+    ContainsErrors,
+    NotContainsErrors,
+    TimePeriod,
+    AboveAverage,
+}
+
+impl ConditionalFormatType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConditionalFormatType::Expression => "expression",
+            ConditionalFormatType::CellIs => "cellIs",
+            ConditionalFormatType::ColorScale => "colorScale",
+            ConditionalFormatType::DataBar => "dataBar",
+            ConditionalFormatType::IconSet => "iconSet",
+            ConditionalFormatType::Top10 => "top10",
+            ConditionalFormatType::UniqueValues => "uniqueValues",
+            ConditionalFormatType::DuplicateValues => "duplicateValues",
+            ConditionalFormatType::ContainsText => "containsText",
+            ConditionalFormatType::NotContainsText => "notContainsText",
+            ConditionalFormatType::BeginsWith => "beginsWith",
+            ConditionalFormatType::EndsWith => "endsWith",
+            ConditionalFormatType::ContainsBlanks => "containsBlanks",
+            ConditionalFormatType::NotContainsBlanks => "notContainsBlanks",
+            ConditionalFormatType::ContainsErrors => "containsErrors",
+            ConditionalFormatType::NotContainsErrors => "notContainsErrors",
+            ConditionalFormatType::TimePeriod => "timePeriod",
+            ConditionalFormatType::AboveAverage => "aboveAverage",
+        }
+    }
+}
+
+/// Operator of `cellIs` and text rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConditionalFormatOperator {
+    LessThan,
+    LessThanOrEqual,
+    Equal,
+    NotEqual,
+    GreaterThanOrEqual,
+    GreaterThan,
+    Between,
+    NotBetween,
+    ContainsText,
+    NotContains,
+    BeginsWith,
+    EndsWith,
+}
+
+impl ConditionalFormatOperator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConditionalFormatOperator::LessThan => "lessThan",
+            ConditionalFormatOperator::LessThanOrEqual => "lessThanOrEqual",
+            ConditionalFormatOperator::Equal => "equal",
+            ConditionalFormatOperator::NotEqual => "notEqual",
+            ConditionalFormatOperator::GreaterThanOrEqual => "greaterThanOrEqual",
+            ConditionalFormatOperator::GreaterThan => "greaterThan",
+            ConditionalFormatOperator::Between => "between",
+            ConditionalFormatOperator::NotBetween => "notBetween",
+            ConditionalFormatOperator::ContainsText => "containsText",
+            ConditionalFormatOperator::NotContains => "notContains",
+            ConditionalFormatOperator::BeginsWith => "beginsWith",
+            ConditionalFormatOperator::EndsWith => "endsWith",
+        }
+    }
+}
+
+/// Date period of `timePeriod` rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimePeriod {
+    Today,
+    Yesterday,
+    Tomorrow,
+    Last7Days,
+    ThisMonth,
+    LastMonth,
+    NextMonth,
+    ThisWeek,
+    LastWeek,
+    NextWeek,
+}
+
+impl TimePeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimePeriod::Today => "today",
+            TimePeriod::Yesterday => "yesterday",
+            TimePeriod::Tomorrow => "tomorrow",
+            TimePeriod::Last7Days => "last7Days",
+            TimePeriod::ThisMonth => "thisMonth",
+            TimePeriod::LastMonth => "lastMonth",
+            TimePeriod::NextMonth => "nextMonth",
+            TimePeriod::ThisWeek => "thisWeek",
+            TimePeriod::LastWeek => "lastWeek",
+            TimePeriod::NextWeek => "nextWeek",
+        }
+    }
+}
+
+/// Type of a threshold value `<cfvo>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ThresholdType {
+    Num,
+    Percent,
+    Max,
+    Min,
+    Formula,
+    Percentile,
+}
+
+impl ThresholdType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThresholdType::Num => "num",
+            ThresholdType::Percent => "percent",
+            ThresholdType::Max => "max",
+            ThresholdType::Min => "min",
+            ThresholdType::Formula => "formula",
+            ThresholdType::Percentile => "percentile",
+        }
+    }
+}
+
+/// A threshold of color scales, data bars and icon sets `<cfvo>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "cfvo")]
+pub struct Threshold {
+    #[serde(rename = "type")]
+    pub kind: ThresholdType,
+    pub val: Option<String>,
+    /// Icon sets only, values equal to the threshold reach it, `true` if not set.
+    pub gte: Option<bool>,
+}
+
+impl Threshold {
+    pub fn new(kind: ThresholdType, val: Option<&str>) -> Self {
+        Self {
+            kind,
+            val: val.map(String::from),
+            gte: None,
+        }
+    }
+
+    fn to_xml(&self) -> String {
+        let escape =
+            |s: &str| String::from_utf8_lossy(&quick_xml::escape::escape(s.as_bytes())).to_string();
+        let mut xml = format!(r#"<cfvo type="{}""#, self.kind.as_str());
+        if let Some(val) = &self.val {
+            xml.push_str(&format!(r#" val="{}""#, escape(val)));
+        }
+        if let Some(gte) = self.gte {
+            xml.push_str(&format!(r#" gte="{}""#, gte as u8));
+        }
+        xml.push_str("/>");
+        xml
+    }
+}
+
+/// Colors of a 2 or 3 color scale by thresholds.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "colorScale")]
+pub struct ColorScale {
+    #[serde(rename = "cfvo", default)]
+    pub thresholds: Vec<Threshold>,
+    #[serde(rename = "color", default)]
+    pub colors: Vec<Color>,
+}
+
+/// A data bar between the min and max thresholds.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "dataBar", rename_all = "camelCase")]
+pub struct DataBar {
+    /// Shortest bar in percent of the cell width, `10` if not set.
+    pub min_length: Option<u32>,
+    /// Longest bar in percent of the cell width, `90` if not set.
+    pub max_length: Option<u32>,
+    pub show_value: Option<bool>,
+    #[serde(rename = "cfvo", default)]
+    pub thresholds: Vec<Threshold>,
+    pub color: Option<Color>,
+}
+
+/// Icons by thresholds, like `3TrafficLights1`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "iconSet", rename_all = "camelCase")]
+pub struct IconSet {
+    /// Name of the icon set, `3TrafficLights1` if not set.
+    pub icon_set: Option<String>,
+    pub show_value: Option<bool>,
+    pub percent: Option<bool>,
+    pub reverse: Option<bool>,
+    #[serde(rename = "cfvo", default)]
+    pub thresholds: Vec<Threshold>,
+}
+
+/// A conditional format rule `<cfRule>`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "cfRule", rename_all = "camelCase")]
+pub struct ConditionalFormatRule {
+    #[serde(rename = "type", default)]
+    pub kind: ConditionalFormatType,
+    /// Differential format of the rule in the style sheet.
+    pub dxf_id: Option<u32>,
+    /// Rules of lower priority values are applied first in the worksheet.
+    #[serde(default)]
+    pub priority: u32,
+    /// Rules of lower priority are not applied if this one is true.
+    pub stop_if_true: Option<bool>,
+    /// `aboveAverage` rules, `true` if not set.
+    pub above_average: Option<bool>,
+    /// `top10` rules, the rank is in percent.
+    pub percent: Option<bool>,
+    /// `top10` rules, rank from the bottom.
+    pub bottom: Option<bool>,
+    pub operator: Option<ConditionalFormatOperator>,
+    /// Text of text rules.
+    pub text: Option<String>,
+    pub time_period: Option<TimePeriod>,
+    /// `top10` rules, count or percent of top values.
+    pub rank: Option<u32>,
+    /// `aboveAverage` rules, standard deviations from the average.
+    pub std_dev: Option<u32>,
+    pub equal_average: Option<bool>,
+    /// Formulas of the rule, relative references are of the top-left cell of the ranges.
+    #[serde(rename = "formula", default)]
+    pub formulas: Vec<String>,
+    pub color_scale: Option<ColorScale>,
+    pub data_bar: Option<DataBar>,
+    pub icon_set: Option<IconSet>,
+}
+
+impl ConditionalFormatRule {
+    pub fn new(kind: ConditionalFormatType) -> Self {
+        Self {
+            kind,
+            ..Default::default()
+        }
+    }
+
+    /// Cells making a formula true, like `$B2>100`.
+    pub fn expression(formula: &str) -> Self {
+        Self {
+            formulas: vec![formula.trim_start_matches('=').to_string()],
+            ..Self::new(ConditionalFormatType::Expression)
+        }
+    }
+
+    /// Cell values compared with one formula, or two for `between` and `notBetween`.
+    pub fn cell_is(operator: ConditionalFormatOperator, formulas: &[&str]) -> Self {
+        Self {
+            operator: Some(operator),
+            formulas: formulas
+                .iter()
+                .map(|f| f.trim_start_matches('=').to_string())
+                .collect(),
+            ..Self::new(ConditionalFormatType::CellIs)
+        }
+    }
+
+    /// Cells containing a text, case insensitive. The formula is of the top-left cell.
+    pub fn contains_text(text: &str, top_left: CellRef) -> Self {
+        let quoted = text.replace('"', "\"\"");
+        Self {
+            operator: Some(ConditionalFormatOperator::ContainsText),
+            text: Some(text.to_string()),
+            formulas: vec![format!(
+                r#"NOT(ISERROR(SEARCH("{}",{})))"#,
+                quoted,
+                top_left.relative()
+            )],
+            ..Self::new(ConditionalFormatType::ContainsText)
+        }
+    }
+
+    /// Top or bottom values by count, or by percent.
+    pub fn top10(rank: u32, bottom: bool, percent: bool) -> Self {
+        Self {
+            rank: Some(rank),
+            bottom: Some(bottom).filter(|b| *b),
+            percent: Some(percent).filter(|p| *p),
+            ..Self::new(ConditionalFormatType::Top10)
+        }
+    }
+
+    pub fn duplicate_values() -> Self {
+        Self::new(ConditionalFormatType::DuplicateValues)
+    }
+
+    /// Colors from the minimum to the maximum value, with the middle at the 50th percentile for
+    /// 3 colors.
+    pub fn color_scale(colors: &[Color]) -> Self {
+        let mut thresholds = vec![Threshold::new(ThresholdType::Min, None)];
+        if colors.len() > 2 {
+            thresholds.push(Threshold::new(ThresholdType::Percentile, Some("50")));
+        }
+        thresholds.push(Threshold::new(ThresholdType::Max, None));
+        Self {
+            color_scale: Some(ColorScale {
+                thresholds,
+                colors: colors.to_vec(),
+            }),
+            ..Self::new(ConditionalFormatType::ColorScale)
+        }
+    }
+
+    /// Data bars of a color from the minimum to the maximum value.
+    pub fn data_bar(color: Color) -> Self {
+        Self {
+            data_bar: Some(DataBar {
+                thresholds: vec![
+                    Threshold::new(ThresholdType::Min, None),
+                    Threshold::new(ThresholdType::Max, None),
+                ],
+                color: Some(color),
+                ..Default::default()
+            }),
+            ..Self::new(ConditionalFormatType::DataBar)
+        }
+    }
+
+    /// Icons like `3TrafficLights1` with thresholds evenly in percent.
+    pub fn icon_set(name: &str, count: u32) -> Self {
+        let thresholds = (0..count)
+            .map(|i| {
+                let percent = ((i * 100 + count / 2) / count).to_string();
+                Threshold::new(ThresholdType::Percent, Some(&percent))
+            })
+            .collect();
+        Self {
+            icon_set: Some(IconSet {
+                icon_set: Some(name.to_string()),
+                thresholds,
+                ..Default::default()
+            }),
+            ..Self::new(ConditionalFormatType::IconSet)
+        }
+    }
+
+    pub fn with_stop_if_true(self, stop_if_true: bool) -> Self {
+        Self {
+            stop_if_true: Some(stop_if_true),
+            ..self
+        }
+    }
+
+    fn to_xml(&self) -> String {
+        let escape =
+            |s: &str| String::from_utf8_lossy(&quick_xml::escape::escape(s.as_bytes())).to_string();
+        let flag = |v: bool| if v { "1" } else { "0" };
+        let mut xml = format!(r#"<cfRule type="{}""#, self.kind.as_str());
+        if let Some(id) = self.dxf_id {
+            xml.push_str(&format!(r#" dxfId="{}""#, id));
+        }
+        xml.push_str(&format!(r#" priority="{}""#, self.priority));
+        for (name, value) in [
+            ("stopIfTrue", self.stop_if_true),
+            ("aboveAverage", self.above_average),
+            ("percent", self.percent),
+            ("bottom", self.bottom),
+        ] {
+            if let Some(value) = value {
+                xml.push_str(&format!(r#" {}="{}""#, name, flag(value)));
+            }
+        }
+        if let Some(operator) = self.operator {
+            xml.push_str(&format!(r#" operator="{}""#, operator.as_str()));
+        }
+        if let Some(text) = &self.text {
+            xml.push_str(&format!(r#" text="{}""#, escape(text)));
+        }
+        if let Some(period) = self.time_period {
+            xml.push_str(&format!(r#" timePeriod="{}""#, period.as_str()));
+        }
+        if let Some(rank) = self.rank {
+            xml.push_str(&format!(r#" rank="{}""#, rank));
+        }
+        if let Some(std_dev) = self.std_dev {
+            xml.push_str(&format!(r#" stdDev="{}""#, std_dev));
+        }
+        if let Some(equal) = self.equal_average {
+            xml.push_str(&format!(r#" equalAverage="{}""#, flag(equal)));
+        }
+        let mut children = String::new();
+        for formula in &self.formulas {
+            children.push_str(&format!("<formula>{}</formula>", escape(formula)));
+        }
+        if let Some(scale) = &self.color_scale {
+            children.push_str("<colorScale>");
+            for threshold in &scale.thresholds {
+                children.push_str(&threshold.to_xml());
+            }
+            for color in &scale.colors {
+                children.push_str(&color.to_xml("color"));
+            }
+            children.push_str("</colorScale>");
+        }
+        if let Some(bar) = &self.data_bar {
+            children.push_str("<dataBar");
+            if let Some(length) = bar.min_length {
+                children.push_str(&format!(r#" minLength="{}""#, length));
+            }
+            if let Some(length) = bar.max_length {
+                children.push_str(&format!(r#" maxLength="{}""#, length));
+            }
+            if let Some(show) = bar.show_value {
+                children.push_str(&format!(r#" showValue="{}""#, flag(show)));
+            }
+            children.push('>');
+            for threshold in &bar.thresholds {
+                children.push_str(&threshold.to_xml());
+            }
+            if let Some(color) = &bar.color {
+                children.push_str(&color.to_xml("color"));
+            }
+            children.push_str("</dataBar>");
+        }
+        if let Some(icons) = &self.icon_set {
+            children.push_str("<iconSet");
+            if let Some(name) = &icons.icon_set {
+                children.push_str(&format!(r#" iconSet="{}""#, escape(name)));
+            }
+            for (name, value) in [
+                ("showValue", icons.show_value),
+                ("percent", icons.percent),
+                ("reverse", icons.reverse),
+            ] {
+                if let Some(value) = value {
+                    children.push_str(&format!(r#" {}="{}""#, name, flag(value)));
+                }
+            }
+            children.push('>');
+            for threshold in &icons.thresholds {
+                children.push_str(&threshold.to_xml());
+            }
+            children.push_str("</iconSet>");
+        }
+        if children.is_empty() {
+            xml.push_str("/>");
+        } else {
+            xml.push('>');
+            xml.push_str(&children);
+            xml.push_str("</cfRule>");
+        }
+        xml
+    }
+}
+
+/// A conditional formatting block `<conditionalFormatting>` of rules applied to ranges.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "conditionalFormatting")]
+pub struct ConditionalFormatting {
+    /// Space separated ranges of the block.
+    #[serde(default)]
+    pub sqref: String,
+    #[serde(rename = "cfRule", default)]
+    pub rules: Vec<ConditionalFormatRule>,
+}
+
+impl ConditionalFormatting {
+    pub fn new(ranges: &[CellRange], rules: Vec<ConditionalFormatRule>) -> Self {
+        Self {
+            sqref: ranges
+                .iter()
+                .map(|range| range.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+            rules,
+        }
+    }
+
+    /// Ranges of the block, invalid references are skipped.
+    pub fn ranges(&self) -> Vec<CellRange> {
+        self.sqref
+            .split_whitespace()
+            .filter_map(|range| range.parse().ok())
+            .collect()
+    }
+
+    pub fn contains(&self, cell: &CellRef) -> bool {
+        self.ranges().iter().any(|range| range.contains(cell))
+    }
+
+    pub fn to_xml(&self) -> String {
+        let escape =
+            |s: &str| String::from_utf8_lossy(&quick_xml::escape::escape(s.as_bytes())).to_string();
+        let rules: String = self.rules.iter().map(|rule| rule.to_xml()).collect();
+        format!(
+            r#"<conditionalFormatting sqref="{}">{}</conditionalFormatting>"#,
+            escape(&self.sqref),
+            rules
+        )
+    }
+}
+
+#[test]
+fn conditional_formats() {
+    let xml = r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData/>
+<conditionalFormatting sqref="B2:B13 D2:D13"><cfRule type="cellIs" dxfId="0" priority="2" operator="greaterThan"><formula>25</formula></cfRule><cfRule type="expression" dxfId="1" priority="1" stopIfTrue="1"><formula>AND($B2&gt;0,$B2&lt;20)</formula></cfRule></conditionalFormatting>
+<conditionalFormatting sqref="C2:C13"><cfRule type="colorScale" priority="3"><colorScale><cfvo type="min"/><cfvo type="percentile" val="50"/><cfvo type="max"/><color rgb="FFF8696B"/><color rgb="FFFFEB84"/><color theme="9" tint="-0.25"/></colorScale></cfRule><cfRule type="dataBar" priority="4"><dataBar showValue="0"><cfvo type="min"/><cfvo type="max"/><color rgb="FF638EC6"/></dataBar></cfRule><cfRule type="iconSet" priority="5"><iconSet iconSet="3Arrows" reverse="1"><cfvo type="percent" val="0"/><cfvo type="percent" val="33"/><cfvo type="percent" val="67" gte="0"/></iconSet></cfRule><cfRule type="top10" dxfId="2" priority="6" percent="1" bottom="1" rank="10"/><cfRule type="duplicateValues" dxfId="3" priority="7"/><cfRule type="containsText" dxfId="4" priority="8" operator="containsText" text="a&quot;b"><formula>NOT(ISERROR(SEARCH("a""b",C2)))</formula></cfRule><cfRule type="timePeriod" dxfId="5" priority="9" timePeriod="last7Days"><formula>AND(TODAY()-FLOOR(C2,1)&lt;=6,FLOOR(C2,1)&lt;=TODAY())</formula></cfRule><cfRule type="aboveAverage" dxfId="6" priority="10" aboveAverage="0" stdDev="1"/></conditionalFormatting>
+<pageMargins left="0.75" right="0.75" top="1" bottom="1" header="0.5" footer="0.5"/></worksheet>"#;
+    use crate::packaging::element::OpenXmlDeserialize;
+    let sheet = super::worksheet::WorksheetPart::from_xml_str(xml).unwrap();
+    let blocks = &sheet.conditional_formattings;
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(blocks[0].ranges().len(), 2);
+    assert!(blocks[0].contains(&"D5".parse().unwrap()));
+    let rules = &blocks[0].rules;
+    assert_eq!(rules[0].kind, ConditionalFormatType::CellIs);
+    assert_eq!(
+        rules[0].operator,
+        Some(ConditionalFormatOperator::GreaterThan)
+    );
+    assert_eq!(rules[0].formulas, ["25"]);
+    assert_eq!(rules[1].formulas, ["AND($B2>0,$B2<20)"]);
+    assert_eq!(rules[1].stop_if_true, Some(true));
+
+    let rules = &blocks[1].rules;
+    let scale = rules[0].color_scale.as_ref().unwrap();
+    assert_eq!(scale.thresholds[1].kind, ThresholdType::Percentile);
+    assert_eq!(scale.thresholds[1].val.as_deref(), Some("50"));
+    assert_eq!(scale.colors[2].theme, Some(9));
+    let bar = rules[1].data_bar.as_ref().unwrap();
+    assert_eq!(bar.show_value, Some(false));
+    assert_eq!(bar.color.as_ref().unwrap().rgb.as_deref(), Some("FF638EC6"));
+    let icons = rules[2].icon_set.as_ref().unwrap();
+    assert_eq!(icons.icon_set.as_deref(), Some("3Arrows"));
+    assert_eq!(icons.thresholds[2].gte, Some(false));
+    assert_eq!(rules[3].kind, ConditionalFormatType::Top10);
+    assert_eq!((rules[3].rank, rules[3].bottom), (Some(10), Some(true)));
+    assert_eq!(rules[4].kind, ConditionalFormatType::DuplicateValues);
+    assert_eq!(rules[4].dxf_id, Some(3));
+    assert_eq!(rules[5].text.as_deref(), Some("a\"b"));
+    assert_eq!(rules[6].time_period, Some(TimePeriod::Last7Days));
+    assert_eq!(rules[7].above_average, Some(false));
+    assert_eq!(rules[7].std_dev, Some(1));
+
+    for block in blocks {
+        let reread: ConditionalFormatting = quick_xml::de::from_str(&block.to_xml()).unwrap();
+        assert_eq!(&reread, block);
+    }
+    let rule = ConditionalFormatRule::contains_text("a\"b", "$C$2".parse().unwrap());
+    assert_eq!(rule.formulas, blocks[1].rules[5].formulas);
+    let rule = ConditionalFormatRule::icon_set("3TrafficLights1", 3);
+    let values: Vec<_> = rule
+        .icon_set
+        .unwrap()
+        .thresholds
+        .into_iter()
+        .map(|t| t.val)
+        .collect();
+    assert_eq!(
+        values,
+        [Some("0".into()), Some("33".into()), Some("67".into())]
+    );
+}
@@ -0,0 +1,583 @@
+//! Data validation rules `<dataValidation>` of worksheets, see ECMA-376 Part 1, 18.3.1.32.
+//!
+//! Rules referring to other sheets may be written by Excel 2010 in the `x14` extension of the
+//! worksheet, where formulas are `<xm:f>` children and ranges are `<xm:sqref>`.
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+
+use crate::error::OoxmlError;
+
+use super::calc::{self, Value};
+use super::cell::CellValue;
+use super::reference::{CellRange, CellRef};
+
+/// Type of values allowed by a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ValidationType {
+    /// Any value.
+    #[default]
+    None,
+    Whole,
+    Decimal,
+    /// Values of a comma separated list or a range.
+    List,
+    Date,
+    Time,
+    TextLength,
+    /// Values making a formula true.
+    Custom,
+}
+
+impl ValidationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValidationType::None => "none",
+            ValidationType::Whole => "whole",
+            ValidationType::Decimal => "decimal",
+            ValidationType::List => "list",
+            ValidationType::Date => "date",
+            ValidationType::Time => "time",
+            ValidationType::TextLength => "textLength",
+            ValidationType::Custom => "custom",
+        }
+    }
+}
+
+/// Comparison of values with the rule formulas, `between` if not set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ValidationOperator {
+    #[default]
+    Between,
+    NotBetween,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+}
+
+impl ValidationOperator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValidationOperator::Between => "between",
+            ValidationOperator::NotBetween => "notBetween",
+            ValidationOperator::Equal => "equal",
+            ValidationOperator::NotEqual => "notEqual",
+            ValidationOperator::LessThan => "lessThan",
+            ValidationOperator::LessThanOrEqual => "lessThanOrEqual",
+            ValidationOperator::GreaterThan => "greaterThan",
+            ValidationOperator::GreaterThanOrEqual => "greaterThanOrEqual",
+        }
+    }
+
+    /// Check if a number satisfies the operator with the operands.
+    pub fn test(&self, v: f64, a: f64, b: Option<f64>) -> bool {
+        let (low, high) = match b {
+            Some(b) if b < a => (b, a),
+            Some(b) => (a, b),
+            None => (a, a),
+        };
+        match self {
+            ValidationOperator::Between => low <= v && v <= high,
+            ValidationOperator::NotBetween => v < low || high < v,
+            ValidationOperator::Equal => v == a,
+            ValidationOperator::NotEqual => v != a,
+            ValidationOperator::LessThan => v < a,
+            ValidationOperator::LessThanOrEqual => v <= a,
+            ValidationOperator::GreaterThan => v > a,
+            ValidationOperator::GreaterThanOrEqual => v >= a,
+        }
+    }
+
+    fn has_two_operands(&self) -> bool {
+        matches!(
+            self,
+            ValidationOperator::Between | ValidationOperator::NotBetween
+        )
+    }
+}
+
+/// Style of the error alert, only `stop` rejects invalid values in Excel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ValidationErrorStyle {
+    #[default]
+    Stop,
+    Warning,
+    Information,
+}
+
+impl ValidationErrorStyle {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValidationErrorStyle::Stop => "stop",
+            ValidationErrorStyle::Warning => "warning",
+            ValidationErrorStyle::Information => "information",
+        }
+    }
+}
+
+/// A data validation rule.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "dataValidation")]
+pub struct DataValidation {
+    #[serde(rename = "type", default)]
+    pub kind: ValidationType,
+    pub operator: Option<ValidationOperator>,
+    pub error_style: Option<ValidationErrorStyle>,
+    pub allow_blank: Option<bool>,
+    /// Hide the in-cell drop-down of list rules, it's inverted as the name is.
+    pub show_drop_down: Option<bool>,
+    pub show_input_message: Option<bool>,
+    pub show_error_message: Option<bool>,
+    pub error_title: Option<String>,
+    pub error: Option<String>,
+    pub prompt_title: Option<String>,
+    pub prompt: Option<String>,
+    /// Space separated ranges of the rule.
+    #[serde(default)]
+    pub sqref: String,
+    /// Formula of the first operand, the list source or the custom formula, without `=`.
+    pub formula1: Option<String>,
+    pub formula2: Option<String>,
+}
+
+impl DataValidation {
+    /// A rule of ranges showing input and error messages and allowing blank cells.
+    pub fn new(kind: ValidationType, ranges: &[CellRange]) -> Self {
+        Self {
+            kind,
+            allow_blank: Some(true),
+            show_input_message: Some(true),
+            show_error_message: Some(true),
+            sqref: ranges
+                .iter()
+                .map(|range| range.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+            ..Default::default()
+        }
+    }
+
+    /// A drop-down list of values, values should not contain commas.
+    pub fn list(ranges: &[CellRange], values: &[&str]) -> Self {
+        let values = values.join(",").replace('"', "\"\"");
+        Self::new(ValidationType::List, ranges).with_formula(format!("\"{}\"", values))
+    }
+
+    /// A drop-down list of values from a range or a name, like `Sheet2!$A$1:$A$5`.
+    pub fn list_source(ranges: &[CellRange], source: &str) -> Self {
+        Self::new(ValidationType::List, ranges).with_formula(source.trim_start_matches('='))
+    }
+
+    /// Values of a formula making it true, relative references are of the top-left cell.
+    pub fn custom(ranges: &[CellRange], formula: &str) -> Self {
+        Self::new(ValidationType::Custom, ranges).with_formula(formula.trim_start_matches('='))
+    }
+
+    /// Set the first formula, the source of lists or the formula of custom rules.
+    pub fn with_formula(self, formula: impl Into<String>) -> Self {
+        Self {
+            formula1: Some(formula.into()),
+            ..self
+        }
+    }
+
+    /// Compare values with one operand, or two for `between` and `notBetween`.
+    pub fn with_operator(
+        self,
+        operator: ValidationOperator,
+        formula1: impl Into<String>,
+        formula2: Option<&str>,
+    ) -> Self {
+        Self {
+            operator: Some(operator),
+            formula1: Some(formula1.into().trim_start_matches('=').to_string()),
+            formula2: formula2.map(|f| f.trim_start_matches('=').to_string()),
+            ..self
+        }
+    }
+
+    pub fn with_error(self, title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            error_title: Some(title.into()),
+            error: Some(message.into()),
+            ..self
+        }
+    }
+
+    pub fn with_error_style(self, style: ValidationErrorStyle) -> Self {
+        Self {
+            error_style: Some(style),
+            ..self
+        }
+    }
+
+    pub fn with_prompt(self, title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            prompt_title: Some(title.into()),
+            prompt: Some(message.into()),
+            ..self
+        }
+    }
+
+    pub fn with_allow_blank(self, allow_blank: bool) -> Self {
+        Self {
+            allow_blank: Some(allow_blank),
+            ..self
+        }
+    }
+
+    /// Ranges of the rule, invalid references are skipped.
+    pub fn ranges(&self) -> Vec<CellRange> {
+        self.sqref
+            .split_whitespace()
+            .filter_map(|range| range.parse().ok())
+            .collect()
+    }
+
+    pub fn contains(&self, cell: &CellRef) -> bool {
+        self.ranges().iter().any(|range| range.contains(cell))
+    }
+
+    pub fn operator(&self) -> ValidationOperator {
+        self.operator.unwrap_or_default()
+    }
+
+    pub fn error_style(&self) -> ValidationErrorStyle {
+        self.error_style.unwrap_or_default()
+    }
+
+    pub fn allow_blank(&self) -> bool {
+        self.allow_blank.unwrap_or_default()
+    }
+
+    /// Values of a literal list like `"a,b,c"`, `None` for other rules.
+    pub fn list_values(&self) -> Option<Vec<String>> {
+        let formula = self.formula1.as_deref()?.trim();
+        if self.kind != ValidationType::List || !formula.starts_with('"') {
+            return None;
+        }
+        match calc::parse(formula) {
+            Ok(calc::Expr::Text(list)) => Some(split_list(&list)),
+            _ => None,
+        }
+    }
+
+    /// Formulas of the rule for a cell, relative references are shifted from the top-left cell
+    /// of the first range.
+    pub fn formulas_at(&self, cell: CellRef) -> Vec<String> {
+        let origin = self.ranges().first().map(|range| range.start);
+        let (rows, cols) = origin.map_or((0, 0), |origin| {
+            (
+                cell.row as i64 - origin.row as i64,
+                cell.col as i64 - origin.col as i64,
+            )
+        });
+        let count = match self.kind {
+            ValidationType::None => 0,
+            ValidationType::List | ValidationType::Custom => 1,
+            _ if self.operator().has_two_operands() => 2,
+            _ => 1,
+        };
+        [&self.formula1, &self.formula2]
+            .iter()
+            .take(count)
+            .map(|formula| {
+                let formula = formula.as_deref().unwrap_or_default();
+                super::formula::shift_references(formula, rows, cols)
+            })
+            .collect()
+    }
+
+    /// Check a value by the evaluated formulas of [`formulas_at`](Self::formulas_at), values of
+    /// custom rules are the results of their formulas.
+    pub fn is_valid(&self, value: &Value, operands: &[Value]) -> bool {
+        let operand = |i: usize| operands.get(i).cloned().unwrap_or_default();
+        if *value == Value::Empty && self.kind != ValidationType::Custom {
+            return self.allow_blank();
+        }
+        let number = match (self.kind, value) {
+            (ValidationType::None, _) => return true,
+            (ValidationType::Custom, _) => return operand(0).scalar().to_bool().unwrap_or(false),
+            (ValidationType::List, value) => {
+                let items = match operand(0) {
+                    Value::Text(list) => split_list(&list).into_iter().map(Value::Text).collect(),
+                    Value::Array(rows) => rows.into_iter().flatten().collect(),
+                    Value::Error(_) => Vec::new(),
+                    value => vec![value],
+                };
+                return items.iter().any(|item| match (item, value) {
+                    (Value::Number(a), Value::Text(b)) | (Value::Text(b), Value::Number(a)) => {
+                        b.trim().parse::<f64>().ok() == Some(*a)
+                    }
+                    (Value::Empty, _) => false,
+                    _ => calc::compare(item, value) == std::cmp::Ordering::Equal,
+                });
+            }
+            (ValidationType::TextLength, Value::Error(_)) => return false,
+            (ValidationType::TextLength, value) => {
+                value.to_text().unwrap_or_default().chars().count() as f64
+            }
+            (ValidationType::Whole, Value::Number(v)) if v.fract() != 0. => return false,
+            (ValidationType::Time, Value::Number(v)) => v.fract(),
+            (_, Value::Number(v)) => *v,
+            _ => return false,
+        };
+        let a = match operand(0).scalar().to_number() {
+            Ok(a) => a,
+            Err(_) => return false,
+        };
+        let b = if self.operator().has_two_operands() {
+            match operand(1).scalar().to_number() {
+                Ok(b) => Some(b),
+                Err(_) => return false,
+            }
+        } else {
+            None
+        };
+        let (a, b) = match self.kind {
+            ValidationType::Time => (a.fract(), b.map(f64::fract)),
+            _ => (a, b),
+        };
+        self.operator().test(number, a, b)
+    }
+
+    fn to_xml(&self) -> String {
+        let escape =
+            |s: &str| String::from_utf8_lossy(&quick_xml::escape::escape(s.as_bytes())).to_string();
+        let flag = |v: bool| if v { "1" } else { "0" };
+        let mut xml = String::from("<dataValidation");
+        if self.kind != ValidationType::None {
+            xml.push_str(&format!(r#" type="{}""#, self.kind.as_str()));
+        }
+        if let Some(style) = self.error_style {
+            xml.push_str(&format!(r#" errorStyle="{}""#, style.as_str()));
+        }
+        if let Some(operator) = self.operator {
+            xml.push_str(&format!(r#" operator="{}""#, operator.as_str()));
+        }
+        for (name, value) in [
+            ("allowBlank", self.allow_blank),
+            ("showDropDown", self.show_drop_down),
+            ("showInputMessage", self.show_input_message),
+            ("showErrorMessage", self.show_error_message),
+        ] {
+            if let Some(value) = value {
+                xml.push_str(&format!(r#" {}="{}""#, name, flag(value)));
+            }
+        }
+        for (name, value) in [
+            ("errorTitle", &self.error_title),
+            ("error", &self.error),
+            ("promptTitle", &self.prompt_title),
+            ("prompt", &self.prompt),
+        ] {
+            if let Some(value) = value {
+                xml.push_str(&format!(r#" {}="{}""#, name, escape(value)));
+            }
+        }
+        xml.push_str(&format!(r#" sqref="{}">"#, escape(&self.sqref)));
+        if let Some(formula) = &self.formula1 {
+            xml.push_str(&format!("<formula1>{}</formula1>", escape(formula)));
+        }
+        if let Some(formula) = &self.formula2 {
+            xml.push_str(&format!("<formula2>{}</formula2>", escape(formula)));
+        }
+        xml.push_str("</dataValidation>");
+        xml
+    }
+}
+
+/// Items of a list rule, separated by commas.
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|item| item.trim().to_string())
+        .collect()
+}
+
+/// Data validation rules `<dataValidations>` of a worksheet.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "dataValidations")]
+pub struct DataValidations {
+    pub count: Option<usize>,
+    pub disable_prompts: Option<bool>,
+    #[serde(rename = "dataValidation", default)]
+    pub data_validations: Vec<DataValidation>,
+}
+
+impl DataValidations {
+    pub fn to_xml(&self) -> String {
+        let mut xml = format!(
+            r#"<dataValidations count="{}""#,
+            self.data_validations.len()
+        );
+        if let Some(disable) = self.disable_prompts {
+            xml.push_str(&format!(r#" disablePrompts="{}""#, disable as u8));
+        }
+        xml.push('>');
+        for validation in &self.data_validations {
+            xml.push_str(&validation.to_xml());
+        }
+        xml.push_str("</dataValidations>");
+        xml
+    }
+}
+
+/// A cell value violating a data validation rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidCell {
+    pub sheet: String,
+    pub cell: CellRef,
+    pub value: CellValue,
+    pub rule: DataValidation,
+}
+
+/// Read the rules of the `x14` extension `<x14:dataValidations>` in worksheet xml.
+pub(crate) fn read_x14_data_validations(data: &[u8]) -> Result<Vec<DataValidation>, OoxmlError> {
+    use quick_xml::events::Event;
+
+    fn parse<'de, T: Deserialize<'de>>(value: &'de str) -> Option<T> {
+        let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+            value.into_deserializer();
+        T::deserialize(deserializer).ok()
+    }
+    let flag = |value: &str| Some(value == "1" || value == "true");
+
+    let mut reader = quick_xml::Reader::from_reader(data);
+    let mut buf = Vec::new();
+    let mut validations = Vec::new();
+    let mut path: Vec<Vec<u8>> = Vec::new();
+    let mut in_rule = false;
+    let mut text = String::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(e) => {
+                let name = e.local_name().to_vec();
+                let in_x14 = path.iter().any(|p| p == b"extLst")
+                    && path.last().map(Vec::as_slice) == Some(b"dataValidations");
+                if in_x14 && name == b"dataValidation" {
+                    in_rule = true;
+                    let mut validation = DataValidation::default();
+                    for attr in e.attributes() {
+                        let attr = attr?;
+                        let value = attr.unescape_and_decode_value(&reader)?;
+                        match attr.key {
+                            b"type" => validation.kind = parse(&value).unwrap_or_default(),
+                            b"operator" => validation.operator = parse(&value),
+                            b"errorStyle" => validation.error_style = parse(&value),
+                            b"allowBlank" => validation.allow_blank = flag(&value),
+                            b"showDropDown" => validation.show_drop_down = flag(&value),
+                            b"showInputMessage" => validation.show_input_message = flag(&value),
+                            b"showErrorMessage" => validation.show_error_message = flag(&value),
+                            b"errorTitle" => validation.error_title = Some(value),
+                            b"error" => validation.error = Some(value),
+                            b"promptTitle" => validation.prompt_title = Some(value),
+                            b"prompt" => validation.prompt = Some(value),
+                            _ => {}
+                        }
+                    }
+                    validations.push(validation);
+                }
+                text.clear();
+                path.push(name);
+            }
+            Event::Text(e) if in_rule => text.push_str(&e.unescape_and_decode(&reader)?),
+            Event::End(_) => {
+                let name = path.pop().unwrap_or_default();
+                if let (true, Some(validation)) = (in_rule, validations.last_mut()) {
+                    let text = text.trim().to_string();
+                    match (name.as_slice(), path.last().map(Vec::as_slice)) {
+                        (b"f", Some(b"formula1")) => validation.formula1 = Some(text),
+                        (b"f", Some(b"formula2")) => validation.formula2 = Some(text),
+                        (b"sqref", Some(b"dataValidation")) => validation.sqref = text,
+                        (b"dataValidation", _) => in_rule = false,
+                        _ => {}
+                    }
+                }
+                text.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(validations)
+}
+
+#[test]
+fn data_validations() {
+    let xml = r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:x14="http://schemas.microsoft.com/office/spreadsheetml/2009/9/main" xmlns:xm="http://schemas.microsoft.com/office/excel/2006/main"><sheetData/>
+<dataValidations count="2"><dataValidation type="whole" operator="between" allowBlank="1" showErrorMessage="1" errorTitle="Age" error="1 to 120" sqref="B2:B13 D2"><formula1>1</formula1><formula2>120</formula2></dataValidation><dataValidation type="list" sqref="C2:C13"><formula1>"Yes,No"</formula1></dataValidation></dataValidations>
+<extLst><ext uri="{CCE6A557-97BC-4b89-ADB6-D9C93CAAB3DF}"><x14:dataValidations count="1"><x14:dataValidation type="list" allowBlank="1" showInputMessage="1" promptTitle="Region"><x14:formula1><xm:f>Sheet2!$A$1:$A$3</xm:f></x14:formula1><xm:sqref>E2:E13</xm:sqref></x14:dataValidation></x14:dataValidations></ext></extLst></worksheet>"#;
+    let start = xml.find("<dataValidations").unwrap();
+    let end = xml.find("</dataValidations>").unwrap() + "</dataValidations>".len();
+    let rules: DataValidations = quick_xml::de::from_str(&xml[start..end]).unwrap();
+    let rules = rules.data_validations;
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].kind, ValidationType::Whole);
+    assert_eq!(rules[0].operator(), ValidationOperator::Between);
+    assert_eq!(rules[0].error.as_deref(), Some("1 to 120"));
+    assert_eq!(rules[0].ranges().len(), 2);
+    assert!(rules[0].contains(&"D2".parse().unwrap()));
+    assert_eq!(rules[0].formulas_at("B5".parse().unwrap()), ["1", "120"]);
+    assert_eq!(rules[1].list_values().unwrap(), ["Yes", "No"]);
+    let reread: DataValidations = quick_xml::de::from_str(
+        &DataValidations {
+            data_validations: rules.clone(),
+            ..Default::default()
+        }
+        .to_xml(),
+    )
+    .unwrap();
+    assert_eq!(reread.data_validations, rules);
+
+    let x14 = read_x14_data_validations(xml.as_bytes()).unwrap();
+    assert_eq!(x14.len(), 1);
+    assert_eq!(x14[0].kind, ValidationType::List);
+    assert_eq!(x14[0].formula1.as_deref(), Some("Sheet2!$A$1:$A$3"));
+    assert_eq!(x14[0].sqref, "E2:E13");
+    assert_eq!(x14[0].prompt_title.as_deref(), Some("Region"));
+    assert_eq!(x14[0].allow_blank, Some(true));
+
+    let n = Value::Number;
+    let whole = &rules[0];
+    assert!(whole.is_valid(&n(18.), &[n(1.), n(120.)]));
+    assert!(!whole.is_valid(&n(18.5), &[n(1.), n(120.)]));
+    assert!(!whole.is_valid(&n(121.), &[n(1.), n(120.)]));
+    assert!(!whole.is_valid(&Value::Text("18".into()), &[n(1.), n(120.)]));
+    assert!(whole.is_valid(&Value::Empty, &[n(1.), n(120.)]));
+    let list = &rules[1];
+    assert!(list.is_valid(&Value::Text("yes".into()), &[Value::Text("Yes,No".into())]));
+    assert!(!list.is_valid(
+        &Value::Text("Maybe".into()),
+        &[Value::Text("Yes,No".into())]
+    ));
+    assert!(!list.is_valid(&Value::Empty, &[Value::Text("Yes,No".into())]));
+    let range = Value::Array(vec![vec![n(1.)], vec![Value::Text("East".into())]]);
+    assert!(x14[0].is_valid(&n(1.), std::slice::from_ref(&range)));
+    assert!(!x14[0].is_valid(&Value::Text("West".into()), &[range]));
+
+    let cells = ["A1:A3".parse().unwrap()];
+    let length = DataValidation::new(ValidationType::TextLength, &cells).with_operator(
+        ValidationOperator::LessThanOrEqual,
+        "=5",
+        None,
+    );
+    assert!(length.is_valid(&Value::Text("hello".into()), &[n(5.)]));
+    assert!(!length.is_valid(&Value::Text("hello!".into()), &[n(5.)]));
+    let time = DataValidation::new(ValidationType::Time, &cells).with_operator(
+        ValidationOperator::GreaterThan,
+        "0.5",
+        None,
+    );
+    assert!(time.is_valid(&n(44000.75), &[n(0.5)]));
+    let custom = DataValidation::custom(&cells, "=ISNUMBER(A1)");
+    assert_eq!(custom.formulas_at("A3".parse().unwrap()), ["ISNUMBER(A3)"]);
+    assert!(!custom.is_valid(&n(1.), &[Value::Bool(false)]));
+    let list = DataValidation::list(&cells, &["a \"b\"", "c"]);
+    assert_eq!(list.list_values().unwrap(), ["a \"b\"", "c"]);
+}
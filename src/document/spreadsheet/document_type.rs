@@ -38,13 +38,22 @@ impl SpreadsheetDocumentType {
         }
     }
     pub fn from_content_type(content_type: &str) -> Self {
+        Self::try_from_content_type(content_type)
+            .expect("unsupported spread sheet document content type")
+    }
+    /// Document type of the main part content type, `None` if it's not a spreadsheet.
+    pub fn try_from_content_type(content_type: &str) -> Option<Self> {
         match content_type {
-            WORKBOOK_CONTENT_TYPE => SpreadsheetDocumentType::Workbook,
-            TEMPLATE_CONTENT_TYPE => SpreadsheetDocumentType::Template,
-            MACRO_ENABLED_WORKBOOK_CONTENT_TYPE => SpreadsheetDocumentType::MacroEnabledWorkbook,
-            MACRO_ENABLED_TEMPLATE_CONTENT_TYPE => SpreadsheetDocumentType::MacroEnabledTemplate,
-            ADDIN_CONTENT_TYPE => SpreadsheetDocumentType::AddIn,
-            _ => panic!("unsupported spread sheet document content type"),
+            WORKBOOK_CONTENT_TYPE => Some(SpreadsheetDocumentType::Workbook),
+            TEMPLATE_CONTENT_TYPE => Some(SpreadsheetDocumentType::Template),
+            MACRO_ENABLED_WORKBOOK_CONTENT_TYPE => {
+                Some(SpreadsheetDocumentType::MacroEnabledWorkbook)
+            }
+            MACRO_ENABLED_TEMPLATE_CONTENT_TYPE => {
+                Some(SpreadsheetDocumentType::MacroEnabledTemplate)
+            }
+            ADDIN_CONTENT_TYPE => Some(SpreadsheetDocumentType::AddIn),
+            _ => None,
         }
     }
 }
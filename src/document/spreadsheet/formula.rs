@@ -0,0 +1,428 @@
+//! Cell formulas `<f>`, see ECMA-376 Part 1, 18.3.1.40.
+//!
+//! Shared formulas are stored once in the master cell, the other cells of the group refer to
+//! it by the shared index `si`. Their concrete formulas are the master one with relative
+//! references shifted by the offset to the master cell.
+//!
+//! ```rust
+//! use ooxml::document::formula::shift_references;
+//!
+//! assert_eq!(shift_references("SUM($A1:B1)*2", 2, 1), "SUM($A3:C3)*2");
+//! ```
+use std::fmt;
+
+use super::reference::{column_name, split_a1, CellRange, CellRef, MAX_COLUMNS, MAX_ROWS};
+
+/// Formula type of the `t` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FormulaKind {
+    #[default]
+    Normal,
+    /// `shared`, one formula for a group of cells.
+    Shared,
+    /// `array`, one formula spilled over the `ref` range.
+    Array,
+    /// `dataTable`, a what-if analysis table.
+    DataTable,
+}
+
+impl FormulaKind {
+    pub fn from_type(t: Option<&str>) -> Self {
+        match t {
+            Some("shared") => FormulaKind::Shared,
+            Some("array") => FormulaKind::Array,
+            Some("dataTable") => FormulaKind::DataTable,
+            _ => FormulaKind::Normal,
+        }
+    }
+
+    pub fn as_type(&self) -> Option<&'static str> {
+        match self {
+            FormulaKind::Normal => None,
+            FormulaKind::Shared => Some("shared"),
+            FormulaKind::Array => Some("array"),
+            FormulaKind::DataTable => Some("dataTable"),
+        }
+    }
+}
+
+/// Inputs of a data table formula.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DataTableInputs {
+    /// `dt2D`, the table has both row and column inputs.
+    pub two_dimensional: bool,
+    /// `dtr`, the one dimensional input is a row input.
+    pub row_input: bool,
+    /// `r1`, the first input cell.
+    pub first: Option<CellRef>,
+    /// `r2`, the second input cell of two dimensional tables.
+    pub second: Option<CellRef>,
+    /// `del1`, the first input cell was deleted.
+    pub first_deleted: bool,
+    /// `del2`, the second input cell was deleted.
+    pub second_deleted: bool,
+}
+
+/// A cell formula.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Formula {
+    pub kind: FormulaKind,
+    /// Formula text without the leading `=`, empty for cells of a shared group.
+    pub text: String,
+    /// `ref`, cells of an array, data table or shared formula group.
+    pub range: Option<CellRange>,
+    /// `si`, shared group index.
+    pub shared_index: Option<usize>,
+    /// `ca`, calculate the cell on every recalculation.
+    pub always_calculate: bool,
+    pub data_table: Option<DataTableInputs>,
+}
+
+impl Formula {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Check if the formula is the master cell of a shared group, which holds the text.
+    pub fn is_shared_master(&self) -> bool {
+        self.kind == FormulaKind::Shared && !self.text.is_empty()
+    }
+
+    /// The formula of a cell moved by `rows` and `cols`, relative references are shifted.
+    pub fn shifted(&self, rows: i64, cols: i64) -> Formula {
+        Formula {
+            text: shift_references(&self.text, rows, cols),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "={}", self.text)
+    }
+}
+
+/// Shift a relative position, `None` if out of the sheet.
+fn shift(index: u32, absolute: bool, offset: i64, max: u32) -> Option<u32> {
+    if absolute {
+        return Some(index);
+    }
+    let index = index as i64 + offset;
+    if (0..max as i64).contains(&index) {
+        Some(index as u32)
+    } else {
+        None
+    }
+}
+
+/// Shift one A1 reference part, `None` for words that are not references, `Some(None)` for
+/// references moved out of the sheet.
+fn shift_part(word: &str, rows: i64, cols: i64) -> Option<Option<String>> {
+    let (col, row) = split_a1(word)?;
+    let marker = |absolute: bool| if absolute { "$" } else { "" };
+    let mut shifted = String::new();
+    if let Some((absolute, col)) = col {
+        match shift(col, absolute, cols, MAX_COLUMNS) {
+            Some(col) => {
+                shifted.push_str(marker(absolute));
+                shifted.push_str(&column_name(col));
+            }
+            None => return Some(None),
+        }
+    }
+    if let Some((absolute, row)) = row {
+        match shift(row, absolute, rows, MAX_ROWS) {
+            Some(row) => {
+                shifted.push_str(marker(absolute));
+                shifted.push_str(&(row + 1).to_string());
+            }
+            None => return Some(None),
+        }
+    }
+    Some(Some(shifted))
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '$' || c == '\\'
+}
+
+/// Shift relative A1 references in a formula by `rows` and `cols`, as Excel does when a
+/// formula is copied. References moved out of the sheet become `#REF!`.
+///
+/// Strings, quoted sheet names, structured references and function names are kept as they
+/// are. Column only and row only parts are shifted in ranges like `A:B` and `1:3` only.
+pub fn shift_references(formula: &str, rows: i64, cols: i64) -> String {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut output = String::with_capacity(formula.len());
+    let mut i = 0;
+    let word_end = |start: usize| {
+        let mut end = start;
+        while end < chars.len() && is_word_char(chars[end]) {
+            end += 1;
+        }
+        end
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' => {
+                // string literal or quoted sheet name, quotes are escaped by doubling.
+                output.push(c);
+                i += 1;
+                while i < chars.len() {
+                    output.push(chars[i]);
+                    i += 1;
+                    if chars[i - 1] == c {
+                        if chars.get(i) == Some(&c) {
+                            output.push(c);
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+            }
+            '[' => {
+                let mut depth = 0;
+                while i < chars.len() {
+                    match chars[i] {
+                        '[' => depth += 1,
+                        ']' => depth -= 1,
+                        _ => {}
+                    }
+                    output.push(chars[i]);
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            c if is_word_char(c) => {
+                let end = word_end(i);
+                let word: String = chars[i..end].iter().collect();
+                let next = chars.get(end).copied();
+                if matches!(next, Some('(') | Some('!') | Some('[')) {
+                    output.push_str(&word);
+                    i = end;
+                    continue;
+                }
+                // a range like `A1:B2`, `A:B` or `1:3`.
+                if next == Some(':') && chars.get(end + 1).is_some_and(|c| is_word_char(*c)) {
+                    let second_end = word_end(end + 1);
+                    let second: String = chars[end + 1..second_end].iter().collect();
+                    let same_shape = match (split_a1(&word), split_a1(&second)) {
+                        (Some((a_col, a_row)), Some((b_col, b_row))) => {
+                            a_col.is_some() == b_col.is_some() && a_row.is_some() == b_row.is_some()
+                        }
+                        _ => false,
+                    };
+                    if same_shape && chars.get(second_end) != Some(&'(') {
+                        let first = shift_part(&word, rows, cols).flatten();
+                        let second = shift_part(&second, rows, cols).flatten();
+                        match (first, second) {
+                            (Some(first), Some(second)) => {
+                                output.push_str(&first);
+                                output.push(':');
+                                output.push_str(&second);
+                            }
+                            _ => output.push_str("#REF!"),
+                        }
+                        i = second_end;
+                        continue;
+                    }
+                }
+                match split_a1(&word) {
+                    Some((Some(_), Some(_))) => match shift_part(&word, rows, cols).flatten() {
+                        Some(shifted) => output.push_str(&shifted),
+                        None => output.push_str("#REF!"),
+                    },
+                    _ => output.push_str(&word),
+                }
+                i = end;
+            }
+            c => {
+                output.push(c);
+                i += 1;
+            }
+        }
+    }
+    output
+}
+
+/// Rename references to a defined name in a formula, `None` if no reference is renamed.
+///
+/// `resolves` tells whether a reference refers to the renaming name by its sheet qualifier,
+/// like `Sheet1` of `Sheet1!Name`, or `None` for unqualified ones. Strings, function names,
+/// tables and names of external workbooks are kept as they are.
+pub fn rename_name_references(
+    formula: &str,
+    name: &str,
+    new_name: &str,
+    resolves: impl Fn(Option<&str>) -> bool,
+) -> Option<String> {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut output = String::with_capacity(formula.len());
+    let mut renamed = false;
+    // sheet qualifier of the next word, `Err` for external workbook references.
+    let mut qualifier: Option<Result<String, ()>> = None;
+    let mut external = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() {
+                    i += 1;
+                    if chars[i - 1] == c {
+                        if chars.get(i) == Some(&c) {
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                output.extend(&chars[start..i]);
+                qualifier = match (c, chars.get(i)) {
+                    ('\'', Some('!')) if external => Some(Err(())),
+                    ('\'', Some('!')) => {
+                        let quoted: String = chars[start + 1..i - 1].iter().collect();
+                        Some(Ok(quoted.replace("''", "'")))
+                    }
+                    _ => None,
+                };
+                external = false;
+            }
+            '[' => {
+                let start = i;
+                let mut depth = 0;
+                while i < chars.len() {
+                    match chars[i] {
+                        '[' => depth += 1,
+                        ']' => depth -= 1,
+                        _ => {}
+                    }
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                output.extend(&chars[start..i]);
+                // a workbook index like `[1]` before a sheet or a name
+                external = start == 0 || !is_word_char(chars[start - 1]);
+            }
+            '!' => {
+                output.push(c);
+                i += 1;
+            }
+            c if is_word_char(c) => {
+                let start = i;
+                while i < chars.len() && is_word_char(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let next = chars.get(i).copied();
+                if next == Some('!') {
+                    qualifier = Some(if external { Err(()) } else { Ok(word) });
+                    external = false;
+                    output.extend(&chars[start..i]);
+                    continue;
+                }
+                let qualified = if start > 0 && chars[start - 1] == '!' {
+                    qualifier.take()
+                } else {
+                    None
+                };
+                let matched = word.to_lowercase() == name.to_lowercase()
+                    && !matches!(next, Some('(') | Some('['))
+                    && !external
+                    && match &qualified {
+                        Some(Ok(sheet)) => resolves(Some(sheet)),
+                        Some(Err(())) => false,
+                        None => resolves(None),
+                    };
+                if matched {
+                    output.push_str(new_name);
+                    renamed = true;
+                } else {
+                    output.push_str(&word);
+                }
+                external = false;
+            }
+            c => {
+                output.push(c);
+                qualifier = None;
+                external = false;
+                i += 1;
+            }
+        }
+    }
+    if renamed {
+        Some(output)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn shift_formula_references() {
+    let cases = [
+        ("A1+B2", 1, 1, "B2+C3"),
+        ("$A$1+$A1+A$1", 2, 2, "$A$1+$A3+C$1"),
+        ("SUM(A1:B3)", 1, 0, "SUM(A2:B4)"),
+        ("SUM(A:B)+SUM(1:2)", 1, 1, "SUM(B:C)+SUM(2:3)"),
+        ("Sheet2!A1*'My Sheet'!B1", 0, 1, "Sheet2!B1*'My Sheet'!C1"),
+        ("\"A1\"&A1", 1, 0, "\"A1\"&A2"),
+        ("LOG10(A1)+ATAN2(1,2)", 1, 0, "LOG10(A2)+ATAN2(1,2)"),
+        (
+            "Table1[[#This Row],[A1]]*2",
+            1,
+            0,
+            "Table1[[#This Row],[A1]]*2",
+        ),
+        ("A1*1.5E+3", 0, 1, "B1*1.5E+3"),
+        ("A1+TRUE", -1, 0, "#REF!+TRUE"),
+        ("SUM(A1:A2)", -1, 0, "SUM(#REF!)"),
+        ("XFD1", 0, 1, "#REF!"),
+    ];
+    for (formula, rows, cols, expected) in cases {
+        assert_eq!(
+            shift_references(formula, rows, cols),
+            expected,
+            "shift {} by ({}, {})",
+            formula,
+            rows,
+            cols
+        );
+    }
+}
+
+#[test]
+fn rename_formula_names() {
+    let global = |sheet: Option<&str>| sheet.is_none();
+    let cases = [
+        ("Rate*2", Some("Tax*2")),
+        ("SUM(rate,A1)+RATE(1,2,3)", Some("SUM(Tax,A1)+RATE(1,2,3)")),
+        ("\"Rate\"&Rates&Rate.1", None),
+        ("Sheet1!Rate+Rate", Some("Sheet1!Rate+Tax")),
+        ("[1]!Rate+[1]Sheet1!Rate+Rate[Col]", None),
+    ];
+    for (formula, expected) in cases {
+        assert_eq!(
+            rename_name_references(formula, "Rate", "Tax", global).as_deref(),
+            expected,
+            "rename in {}",
+            formula
+        );
+    }
+    let local = |sheet: Option<&str>| sheet.is_some_and(|sheet| sheet == "My Sheet");
+    assert_eq!(
+        rename_name_references("'My Sheet'!Rate+Sheet2!Rate+Rate", "Rate", "Tax", local).as_deref(),
+        Some("'My Sheet'!Tax+Sheet2!Rate+Rate")
+    );
+}
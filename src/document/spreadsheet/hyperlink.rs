@@ -0,0 +1,130 @@
+//! Worksheet hyperlinks `<hyperlink>`, see ECMA-376 Part 1, 18.3.1.47.
+//!
+//! External hyperlinks keep their urls in the worksheet relationships part and refer to them
+//! by `r:id`, internal ones jump to a `location` like `Sheet2!A1` or a defined name.
+use serde::{Deserialize, Serialize};
+
+use super::reference::CellRange;
+use super::RELATIONSHIPS_NAMESPACE;
+
+/// A resolved worksheet hyperlink.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hyperlink {
+    /// Cells of the hyperlink.
+    pub range: CellRange,
+    /// Target url of external hyperlinks.
+    pub url: Option<String>,
+    /// Location in the workbook, or the fragment of the url of external hyperlinks.
+    pub location: Option<String>,
+    /// Text to display, it should be the cell text.
+    pub display: Option<String>,
+    /// Tooltip shown on hover.
+    pub tooltip: Option<String>,
+}
+
+impl Hyperlink {
+    /// Hyperlink to an external url.
+    pub fn external(range: CellRange, url: impl Into<String>) -> Self {
+        Self {
+            range,
+            url: Some(url.into()),
+            location: None,
+            display: None,
+            tooltip: None,
+        }
+    }
+
+    /// Hyperlink to a location in the workbook, like `'My Sheet'!A1`.
+    pub fn internal(range: CellRange, location: impl Into<String>) -> Self {
+        Self {
+            range,
+            url: None,
+            location: Some(location.into()),
+            display: None,
+            tooltip: None,
+        }
+    }
+
+    pub fn with_display(self, display: impl Into<String>) -> Self {
+        Self {
+            display: Some(display.into()),
+            ..self
+        }
+    }
+
+    pub fn with_tooltip(self, tooltip: impl Into<String>) -> Self {
+        Self {
+            tooltip: Some(tooltip.into()),
+            ..self
+        }
+    }
+
+    pub fn is_external(&self) -> bool {
+        self.url.is_some()
+    }
+}
+
+/// Hyperlink element of worksheet.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "hyperlink")]
+pub struct SheetHyperlink {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    /// Relationship id of the external url.
+    #[serde(rename = "r:id")]
+    pub r_id: Option<String>,
+    pub location: Option<String>,
+    pub display: Option<String>,
+    pub tooltip: Option<String>,
+}
+
+/// Hyperlinks `<hyperlinks>` of a worksheet.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "hyperlinks")]
+pub struct SheetHyperlinks {
+    #[serde(rename = "hyperlink", default)]
+    pub hyperlinks: Vec<SheetHyperlink>,
+}
+
+impl SheetHyperlinks {
+    pub fn to_xml(&self) -> String {
+        let escape =
+            |s: &str| String::from_utf8_lossy(&quick_xml::escape::escape(s.as_bytes())).to_string();
+        let mut xml = String::from("<hyperlinks");
+        if self.hyperlinks.iter().any(|link| link.r_id.is_some()) {
+            xml.push_str(&format!(r#" xmlns:r="{}""#, RELATIONSHIPS_NAMESPACE));
+        }
+        xml.push('>');
+        for link in &self.hyperlinks {
+            xml.push_str(&format!(r#"<hyperlink ref="{}""#, escape(&link.reference)));
+            let attributes = [
+                ("r:id", &link.r_id),
+                ("location", &link.location),
+                ("display", &link.display),
+                ("tooltip", &link.tooltip),
+            ];
+            for (name, value) in attributes {
+                if let Some(value) = value {
+                    xml.push_str(&format!(r#" {}="{}""#, name, escape(value)));
+                }
+            }
+            xml.push_str("/>");
+        }
+        xml.push_str("</hyperlinks>");
+        xml
+    }
+}
+
+#[test]
+fn hyperlinks_xml() {
+    let xml = r#"<hyperlinks><hyperlink ref="A1" r:id="rId1" tooltip="a &amp; b"/><hyperlink ref="B2:C3" location="Sheet2!A1" display="Go"/></hyperlinks>"#;
+    let links: SheetHyperlinks = quick_xml::de::from_str(xml).unwrap();
+    assert_eq!(links.hyperlinks.len(), 2);
+    assert_eq!(links.hyperlinks[0].r_id.as_deref(), Some("rId1"));
+    assert_eq!(links.hyperlinks[0].tooltip.as_deref(), Some("a & b"));
+    assert_eq!(links.hyperlinks[1].location.as_deref(), Some("Sheet2!A1"));
+    assert_eq!(
+        links.to_xml(),
+        r#"<hyperlinks xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><hyperlink ref="A1" r:id="rId1" tooltip="a &amp; b"/><hyperlink ref="B2:C3" location="Sheet2!A1" display="Go"/></hyperlinks>"#
+    );
+}
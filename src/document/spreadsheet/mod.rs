@@ -1,39 +1,95 @@
 //! Excel file format .xlsx document implementation.
 
-use std::{cell::RefCell, path::Path, rc::Rc, io::{Read, Seek, Cursor}};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::BTreeMap,
+    io::{Cursor, Read, Seek},
+    path::Path,
+    rc::Rc,
+};
 
 // use derivative::Derivative;
 use derivative::Derivative;
 
-use static_init::dynamic;
-
 use crate::{
-    error::Result,
+    error::{OoxmlError, Result},
+    packaging::conformance::Conformance,
+    packaging::content_type::ContentType,
     packaging::element::*,
     packaging::package::{OpenXmlPackage, Relationships},
+    packaging::relationship::{
+        relationships_uri, relative_target, resolve_target, Relationship, RelationshipType,
+    },
 };
 
+pub mod calc;
 mod cell;
 mod chart;
+mod comment;
+mod conditional_format;
+mod data_validation;
 mod document_type;
 mod drawing;
+pub mod formula;
+mod hyperlink;
 mod media;
+pub mod number_format;
+pub mod reference;
 mod shared_string;
 mod style;
+mod table;
+mod threaded_comment;
 mod workbook;
 mod worksheet;
 
+pub use self::calc::Calculation;
+pub use self::cell::{CellError, CellValue, DateSystem};
+pub use self::comment::Comment;
+pub use self::conditional_format::{
+    ColorScale, ConditionalFormatOperator, ConditionalFormatRule, ConditionalFormatType,
+    ConditionalFormatting, DataBar, IconSet, Threshold, ThresholdType, TimePeriod,
+};
+pub use self::data_validation::{
+    DataValidation, InvalidCell, ValidationErrorStyle, ValidationOperator, ValidationType,
+};
+pub use self::document_type::SpreadsheetDocumentType;
+pub use self::formula::{Formula, FormulaKind};
+pub use self::hyperlink::Hyperlink;
+pub use self::number_format::{DateTimeKind, NumberFormatCode};
+pub use self::reference::{CellRange, CellRef, SheetRef};
+pub use self::shared_string::{
+    PhoneticProperties, PhoneticRun, RunColor, RunProperties, SharedString, TextRun,
+};
+pub use self::style::{
+    BooleanProperty, BorderEdge, Color, DifferentialBorder, DifferentialFill, DifferentialFont,
+    DifferentialFormat, DifferentialPatternFill, Underline,
+};
+pub use self::table::{
+    AutoFilter, Table, TableColumn, TableColumns, TableStyleInfo, DEFAULT_TABLE_STYLE,
+};
+pub use self::threaded_comment::{CommentThread, Person, ThreadedComment};
+pub use self::workbook::{is_valid_name, DefinedName, PRINT_AREA, PRINT_TITLES};
 use self::{
-    document_type::SpreadsheetDocumentType,
     style::{CellFormatComponent, CellStyleComponent},
     worksheet::SheetCol,
 };
 
-use self::cell::CellValue;
+use self::comment::CommentsPart;
+use self::data_validation::DataValidations;
+use self::hyperlink::SheetHyperlink;
 use self::shared_string::SharedStringsPart;
 use self::style::StylesPart;
-use self::workbook::WorkbookPart;
-use self::worksheet::WorksheetPart;
+use self::threaded_comment::{PersonsPart, ThreadedCommentsPart};
+use self::workbook::{DefinedNames, WorkbookPart};
+use self::worksheet::{CellGrid, MergeCells, WorksheetPart};
+
+const WORKBOOK_URI: &str = "xl/workbook.xml";
+const SHARED_STRINGS_URI: &str = "xl/sharedStrings.xml";
+const STYLES_URI: &str = "xl/styles.xml";
+const PERSONS_URI: &str = "xl/persons/person.xml";
+const RELATIONSHIPS_NAMESPACE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
 
 #[derive(Derivative, Clone, Default)]
 #[derivative(Debug)]
@@ -41,6 +97,8 @@ pub struct SpreadsheetParts {
     initialized: bool,
     #[derivative(Debug = "ignore")]
     pub package: Rc<RefCell<OpenXmlPackage>>,
+    /// Uri of the workbook part, eg. `xl/workbook.xml`.
+    pub workbook_uri: String,
     pub relationships: Relationships,
     pub workbook: WorkbookPart,
     pub styles: StylesPart,
@@ -53,32 +111,53 @@ pub struct SpreadsheetParts {
 }
 
 impl SpreadsheetParts {
-    pub fn from_package(package: Rc<RefCell<OpenXmlPackage>>) -> Self {
-        // let parts = package.borrow();
-        // let part = parts.get_part("xl/_rels/workbook.xml.rels").unwrap();
+    /// Parse parts of a spreadsheet package, shared strings and styles are optional.
+    pub fn from_package(package: Rc<RefCell<OpenXmlPackage>>) -> Result<Self> {
+        let workbook_uri = package
+            .borrow()
+            .main_part_uri()
+            .unwrap_or_else(|| WORKBOOK_URI.to_string());
         let relationships = {
             let package = package.borrow();
-            let part = package.get_part("xl/_rels/workbook.xml.rels").unwrap();
+            let uri = relationships_uri(&workbook_uri);
+            let part = package
+                .get_part(&uri)
+                .ok_or(OoxmlError::PartNotFound(uri))?;
             Relationships::parse_from_xml_reader(part.as_part_bytes())
         };
         let workbook = {
             let package = package.borrow();
-            let part = package.get_part("xl/workbook.xml").unwrap();
-            WorkbookPart::from_xml_reader(part.as_part_bytes()).expect("workbook main part error")
+            let part = package
+                .get_part(&workbook_uri)
+                .ok_or_else(|| OoxmlError::PartNotFound(workbook_uri.clone()))?;
+            WorkbookPart::from_xml_reader(part.as_part_bytes())?
+        };
+        let part_uri = |relationship_type: RelationshipType, default: &str| {
+            relationships
+                .get_relationship_by_type(&relationship_type)
+                .map(|r| resolve_target(&workbook_uri, r.target()))
+                .unwrap_or_else(|| default.to_string())
         };
         let shared_strings = {
             let package = package.borrow();
-            let part = package.get_part("xl/sharedStrings.xml").unwrap();
-            SharedStringsPart::from_xml_reader(part.as_part_bytes())
-                .expect("workbook shared strings error")
+            match package.get_part(&part_uri(
+                RelationshipType::SharedStrings,
+                SHARED_STRINGS_URI,
+            )) {
+                Some(part) => SharedStringsPart::from_xml_reader(part.as_part_bytes())?,
+                None => SharedStringsPart::default(),
+            }
         };
         let styles = {
             let package = package.borrow();
-            let part = package.get_part("xl/styles.xml").unwrap();
-            StylesPart::from_xml_reader(part.as_part_bytes()).expect("workbook styles error")
+            match package.get_part(&part_uri(RelationshipType::Styles, STYLES_URI)) {
+                Some(part) => StylesPart::from_xml_reader(part.as_part_bytes())?,
+                None => StylesPart::default(),
+            }
         };
         let mut this = Self {
-            package: package,
+            package,
+            workbook_uri,
             relationships,
             workbook,
             shared_strings,
@@ -86,8 +165,8 @@ impl SpreadsheetParts {
             initialized: true,
             ..Default::default()
         };
-        this.parse_worksheets();
-        this
+        this.parse_worksheets()?;
+        Ok(this)
     }
 
     pub fn get_shared_string(&self, idx: usize) -> Option<&str> {
@@ -100,6 +179,12 @@ impl SpreadsheetParts {
     pub fn get_cell_format<'a>(&'a self, id: usize) -> Option<CellFormatComponent<'a>> {
         self.styles.get_cell_format_component(id)
     }
+    /// Check if the cell format is for date or time.
+    pub fn is_date_style(&self, id: usize) -> bool {
+        self.get_cell_format(id)
+            .and_then(|cf| cf.number_format().map(|nf| is_datetime_format(&nf.code)))
+            .unwrap_or_default()
+    }
     pub fn get_worksheet_part<T: AsRef<str>>(&self, uri: T) -> Option<&WorksheetPart> {
         self.worksheets.get(uri.as_ref())
     }
@@ -108,24 +193,114 @@ impl SpreadsheetParts {
         self.workbook.sheet_names()
     }
 
-    fn parse_worksheets(&mut self) {
+    /// The person list of threaded comment authors, empty if the workbook has none.
+    pub fn persons_part(&self) -> PersonsPart {
+        let package = self.package.borrow();
+        package
+            .find_related_part_uri(&self.workbook_uri, &RelationshipType::Person)
+            .and_then(|uri| package.get_part(&uri))
+            .and_then(|part| PersonsPart::from_xml_reader(part.as_part_bytes()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Tables of all worksheets with their worksheet and table part uris.
+    pub fn tables(&self) -> Vec<(String, String, Table)> {
+        let package = self.package.borrow();
+        let mut tables = Vec::new();
+        for sheet_uri in self.worksheets.keys() {
+            let relationships = match package.get_part_relationships(sheet_uri) {
+                Some(relationships) => relationships,
+                None => continue,
+            };
+            for relationship in relationships.get_relationships_by_type(&RelationshipType::Table) {
+                if relationship.is_external() {
+                    continue;
+                }
+                let uri = resolve_target(sheet_uri, relationship.target());
+                let table = package
+                    .get_part(&uri)
+                    .and_then(|part| Table::from_xml_reader(part.as_part_bytes()).ok());
+                if let Some(table) = table {
+                    tables.push((sheet_uri.clone(), uri, table));
+                }
+            }
+        }
+        tables
+    }
+
+    /// Add a differential format to the style sheet and return its id.
+    ///
+    /// The style sheet part and its relationship from workbook are created if not exist.
+    pub fn add_differential_format(&mut self, dxf: &DifferentialFormat) -> Result<usize> {
+        {
+            let mut package = self.package.borrow_mut();
+            let related =
+                package.find_related_part_uri(&self.workbook_uri, &RelationshipType::Styles);
+            let uri = related.clone().unwrap_or_else(|| STYLES_URI.to_string());
+            let count = self.styles.differential_formats().len();
+            let data = match package.get_part(&uri) {
+                Some(part) => part.as_part_bytes(),
+                None => style::DEFAULT_STYLE_SHEET.as_bytes(),
+            };
+            let data = style::append_differential_format(data, count, dxf)?;
+            if package.get_part(&uri).is_some() {
+                package.set_part_data(&uri, data);
+            } else {
+                package.create_part_core_with_data(&uri, &ContentType::SpreadsheetStyles, &data)?;
+            }
+            if related.is_none() {
+                let id = self.relationships.next_id();
+                self.relationships.add_relationship(Relationship::new(
+                    id,
+                    RelationshipType::Styles,
+                    relative_target(&self.workbook_uri, STYLES_URI),
+                ));
+                let uri = relationships_uri(&self.workbook_uri);
+                package.set_part_data(&uri, self.relationships.to_string().into_bytes());
+            }
+        }
+        Ok(self.styles.push_differential_format(dxf.clone()))
+    }
+
+    /// Write the person list, the part and its relationship from workbook are created if not exist.
+    pub fn set_persons_part(&mut self, persons: &PersonsPart) -> Result<()> {
+        let data = persons.to_string().into_bytes();
+        let mut package = self.package.borrow_mut();
+        if let Some(uri) =
+            package.find_related_part_uri(&self.workbook_uri, &RelationshipType::Person)
+        {
+            package.set_part_data(&uri, data);
+            return Ok(());
+        }
+        package.create_part_core_with_data(PERSONS_URI, &ContentType::Person, &data)?;
+        let id = self.relationships.next_id();
+        self.relationships.add_relationship(Relationship::new(
+            id,
+            RelationshipType::Person,
+            relative_target(&self.workbook_uri, PERSONS_URI),
+        ));
+        let uri = relationships_uri(&self.workbook_uri);
+        package.set_part_data(&uri, self.relationships.to_string().into_bytes());
+        Ok(())
+    }
+
+    fn parse_worksheets(&mut self) -> Result<()> {
         // Parse sheet data by relationship target.
         for sheet in &self.workbook.sheets.sheets {
             let relationship = self
                 .relationships
                 .get_relationship_by_id(&sheet.r_id)
-                .expect("the worksheet relationship doest not exist");
-            let worksheet_uri = relationship.target();
-            // println!("{}", worksheet_uri);
+                .ok_or_else(|| OoxmlError::PartNotFound(sheet.r_id.clone()))?;
+            let worksheet_uri = resolve_target(&self.workbook_uri, relationship.target());
             let package = self.package.borrow();
             let part = package
-                .get_part(&format!("xl/{}", worksheet_uri))
-                .expect("get worksheet part by uri");
-            let sheet = WorksheetPart::from_xml_reader(part.as_part_bytes())
-                .expect("parse worksheet error");
+                .get_part(&worksheet_uri)
+                .ok_or_else(|| OoxmlError::PartNotFound(worksheet_uri.clone()))?;
+            let sheet = WorksheetPart::from_xml_reader(part.as_part_bytes())?;
 
-            self.worksheets.insert(worksheet_uri.into(), sheet);
+            self.worksheets.insert(worksheet_uri, sheet);
         }
+        Ok(())
     }
 }
 #[derive(Derivative, Default)]
@@ -155,36 +330,859 @@ pub struct Worksheet {
     parts: Rc<RefCell<SpreadsheetParts>>,
     name: String,
     sheet_id: usize,
+    /// Worksheet part uri in package.
+    uri: String,
     part: WorksheetPart,
+    grid: CellGrid,
+}
+
+/// Render a number with the format code, or in `General` without it.
+fn format_number(v: f64, code: Option<&NumberFormatCode>) -> String {
+    match code {
+        Some(code) => code.format(v),
+        None => number_format::format_general(v),
+    }
 }
 
-#[dynamic(lazy)]
-static DATETIME_RE: regex::Regex = regex::Regex::new("y{1,4}|m{1,5}|d+|h|ss|a{2,5}").unwrap();
-
-#[dynamic(lazy)]
-static DATETIME_REPLACES: Vec<(regex::Regex, &'static str)> = vec![
-    (regex::Regex::new(":mm").unwrap(), ":%M"),
-    (regex::Regex::new("mm:").unwrap(), "%M:"),
-    (regex::Regex::new("mm").unwrap(), "%m"),
-    (regex::Regex::new("yyyy+").unwrap(), "%Y"),
-    (regex::Regex::new("yy+").unwrap(), "%y"),
-    (regex::Regex::new("mmmm+").unwrap(), "%B"),
-    (regex::Regex::new("mmm").unwrap(), "%b"),
-    (regex::Regex::new("([^%]|^)m").unwrap(), "$1%-m"),
-    (regex::Regex::new("d{2,}").unwrap(), "%d"),
-    (regex::Regex::new("d{1}").unwrap(), "%-d"),
-    (regex::Regex::new("a{4,}").unwrap(), "%A"),
-    (regex::Regex::new("a{3}").unwrap(), "%a"),
-    (regex::Regex::new("a{2}").unwrap(), "%w"),
-    (regex::Regex::new("h").unwrap(), "%H"),
-    (regex::Regex::new("ss").unwrap(), "%S"),
-    (regex::Regex::new("\\\\").unwrap(), ""),
-];
+/// Check if a number format code is for date or time.
+fn is_datetime_format(code: &str) -> bool {
+    NumberFormatCode::parse(code).is_date_time()
+}
 
 impl Worksheet {
+    /// Formulas of all cells by reference, shared formulas are expanded to their concrete forms.
+    pub fn formulas(&self) -> BTreeMap<CellRef, Formula> {
+        self.part.formulas(&self.grid)
+    }
+    /// Merged ranges of the worksheet.
+    pub fn merged_ranges(&self) -> Vec<CellRange> {
+        self.part.merged_ranges()
+    }
+    /// The merged range a cell is in.
+    pub fn merge_area(&self, cell: CellRef) -> Option<CellRange> {
+        self.part
+            .merged_ranges()
+            .into_iter()
+            .find(|range| range.contains(&cell))
+    }
+    /// Merge a range, cells other than the top-left one are hidden by it.
+    ///
+    /// Single cells and ranges overlapping merged ranges can not be merged.
+    pub fn merge_cells(&mut self, range: CellRange) -> Result<()> {
+        if range.is_single_cell() {
+            return Err(OoxmlError::InvalidReference(format!(
+                "can not merge single cell {}",
+                range
+            )));
+        }
+        let mut ranges = self.merged_ranges();
+        if let Some(merged) = ranges.iter().find(|merged| merged.intersects(&range)) {
+            return Err(OoxmlError::InvalidReference(format!(
+                "{} overlaps merged range {}",
+                range, merged
+            )));
+        }
+        ranges.push(range);
+        self.set_merged_ranges(ranges)
+    }
+    /// Unmerge all merged ranges intersecting a range, returns whether any is unmerged.
+    pub fn unmerge_cells(&mut self, range: CellRange) -> Result<bool> {
+        let ranges = self.merged_ranges();
+        let kept: Vec<CellRange> = ranges
+            .iter()
+            .filter(|merged| !merged.intersects(&range))
+            .copied()
+            .collect();
+        if kept.len() == ranges.len() {
+            return Ok(false);
+        }
+        self.set_merged_ranges(kept)?;
+        Ok(true)
+    }
+    /// Write merged ranges to the worksheet part.
+    fn set_merged_ranges(&mut self, ranges: Vec<CellRange>) -> Result<()> {
+        let merge_cells = if ranges.is_empty() {
+            None
+        } else {
+            Some(MergeCells::from_ranges(&ranges))
+        };
+        let xml = merge_cells.as_ref().map(MergeCells::to_xml);
+        self.update_part_element("mergeCells", xml.as_deref())?;
+        self.part.merge_cells = merge_cells;
+        self.parts
+            .borrow_mut()
+            .worksheets
+            .insert(self.uri.clone(), self.part.clone());
+        Ok(())
+    }
+    /// Conditional formatting blocks of the worksheet.
+    pub fn conditional_formattings(&self) -> Vec<ConditionalFormatting> {
+        self.part.conditional_formattings.clone()
+    }
+    /// Conditional format rules applied to a cell, by priority.
+    pub fn conditional_format_rules(&self, cell: CellRef) -> Vec<ConditionalFormatRule> {
+        let mut rules: Vec<ConditionalFormatRule> = self
+            .part
+            .conditional_formattings
+            .iter()
+            .filter(|block| block.contains(&cell))
+            .flat_map(|block| block.rules.iter().cloned())
+            .collect();
+        rules.sort_by_key(|rule| rule.priority);
+        rules
+    }
+    /// The differential format of a conditional format rule by its `dxfId`.
+    pub fn differential_format(&self, id: u32) -> Option<DifferentialFormat> {
+        let parts = self.parts.borrow();
+        parts.styles.get_differential_format(id as usize).cloned()
+    }
+    /// Add a conditional format rule to ranges with the lowest priority of the worksheet.
+    ///
+    /// The format is added to the style sheet as the differential format of the rule, rules
+    /// like color scales have none.
+    pub fn add_conditional_format(
+        &mut self,
+        ranges: &[CellRange],
+        rule: ConditionalFormatRule,
+        format: Option<&DifferentialFormat>,
+    ) -> Result<()> {
+        if ranges.is_empty() {
+            return Err(OoxmlError::InvalidReference(
+                "conditional format without ranges".into(),
+            ));
+        }
+        let mut blocks = self.conditional_formattings();
+        let priority = blocks
+            .iter()
+            .flat_map(|block| &block.rules)
+            .map(|rule| rule.priority)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let dxf_id = match format {
+            Some(format) => Some(self.parts.borrow_mut().add_differential_format(format)? as u32),
+            None => rule.dxf_id,
+        };
+        let rule = ConditionalFormatRule {
+            priority,
+            dxf_id,
+            ..rule
+        };
+        let block = ConditionalFormatting::new(ranges, Vec::new());
+        match blocks.iter_mut().find(|other| other.sqref == block.sqref) {
+            Some(other) => other.rules.push(rule),
+            None => blocks.push(ConditionalFormatting {
+                rules: vec![rule],
+                ..block
+            }),
+        }
+        self.set_conditional_formattings(blocks)
+    }
+    /// Remove conditional formatting blocks intersecting a range, returns whether any is removed.
+    pub fn remove_conditional_formats(&mut self, range: CellRange) -> Result<bool> {
+        let blocks = self.conditional_formattings();
+        let kept: Vec<ConditionalFormatting> = blocks
+            .iter()
+            .filter(|block| !block.ranges().iter().any(|other| other.intersects(&range)))
+            .cloned()
+            .collect();
+        if kept.len() == blocks.len() {
+            return Ok(false);
+        }
+        self.set_conditional_formattings(kept)?;
+        Ok(true)
+    }
+    /// Write conditional formatting blocks to the worksheet part.
+    fn set_conditional_formattings(&mut self, blocks: Vec<ConditionalFormatting>) -> Result<()> {
+        let xml: String = blocks.iter().map(ConditionalFormatting::to_xml).collect();
+        let xml = Some(xml).filter(|xml| !xml.is_empty());
+        self.update_part_element("conditionalFormatting", xml.as_deref())?;
+        self.part.conditional_formattings = blocks;
+        self.parts
+            .borrow_mut()
+            .worksheets
+            .insert(self.uri.clone(), self.part.clone());
+        Ok(())
+    }
+    /// Data validation rules of the worksheet, rules of the `x14` extension included.
+    pub fn data_validations(&self) -> Vec<DataValidation> {
+        let mut rules = self
+            .part
+            .data_validations
+            .as_ref()
+            .map(|rules| rules.data_validations.clone())
+            .unwrap_or_default();
+        let parts = self.parts.borrow();
+        let package = parts.package.borrow();
+        if let Some(part) = package.get_part(&self.uri) {
+            let x14 = data_validation::read_x14_data_validations(part.as_part_bytes());
+            rules.extend(x14.unwrap_or_default());
+        }
+        rules
+    }
+    /// The data validation rule of a cell.
+    pub fn data_validation(&self, cell: CellRef) -> Option<DataValidation> {
+        self.data_validations()
+            .into_iter()
+            .find(|rule| rule.contains(&cell))
+    }
+    /// Add a data validation rule, its ranges can not overlap the ones of other rules.
+    pub fn add_data_validation(&mut self, rule: DataValidation) -> Result<()> {
+        let ranges = rule.ranges();
+        if ranges.is_empty() {
+            return Err(OoxmlError::InvalidReference(format!(
+                "invalid data validation ranges {}",
+                rule.sqref
+            )));
+        }
+        for other in self.data_validations() {
+            let overlapped = other
+                .ranges()
+                .into_iter()
+                .find(|other| ranges.iter().any(|range| range.intersects(other)));
+            if let Some(other) = overlapped {
+                return Err(OoxmlError::InvalidReference(format!(
+                    "{} overlaps data validation {}",
+                    rule.sqref, other
+                )));
+            }
+        }
+        let mut rules = self
+            .part
+            .data_validations
+            .as_ref()
+            .map(|rules| rules.data_validations.clone())
+            .unwrap_or_default();
+        rules.push(rule);
+        self.set_data_validations(rules)
+    }
+    /// Remove data validation rules intersecting a range, returns whether any is removed.
+    ///
+    /// Rules of the `x14` extension are kept.
+    pub fn remove_data_validations(&mut self, range: CellRange) -> Result<bool> {
+        let rules = self
+            .part
+            .data_validations
+            .as_ref()
+            .map(|rules| rules.data_validations.clone())
+            .unwrap_or_default();
+        let kept: Vec<DataValidation> = rules
+            .iter()
+            .filter(|rule| !rule.ranges().iter().any(|other| other.intersects(&range)))
+            .cloned()
+            .collect();
+        if kept.len() == rules.len() {
+            return Ok(false);
+        }
+        self.set_data_validations(kept)?;
+        Ok(true)
+    }
+    /// Write data validation rules to the worksheet part.
+    fn set_data_validations(&mut self, rules: Vec<DataValidation>) -> Result<()> {
+        let data_validations = if rules.is_empty() {
+            None
+        } else {
+            Some(DataValidations {
+                count: Some(rules.len()),
+                data_validations: rules,
+                ..Default::default()
+            })
+        };
+        let xml = data_validations.as_ref().map(DataValidations::to_xml);
+        self.update_part_element("dataValidations", xml.as_deref())?;
+        self.part.data_validations = data_validations;
+        self.parts
+            .borrow_mut()
+            .worksheets
+            .insert(self.uri.clone(), self.part.clone());
+        Ok(())
+    }
+    /// Hyperlinks of the worksheet, external urls are resolved by the worksheet relationships.
+    pub fn hyperlinks(&self) -> Vec<Hyperlink> {
+        let links = match &self.part.hyperlinks {
+            Some(links) => &links.hyperlinks,
+            None => return Vec::new(),
+        };
+        let relationships = {
+            let parts = self.parts.borrow();
+            let package = parts.package.borrow();
+            package.get_part_relationships(&self.uri)
+        };
+        links
+            .iter()
+            .filter_map(|link| {
+                let url = link.r_id.as_ref().and_then(|id| {
+                    let relationship = relationships.as_ref()?.get_relationship_by_id(id)?;
+                    Some(relationship.target().to_string())
+                });
+                Some(Hyperlink {
+                    range: link.reference.parse().ok()?,
+                    url,
+                    location: link.location.clone(),
+                    display: link.display.clone(),
+                    tooltip: link.tooltip.clone(),
+                })
+            })
+            .collect()
+    }
+    /// Hyperlink of a cell.
+    pub fn hyperlink(&self, cell: CellRef) -> Option<Hyperlink> {
+        self.hyperlinks()
+            .into_iter()
+            .find(|link| link.range.contains(&cell))
+    }
+    /// Add a hyperlink, external urls are added to the worksheet relationships.
+    ///
+    /// Hyperlinks of the same range are replaced.
+    pub fn add_hyperlink(&mut self, hyperlink: Hyperlink) -> Result<()> {
+        let reference = hyperlink.range.to_string();
+        let mut links = self.part.hyperlinks.clone().unwrap_or_default();
+        let replaced: Vec<SheetHyperlink> = links
+            .hyperlinks
+            .iter()
+            .filter(|link| link.reference == reference)
+            .cloned()
+            .collect();
+        links.hyperlinks.retain(|link| link.reference != reference);
+        let replaced_ids: Vec<&String> = replaced
+            .iter()
+            .filter_map(|link| link.r_id.as_ref())
+            .collect();
+        let r_id = if hyperlink.url.is_some() || !replaced_ids.is_empty() {
+            self.update_relationships(|relationships| {
+                for id in replaced_ids {
+                    relationships.delete_relationship(id);
+                }
+                hyperlink.url.as_ref().map(|url| {
+                    let id = relationships.next_id();
+                    relationships.add_relationship(Relationship::new_external(
+                        id.clone(),
+                        RelationshipType::Hyperlink,
+                        url.as_str(),
+                    ));
+                    id
+                })
+            })?
+        } else {
+            None
+        };
+        links.hyperlinks.push(SheetHyperlink {
+            reference,
+            r_id,
+            location: hyperlink.location,
+            display: hyperlink.display,
+            tooltip: hyperlink.tooltip,
+        });
+        self.update_part_element("hyperlinks", Some(&links.to_xml()))?;
+        self.part.hyperlinks = Some(links);
+        self.parts
+            .borrow_mut()
+            .worksheets
+            .insert(self.uri.clone(), self.part.clone());
+        Ok(())
+    }
+    /// Comments of the worksheet.
+    pub fn comments(&self) -> Vec<Comment> {
+        match self.comments_part() {
+            Ok(Some((_, part))) => part.to_comments(),
+            _ => Vec::new(),
+        }
+    }
+    /// Comment of a cell.
+    pub fn comment(&self, cell: CellRef) -> Option<Comment> {
+        self.comments()
+            .into_iter()
+            .find(|comment| comment.reference == cell)
+    }
+    /// Add a comment, the comment of the same cell is replaced.
+    ///
+    /// The comments part and the legacy VML drawing showing the notes are created if not exist.
+    pub fn add_comment(&mut self, comment: Comment) -> Result<()> {
+        let cell = comment.reference;
+        self.update_comments_part(|part| part.set_comment(comment))?;
+        self.add_vml_note(cell)
+    }
+    /// Update the comments part, created if not exists.
+    fn update_comments_part(&mut self, f: impl FnOnce(&mut CommentsPart)) -> Result<()> {
+        let (uri, mut part) = match self.comments_part()? {
+            Some(comments) => comments,
+            None => {
+                let part = CommentsPart::default();
+                let (uri, _, _) = self.add_related_part(
+                    RelationshipType::Comments,
+                    "xl/comments",
+                    "xml",
+                    &ContentType::SpreadsheetComments,
+                    part.to_string().as_bytes(),
+                )?;
+                (uri, part)
+            }
+        };
+        f(&mut part);
+        self.set_package_part_data(&uri, part.to_string().into_bytes());
+        Ok(())
+    }
+    /// Threaded comments of the worksheet, authors are resolved by the workbook person list.
+    pub fn comment_threads(&self) -> Vec<CommentThread> {
+        let part = match self.threaded_comments_part() {
+            Ok(Some((_, part))) => part,
+            _ => return Vec::new(),
+        };
+        let persons = self.parts.borrow().persons_part();
+        part.to_threads(&persons)
+    }
+    /// The comment thread of a cell.
+    pub fn comment_thread(&self, cell: CellRef) -> Option<CommentThread> {
+        self.comment_threads()
+            .into_iter()
+            .find(|thread| thread.reference == cell)
+    }
+    /// Add a threaded comment to a cell, it is a reply if the cell has a thread already.
+    ///
+    /// The author is added to the person list if not exists, and the legacy comment of the
+    /// thread is updated for applications without threaded comments. Returns the thread id.
+    pub fn add_threaded_comment(
+        &mut self,
+        cell: CellRef,
+        author: &str,
+        text: &str,
+    ) -> Result<String> {
+        let person = {
+            let mut parts = self.parts.borrow_mut();
+            let mut persons = parts.persons_part();
+            let count = persons.persons.len();
+            let person = persons.person(author);
+            if persons.persons.len() != count {
+                parts.set_persons_part(&persons)?;
+            }
+            person
+        };
+        let created = chrono::Utc::now().naive_utc();
+        let mut thread_id = String::new();
+        self.update_threaded_comments_part(|part| {
+            thread_id = part.add_comment(cell, &person, created, text);
+        })?;
+        Ok(thread_id)
+    }
+    /// Mark the thread of a cell resolved or not, returns false if the cell has no thread.
+    pub fn resolve_comment_thread(&mut self, cell: CellRef, resolved: bool) -> Result<bool> {
+        let (uri, mut part) = match self.threaded_comments_part()? {
+            Some(threads) => threads,
+            None => return Ok(false),
+        };
+        if !part.set_resolved(cell, resolved) {
+            return Ok(false);
+        }
+        self.set_package_part_data(&uri, part.to_string().into_bytes());
+        Ok(true)
+    }
+    /// Parsed threaded comments part of the worksheet with its uri, `None` if not exists.
+    fn threaded_comments_part(&self) -> Result<Option<(String, ThreadedCommentsPart)>> {
+        let uri = match self.related_part_uri(&RelationshipType::ThreadedComment) {
+            Some(uri) => uri,
+            None => return Ok(None),
+        };
+        let parts = self.parts.borrow();
+        let package = parts.package.borrow();
+        match package.get_part(&uri) {
+            Some(part) => {
+                let part = ThreadedCommentsPart::from_xml_reader(part.as_part_bytes())?;
+                Ok(Some((uri, part)))
+            }
+            None => Ok(None),
+        }
+    }
+    /// Update the threaded comments part, created if not exists, then update the legacy
+    /// comments of the changed threads.
+    fn update_threaded_comments_part(
+        &mut self,
+        f: impl FnOnce(&mut ThreadedCommentsPart),
+    ) -> Result<()> {
+        let (uri, mut part) = match self.threaded_comments_part()? {
+            Some(threads) => threads,
+            None => {
+                let part = ThreadedCommentsPart::default();
+                let (uri, _, _) = self.add_related_part(
+                    RelationshipType::ThreadedComment,
+                    "xl/threadedComments/threadedComment",
+                    "xml",
+                    &ContentType::ThreadedComments,
+                    part.to_string().as_bytes(),
+                )?;
+                (uri, part)
+            }
+        };
+        let before = part.clone();
+        f(&mut part);
+        self.set_package_part_data(&uri, part.to_string().into_bytes());
+        let changed: Vec<String> = part
+            .comments
+            .iter()
+            .filter(|item| !before.comments.contains(item))
+            .map(|item| item.parent_id.clone().unwrap_or_else(|| item.id.clone()))
+            .collect();
+        for thread_id in changed {
+            if let Some((author, item)) = part.legacy_comment(&thread_id) {
+                let cell = item.reference.parse::<CellRef>()?;
+                self.update_comments_part(|comments| comments.set_item(&author, item))?;
+                self.add_vml_note(cell)?;
+            }
+        }
+        Ok(())
+    }
+    /// Parsed comments part of the worksheet with its uri, `None` if not exists.
+    fn comments_part(&self) -> Result<Option<(String, CommentsPart)>> {
+        let uri = match self.related_part_uri(&RelationshipType::Comments) {
+            Some(uri) => uri,
+            None => return Ok(None),
+        };
+        let parts = self.parts.borrow();
+        let package = parts.package.borrow();
+        match package.get_part(&uri) {
+            Some(part) => {
+                let part = CommentsPart::from_xml_reader(part.as_part_bytes())?;
+                Ok(Some((uri, part)))
+            }
+            None => Ok(None),
+        }
+    }
+    /// Add the note shape of a cell to the legacy VML drawing, created if not exists.
+    fn add_vml_note(&mut self, cell: CellRef) -> Result<()> {
+        if let Some(uri) = self.related_part_uri(&RelationshipType::VmlDrawing) {
+            let data = {
+                let parts = self.parts.borrow();
+                let package = parts.package.borrow();
+                match package.get_part(&uri) {
+                    Some(part) => comment::add_vml_note(part.as_part_bytes(), cell)?,
+                    None => return Ok(()),
+                }
+            };
+            self.set_package_part_data(&uri, data);
+            return Ok(());
+        }
+        let (uri, r_id, idx) = self.add_related_part(
+            RelationshipType::VmlDrawing,
+            "xl/drawings/vmlDrawing",
+            "vml",
+            &ContentType::VmlDrawing,
+            &[],
+        )?;
+        let data = comment::add_vml_note(comment::new_vml_drawing(idx).as_bytes(), cell)?;
+        self.set_package_part_data(&uri, data);
+        let element = format!(
+            r#"<legacyDrawing xmlns:r="{}" r:id="{}"/>"#,
+            RELATIONSHIPS_NAMESPACE, r_id
+        );
+        self.update_part_element("legacyDrawing", Some(&element))
+    }
+    /// Tables of the worksheet.
+    pub fn tables(&self) -> Vec<Table> {
+        self.table_parts()
+            .into_iter()
+            .map(|(_, table)| table)
+            .collect()
+    }
+    /// Get a table by name, case insensitive.
+    pub fn table(&self, name: &str) -> Option<Table> {
+        self.tables().into_iter().find(|table| table.is_named(name))
+    }
+    /// Data rows of a table as records keyed by column names, header and totals rows excluded.
+    pub fn table_records(
+        &self,
+        name: &str,
+    ) -> Option<Vec<linked_hash_map::LinkedHashMap<String, CellValue>>> {
+        let table = self.table(name)?;
+        let range = match table.data_range() {
+            Some(range) => range,
+            None => return Some(Vec::new()),
+        };
+        let records = (range.start.row..=range.end.row)
+            .map(|row| {
+                table
+                    .columns()
+                    .iter()
+                    .zip(range.start.col..=range.end.col)
+                    .map(|(column, col)| {
+                        let value = self.cell_at(row, col).value().unwrap_or(CellValue::Null);
+                        (column.name.clone(), value)
+                    })
+                    .collect()
+            })
+            .collect();
+        Some(records)
+    }
+    /// Add a table of a range, the first row is the header row of column names.
+    ///
+    /// Header cells must be unique non-empty texts, the new table is styled by the default
+    /// table style with an auto filter. Table names are unique with defined names in the workbook,
+    /// and tables can not overlap each other.
+    pub fn add_table(&mut self, name: &str, range: CellRange) -> Result<Table> {
+        if !is_valid_name(name) {
+            return Err(OoxmlError::InvalidReference(format!(
+                "invalid table name {}",
+                name
+            )));
+        }
+        let tables = self.parts.borrow().tables();
+        let defined = self
+            .parts
+            .borrow()
+            .workbook
+            .defined_names()
+            .iter()
+            .any(|defined| defined.is_named(name));
+        if defined || tables.iter().any(|(_, _, table)| table.is_named(name)) {
+            return Err(OoxmlError::InvalidReference(format!(
+                "name {} exists",
+                name
+            )));
+        }
+        if range.height() < 2 {
+            return Err(OoxmlError::InvalidReference(format!(
+                "table {} needs a header row and a data row",
+                range
+            )));
+        }
+        self.check_table_overlap(range, None)?;
+        let names = table::column_names(&[], self.table_headers(range))?;
+        let id = tables
+            .iter()
+            .map(|(_, _, table)| table.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let table = Table::new(id, name, range, names);
+        self.add_related_part(
+            RelationshipType::Table,
+            "xl/tables/table",
+            "xml",
+            &ContentType::Table,
+            table.to_xml().as_bytes(),
+        )?;
+        self.update_table_parts()?;
+        Ok(table)
+    }
+    /// Resize a table to a range of the same header row and first column.
+    ///
+    /// Columns beyond the new range are removed, new columns are named by their header cells,
+    /// which must be unique non-empty texts.
+    pub fn resize_table(&mut self, name: &str, range: CellRange) -> Result<()> {
+        let (uri, table) = self
+            .table_parts()
+            .into_iter()
+            .find(|(_, table)| table.is_named(name))
+            .ok_or_else(|| OoxmlError::InvalidReference(format!("no table {}", name)))?;
+        let current = table
+            .range()
+            .ok_or_else(|| OoxmlError::InvalidReference(table.r#ref.clone()))?;
+        if range.start.row != current.start.row || range.start.col != current.start.col {
+            return Err(OoxmlError::InvalidReference(format!(
+                "table {} can not be moved to {}",
+                name, range
+            )));
+        }
+        if range.height() <= table.header_row_count() + table.totals_row_count() {
+            return Err(OoxmlError::InvalidReference(format!(
+                "table {} needs a data row",
+                range
+            )));
+        }
+        self.check_table_overlap(range, Some(&uri))?;
+        let kept: Vec<&str> = table
+            .column_names()
+            .into_iter()
+            .take(range.width() as usize)
+            .collect();
+        let start = range.start.col + kept.len() as u32;
+        let headers = if start <= range.end.col {
+            self.table_headers(CellRange::new(
+                CellRef::new(range.start.row, start),
+                CellRef::new(range.start.row, range.end.col),
+            ))
+        } else {
+            Vec::new()
+        };
+        let max_id = table.columns().iter().map(|c| c.id).max().unwrap_or(0);
+        let columns: Vec<TableColumn> = table::column_names(&kept, headers)?
+            .into_iter()
+            .zip(max_id + 1..)
+            .map(|(name, id)| TableColumn {
+                id,
+                name,
+                ..Default::default()
+            })
+            .collect();
+        let data = {
+            let parts = self.parts.borrow();
+            let package = parts.package.borrow();
+            match package.get_part(&uri) {
+                Some(part) => table::resize_table_xml(part.as_part_bytes(), range, &columns)?,
+                None => return Ok(()),
+            }
+        };
+        self.set_package_part_data(&uri, data);
+        Ok(())
+    }
+    /// Header cells and their values of the first row of a table range.
+    fn table_headers(&self, range: CellRange) -> Vec<(CellRef, CellValue)> {
+        (range.start.col..=range.end.col)
+            .map(|col| {
+                let cell = CellRef::new(range.start.row, col);
+                let value = self
+                    .cell_at(cell.row, col)
+                    .value()
+                    .unwrap_or(CellValue::Null);
+                (cell, value)
+            })
+            .collect()
+    }
+    /// Parsed table parts of the worksheet with their uris.
+    fn table_parts(&self) -> Vec<(String, Table)> {
+        self.parts
+            .borrow()
+            .tables()
+            .into_iter()
+            .filter(|(sheet_uri, _, _)| sheet_uri == &self.uri)
+            .map(|(_, uri, table)| (uri, table))
+            .collect()
+    }
+    /// Check a table range not overlapping tables of the worksheet other than `except`.
+    fn check_table_overlap(&self, range: CellRange, except: Option<&str>) -> Result<()> {
+        let overlapped = self.table_parts().into_iter().find(|(uri, table)| {
+            Some(uri.as_str()) != except
+                && table.range().is_some_and(|other| other.intersects(&range))
+        });
+        match overlapped {
+            Some((_, table)) => Err(OoxmlError::InvalidReference(format!(
+                "{} overlaps table {}",
+                range, table.display_name
+            ))),
+            None => Ok(()),
+        }
+    }
+    /// Write `<tableParts>` of all table relationships of the worksheet.
+    fn update_table_parts(&self) -> Result<()> {
+        let ids: Vec<String> = {
+            let parts = self.parts.borrow();
+            let package = parts.package.borrow();
+            package
+                .get_part_relationships(&self.uri)
+                .map(|relationships| {
+                    relationships
+                        .get_relationships_by_type(&RelationshipType::Table)
+                        .map(|r| r.id().to_string())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        if ids.is_empty() {
+            return self.update_part_element("tableParts", None);
+        }
+        let mut element = format!(
+            r#"<tableParts xmlns:r="{}" count="{}">"#,
+            RELATIONSHIPS_NAMESPACE,
+            ids.len()
+        );
+        for id in ids {
+            element.push_str(&format!(r#"<tablePart r:id="{}"/>"#, id));
+        }
+        element.push_str("</tableParts>");
+        self.update_part_element("tableParts", Some(&element))
+    }
+    /// Uri of the first part related from the worksheet by the relationship type.
+    fn related_part_uri(&self, relationship_type: &RelationshipType) -> Option<String> {
+        let parts = self.parts.borrow();
+        let package = parts.package.borrow();
+        package.find_related_part_uri(&self.uri, relationship_type)
+    }
+    /// Add a part related from the worksheet, named like `{prefix}{n}.{extension}` with the
+    /// first unused number `n`, returns the part uri, the relationship id and the number.
+    fn add_related_part(
+        &self,
+        relationship_type: RelationshipType,
+        prefix: &str,
+        extension: &str,
+        content_type: &ContentType,
+        data: &[u8],
+    ) -> Result<(String, String, usize)> {
+        let (uri, idx) = {
+            let parts = self.parts.borrow();
+            let package = parts.package.borrow();
+            (1..)
+                .map(|idx| (format!("{}{}.{}", prefix, idx, extension), idx))
+                .find(|(uri, _)| package.get_part(uri).is_none())
+                .expect("unused part name")
+        };
+        {
+            let parts = self.parts.borrow();
+            let mut package = parts.package.borrow_mut();
+            package.create_part_core_with_data(&uri, content_type, data)?;
+        }
+        let target = relative_target(&self.uri, &uri);
+        let r_id = self.update_relationships(|relationships| {
+            let id = relationships.next_id();
+            relationships.add_relationship(Relationship::new(
+                id.clone(),
+                relationship_type,
+                target,
+            ));
+            id
+        })?;
+        Ok((uri, r_id, idx))
+    }
+    /// Update the worksheet relationships, the relationships part is created if not exists.
+    fn update_relationships<T>(&self, f: impl FnOnce(&mut Relationships) -> T) -> Result<T> {
+        let parts = self.parts.borrow();
+        let mut package = parts.package.borrow_mut();
+        let mut relationships = package
+            .get_part_relationships(&self.uri)
+            .unwrap_or_default();
+        let result = f(&mut relationships);
+        let uri = relationships_uri(&self.uri);
+        let data = relationships.to_string().into_bytes();
+        if !package.set_part_data(&uri, data.clone()) {
+            package.create_part_core_with_data(&uri, &ContentType::Relationships, &data)?;
+        }
+        Ok(result)
+    }
+    /// Set the data of a part in package.
+    fn set_package_part_data(&self, uri: &str, data: Vec<u8>) {
+        let parts = self.parts.borrow();
+        parts.package.borrow_mut().set_part_data(uri, data);
+    }
+    /// Replace a child element of the worksheet part xml in package.
+    fn update_part_element(&self, tag: &str, element: Option<&str>) -> Result<()> {
+        let parts = self.parts.borrow();
+        let mut package = parts.package.borrow_mut();
+        let data = match package.get_part(&self.uri) {
+            Some(part) => worksheet::replace_worksheet_element(part.as_part_bytes(), tag, element)?,
+            None => return Ok(()),
+        };
+        package.set_part_data(&self.uri, data);
+        Ok(())
+    }
+    /// The range of the cells in sheet data, or the dimension element for sheets without cells.
+    pub fn used_range(&self) -> Option<CellRange> {
+        self.grid
+            .range()
+            .or_else(|| self.part.dimension.as_ref().and_then(|d| d.range()))
+    }
     pub fn dimenstion(&self) -> Option<(usize, usize)> {
-        // self.part.dimension()
-        self.part.dimension().or_else(|| self.part.real_dimension())
+        self.used_range()
+            .map(|range| (range.height() as usize, range.width() as usize))
+    }
+    /// Get a cell by A1 reference like `C7`, `None` if the reference is invalid.
+    pub fn cell(&self, reference: &str) -> Option<Cell<'_>> {
+        let reference: CellRef = reference.parse().ok()?;
+        Some(self.cell_at(reference.row, reference.col))
+    }
+    /// Get a cell by 0-based row and column, cells not in sheet data are empty.
+    pub fn cell_at(&self, row: u32, col: u32) -> Cell<'_> {
+        Cell {
+            sheet: self,
+            row,
+            col,
+        }
     }
     pub fn get_row_size(&self) -> usize {
         self.dimenstion().unwrap_or_default().0
@@ -196,6 +1194,11 @@ impl Worksheet {
         let parts = self.parts.as_ref().borrow();
         parts.get_shared_string(idx).map(|s| s.into())
     }
+    /// Shared string item with rich text runs and phonetic data.
+    pub fn get_shared_string_item(&self, idx: usize) -> Option<SharedString> {
+        let parts = self.parts.as_ref().borrow();
+        parts.shared_strings.get(idx).cloned()
+    }
     pub fn get_cell_style(&self, id: usize) {
         let parts = self.parts.as_ref().borrow();
         let cs = parts.get_cell_style(id);
@@ -203,6 +1206,18 @@ impl Worksheet {
         let _nf = cs.number_format();
         unimplemented!()
     }
+    /// Date system of the workbook.
+    pub fn date_system(&self) -> DateSystem {
+        self.parts.as_ref().borrow().workbook.date_system()
+    }
+    /// Number format code of the cell style id.
+    pub fn number_format_code(&self, style_id: usize) -> Option<NumberFormatCode> {
+        let parts = self.parts.as_ref().borrow();
+        let cs = parts.get_cell_format(style_id)?;
+        let date_system = parts.workbook.date_system();
+        cs.number_format()
+            .map(|nf| NumberFormatCode::parse(&nf.code).with_date_system(date_system))
+    }
     /// Format a cell's raw value with given cell style id.
     pub fn to_cell_value(&self, raw: &str, style_id: usize) -> Option<CellValue> {
         let parts = self.parts.as_ref().borrow();
@@ -214,52 +1229,20 @@ impl Worksheet {
         // }
         let nf = cs.number_format();
         if nf.is_none() {
-            dbg!(raw, style_id);
-            // dbg!(cs.xf());
-            return Some(CellValue::String(raw.to_string()));
+            return Some(CellValue::from_number_str(raw));
         }
         let nf = nf.unwrap();
         let code = nf.code.as_str();
         //println!("code: {}", code);
 
-        fn parse_datetime(raw: &str) -> Option<chrono::NaiveDateTime> {
-            if let Ok(days) = raw.parse::<i64>() {
-                let days = days - 25569;
-                let secs = days * 86400;
-                chrono::NaiveDateTime::from_timestamp_opt(secs, 0)
-            } else if let Ok(datetime) = raw.parse::<f64>() {
-                let unix_days = datetime - 25569.;
-                let unix_secs = unix_days * 86400.;
-                let secs = unix_secs.trunc() as i64;
-                let nsecs = (unix_secs.fract().abs() * 1e9) as u32;
-                chrono::NaiveDateTime::from_timestamp_opt(secs, nsecs)
-            } else {
-                None
-            }
-        }
-        let s = match code {
-            s if s == "General" => CellValue::String(raw.to_string()),
-            format if DATETIME_RE.is_match(format) | format.ends_with(";@") => {
-                // dbg!(&format);
-                let format = format.trim_end_matches(";@");
-                let datetime = parse_datetime(raw).unwrap();
-
-                let format = DATETIME_REPLACES
-                    .iter()
-                    .fold(escape8259::unescape(format).unwrap(), |f, (re, s)| {
-                        re.replace_all(&f, *s).to_string()
-                    });
-                // dbg!(&format);
-                CellValue::DateTime(datetime, format)
-                // format!("{}", datetime.format(&format))
-            }
-            s => {
-                // FIXME(@zitsen): support custom format like dollars, etc.
-                eprintln!("unimplemented format support: {}", s);
-                CellValue::String(raw.to_string())
-            }
+        let value = match NumberFormatCode::parse(code).date_time_kind() {
+            Some(kind) => match raw.trim().parse() {
+                Ok(serial) => CellValue::from_serial(serial, kind, self.date_system()),
+                Err(_) => CellValue::from_number_str(raw),
+            },
+            None => CellValue::from_number_str(raw),
         };
-        Some(s)
+        Some(value)
     }
     /// Format a cell's raw value with given cell style id.
     pub fn format_cell_with(&self, raw: &str, style_id: usize) -> Option<String> {
@@ -344,38 +1327,37 @@ impl Worksheet {
     }
 }
 
+/// Rows of the used range.
 #[derive(Debug)]
 pub struct RowsIter<'a> {
     sheet: &'a Worksheet,
-    row: usize,
-    col: usize,
+    range: Option<CellRange>,
+    row: u32,
 }
 
+/// Cells of a row in the used range.
 #[derive(Debug)]
 pub struct RowIter<'a> {
     sheet: &'a Worksheet,
-    row: usize,
-    col: usize,
-}
-
-impl<'a> RowsIter<'a> {
-    fn row_iter(&self) -> RowIter<'a> {
-        RowIter {
-            sheet: self.sheet,
-            row: self.row,
-            col: self.col,
-        }
-    }
+    row: u32,
+    col: u32,
+    end: u32,
 }
 
 impl<'a> Iterator for RowsIter<'a> {
     type Item = RowIter<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.row >= self.sheet.get_row_size() {
+        let range = self.range?;
+        if self.row > range.end.row {
             return None;
         };
-        let row = self.row_iter();
+        let row = RowIter {
+            sheet: self.sheet,
+            row: self.row,
+            col: range.start.col,
+            end: range.end.col,
+        };
         self.row += 1;
         Some(row)
     }
@@ -383,17 +1365,22 @@ impl<'a> Iterator for RowsIter<'a> {
 
 pub struct Cell<'a> {
     sheet: &'a Worksheet,
-    row: usize,
-    col: usize,
+    row: u32,
+    col: u32,
 }
 
 impl<'a> Cell<'a> {
     fn inner(&self) -> Option<&SheetCol> {
-        let data = self.sheet.part.sheet_data.as_ref().unwrap();
-        data.rows
-            .as_ref()
-            .and_then(|rows| rows.get(self.row))
-            .and_then(|row| row.cols.as_ref().and_then(|cols| cols.get(self.col)))
+        let position = self.sheet.grid.get(self.row, self.col)?;
+        self.sheet.part.cell_at(position)
+    }
+    /// Formula of the cell, shared formulas are in their concrete forms for the cell.
+    pub fn formula(&self) -> Option<Formula> {
+        self.sheet.part.formula(&self.sheet.grid, self.reference())
+    }
+    /// Reference of the cell, eg. `C7`.
+    pub fn reference(&self) -> CellRef {
+        CellRef::new(self.row, self.col)
     }
     pub fn cell_type(&self) {
         unimplemented!()
@@ -413,72 +1400,113 @@ impl<'a> Cell<'a> {
         }
     }
 
+    /// Comment of the cell.
+    pub fn comment(&self) -> Option<Comment> {
+        self.sheet.comment(self.reference())
+    }
+
+    /// Comment thread of the cell.
+    pub fn comment_thread(&self) -> Option<CommentThread> {
+        self.sheet.comment_thread(self.reference())
+    }
+
+    /// Hyperlink of the cell.
+    pub fn hyperlink(&self) -> Option<Hyperlink> {
+        self.sheet.hyperlink(self.reference())
+    }
+
+    /// Check if the cell is in a merged range.
     pub fn is_merged_cell(&self) -> bool {
-        unimplemented!()
+        self.merge_area().is_some()
+    }
+
+    /// The merged range the cell is in.
+    pub fn merge_area(&self) -> Option<CellRange> {
+        self.sheet.merge_area(self.reference())
+    }
+
+    /// The top-left cell of the merged range, which holds the value of all its cells.
+    pub fn merge_anchor(&self) -> Option<Cell<'a>> {
+        let anchor = self.merge_area()?.start;
+        Some(self.sheet.cell_at(anchor.row, anchor.col))
+    }
+
+    /// Value of the cell, or of the merged range it is in.
+    pub fn merged_value(&self) -> Option<CellValue> {
+        match self.merge_anchor() {
+            Some(anchor) => anchor.value(),
+            None => self.value(),
+        }
     }
 
-    pub fn as_raw_str(&self) -> Option<&str> {
+    pub fn as_raw_str(&self) -> Option<Cow<'_, str>> {
         self.inner().map(|s| s.as_raw_str())
     }
+    /// Shared string of the cell with its rich text runs, `None` for other cells.
+    pub fn shared_string(&self) -> Option<SharedString> {
+        match self.inner()?.cell_type() {
+            cell::CellType::Shared(idx) => self.sheet.get_shared_string_item(idx),
+            _ => None,
+        }
+    }
     // pub fn cell_value(&self) -> Option<CellValue> {
     //     self.inner().map(|cell| cell.raw_value())
     // }
 
     /// get cell value
     pub fn value(&self) -> Option<CellValue> {
-        let inner = self.inner();
-        if inner.is_none() {
-            return None;
-        }
-        let inner = inner.unwrap();
+        let inner = self.inner()?;
         let raw = inner.as_raw_str();
-        let ctype = inner.cell_type();
-        let value = match ctype {
+        let raw = raw.as_ref();
+        let value = match inner.cell_type() {
             cell::CellType::Empty => CellValue::Null,
-            cell::CellType::Raw => CellValue::String(raw.to_string()),
-            cell::CellType::Number => CellValue::String(raw.to_string()),
+            cell::CellType::Raw | cell::CellType::Number => CellValue::from_number_str(raw),
             cell::CellType::Shared(shared_string_id) => CellValue::String(
                 self.sheet
                     .get_shared_string(shared_string_id)
-                    .expect(&format!("shared string not found {}", shared_string_id)),
+                    .unwrap_or_default(),
             ),
-            cell::CellType::Styled(style_id) => self
-                .sheet
-                .to_cell_value(&inner.as_raw_str(), style_id)
-                .expect("format with cell style"),
-            cell::CellType::StyledNumber(style_id) => self
+            cell::CellType::Styled(style_id) | cell::CellType::StyledNumber(style_id) => self
                 .sheet
-                .to_cell_value(&inner.as_raw_str(), style_id)
+                .to_cell_value(raw, style_id)
                 .expect("format with cell style"),
+            cell::CellType::Bool => CellValue::from_bool_str(raw),
+            cell::CellType::Error => CellValue::from_error_str(raw),
+            cell::CellType::FormulaString | cell::CellType::InlineString => {
+                CellValue::String(raw.to_string())
+            }
+            cell::CellType::Date => match cell::parse_iso_datetime(raw) {
+                Some(datetime) => CellValue::from(datetime),
+                None => CellValue::String(raw.to_string()),
+            },
+            cell::CellType::StyledDate(style_id) => match cell::parse_iso_datetime(raw) {
+                Some(datetime) => {
+                    let serial = self.sheet.date_system().datetime_to_serial(&datetime);
+                    self.sheet
+                        .to_cell_value(&serial.to_string(), style_id)
+                        .expect("format with cell style")
+                }
+                None => CellValue::String(raw.to_string()),
+            },
         };
         Some(value)
     }
 
+    /// The cell text as Excel displays it, numbers are rendered with the cell number format.
     pub fn to_string(&self) -> Option<String> {
-        let inner = self.inner();
-        if inner.is_none() {
-            return None;
-        }
-        let inner = inner.unwrap();
-        let ctype = inner.cell_type();
-        let value = match ctype {
-            cell::CellType::Empty => "".to_string(),
-            cell::CellType::Raw => inner.raw_value().to_string(),
-            cell::CellType::Number => inner.raw_value().to_string(),
-            cell::CellType::Shared(shared_string_id) => self
-                .sheet
-                .get_shared_string(shared_string_id)
-                .expect(&format!("shared string not found {}", shared_string_id)),
-            cell::CellType::Styled(style_id) => self
-                .sheet
-                .format_cell_with(&inner.as_raw_str(), style_id)
-                .expect("format with cell style"),
-            cell::CellType::StyledNumber(style_id) => self
-                .sheet
-                .format_cell_with(&inner.as_raw_str(), style_id)
-                .expect("format with cell style"),
+        let value = self.value()?;
+        let code = self
+            .inner()
+            .and_then(|inner| inner.s)
+            .and_then(|style_id| self.sheet.number_format_code(style_id));
+        let serial = value.to_serial(self.sheet.date_system());
+        let text = match (&value, serial, code) {
+            (CellValue::Bool(_), _, _) | (CellValue::Error(_), _, _) => value.to_string(),
+            (_, Some(serial), code) => format_number(serial, code.as_ref()),
+            (CellValue::String(s), _, Some(code)) => code.format_text(s).text,
+            (value, _, _) => value.to_string(),
         };
-        Some(value)
+        Some(text)
     }
     pub fn cell_style(&self) {}
     pub fn cell_number_format(&self) {}
@@ -487,7 +1515,7 @@ impl<'a> Iterator for RowIter<'a> {
     type Item = Cell<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.col >= self.sheet.get_col_size() {
+        if self.col > self.end {
             return None;
         };
         let cell = Cell {
@@ -502,10 +1530,11 @@ impl<'a> Iterator for RowIter<'a> {
 
 impl Worksheet {
     pub fn rows<'a>(&'a self) -> RowsIter<'a> {
+        let range = self.used_range();
         RowsIter {
             sheet: self,
-            row: 0,
-            col: 0,
+            range,
+            row: range.map_or(0, |range| range.start.row),
         }
     }
 }
@@ -530,7 +1559,7 @@ impl Workbook {
                 .relationships
                 .get_relationship_by_id(&sheet.r_id)
                 .expect("the worksheet relationship doest not exist");
-            let worksheet_uri = relationship.target();
+            let worksheet_uri = resolve_target(&borrowed_parts.workbook_uri, relationship.target());
 
             let part = borrowed_parts.get_worksheet_part(&worksheet_uri).unwrap();
             // println!("{:?}", part);
@@ -538,6 +1567,8 @@ impl Workbook {
                 parts: parts.clone(),
                 name: sheet.name.clone(),
                 sheet_id: sheet.sheet_id,
+                uri: worksheet_uri.clone(),
+                grid: part.cell_grid(),
                 part: part.clone(),
             };
             worksheets.push(worksheet);
@@ -577,10 +1608,386 @@ impl Workbook {
         self.worksheets.as_slice()
     }
     /// Mutable worksheets slice
-    pub fn worksheets_mut(&mut self) -> &[Worksheet] {
+    pub fn worksheets_mut(&mut self) -> &mut [Worksheet] {
         self.worksheets.as_mut_slice()
     }
 
+    pub fn get_worksheet_by_name_mut(&mut self, name: &str) -> Option<&mut Worksheet> {
+        self.worksheets.iter_mut().find(|sheet| sheet.name == name)
+    }
+
+    /// Calculate all formulas of the workbook, cached values of formula cells are not used.
+    pub fn calculate(&self) -> Calculation {
+        self.model().calculate()
+    }
+
+    /// Cell values violating data validation rules of all sheets, by sheets and rules.
+    ///
+    /// Ranges of rules are clamped to the used range of their sheets, blank cells in it are
+    /// invalid for rules not allowing blank.
+    pub fn invalid_cells(&self) -> Vec<InvalidCell> {
+        let date_system = self.parts.borrow().workbook.date_system();
+        let mut checks = Vec::new();
+        for (index, sheet) in self.worksheets.iter().enumerate() {
+            let used = match sheet.used_range() {
+                Some(used) => used,
+                None => continue,
+            };
+            for rule in sheet.data_validations() {
+                for range in rule.ranges() {
+                    let start = CellRef::new(
+                        range.start.row.max(used.start.row),
+                        range.start.col.max(used.start.col),
+                    );
+                    let end = CellRef::new(
+                        range.end.row.min(used.end.row),
+                        range.end.col.min(used.end.col),
+                    );
+                    if start.row > end.row || start.col > end.col {
+                        continue;
+                    }
+                    for cell in CellRange::new(start, end).cells() {
+                        checks.push((index, cell, rule.clone()));
+                    }
+                }
+            }
+        }
+        let formulas: Vec<(usize, CellRef, String)> = checks
+            .iter()
+            .flat_map(|(index, cell, rule)| {
+                let formulas = rule.formulas_at(*cell);
+                formulas.into_iter().map(move |f| (*index, *cell, f))
+            })
+            .collect();
+        let formulas: Vec<(usize, CellRef, &str)> = formulas
+            .iter()
+            .map(|(index, cell, formula)| (*index, *cell, formula.as_str()))
+            .collect();
+        let mut operands = self.model().evaluate(&formulas).into_iter();
+        let mut invalid = Vec::new();
+        for (index, cell, rule) in checks {
+            let operands: Vec<calc::Value> = operands
+                .by_ref()
+                .take(rule.formulas_at(cell).len())
+                .collect();
+            let sheet = &self.worksheets[index];
+            let value = sheet
+                .cell_at(cell.row, cell.col)
+                .value()
+                .unwrap_or(CellValue::Null);
+            if !rule.is_valid(
+                &calc::Value::from_cell_value(&value, date_system),
+                &operands,
+            ) {
+                invalid.push(InvalidCell {
+                    sheet: sheet.name.clone(),
+                    cell,
+                    value,
+                    rule,
+                });
+            }
+        }
+        invalid
+    }
+
+    /// Check a value to enter into a cell by the data validation rule of the cell, returns the
+    /// violated rule.
+    pub fn check_value(
+        &self,
+        sheet: &str,
+        cell: CellRef,
+        value: &CellValue,
+    ) -> Option<DataValidation> {
+        let index = self.sheet_index(sheet)?;
+        let rule = self.worksheets[index].data_validation(cell)?;
+        let date_system = self.parts.borrow().workbook.date_system();
+        let value = calc::Value::from_cell_value(value, date_system);
+        let mut model = self.model();
+        model.set_value(index, cell, value.clone());
+        let formulas = rule.formulas_at(cell);
+        let formulas: Vec<(usize, CellRef, &str)> = formulas
+            .iter()
+            .map(|formula| (index, cell, formula.as_str()))
+            .collect();
+        let operands = model.evaluate(&formulas);
+        if rule.is_valid(&value, &operands) {
+            None
+        } else {
+            Some(rule)
+        }
+    }
+
+    /// Calculation model of the workbook values, formulas and defined names.
+    fn model(&self) -> calc::Model {
+        let date_system = self.parts.borrow().workbook.date_system();
+        let mut model = calc::Model::new(date_system);
+        for sheet in &self.worksheets {
+            model.add_sheet(sheet.name.clone());
+        }
+        for name in self.defined_names() {
+            model.define_name(name.name, name.local_sheet_id, &name.formula);
+        }
+        for (index, sheet) in self.worksheets.iter().enumerate() {
+            let formulas = sheet.formulas();
+            for reference in sheet.grid.cells() {
+                match formulas.get(&reference) {
+                    Some(formula) if formula.kind != FormulaKind::DataTable => {
+                        model.set_formula(index, reference, formula)
+                    }
+                    _ => {
+                        let cell = sheet.cell_at(reference.row, reference.col);
+                        if let Some(value) = cell.value() {
+                            let value = calc::Value::from_cell_value(&value, date_system);
+                            model.set_value(index, reference, value);
+                        }
+                    }
+                }
+            }
+        }
+        model
+    }
+
+    /// Defined names of the workbook and its sheets.
+    pub fn defined_names(&self) -> Vec<DefinedName> {
+        self.parts.borrow().workbook.defined_names().to_vec()
+    }
+
+    /// Get a defined name of the workbook scope, or of a sheet scope by the sheet name.
+    pub fn defined_name(&self, name: &str, scope: Option<&str>) -> Option<DefinedName> {
+        let scope = match scope {
+            Some(sheet) => Some(self.sheet_index(sheet)?),
+            None => None,
+        };
+        self.defined_names()
+            .into_iter()
+            .find(|defined| defined.is_named(name) && defined.local_sheet_id == scope)
+    }
+
+    /// Resolve a name used in a sheet, or in the workbook for `None`, to its reference.
+    ///
+    /// Names of the sheet scope take precedence over the workbook ones, `None` if the name
+    /// does not exist or is not a reference, like a constant or a formula.
+    pub fn resolve_name(&self, name: &str, sheet: Option<&str>) -> Option<SheetRef> {
+        let defined = sheet
+            .and_then(|sheet| self.defined_name(name, Some(sheet)))
+            .or_else(|| self.defined_name(name, None))?;
+        let mut reference: SheetRef = defined.formula.trim_start_matches('=').parse().ok()?;
+        if reference.sheet.is_none() {
+            let scope = defined
+                .local_sheet_id
+                .and_then(|id| self.worksheets.get(id));
+            reference.sheet = Some(scope.map(|s| s.name.clone()).or(sheet.map(String::from))?);
+        }
+        Some(reference)
+    }
+
+    /// Values of the first area a name refers to, by rows. Whole rows and columns are clamped
+    /// to the used range of the sheet.
+    pub fn name_values(&self, name: &str, sheet: Option<&str>) -> Option<Vec<Vec<CellValue>>> {
+        let reference = self.resolve_name(name, sheet)?;
+        let worksheet = self.get_worksheet_by_name(reference.sheet.as_deref()?)?;
+        let area = reference.areas.first()?;
+        let used = worksheet.used_range();
+        let end = match used {
+            Some(used) if !area.is_single_cell() => CellRef::new(
+                area.end.row.min(used.end.row),
+                area.end.col.min(used.end.col),
+            ),
+            _ => area.end,
+        };
+        let values = (area.start.row..=end.row)
+            .map(|row| {
+                (area.start.col..=end.col)
+                    .map(|col| {
+                        worksheet
+                            .cell_at(row, col)
+                            .value()
+                            .unwrap_or(CellValue::Null)
+                    })
+                    .collect()
+            })
+            .collect();
+        Some(values)
+    }
+
+    /// Add a defined name of the workbook scope, or of a sheet scope by the sheet name.
+    pub fn add_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<&str>,
+        formula: &str,
+    ) -> Result<()> {
+        if !is_valid_name(name) {
+            return Err(OoxmlError::InvalidReference(format!(
+                "invalid name {}",
+                name
+            )));
+        }
+        if self.defined_name(name, scope).is_some() {
+            return Err(OoxmlError::InvalidReference(format!(
+                "name {} exists",
+                name
+            )));
+        }
+        let local_sheet_id = self.scope_index(scope)?;
+        let mut names = self.defined_names();
+        names.push(DefinedName {
+            local_sheet_id,
+            ..DefinedName::new(name, formula.trim_start_matches('='))
+        });
+        self.set_defined_names(names)
+    }
+
+    /// Rename a defined name, returns false if the name does not exist.
+    ///
+    /// References to the name are renamed in formulas of cells, conditional formatting, data
+    /// validations and other defined names, except in sheets where a local name of the same
+    /// name takes precedence over a workbook one.
+    pub fn rename_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<&str>,
+        new_name: &str,
+    ) -> Result<bool> {
+        if !is_valid_name(new_name) {
+            return Err(OoxmlError::InvalidReference(format!(
+                "invalid name {}",
+                new_name
+            )));
+        }
+        let local_sheet_id = self.scope_index(scope)?;
+        let exists = self
+            .defined_name(new_name, scope)
+            .filter(|defined| !defined.is_named(name));
+        if exists.is_some() {
+            return Err(OoxmlError::InvalidReference(format!(
+                "name {} exists",
+                new_name
+            )));
+        }
+        let mut names = self.defined_names();
+        let old_name = match names
+            .iter_mut()
+            .find(|defined| defined.is_named(name) && defined.local_sheet_id == local_sheet_id)
+        {
+            Some(defined) => std::mem::replace(&mut defined.name, new_name.to_string()),
+            None => return Ok(false),
+        };
+        // sheets of local names shadowing the renaming workbook one.
+        let shadowed: Vec<usize> = names
+            .iter()
+            .filter(|defined| defined.is_named(&old_name))
+            .filter_map(|defined| defined.local_sheet_id)
+            .collect();
+        let sheet_names: Vec<String> = self
+            .worksheets
+            .iter()
+            .map(|sheet| sheet.name.to_lowercase())
+            .collect();
+        // whether a reference in a formula of the `context` sheet refers to the renaming name.
+        let resolves = |context: Option<usize>, sheet: Option<&str>| match (local_sheet_id, sheet) {
+            (Some(scope), Some(sheet)) => sheet_names.get(scope) == Some(&sheet.to_lowercase()),
+            (Some(scope), None) => context == Some(scope),
+            (None, Some(_)) => false,
+            (None, None) => !context.is_some_and(|context| shadowed.contains(&context)),
+        };
+        let rename = |formula: &str, context: Option<usize>| {
+            formula::rename_name_references(formula, &old_name, new_name, |sheet| {
+                resolves(context, sheet)
+            })
+        };
+        for defined in names.iter_mut() {
+            if let Some(formula) = rename(&defined.formula, defined.local_sheet_id) {
+                defined.formula = formula;
+            }
+        }
+        let mut rewritten = Vec::new();
+        {
+            let parts = self.parts.borrow();
+            let package = parts.package.borrow();
+            for (index, sheet) in self.worksheets.iter().enumerate() {
+                let data = match package.get_part(&sheet.uri) {
+                    Some(part) => part.as_part_bytes(),
+                    None => continue,
+                };
+                let data = worksheet::rewrite_formulas(data, |f| rename(f, Some(index)))?;
+                if let Some(data) = data {
+                    rewritten.push((index, data));
+                }
+            }
+        }
+        self.set_defined_names(names)?;
+        for (index, data) in rewritten {
+            let part = WorksheetPart::from_xml_reader(data.as_slice())?;
+            let sheet = &mut self.worksheets[index];
+            sheet.grid = part.cell_grid();
+            sheet.part = part.clone();
+            let mut parts = self.parts.borrow_mut();
+            parts.worksheets.insert(sheet.uri.clone(), part);
+            parts.package.borrow_mut().set_part_data(&sheet.uri, data);
+        }
+        Ok(true)
+    }
+
+    /// Delete a defined name, returns false if the name does not exist.
+    pub fn delete_defined_name(&mut self, name: &str, scope: Option<&str>) -> Result<bool> {
+        let local_sheet_id = self.scope_index(scope)?;
+        let mut names = self.defined_names();
+        let count = names.len();
+        names.retain(|defined| {
+            !(defined.is_named(name) && defined.local_sheet_id == local_sheet_id)
+        });
+        if names.len() == count {
+            return Ok(false);
+        }
+        self.set_defined_names(names)?;
+        Ok(true)
+    }
+
+    /// Index of a sheet by name.
+    fn sheet_index(&self, name: &str) -> Option<usize> {
+        self.worksheets.iter().position(|sheet| sheet.name == name)
+    }
+
+    /// `localSheetId` of a sheet scope.
+    fn scope_index(&self, scope: Option<&str>) -> Result<Option<usize>> {
+        match scope {
+            Some(sheet) => self
+                .sheet_index(sheet)
+                .map(Some)
+                .ok_or_else(|| OoxmlError::InvalidReference(format!("no sheet {}", sheet))),
+            None => Ok(None),
+        }
+    }
+
+    /// Write defined names to the workbook part.
+    fn set_defined_names(&mut self, names: Vec<DefinedName>) -> Result<()> {
+        let defined_names = if names.is_empty() {
+            None
+        } else {
+            Some(DefinedNames {
+                defined_names: names,
+            })
+        };
+        let xml = defined_names.as_ref().map(DefinedNames::to_xml);
+        let mut parts = self.parts.borrow_mut();
+        {
+            let mut package = parts.package.borrow_mut();
+            let uri = parts.workbook_uri.clone();
+            let data = match package.get_part(&uri) {
+                Some(part) => workbook::replace_workbook_element(
+                    part.as_part_bytes(),
+                    "definedNames",
+                    xml.as_deref(),
+                )?,
+                None => return Ok(()),
+            };
+            package.set_part_data(&uri, data);
+        }
+        parts.workbook.defined_names = defined_names;
+        Ok(())
+    }
+
     /// Add a worksheet.
     pub fn add_worksheet(&mut self, _name: &str) -> &mut Worksheet {
         unimplemented!()
@@ -612,14 +2019,18 @@ impl SpreadsheetDocument {
         }
     }
 
-    fn from_package(package:OpenXmlPackage)->Result<Self> {
+    pub(crate) fn from_package(package: OpenXmlPackage) -> Result<Self> {
+        let document_type = package
+            .main_part_uri()
+            .and_then(|uri| package.get_part_content_type(&uri).cloned())
+            .and_then(|content_type| {
+                SpreadsheetDocumentType::try_from_content_type(content_type.as_str())
+            })
+            .unwrap_or_default();
         let package = Rc::new(RefCell::new(package));
-        let parts = SpreadsheetParts::from_package(package.clone());
+        let parts = SpreadsheetParts::from_package(package.clone())?;
         let parts = Rc::new(RefCell::new(parts));
         let workbook = Workbook::new(parts.clone());
-        // let content_type = "";
-        // let document_type = SpreadsheetDocumentType::from_content_type(content_type);
-        let document_type = SpreadsheetDocumentType::Workbook;
         Ok(Self {
             package,
             parts,
@@ -651,6 +2062,72 @@ impl SpreadsheetDocument {
         Ok(())
     }
 
+    /// The document type by main part content type.
+    pub fn document_type(&self) -> SpreadsheetDocumentType {
+        self.document_type
+    }
+
+    /// Conformance class of the document, strict documents are read as transitional ones.
+    pub fn conformance(&self) -> Conformance {
+        self.package.borrow().conformance()
+    }
+
+    /// Change the conformance class to save in.
+    ///
+    /// Saving as strict writes `purl.oclc.org` namespaces and ISO 8601 `t="d"` values for
+    /// cells with date styles, saving as transitional writes serial numbers back.
+    pub fn set_conformance(&mut self, conformance: Conformance) -> Result<()> {
+        let parts = self.parts.borrow();
+        let mut package = self.package.borrow_mut();
+        package.set_conformance(conformance);
+        let date_system = parts.workbook.date_system();
+        for uri in parts.worksheets.keys() {
+            let data = match package.get_part(uri) {
+                Some(part) => part.as_part_bytes(),
+                None => continue,
+            };
+            let converted =
+                worksheet::convert_date_cells(data, conformance, date_system, |style_id| {
+                    parts.is_date_style(style_id)
+                })?;
+            if let Some(converted) = converted {
+                package.set_part_data(uri, converted);
+            }
+        }
+        if let Some(part) = package.get_part(&parts.workbook_uri) {
+            let data = workbook::set_workbook_conformance(part.as_part_bytes(), conformance)?;
+            package.set_part_data(&parts.workbook_uri, data);
+        }
+        Ok(())
+    }
+
+    /// Recalculate all formulas and write the results as cached values of formula cells, so
+    /// that readers without a calculation engine get fresh values after saving.
+    pub fn recalculate(&mut self) -> Result<Calculation> {
+        let calculation = self.workbook.calculate();
+        {
+            let mut parts = self.parts.borrow_mut();
+            let mut package = self.package.borrow_mut();
+            for sheet in self.workbook.worksheets() {
+                let values = match calculation.sheet(&sheet.name) {
+                    Some(values) => values,
+                    None => continue,
+                };
+                let data = match package.get_part(&sheet.uri) {
+                    Some(part) => part.as_part_bytes(),
+                    None => continue,
+                };
+                if let Some(data) = worksheet::set_cell_values(data, values)? {
+                    let part = WorksheetPart::from_xml_reader(data.as_slice())?;
+                    parts.worksheets.insert(sheet.uri.clone(), part);
+                    package.set_part_data(&sheet.uri, data);
+                }
+            }
+        }
+        self.workbook = Workbook::new(self.parts.clone());
+        Ok(calculation)
+    }
+
     pub fn add_workbook(&mut self) -> Workbook {
         Workbook::new(self.parts.clone())
     }
@@ -665,17 +2142,67 @@ impl SpreadsheetDocument {
     }
     /// Get workbook
     pub fn get_workbook_mut(&mut self) -> &mut Workbook {
-        unimplemented!()
+        &mut self.workbook
     }
 }
 
+#[test]
+fn optional_parts() {
+    let mut package = OpenXmlPackage::open("examples/excel-demo/demo.xlsx").unwrap();
+    package.delete_part_core(SHARED_STRINGS_URI);
+    package.delete_part_core(STYLES_URI);
+    let mut bytes = Cursor::new(Vec::new());
+    package.write(&mut bytes).unwrap();
+    let xlsx = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+    let sheet = &xlsx.get_workbook().worksheets()[0];
+    assert_eq!(sheet.cell("B2").unwrap().value(), Some(CellValue::Int(18)));
+
+    for delete_relationship in [false, true] {
+        let mut package = OpenXmlPackage::open("examples/excel-demo/demo.xlsx").unwrap();
+        package.delete_part_core(STYLES_URI);
+        if delete_relationship {
+            let rels = "xl/_rels/workbook.xml.rels";
+            let styles = r#"<Relationship Id="rId4" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>"#;
+            let data = String::from_utf8_lossy(package.get_part(rels).unwrap().as_part_bytes())
+                .into_owned();
+            assert!(data.contains(styles));
+            package.set_part_data(rels, data.replace(styles, "").into_bytes());
+        }
+        let mut bytes = Cursor::new(Vec::new());
+        package.write(&mut bytes).unwrap();
+        let mut xlsx = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+        let red = DifferentialFormat::colored("9C0006", "FFC7CE");
+        let sheet = &mut xlsx.get_workbook_mut().worksheets_mut()[0];
+        let rule = ConditionalFormatRule::cell_is(ConditionalFormatOperator::GreaterThan, &["25"]);
+        sheet
+            .add_conditional_format(&["B2:B13".parse().unwrap()], rule, Some(&red))
+            .unwrap();
+        let mut bytes = Cursor::new(Vec::new());
+        xlsx.package.borrow().write(&mut bytes).unwrap();
+        let xlsx = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+        let sheet = &xlsx.get_workbook().worksheets()[0];
+        let rules = sheet.conditional_format_rules("B5".parse().unwrap());
+        let format = sheet.differential_format(rules[0].dxf_id.unwrap()).unwrap();
+        assert_eq!(format, red);
+    }
+
+    let mut package = OpenXmlPackage::open("examples/excel-demo/demo.xlsx").unwrap();
+    package.delete_part_core("xl/_rels/workbook.xml.rels");
+    let mut bytes = Cursor::new(Vec::new());
+    package.write(&mut bytes).unwrap();
+    assert!(matches!(
+        SpreadsheetDocument::from_bytes(bytes.get_ref()),
+        Err(OoxmlError::PartNotFound(_))
+    ));
+}
+
 #[test]
 fn from_bytes() {
     let xlsx_bytes = include_bytes!("../../../examples/excel-demo/demo.xlsx");
     let xlsx = SpreadsheetDocument::from_bytes(xlsx_bytes).unwrap();
     let workbook = xlsx.get_workbook();
     let _sheet_names = workbook.worksheet_names();
-    assert_eq!(_sheet_names,vec!["Sheet1","Sheet2"]);
+    assert_eq!(_sheet_names, vec!["Sheet1", "Sheet2"]);
 }
 
 #[test]
@@ -727,3 +2254,532 @@ fn chrono() {
     let d3 = d1900 + chrono::Duration::days(v);
     println!("{}", d3);
 }
+
+#[test]
+fn strict_round_trip() {
+    fn cells(xlsx: &SpreadsheetDocument) -> Vec<Vec<String>> {
+        let sheet = &xlsx.get_workbook().worksheets()[0];
+        sheet
+            .rows()
+            .map(|row| row.map(|c| c.to_string().unwrap_or_default()).collect())
+            .collect()
+    }
+    let path = std::env::temp_dir().join("ooxml-strict-round-trip.xlsx");
+    let mut xlsx = SpreadsheetDocument::open("examples/issue-12/demo.xlsx").unwrap();
+    let expected = cells(&xlsx);
+    assert_eq!(xlsx.conformance(), Conformance::Transitional);
+    xlsx.set_conformance(Conformance::Strict).unwrap();
+    xlsx.save_as(&path).unwrap();
+
+    let mut strict = SpreadsheetDocument::open(&path).unwrap();
+    assert_eq!(strict.conformance(), Conformance::Strict);
+    assert_eq!(cells(&strict), expected);
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let mut sheet = String::new();
+    zip.by_name("xl/worksheets/sheet1.xml")
+        .unwrap()
+        .read_to_string(&mut sheet)
+        .unwrap();
+    assert!(sheet.contains("http://purl.oclc.org/ooxml/spreadsheetml/main"));
+    assert!(sheet.contains(r#"t="d""#));
+
+    strict.set_conformance(Conformance::Transitional).unwrap();
+    strict.save_as(&path).unwrap();
+    let transitional = SpreadsheetDocument::open(&path).unwrap();
+    assert_eq!(transitional.conformance(), Conformance::Transitional);
+    assert_eq!(cells(&transitional), expected);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn cell_by_reference() {
+    let xlsx = SpreadsheetDocument::open("examples/excel-demo/demo.xlsx").unwrap();
+    let sheet = &xlsx.get_workbook().worksheets()[0];
+    assert_eq!(sheet.used_range().unwrap().to_string(), "A1:B13");
+    assert_eq!(sheet.cell("B2").unwrap().to_string().as_deref(), Some("18"));
+    assert_eq!(sheet.cell_at(3, 1).to_string().as_deref(), Some("29"));
+    assert_eq!(sheet.cell_at(3, 1).reference().to_string(), "B4");
+    assert!(sheet.cell("Z99").unwrap().is_empty());
+    assert!(sheet.cell("not a cell").is_none());
+}
+
+#[test]
+fn merge_cells() {
+    let mut xlsx = SpreadsheetDocument::open("examples/excel-demo/demo.xlsx").unwrap();
+    let sheet = &mut xlsx.get_workbook_mut().worksheets_mut()[0];
+    assert!(sheet.merged_ranges().is_empty());
+    sheet.merge_cells("A1:B1".parse().unwrap()).unwrap();
+    sheet.merge_cells("A3:A4".parse().unwrap()).unwrap();
+    assert!(sheet.merge_cells("B1:B2".parse().unwrap()).is_err());
+    assert!(sheet.merge_cells("C5".parse().unwrap()).is_err());
+    assert!(sheet.unmerge_cells("A4".parse().unwrap()).unwrap());
+    assert!(!sheet.unmerge_cells("A4".parse().unwrap()).unwrap());
+
+    let mut bytes = Cursor::new(Vec::new());
+    xlsx.package.borrow().write(&mut bytes).unwrap();
+    let xlsx = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+    let sheet = &xlsx.get_workbook().worksheets()[0];
+    assert_eq!(sheet.merged_ranges(), vec!["A1:B1".parse().unwrap()]);
+    let cell = sheet.cell("B1").unwrap();
+    assert!(cell.is_merged_cell());
+    assert_eq!(cell.merge_area(), "A1:B1".parse().ok());
+    assert_eq!(cell.merge_anchor().unwrap().reference().to_string(), "A1");
+    assert_eq!(cell.merged_value(), sheet.cell("A1").unwrap().value());
+    assert!(!sheet.cell("B2").unwrap().is_merged_cell());
+}
+
+#[test]
+fn hyperlinks() {
+    let mut xlsx = SpreadsheetDocument::open("examples/excel-demo/demo.xlsx").unwrap();
+    let sheet = &mut xlsx.get_workbook_mut().worksheets_mut()[0];
+    assert!(sheet.hyperlinks().is_empty());
+    sheet
+        .add_hyperlink(Hyperlink::external(
+            "A1".parse().unwrap(),
+            "https://example.com/old",
+        ))
+        .unwrap();
+    sheet
+        .add_hyperlink(
+            Hyperlink::external("A1".parse().unwrap(), "https://example.com/?a=1&b=2")
+                .with_tooltip("Example"),
+        )
+        .unwrap();
+    sheet
+        .add_hyperlink(
+            Hyperlink::internal("B2:C3".parse().unwrap(), "Sheet1!A1").with_display("Top"),
+        )
+        .unwrap();
+
+    let mut bytes = Cursor::new(Vec::new());
+    xlsx.package.borrow().write(&mut bytes).unwrap();
+    let xlsx = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+    let sheet = &xlsx.get_workbook().worksheets()[0];
+    let links = sheet.hyperlinks();
+    assert_eq!(links.len(), 2);
+    let link = sheet.cell("A1").unwrap().hyperlink().unwrap();
+    assert!(link.is_external());
+    assert_eq!(link.url.as_deref(), Some("https://example.com/?a=1&b=2"));
+    assert_eq!(link.tooltip.as_deref(), Some("Example"));
+    let link = sheet.hyperlink("C3".parse().unwrap()).unwrap();
+    assert_eq!(link.location.as_deref(), Some("Sheet1!A1"));
+    assert_eq!(link.display.as_deref(), Some("Top"));
+    assert!(sheet.hyperlink("D4".parse().unwrap()).is_none());
+    let relationships = xlsx
+        .package
+        .borrow()
+        .get_part_relationships(&sheet.uri)
+        .unwrap();
+    assert_eq!(relationships.to_string().matches("hyperlink").count(), 1);
+}
+
+#[test]
+fn comments() {
+    let mut xlsx = SpreadsheetDocument::open("examples/excel-demo/demo.xlsx").unwrap();
+    let sheet = &mut xlsx.get_workbook_mut().worksheets_mut()[0];
+    assert!(sheet.comments().is_empty());
+    let a1 = CellRef::new(0, 0);
+    sheet
+        .add_comment(Comment::new(a1, "Reviewer", "draft"))
+        .unwrap();
+    sheet
+        .add_comment(Comment::new(a1, "Reviewer", "Check the total"))
+        .unwrap();
+    sheet
+        .add_comment(Comment::from_runs(
+            CellRef::new(2, 1),
+            "Author",
+            vec![
+                TextRun::new("Author:").with_properties(RunProperties {
+                    bold: Some(true),
+                    ..Default::default()
+                }),
+                TextRun::new("\nexplained"),
+            ],
+        ))
+        .unwrap();
+
+    let mut bytes = Cursor::new(Vec::new());
+    xlsx.package.borrow().write(&mut bytes).unwrap();
+    let xlsx = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+    let sheet = &xlsx.get_workbook().worksheets()[0];
+    let comments = sheet.comments();
+    assert_eq!(comments.len(), 2);
+    let comment = sheet.cell("A1").unwrap().comment().unwrap();
+    assert_eq!(comment.author, "Reviewer");
+    assert_eq!(comment.text(), "Check the total");
+    let comment = sheet.comment(CellRef::new(2, 1)).unwrap();
+    assert_eq!(comment.text(), "Author:\nexplained");
+    assert_eq!(
+        comment.text.runs()[0].properties.as_ref().unwrap().bold,
+        Some(true)
+    );
+    assert!(sheet.cell("B2").unwrap().comment().is_none());
+
+    // a broken comments part is an error rather than replaced by a new part
+    let mut broken = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+    let sheet = &mut broken.get_workbook_mut().worksheets_mut()[0];
+    let uri = sheet.related_part_uri(&RelationshipType::Comments).unwrap();
+    sheet.set_package_part_data(&uri, b"<comments><authors></comments>".to_vec());
+    assert!(sheet.comments().is_empty());
+    assert!(sheet
+        .add_comment(Comment::new(CellRef::new(3, 0), "Reviewer", "lost"))
+        .is_err());
+    let sheet_uri = sheet.uri.clone();
+    let package = broken.package.borrow();
+    let relationships = package.get_part_relationships(&sheet_uri).unwrap();
+    assert_eq!(
+        relationships
+            .get_relationships_by_type(&RelationshipType::Comments)
+            .count(),
+        1
+    );
+
+    let sheet = &xlsx.get_workbook().worksheets()[0];
+    let package = xlsx.package.borrow();
+    let vml_uri = package
+        .find_related_part_uri(&sheet.uri, &RelationshipType::VmlDrawing)
+        .unwrap();
+    let vml = String::from_utf8_lossy(package.get_part(&vml_uri).unwrap().as_part_bytes());
+    assert_eq!(vml.matches(r#"ObjectType="Note""#).count(), 2);
+    let data = package.get_part(&sheet.uri).unwrap().as_part_bytes();
+    assert!(String::from_utf8_lossy(data).contains("<legacyDrawing"));
+}
+
+#[test]
+fn comment_threads() {
+    let mut xlsx = SpreadsheetDocument::open("examples/excel-demo/demo.xlsx").unwrap();
+    let sheet = &mut xlsx.get_workbook_mut().worksheets_mut()[0];
+    assert!(sheet.comment_threads().is_empty());
+    let b2 = CellRef::new(1, 1);
+    let thread_id = sheet
+        .add_threaded_comment(b2, "Ann", "Is this right?")
+        .unwrap();
+    let reply_id = sheet.add_threaded_comment(b2, "Bob", "Yes").unwrap();
+    assert_eq!(reply_id, thread_id);
+    assert!(sheet.resolve_comment_thread(b2, true).unwrap());
+    assert!(!sheet
+        .resolve_comment_thread(CellRef::new(0, 0), true)
+        .unwrap());
+
+    let mut bytes = Cursor::new(Vec::new());
+    xlsx.package.borrow().write(&mut bytes).unwrap();
+    let xlsx = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+    let sheet = &xlsx.get_workbook().worksheets()[0];
+    let thread = sheet.cell("B2").unwrap().comment_thread().unwrap();
+    assert_eq!(thread.id(), thread_id);
+    assert!(thread.resolved);
+    assert_eq!(thread.comment.author.display_name, "Ann");
+    assert_eq!(thread.comment.text, "Is this right?");
+    assert!(thread.comment.created.is_some());
+    assert_eq!(thread.replies.len(), 1);
+    assert_eq!(thread.replies[0].author.display_name, "Bob");
+    assert_eq!(xlsx.parts.borrow().persons_part().persons.len(), 2);
+
+    let legacy = sheet.comment(b2).unwrap();
+    assert_eq!(legacy.author, format!("tc={}", thread_id));
+    assert!(legacy.text().starts_with("[Threaded comment]"));
+    assert!(legacy
+        .text()
+        .ends_with("Comment:\n    Is this right?\nReply:\n    Yes"));
+}
+
+#[test]
+fn defined_names() {
+    let mut xlsx = SpreadsheetDocument::open("examples/excel-demo/demo.xlsx").unwrap();
+    let workbook = xlsx.get_workbook_mut();
+    assert!(workbook.defined_names().is_empty());
+    workbook
+        .add_defined_name("Ages", None, "=Sheet1!$B$2:$B$4")
+        .unwrap();
+    workbook
+        .add_defined_name("Ages", Some("Sheet2"), "Sheet1!$B$2")
+        .unwrap();
+    workbook
+        .add_defined_name("Old", None, "Sheet1!$A:$A")
+        .unwrap();
+    workbook
+        .add_defined_name(PRINT_AREA, Some("Sheet1"), "Sheet1!$A$1:$B$13")
+        .unwrap();
+    assert!(workbook.add_defined_name("ages", None, "1").is_err());
+    assert!(workbook.add_defined_name("A1", None, "1").is_err());
+    assert!(workbook
+        .add_defined_name("Name", Some("Nope"), "1")
+        .is_err());
+    // references to the renaming name in formulas, the local `Old` of Sheet2 shadows it there
+    workbook
+        .add_defined_name("Old", Some("Sheet2"), "Sheet1!$B:$B")
+        .unwrap();
+    workbook
+        .add_defined_name("Count", None, "COUNTA(Old)")
+        .unwrap();
+    let sheet = workbook.get_worksheet_by_name_mut("Sheet1").unwrap();
+    let data = {
+        let parts = sheet.parts.borrow();
+        let package = parts.package.borrow();
+        String::from_utf8_lossy(package.get_part(&sheet.uri).unwrap().as_part_bytes()).replace(
+            r#"<c r="B2"><v>18</v></c>"#,
+            r#"<c r="B2"><f>COUNTA(Old)+LEN("Old")</f><v>18</v></c>"#,
+        )
+    };
+    sheet.set_package_part_data(&sheet.uri.clone(), data.into_bytes());
+    let list = |cell: &str| DataValidation::list_source(&[cell.parse().unwrap()], "Old");
+    sheet.add_data_validation(list("D1")).unwrap();
+    let sheet2 = workbook.get_worksheet_by_name_mut("Sheet2").unwrap();
+    sheet2.add_data_validation(list("A1")).unwrap();
+    assert!(workbook.rename_defined_name("Old", None, "Names").unwrap());
+    let sheet = workbook.get_worksheet_by_name("Sheet1").unwrap();
+    let formula = sheet.cell("B2").unwrap().formula().unwrap();
+    assert_eq!(formula.text, r#"COUNTA(Names)+LEN("Old")"#);
+    assert_eq!(
+        sheet.data_validations()[0].formula1.as_deref(),
+        Some("Names")
+    );
+    let sheet2 = workbook.get_worksheet_by_name("Sheet2").unwrap();
+    assert_eq!(
+        sheet2.data_validations()[0].formula1.as_deref(),
+        Some("Old")
+    );
+    assert_eq!(
+        workbook.defined_name("Count", None).unwrap().formula,
+        "COUNTA(Names)"
+    );
+    assert!(workbook.defined_name("Old", Some("Sheet2")).is_some());
+    assert!(workbook.rename_defined_name("Ages", None, "Names").is_err());
+    assert!(!workbook.rename_defined_name("Nope", None, "Other").unwrap());
+    workbook.add_defined_name("Temp", None, "42").unwrap();
+    assert!(workbook.delete_defined_name("temp", None).unwrap());
+    assert!(!workbook.delete_defined_name("Temp", None).unwrap());
+
+    let mut bytes = Cursor::new(Vec::new());
+    xlsx.package.borrow().write(&mut bytes).unwrap();
+    let xlsx = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+    let workbook = xlsx.get_workbook();
+    assert_eq!(workbook.defined_names().len(), 6);
+    let area = workbook.defined_name(PRINT_AREA, Some("Sheet1")).unwrap();
+    assert!(area.is_builtin());
+    assert_eq!(area.local_sheet_id, Some(0));
+    assert_eq!(
+        workbook.resolve_name("AGES", None).unwrap().to_string(),
+        "Sheet1!$B$2:$B$4"
+    );
+    assert_eq!(
+        workbook
+            .resolve_name("Ages", Some("Sheet2"))
+            .unwrap()
+            .to_string(),
+        "Sheet1!$B$2"
+    );
+    assert_eq!(
+        workbook
+            .resolve_name("Ages", Some("Sheet1"))
+            .unwrap()
+            .to_string(),
+        "Sheet1!$B$2:$B$4"
+    );
+    assert!(workbook.resolve_name("Old", None).is_none());
+    let values = workbook.name_values("Ages", None).unwrap();
+    assert_eq!(
+        values,
+        vec![
+            vec![CellValue::Int(18)],
+            vec![CellValue::Int(23)],
+            vec![CellValue::Int(29)]
+        ]
+    );
+    assert_eq!(workbook.name_values("Names", None).unwrap().len(), 13);
+}
+
+#[test]
+fn tables() {
+    let mut xlsx = SpreadsheetDocument::open("examples/excel-demo/demo.xlsx").unwrap();
+    let workbook = xlsx.get_workbook_mut();
+    workbook
+        .add_defined_name("Ages", None, "Sheet1!$B$2")
+        .unwrap();
+    let sheet = workbook.get_worksheet_by_name_mut("Sheet1").unwrap();
+    assert!(sheet.tables().is_empty());
+    let range: CellRange = "A1:B13".parse().unwrap();
+    let table = sheet.add_table("People", range).unwrap();
+    assert_eq!(table.id, 1);
+    assert_eq!(table.column_names(), ["name", "age"]);
+    assert!(sheet.add_table("ages", "D1:E3".parse().unwrap()).is_err());
+    assert!(sheet.add_table("people", "D1:E3".parse().unwrap()).is_err());
+    assert!(sheet
+        .add_table("My Table", "D1:E3".parse().unwrap())
+        .is_err());
+    assert!(sheet.add_table("Other", "B5:C9".parse().unwrap()).is_err());
+    // empty and number headers can not be column names
+    assert!(sheet.add_table("Other", "D1:E3".parse().unwrap()).is_err());
+    assert!(sheet
+        .add_table("Other", "A14:B16".parse().unwrap())
+        .is_err());
+
+    let mut bytes = Cursor::new(Vec::new());
+    xlsx.package.borrow().write(&mut bytes).unwrap();
+    let mut xlsx = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+    let sheet = xlsx
+        .get_workbook_mut()
+        .get_worksheet_by_name_mut("Sheet1")
+        .unwrap();
+    assert_eq!(sheet.tables().len(), 1);
+    let table = sheet.table("PEOPLE").unwrap();
+    assert_eq!(table.r#ref, "A1:B13");
+    assert_eq!(table.auto_filter.as_ref().unwrap().r#ref, "A1:B13");
+    let style = table.table_style_info.as_ref().unwrap();
+    assert_eq!(style.name.as_deref(), Some(DEFAULT_TABLE_STYLE));
+    let records = sheet.table_records("People").unwrap();
+    assert_eq!(records.len(), 12);
+    assert_eq!(records[0]["name"], CellValue::String("张三".into()));
+    assert_eq!(records[0]["age"], CellValue::Int(18));
+
+    assert!(sheet
+        .resize_table("People", "B1:B4".parse().unwrap())
+        .is_err());
+    assert!(sheet
+        .resize_table("People", "A1:D4".parse().unwrap())
+        .is_err());
+    assert!(sheet
+        .resize_table("People", "A1:C4".parse().unwrap())
+        .is_err());
+    sheet
+        .resize_table("People", "A1:A2".parse().unwrap())
+        .unwrap();
+    assert_eq!(sheet.table("People").unwrap().column_names(), ["name"]);
+    let other = sheet.add_table("Other", "A3:A5".parse().unwrap()).unwrap();
+    assert_eq!(other.id, 2);
+    assert_eq!(other.column_names(), ["李四"]);
+    sheet
+        .resize_table("People", "A1:B2".parse().unwrap())
+        .unwrap();
+    let table = sheet.table("People").unwrap();
+    assert_eq!(table.r#ref, "A1:B2");
+    assert_eq!(table.column_names(), ["name", "age"]);
+    assert_eq!(table.columns()[1].id, 2);
+    let records = sheet.table_records("People").unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["age"], CellValue::Int(18));
+}
+
+#[test]
+fn data_validations() {
+    let mut xlsx = SpreadsheetDocument::open("examples/excel-demo/demo.xlsx").unwrap();
+    let sheet = xlsx
+        .get_workbook_mut()
+        .get_worksheet_by_name_mut("Sheet1")
+        .unwrap();
+    assert!(sheet.data_validations().is_empty());
+    let ages = ["B2:B13".parse().unwrap()];
+    let rule = DataValidation::new(ValidationType::Whole, &ages)
+        .with_operator(ValidationOperator::Between, "1", Some("25"))
+        .with_error("Age", "1 to 25");
+    sheet.add_data_validation(rule).unwrap();
+    let names = ["A2:A13".parse().unwrap()];
+    sheet
+        .add_data_validation(DataValidation::list_source(&names, "=$A$2:$A$3"))
+        .unwrap();
+    let overlapped = DataValidation::custom(&["B13:C13".parse().unwrap()], "B13>0");
+    assert!(sheet.add_data_validation(overlapped).is_err());
+    let temp = DataValidation::list(&["D1".parse().unwrap()], &["a", "b"]);
+    sheet.add_data_validation(temp).unwrap();
+    assert!(sheet
+        .remove_data_validations("D1:E2".parse().unwrap())
+        .unwrap());
+    assert!(!sheet
+        .remove_data_validations("D1:E2".parse().unwrap())
+        .unwrap());
+
+    let mut bytes = Cursor::new(Vec::new());
+    xlsx.package.borrow().write(&mut bytes).unwrap();
+    let xlsx = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+    let workbook = xlsx.get_workbook();
+    let sheet = workbook.get_worksheet_by_name("Sheet1").unwrap();
+    assert_eq!(sheet.data_validations().len(), 2);
+    let rule = sheet.data_validation("B5".parse().unwrap()).unwrap();
+    assert_eq!(rule.kind, ValidationType::Whole);
+    assert_eq!(rule.error.as_deref(), Some("1 to 25"));
+    assert!(sheet.data_validation("C5".parse().unwrap()).is_none());
+
+    let invalid = workbook.invalid_cells();
+    let cells: Vec<String> = invalid.iter().map(|i| i.cell.to_string()).collect();
+    assert_eq!(cells, ["B4", "B7", "B10", "B13", "A4", "A7", "A10", "A13"]);
+    assert_eq!(invalid[0].value, CellValue::Int(29));
+    assert_eq!(invalid[4].rule.kind, ValidationType::List);
+    let b2 = "B2".parse().unwrap();
+    assert!(workbook
+        .check_value("Sheet1", b2, &CellValue::Int(20))
+        .is_none());
+    assert!(workbook
+        .check_value("Sheet1", b2, &CellValue::Int(30))
+        .is_some());
+    let a5 = "A5".parse().unwrap();
+    assert!(workbook
+        .check_value("Sheet1", a5, &CellValue::String("李四".into()))
+        .is_none());
+    assert!(workbook
+        .check_value("Sheet1", a5, &CellValue::Null)
+        .is_none());
+}
+
+#[test]
+fn conditional_formats() {
+    for path in [
+        "examples/excel-demo/demo.xlsx",
+        "examples/issue-12/demo.xlsx",
+    ] {
+        let mut xlsx = SpreadsheetDocument::open(path).unwrap();
+        let workbook = xlsx.get_workbook_mut();
+        let sheet = &mut workbook.worksheets_mut()[0];
+        assert!(sheet.conditional_formattings().is_empty());
+        let ages = ["B2:B13".parse().unwrap()];
+        let red = DifferentialFormat::colored("9C0006", "FFC7CE");
+        let green = DifferentialFormat::colored("006100", "C6EFCE");
+        let rule = ConditionalFormatRule::cell_is(ConditionalFormatOperator::GreaterThan, &["25"]);
+        sheet
+            .add_conditional_format(&ages, rule, Some(&red))
+            .unwrap();
+        let rule = ConditionalFormatRule::expression("=$B2<20").with_stop_if_true(true);
+        sheet
+            .add_conditional_format(&ages, rule, Some(&green))
+            .unwrap();
+        let scale =
+            ConditionalFormatRule::color_scale(&[Color::rgb("F8696B"), Color::rgb("63BE7B")]);
+        sheet
+            .add_conditional_format(&["A2:A13".parse().unwrap()], scale, None)
+            .unwrap();
+        let bar = ConditionalFormatRule::data_bar(Color::rgb("638EC6"));
+        sheet
+            .add_conditional_format(&["D1".parse().unwrap()], bar, None)
+            .unwrap();
+        assert!(sheet
+            .remove_conditional_formats("D1:D2".parse().unwrap())
+            .unwrap());
+        assert!(sheet
+            .add_conditional_format(&[], ConditionalFormatRule::duplicate_values(), None)
+            .is_err());
+
+        let mut bytes = Cursor::new(Vec::new());
+        xlsx.package.borrow().write(&mut bytes).unwrap();
+        let xlsx = SpreadsheetDocument::from_bytes(bytes.get_ref()).unwrap();
+        let sheet = &xlsx.get_workbook().worksheets()[0];
+        let blocks = sheet.conditional_formattings();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].sqref, "B2:B13");
+        let rules = sheet.conditional_format_rules("B5".parse().unwrap());
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].priority, 1);
+        assert_eq!(rules[0].formulas, ["25"]);
+        assert_eq!(rules[1].formulas, ["$B2<20"]);
+        assert_eq!(rules[1].stop_if_true, Some(true));
+        let format = sheet.differential_format(rules[0].dxf_id.unwrap()).unwrap();
+        assert_eq!(format, red);
+        let format = sheet.differential_format(rules[1].dxf_id.unwrap()).unwrap();
+        assert_eq!(format, green);
+        let rules = sheet.conditional_format_rules("A2".parse().unwrap());
+        assert_eq!(rules[0].priority, 3);
+        assert_eq!(rules[0].color_scale.as_ref().unwrap().colors.len(), 2);
+        assert!(sheet
+            .conditional_format_rules("D1".parse().unwrap())
+            .is_empty());
+    }
+}
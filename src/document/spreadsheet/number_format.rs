@@ -0,0 +1,1264 @@
+//! Number format codes rendering, see ECMA-376 Part 1, 18.8.31.
+//!
+//! A format code has up to four sections separated by `;`, for positive numbers, negative
+//! numbers, zero and text. Sections may have a color like `[Red]` and a condition like
+//! `[>100]`, which overrides the default sign based section selection.
+//!
+//! ```rust
+//! use ooxml::document::NumberFormatCode;
+//!
+//! let code = NumberFormatCode::parse("#,##0.00_);[Red](#,##0.00)");
+//! assert_eq!(code.format(1234.5), "1,234.50 ");
+//! assert_eq!(code.format(-1234.5), "(1,234.50)");
+//! ```
+use chrono::{Datelike, Timelike};
+
+use super::cell::DateSystem;
+
+/// Month names for `mmm` and `mmmm`.
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+/// Weekday names for `ddd` and `dddd`, from Monday as chrono.
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// What a date or time number format shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateTimeKind {
+    /// Years, months or days only.
+    Date,
+    /// Hours, minutes or seconds only.
+    Time,
+    DateTime,
+    /// Elapsed time like `[h]:mm:ss`.
+    Duration,
+}
+
+/// Section colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatColor {
+    Black,
+    Blue,
+    Cyan,
+    Green,
+    Magenta,
+    Red,
+    White,
+    Yellow,
+    /// `[Color n]`, 1 to 56 in the indexed palette.
+    Indexed(u8),
+}
+
+impl FormatColor {
+    fn from_name(name: &str) -> Option<Self> {
+        let color = match name.to_ascii_lowercase().as_str() {
+            "black" => FormatColor::Black,
+            "blue" => FormatColor::Blue,
+            "cyan" => FormatColor::Cyan,
+            "green" => FormatColor::Green,
+            "magenta" => FormatColor::Magenta,
+            "red" => FormatColor::Red,
+            "white" => FormatColor::White,
+            "yellow" => FormatColor::Yellow,
+            name => {
+                let index = name.strip_prefix("color")?.trim().parse().ok()?;
+                FormatColor::Indexed(index)
+            }
+        };
+        Some(color)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+/// Section condition like `[>=100]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Condition {
+    comparison: Comparison,
+    value: f64,
+}
+
+impl Condition {
+    fn parse(s: &str) -> Option<Self> {
+        let (comparison, rest) = [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<>", Comparison::NotEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ]
+        .iter()
+        .find_map(|(op, comparison)| s.strip_prefix(op).map(|rest| (*comparison, rest)))?;
+        Some(Self {
+            comparison,
+            value: rest.trim().parse().ok()?,
+        })
+    }
+
+    fn matches(&self, v: f64) -> bool {
+        match self.comparison {
+            Comparison::Less => v < self.value,
+            Comparison::LessOrEqual => v <= self.value,
+            Comparison::Greater => v > self.value,
+            Comparison::GreaterOrEqual => v >= self.value,
+            Comparison::Equal => v == self.value,
+            Comparison::NotEqual => v != self.value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    /// `0`, shows insignificant zeros.
+    Zero,
+    /// `#`, shows significant digits only.
+    Hash,
+    /// `?`, pads insignificant zeros with spaces.
+    Question,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Elapsed {
+    Hours,
+    Minutes,
+    Seconds,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(String),
+    Digit(Placeholder),
+    Point,
+    Comma,
+    Percent,
+    /// `E+` or `E-`, true to always show the exponent sign.
+    Exponent(bool),
+    Slash,
+    /// Fixed fraction denominator like `8` in `# ?/8`.
+    Denominator(u32),
+    Text,
+    General,
+    /// `_x`, space as wide as `x`.
+    Skip,
+    /// `*x`, repeat `x` to fill the column width.
+    Fill(char),
+    Year(usize),
+    Month(usize),
+    Minute(usize),
+    Day(usize),
+    Hour(usize),
+    Second(usize),
+    /// Fraction of seconds like `.00`, by digits.
+    SubSecond(usize),
+    /// `AM/PM` or `A/P`, the am and pm texts as in the code.
+    AmPm(String, String),
+    Elapsed(Elapsed, usize),
+}
+
+impl Token {
+    fn is_date_time(&self) -> bool {
+        matches!(
+            self,
+            Token::Year(_)
+                | Token::Month(_)
+                | Token::Minute(_)
+                | Token::Day(_)
+                | Token::Hour(_)
+                | Token::Second(_)
+                | Token::SubSecond(_)
+                | Token::AmPm(..)
+                | Token::Elapsed(..)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Section {
+    tokens: Vec<Token>,
+    color: Option<FormatColor>,
+    condition: Option<Condition>,
+}
+
+impl Section {
+    fn parse(code: &str) -> Self {
+        let mut section = Section::default();
+        let chars: Vec<char> = code.chars().collect();
+        let mut i = 0;
+        let literal = |tokens: &mut Vec<Token>, s: &str| match tokens.last_mut() {
+            Some(Token::Literal(last)) => last.push_str(s),
+            _ => tokens.push(Token::Literal(s.to_string())),
+        };
+        while i < chars.len() {
+            let c = chars[i];
+            let run = chars[i..]
+                .iter()
+                .take_while(|x| x.eq_ignore_ascii_case(&c))
+                .count();
+            let rest = &chars[i..];
+            // compare chars rather than bytes, literals may be multi-byte chars.
+            let starts_with = |keyword: &str| {
+                rest.len() >= keyword.len()
+                    && rest
+                        .iter()
+                        .zip(keyword.chars())
+                        .all(|(a, b)| a.eq_ignore_ascii_case(&b))
+            };
+            let text = |range: std::ops::Range<usize>| rest[range].iter().collect::<String>();
+            let tokens = &mut section.tokens;
+            match c {
+                '"' => {
+                    let end = chars[i + 1..]
+                        .iter()
+                        .position(|c| *c == '"')
+                        .map_or(chars.len(), |p| i + 1 + p);
+                    let text: String = chars[i + 1..end].iter().collect();
+                    literal(tokens, &text);
+                    i = end + 1;
+                    continue;
+                }
+                '\\' | '!' if i + 1 < chars.len() => {
+                    literal(tokens, &chars[i + 1].to_string());
+                    i += 2;
+                    continue;
+                }
+                '_' if i + 1 < chars.len() => {
+                    tokens.push(Token::Skip);
+                    i += 2;
+                    continue;
+                }
+                '*' if i + 1 < chars.len() => {
+                    tokens.push(Token::Fill(chars[i + 1]));
+                    i += 2;
+                    continue;
+                }
+                '[' => {
+                    let end = chars[i..]
+                        .iter()
+                        .position(|c| *c == ']')
+                        .map_or(chars.len(), |p| i + p);
+                    let content: String = chars[i + 1..end].iter().collect();
+                    let lower = content.to_ascii_lowercase();
+                    if let Some(color) = FormatColor::from_name(&content) {
+                        section.color = Some(color);
+                    } else if let Some(condition) = Condition::parse(&content) {
+                        section.condition = Some(condition);
+                    } else if let Some(currency) = content.strip_prefix('$') {
+                        let symbol = currency.split('-').next().unwrap_or_default();
+                        if !symbol.is_empty() {
+                            literal(tokens, symbol);
+                        }
+                    } else if !lower.is_empty()
+                        && lower.chars().all(|c| c == lower.as_bytes()[0] as char)
+                    {
+                        let unit = match lower.as_bytes()[0] {
+                            b'h' => Some(Elapsed::Hours),
+                            b'm' => Some(Elapsed::Minutes),
+                            b's' => Some(Elapsed::Seconds),
+                            _ => None,
+                        };
+                        if let Some(unit) = unit {
+                            tokens.push(Token::Elapsed(unit, lower.len()));
+                        }
+                    }
+                    i = end + 1;
+                    continue;
+                }
+                '0' => tokens.push(Token::Digit(Placeholder::Zero)),
+                '#' => tokens.push(Token::Digit(Placeholder::Hash)),
+                '?' => tokens.push(Token::Digit(Placeholder::Question)),
+                '.' if matches!(
+                    tokens.last(),
+                    Some(Token::Second(_)) | Some(Token::Elapsed(Elapsed::Seconds, _))
+                ) && chars.get(i + 1) == Some(&'0') =>
+                {
+                    let zeros = chars[i + 1..].iter().take_while(|c| **c == '0').count();
+                    tokens.push(Token::SubSecond(zeros));
+                    i += 1 + zeros;
+                    continue;
+                }
+                '.' => tokens.push(Token::Point),
+                ',' => tokens.push(Token::Comma),
+                '%' => tokens.push(Token::Percent),
+                'E' | 'e' if matches!(chars.get(i + 1), Some('+') | Some('-')) => {
+                    tokens.push(Token::Exponent(chars[i + 1] == '+'));
+                    i += 2;
+                    continue;
+                }
+                '/' => tokens.push(Token::Slash),
+                '1'..='9' if matches!(tokens.last(), Some(Token::Slash)) => {
+                    let digits: String = chars[i..]
+                        .iter()
+                        .take_while(|c| c.is_ascii_digit())
+                        .collect();
+                    tokens.push(Token::Denominator(digits.parse().unwrap_or(1)));
+                    i += digits.len();
+                    continue;
+                }
+                '@' => tokens.push(Token::Text),
+                'G' | 'g' if starts_with("general") => {
+                    tokens.push(Token::General);
+                    i += 7;
+                    continue;
+                }
+                'A' | 'a' if starts_with("am/pm") => {
+                    tokens.push(Token::AmPm(text(0..2), text(3..5)));
+                    i += 5;
+                    continue;
+                }
+                'A' | 'a' if starts_with("a/p") => {
+                    tokens.push(Token::AmPm(text(0..1), text(2..3)));
+                    i += 3;
+                    continue;
+                }
+                'y' | 'Y' | 'e' => {
+                    tokens.push(Token::Year(if c == 'e' { 4 } else { run }));
+                    i += run;
+                    continue;
+                }
+                'm' | 'M' => {
+                    tokens.push(Token::Month(run));
+                    i += run;
+                    continue;
+                }
+                'd' | 'D' => {
+                    tokens.push(Token::Day(run));
+                    i += run;
+                    continue;
+                }
+                'h' | 'H' => {
+                    tokens.push(Token::Hour(run));
+                    i += run;
+                    continue;
+                }
+                's' | 'S' => {
+                    tokens.push(Token::Second(run));
+                    i += run;
+                    continue;
+                }
+                // era and calendar markers.
+                'g' | 'G' | 'b' | 'B' => {}
+                c => literal(tokens, &c.to_string()),
+            }
+            i += 1;
+        }
+        section.resolve();
+        section
+    }
+
+    /// Resolve `m` as minutes after hours or before seconds, and `/` as literal in dates.
+    fn resolve(&mut self) {
+        if !self.is_date_time() {
+            return;
+        }
+        let time_units: Vec<(usize, &Token)> = self
+            .tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.is_date_time())
+            .collect();
+        let mut minutes = Vec::new();
+        for (n, (index, token)) in time_units.iter().enumerate() {
+            if let Token::Month(len) = token {
+                let after_hour = n > 0
+                    && matches!(
+                        time_units[n - 1].1,
+                        Token::Hour(_) | Token::Elapsed(Elapsed::Hours, _)
+                    );
+                let before_second = matches!(
+                    time_units.get(n + 1).map(|(_, t)| *t),
+                    Some(Token::Second(_)) | Some(Token::Elapsed(Elapsed::Seconds, _))
+                );
+                if *len <= 2 && (after_hour || before_second) {
+                    minutes.push((*index, *len));
+                }
+            }
+        }
+        for (index, len) in minutes {
+            self.tokens[index] = Token::Minute(len);
+        }
+        for token in self.tokens.iter_mut() {
+            match token {
+                Token::Slash => *token = Token::Literal("/".into()),
+                Token::Point => *token = Token::Literal(".".into()),
+                Token::Comma => *token = Token::Literal(",".into()),
+                Token::Digit(Placeholder::Zero) => *token = Token::Literal("0".into()),
+                _ => {}
+            }
+        }
+    }
+
+    fn is_date_time(&self) -> bool {
+        self.tokens.iter().any(|t| t.is_date_time())
+    }
+
+    fn is_text(&self) -> bool {
+        self.tokens.contains(&Token::Text)
+    }
+
+    fn has_digits(&self) -> bool {
+        self.tokens
+            .iter()
+            .any(|t| matches!(t, Token::Digit(_) | Token::General))
+    }
+
+    /// Render the text section.
+    fn format_text(&self, text: &str) -> String {
+        let mut output = String::new();
+        for token in &self.tokens {
+            match token {
+                Token::Text => output.push_str(text),
+                Token::Literal(s) => output.push_str(s),
+                Token::Skip => output.push(' '),
+                _ => {}
+            }
+        }
+        output
+    }
+
+    /// Render a number with the section, `v` is not negative.
+    fn format_number(&self, v: f64, date_system: DateSystem) -> String {
+        if self.is_date_time() {
+            return self.format_date_time(v, date_system);
+        }
+        let tokens = &self.tokens;
+        let percent = tokens.iter().filter(|t| **t == Token::Percent).count() as i32;
+        let v = v * 100f64.powi(percent);
+
+        // scaling commas follow the last integer or decimal digit placeholder.
+        let number_end = tokens
+            .iter()
+            .position(|t| matches!(t, Token::Point | Token::Exponent(_)))
+            .unwrap_or(tokens.len());
+        let last_digit = tokens[..number_end]
+            .iter()
+            .rposition(|t| matches!(t, Token::Digit(_)));
+        let last_decimal = tokens[number_end..]
+            .iter()
+            .rposition(|t| matches!(t, Token::Digit(_)))
+            .map(|p| number_end + p);
+        let mut scaling = Vec::new();
+        for last in last_digit.iter().chain(last_decimal.iter()) {
+            let mut i = last + 1;
+            while tokens.get(i) == Some(&Token::Comma) {
+                scaling.push(i);
+                i += 1;
+            }
+        }
+        let v = v / 1000f64.powi(scaling.len() as i32);
+        let first_digit = tokens.iter().position(|t| matches!(t, Token::Digit(_)));
+        let thousands = match (first_digit, last_digit) {
+            (Some(first), Some(last)) => tokens[first..last].contains(&Token::Comma),
+            _ => false,
+        };
+
+        if let Some(slash) = tokens.iter().position(|t| *t == Token::Slash) {
+            let numerator = tokens[..slash]
+                .iter()
+                .rposition(|t| matches!(t, Token::Digit(_)));
+            if numerator.is_some()
+                && tokens[slash + 1..]
+                    .iter()
+                    .any(|t| matches!(t, Token::Digit(_) | Token::Denominator(_)))
+            {
+                return self.format_fraction(v, slash, thousands);
+            }
+        }
+        if let Some(exponent) = tokens.iter().position(|t| matches!(t, Token::Exponent(_))) {
+            return self.format_scientific(v, exponent);
+        }
+
+        let decimals = tokens[number_end..]
+            .iter()
+            .take_while(|t| !matches!(t, Token::Exponent(_)))
+            .filter(|t| matches!(t, Token::Digit(_)))
+            .count();
+        let (int_digits, frac_digits) = round_decimal(v, decimals);
+        let mut output = String::new();
+        let integer_tokens: Vec<(usize, &Token)> = tokens[..number_end]
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !scaling.contains(i))
+            .collect();
+        render_integer(&integer_tokens, &int_digits, thousands, &mut output, v);
+        render_fraction_digits(&tokens[number_end..], &frac_digits, &mut output, v);
+        output
+    }
+
+    fn format_scientific(&self, v: f64, exponent: usize) -> String {
+        let tokens = &self.tokens;
+        let point = tokens[..exponent].iter().position(|t| *t == Token::Point);
+        let int_end = point.unwrap_or(exponent);
+        let int_placeholders: Vec<Placeholder> = tokens[..int_end]
+            .iter()
+            .filter_map(|t| match t {
+                Token::Digit(p) => Some(*p),
+                _ => None,
+            })
+            .collect();
+        let decimals = point.map_or(0, |point| {
+            tokens[point..exponent]
+                .iter()
+                .filter(|t| matches!(t, Token::Digit(_)))
+                .count()
+        });
+        // engineering notation like `##0.0E+0` keeps exponents a multiple of the integer digits.
+        let step = if int_placeholders.len() > 1 && int_placeholders.contains(&Placeholder::Hash) {
+            int_placeholders.len() as i32
+        } else {
+            1
+        };
+        let mut exp = if v == 0. {
+            0
+        } else {
+            v.abs().log10().floor() as i32
+        };
+        exp = exp.div_euclid(step) * step;
+        let mut mantissa = v / 10f64.powi(exp);
+        let (mut int_digits, mut frac_digits) = round_decimal(mantissa, decimals);
+        if v != 0. && int_digits.len() as i32 > step.max(1) {
+            exp += step;
+            mantissa = v / 10f64.powi(exp);
+            let rounded = round_decimal(mantissa, decimals);
+            int_digits = rounded.0;
+            frac_digits = rounded.1;
+        }
+        let mut output = String::new();
+        let integer_tokens: Vec<(usize, &Token)> = tokens[..int_end].iter().enumerate().collect();
+        render_integer(&integer_tokens, &int_digits, false, &mut output, mantissa);
+        if let Some(point) = point {
+            render_fraction_digits(
+                &tokens[point..exponent],
+                &frac_digits,
+                &mut output,
+                mantissa,
+            );
+        }
+        let show_plus = matches!(tokens[exponent], Token::Exponent(true));
+        output.push('E');
+        if exp < 0 {
+            output.push('-');
+        } else if show_plus {
+            output.push('+');
+        }
+        let exp_tokens: Vec<(usize, &Token)> = tokens[exponent + 1..].iter().enumerate().collect();
+        let exp_digits = exp.abs().to_string();
+        let exp_digits = if exp_digits == "0" {
+            String::new()
+        } else {
+            exp_digits
+        };
+        render_integer(
+            &exp_tokens,
+            &exp_digits,
+            false,
+            &mut output,
+            exp.abs() as f64,
+        );
+        output
+    }
+
+    fn format_fraction(&self, v: f64, slash: usize, thousands: bool) -> String {
+        let tokens = &self.tokens;
+        // numerator placeholders are the digits right before the slash, the integer part
+        // placeholders are separated from them by a literal.
+        let mut numerator_start = slash;
+        while numerator_start > 0 && matches!(tokens[numerator_start - 1], Token::Digit(_)) {
+            numerator_start -= 1;
+        }
+        let integer_end = tokens[..numerator_start]
+            .iter()
+            .rposition(|t| matches!(t, Token::Digit(_)))
+            .map(|i| i + 1);
+        let denominator_end = tokens[slash + 1..]
+            .iter()
+            .position(|t| !matches!(t, Token::Digit(_) | Token::Denominator(_)))
+            .map_or(tokens.len(), |p| slash + 1 + p);
+        let denominator_tokens = &tokens[slash + 1..denominator_end];
+        let fixed = denominator_tokens.iter().find_map(|t| match t {
+            Token::Denominator(d) => Some(*d),
+            _ => None,
+        });
+        let denominator_digits = denominator_tokens.len() as u32;
+
+        let (whole, fraction) = match integer_end {
+            Some(_) => (v.trunc(), v.fract()),
+            None => (0., v),
+        };
+        let (mut numerator, denominator) = match fixed {
+            Some(d) => ((fraction * d as f64).round() as u64, d as u64),
+            None => best_fraction(fraction, 10u64.pow(denominator_digits.min(7)) - 1),
+        };
+        let mut whole = whole as u64;
+        if numerator == denominator && integer_end.is_some() {
+            whole += 1;
+            numerator = 0;
+        }
+
+        let mut output = String::new();
+        if let Some(integer_end) = integer_end {
+            let integer_tokens: Vec<(usize, &Token)> =
+                tokens[..integer_end].iter().enumerate().collect();
+            let digits = if whole == 0 {
+                String::new()
+            } else {
+                whole.to_string()
+            };
+            // zero wholes with a fraction show nothing, `0` shows when there's no fraction.
+            let digits = if whole == 0 && numerator == 0 {
+                "0".to_string()
+            } else {
+                digits
+            };
+            render_integer(
+                &integer_tokens,
+                &digits,
+                thousands,
+                &mut output,
+                whole as f64,
+            );
+        }
+        let mut fraction = String::new();
+        for token in &tokens[integer_end.unwrap_or(0)..numerator_start] {
+            match token {
+                Token::Literal(s) => fraction.push_str(s),
+                Token::Skip => fraction.push(' '),
+                _ => {}
+            }
+        }
+        let numerator_tokens = &tokens[numerator_start..slash];
+        let numerator_text = numerator.to_string();
+        let pad = numerator_tokens.len().saturating_sub(numerator_text.len());
+        for placeholder in &numerator_tokens[..pad] {
+            match placeholder {
+                Token::Digit(Placeholder::Zero) => fraction.push('0'),
+                Token::Digit(Placeholder::Question) => fraction.push(' '),
+                _ => {}
+            }
+        }
+        fraction.push_str(&numerator_text);
+        fraction.push('/');
+        let denominator_text = denominator.to_string();
+        fraction.push_str(&denominator_text);
+        if fixed.is_none() {
+            let pad = denominator_tokens
+                .len()
+                .saturating_sub(denominator_text.len());
+            for placeholder in &denominator_tokens[denominator_tokens.len() - pad..] {
+                match placeholder {
+                    Token::Digit(Placeholder::Zero) => fraction.push('0'),
+                    Token::Digit(Placeholder::Question) => fraction.push(' '),
+                    _ => {}
+                }
+            }
+        }
+        if numerator == 0 && integer_end.is_some() {
+            // whole numbers pad the fraction part with spaces.
+            output.extend(std::iter::repeat_n(' ', fraction.chars().count()));
+        } else {
+            output.push_str(&fraction);
+        }
+        for token in &tokens[denominator_end..] {
+            match token {
+                Token::Literal(s) => output.push_str(s),
+                Token::Skip => output.push(' '),
+                Token::Percent => output.push('%'),
+                _ => {}
+            }
+        }
+        output
+    }
+
+    fn format_date_time(&self, serial: f64, date_system: DateSystem) -> String {
+        let sub_second_digits = self
+            .tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::SubSecond(n) => Some(*n),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+            .min(3);
+        let units_per_day = 86_400. * 10f64.powi(sub_second_digits as i32);
+        let serial = (serial * units_per_day).round() / units_per_day;
+        // the fictional 1900-02-29 shows as is, with the time of the day before.
+        let datetime = match date_system
+            .serial_to_datetime(serial)
+            .or_else(|| date_system.serial_to_datetime(serial - 1.))
+        {
+            Some(datetime) => datetime,
+            None => return "#".repeat(8),
+        };
+        let (year, month, day) = match (date_system, serial.floor() as i64) {
+            (DateSystem::Date1900, 0) => (1900, 1, 0),
+            (DateSystem::Date1900, 60) => (1900, 2, 29),
+            _ => (datetime.year(), datetime.month(), datetime.day()),
+        };
+        let total_seconds = serial * 86_400.;
+        let twelve_hours = self.tokens.iter().any(|t| matches!(t, Token::AmPm(..)));
+        let mut output = String::new();
+        for token in &self.tokens {
+            match token {
+                Token::Literal(s) => output.push_str(s),
+                Token::Skip => output.push(' '),
+                Token::Year(n) if *n <= 2 => output.push_str(&format!("{:02}", year % 100)),
+                Token::Year(_) => output.push_str(&format!("{:04}", year)),
+                Token::Month(1) => output.push_str(&month.to_string()),
+                Token::Month(2) => output.push_str(&format!("{:02}", month)),
+                Token::Month(n) => {
+                    let name = MONTHS[month as usize - 1];
+                    match n {
+                        3 => output.push_str(&name[..3]),
+                        4 => output.push_str(name),
+                        _ => output.push_str(&name[..1]),
+                    }
+                }
+                Token::Day(1) => output.push_str(&day.to_string()),
+                Token::Day(2) => output.push_str(&format!("{:02}", day)),
+                Token::Day(n) => {
+                    let name = WEEKDAYS[datetime.weekday().num_days_from_monday() as usize];
+                    match n {
+                        3 => output.push_str(&name[..3]),
+                        _ => output.push_str(name),
+                    }
+                }
+                Token::Hour(n) => {
+                    let hour = match datetime.hour() {
+                        h if twelve_hours && h % 12 == 0 => 12,
+                        h if twelve_hours => h % 12,
+                        h => h,
+                    };
+                    push_padded(&mut output, hour as u64, (*n).min(2));
+                }
+                Token::Minute(n) => push_padded(&mut output, datetime.minute() as u64, *n),
+                Token::Second(n) => push_padded(&mut output, datetime.second() as u64, (*n).min(2)),
+                Token::SubSecond(n) => {
+                    let millis = datetime.nanosecond() / 1_000_000;
+                    let digits = format!("{:03}", millis);
+                    output.push('.');
+                    output.push_str(&digits[..(*n).min(3)]);
+                }
+                Token::AmPm(am, pm) => output.push_str(if datetime.hour() < 12 { am } else { pm }),
+                Token::Elapsed(unit, n) => {
+                    let value = match unit {
+                        Elapsed::Hours => total_seconds / 3600.,
+                        Elapsed::Minutes => total_seconds / 60.,
+                        Elapsed::Seconds => total_seconds,
+                    };
+                    push_padded(&mut output, value.floor() as u64, *n);
+                }
+                _ => {}
+            }
+        }
+        output
+    }
+}
+
+fn push_padded(output: &mut String, value: u64, width: usize) {
+    output.push_str(&format!("{:0width$}", value, width = width));
+}
+
+/// Round a non-negative number half away from zero at `decimals` as Excel does, on its
+/// 15 significant digits, returns integer digits without leading zeros and fraction digits.
+fn round_decimal(v: f64, decimals: usize) -> (String, String) {
+    let v = v.abs();
+    if v == 0. || !v.is_finite() {
+        return (String::new(), "0".repeat(decimals));
+    }
+    let scientific = format!("{:.14e}", v);
+    let (mantissa, exponent) = scientific.split_once('e').expect("scientific notation");
+    let exponent: i32 = exponent.parse().expect("exponent");
+    let mut digits: Vec<u8> = mantissa
+        .bytes()
+        .filter(|b| b.is_ascii_digit())
+        .map(|b| b - b'0')
+        .collect();
+    // digits[0] is at 10^exponent, make the integer part at least one digit long.
+    let mut int_len = exponent + 1;
+    if int_len < 1 {
+        let zeros = (1 - int_len) as usize;
+        digits.splice(0..0, std::iter::repeat_n(0, zeros));
+        int_len = 1;
+    }
+    let int_len = int_len as usize;
+    let keep = int_len + decimals;
+    if digits.len() < keep {
+        digits.resize(keep, 0);
+    }
+    let round_up = digits.get(keep).is_some_and(|d| *d >= 5);
+    digits.truncate(keep);
+    let mut int_len = int_len;
+    if round_up {
+        let mut i = keep;
+        loop {
+            if i == 0 {
+                digits.insert(0, 1);
+                int_len += 1;
+                break;
+            }
+            i -= 1;
+            if digits[i] == 9 {
+                digits[i] = 0;
+            } else {
+                digits[i] += 1;
+                break;
+            }
+        }
+    }
+    let text: String = digits.iter().map(|d| (d + b'0') as char).collect();
+    let (int, frac) = text.split_at(int_len);
+    (int.trim_start_matches('0').to_string(), frac.to_string())
+}
+
+/// Render integer placeholders and the literals between them, extra digits go to the first
+/// placeholder.
+fn render_integer(
+    tokens: &[(usize, &Token)],
+    digits: &str,
+    thousands: bool,
+    output: &mut String,
+    v: f64,
+) {
+    let placeholders = tokens
+        .iter()
+        .filter(|(_, t)| matches!(t, Token::Digit(_)))
+        .count();
+    let digits: Vec<char> = digits.chars().collect();
+    let mut seen = 0;
+    for (_, token) in tokens {
+        match token {
+            Token::Digit(placeholder) => {
+                // position from the units digit.
+                let position = placeholders - seen - 1;
+                let emit = |c: char, position: usize, output: &mut String| {
+                    output.push(c);
+                    if thousands && position > 0 && position.is_multiple_of(3) {
+                        output.push(',');
+                    }
+                };
+                if seen == 0 && digits.len() > placeholders {
+                    let extra = digits.len() - placeholders;
+                    for (i, c) in digits[..extra].iter().enumerate() {
+                        emit(*c, digits.len() - i - 1, output);
+                    }
+                }
+                match digits.len().checked_sub(position + 1) {
+                    Some(index) => emit(digits[index], position, output),
+                    None => match placeholder {
+                        Placeholder::Zero => emit('0', position, output),
+                        Placeholder::Question => output.push(' '),
+                        Placeholder::Hash => {}
+                    },
+                }
+                seen += 1;
+            }
+            Token::Literal(s) => output.push_str(s),
+            Token::Skip => output.push(' '),
+            Token::Percent => output.push('%'),
+            Token::General => output.push_str(&format_general(v)),
+            Token::Text => {}
+            _ => {}
+        }
+    }
+}
+
+/// Render the decimal point, fraction digit placeholders and the literals after them.
+fn render_fraction_digits(tokens: &[Token], digits: &str, output: &mut String, v: f64) {
+    let digits: Vec<char> = digits.chars().collect();
+    let significant = digits.iter().rposition(|c| *c != '0').map_or(0, |p| p + 1);
+    let mut seen = 0;
+    for token in tokens {
+        match token {
+            Token::Point => output.push('.'),
+            Token::Digit(placeholder) => {
+                match (seen < significant, placeholder) {
+                    (true, _) | (false, Placeholder::Zero) => {
+                        output.push(digits.get(seen).copied().unwrap_or('0'))
+                    }
+                    (false, Placeholder::Question) => output.push(' '),
+                    (false, Placeholder::Hash) => {}
+                }
+                seen += 1;
+            }
+            Token::Literal(s) => output.push_str(s),
+            Token::Skip => output.push(' '),
+            Token::Percent => output.push('%'),
+            Token::General => output.push_str(&format_general(v)),
+            _ => {}
+        }
+    }
+}
+
+/// Best fraction approximation with a denominator up to `max_denominator`.
+fn best_fraction(v: f64, max_denominator: u64) -> (u64, u64) {
+    let max_denominator = max_denominator.max(1);
+    let mut best = (v.round() as u64, 1);
+    let mut best_error = (v - best.0 as f64).abs();
+    for denominator in 2..=max_denominator {
+        let numerator = (v * denominator as f64).round();
+        let error = (v - numerator / denominator as f64).abs();
+        if error < best_error - 1e-12 {
+            best = (numerator as u64, denominator);
+            best_error = error;
+            if error < 1e-12 {
+                break;
+            }
+        }
+    }
+    best
+}
+
+/// Render a number in the `General` format, with up to 11 characters as Excel shows.
+pub fn format_general(v: f64) -> String {
+    if v == 0. {
+        return "0".into();
+    }
+    if !v.is_finite() {
+        return "#NUM!".into();
+    }
+    let abs = v.abs();
+    let sign = if v < 0. { "-" } else { "" };
+    if !(1e-9..1e11).contains(&abs) {
+        let scientific = format!("{:.5e}", abs);
+        let (mantissa, exponent) = scientific.split_once('e').expect("scientific notation");
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        let exponent: i32 = exponent.parse().expect("exponent");
+        let exponent_sign = if exponent < 0 { '-' } else { '+' };
+        return format!(
+            "{}{}E{}{:02}",
+            sign,
+            mantissa,
+            exponent_sign,
+            exponent.abs()
+        );
+    }
+    let int_len = (abs.log10().floor() as i32 + 1).max(1) as usize;
+    let decimals = 10usize.saturating_sub(int_len);
+    let (int, frac) = round_decimal(abs, decimals);
+    let frac = frac.trim_end_matches('0');
+    let int = if int.is_empty() { "0" } else { int.as_str() };
+    if frac.is_empty() {
+        format!("{}{}", sign, int)
+    } else {
+        format!("{}{}.{}", sign, int, frac)
+    }
+}
+
+/// Split format code sections at `;` outside of quotes, brackets and escapes.
+fn split_sections(code: &str) -> Vec<&str> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut bracket = false;
+    let mut escaped = false;
+    for (i, c) in code.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if !quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '[' if !quoted => bracket = true,
+            ']' if !quoted => bracket = false,
+            ';' if !quoted && !bracket => {
+                sections.push(&code[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    sections.push(&code[start..]);
+    sections
+}
+
+/// A parsed number format code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberFormatCode {
+    code: String,
+    sections: Vec<Section>,
+    date_system: DateSystem,
+}
+
+/// A formatted value with the color of its section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormattedValue {
+    pub text: String,
+    pub color: Option<FormatColor>,
+}
+
+impl NumberFormatCode {
+    pub fn parse(code: &str) -> Self {
+        let sections = split_sections(code)
+            .into_iter()
+            .take(4)
+            .map(Section::parse)
+            .collect();
+        Self {
+            code: code.to_string(),
+            sections,
+            date_system: DateSystem::default(),
+        }
+    }
+
+    /// Render dates in the date system of the workbook, 1900 by default.
+    pub fn with_date_system(mut self, date_system: DateSystem) -> Self {
+        self.date_system = date_system;
+        self
+    }
+
+    pub fn date_system(&self) -> DateSystem {
+        self.date_system
+    }
+
+    /// The format code as it is.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Check if numbers are rendered as date or time.
+    pub fn is_date_time(&self) -> bool {
+        self.sections.first().is_some_and(|s| s.is_date_time())
+    }
+
+    /// Kind of date or time values the format shows, `None` for number formats.
+    pub fn date_time_kind(&self) -> Option<DateTimeKind> {
+        let section = self.sections.first().filter(|s| s.is_date_time())?;
+        let has = |f: fn(&Token) -> bool| section.tokens.iter().any(f);
+        if has(|t| matches!(t, Token::Elapsed(..))) {
+            return Some(DateTimeKind::Duration);
+        }
+        let date = has(|t| matches!(t, Token::Year(_) | Token::Month(_) | Token::Day(_)));
+        let time = has(|t| {
+            matches!(
+                t,
+                Token::Hour(_) | Token::Minute(_) | Token::Second(_) | Token::SubSecond(_)
+            )
+        });
+        let kind = match (date, time) {
+            (true, false) => DateTimeKind::Date,
+            (false, true) => DateTimeKind::Time,
+            _ => DateTimeKind::DateTime,
+        };
+        Some(kind)
+    }
+
+    /// Number sections and the text section.
+    fn split(&self) -> (&[Section], Option<&Section>) {
+        match self.sections.len() {
+            4 => (&self.sections[..3], self.sections.get(3)),
+            n if n > 1 && self.sections[n - 1].is_text() && !self.sections[n - 1].has_digits() => {
+                (&self.sections[..n - 1], self.sections.last())
+            }
+            _ => (&self.sections[..], None),
+        }
+    }
+
+    /// Select the number section, and whether the sign is shown by the format itself.
+    fn select(&self, v: f64) -> Option<(&Section, bool)> {
+        let (numbers, _) = self.split();
+        if numbers.iter().take(2).any(|s| s.condition.is_some()) {
+            for (i, section) in numbers.iter().enumerate() {
+                match section.condition {
+                    Some(condition) if condition.matches(v) => return Some((section, i > 0)),
+                    Some(_) => {}
+                    None => return Some((section, i > 0)),
+                }
+            }
+            return None;
+        }
+        match numbers.len() {
+            0 => None,
+            1 => Some((&numbers[0], false)),
+            2 if v >= 0. => Some((&numbers[0], false)),
+            2 => Some((&numbers[1], true)),
+            _ if v > 0. => Some((&numbers[0], false)),
+            _ if v < 0. => Some((&numbers[1], true)),
+            _ => Some((&numbers[2], false)),
+        }
+    }
+
+    /// Render a number with its section color.
+    pub fn format_with_color(&self, v: f64) -> FormattedValue {
+        let (section, signed) = match self.select(v) {
+            Some(selected) => selected,
+            None => {
+                return FormattedValue {
+                    text: "#".repeat(11),
+                    color: None,
+                }
+            }
+        };
+        let text = if section.tokens.is_empty() && self.sections.len() == 1 {
+            format_general(v)
+        } else if section.is_text() && !section.has_digits() && !section.is_date_time() {
+            section.format_text(&format_general(v))
+        } else if v < 0. && section.is_date_time() {
+            "#".repeat(11)
+        } else {
+            let text = section.format_number(v.abs(), self.date_system);
+            if v < 0. && !signed {
+                format!("-{}", text)
+            } else {
+                text
+            }
+        };
+        FormattedValue {
+            text,
+            color: section.color,
+        }
+    }
+
+    /// Render a number.
+    pub fn format(&self, v: f64) -> String {
+        self.format_with_color(v).text
+    }
+
+    /// Render a text with the text section, text without a text section is kept as it is.
+    pub fn format_text(&self, text: &str) -> FormattedValue {
+        match self.split() {
+            (_, Some(section)) => FormattedValue {
+                text: section.format_text(text),
+                color: section.color,
+            },
+            _ => FormattedValue {
+                text: text.to_string(),
+                color: None,
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for NumberFormatCode {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(s))
+    }
+}
+
+#[test]
+fn number_formats() {
+    let cases: &[(&str, f64, &str)] = &[
+        ("General", 0.1 + 0.2, "0.3"),
+        ("General", 1234567.891, "1234567.891"),
+        ("General", 123456789012., "1.23457E+11"),
+        ("General", -0.000000000123, "-1.23E-10"),
+        ("0", 2.5, "3"),
+        ("0.00", 2.675, "2.68"),
+        ("0.00", -1.005, "-1.01"),
+        ("#,##0", 1234567., "1,234,567"),
+        ("#,##0.00", 0.5, "0.50"),
+        ("#,###", 0., ""),
+        ("0,", 12345., "12"),
+        ("0.0,,\"M\"", 12345678., "12.3M"),
+        ("0%", 0.256, "26%"),
+        ("0.00%", 0.0125, "1.25%"),
+        ("0.00E+00", 12345., "1.23E+04"),
+        ("0.00E+00", 0.00012, "1.20E-04"),
+        ("##0.0E+0", 12345., "12.3E+3"),
+        ("# ?/?", 1.5, "1 1/2"),
+        ("# ??/??", std::f64::consts::PI, "3 14/99"),
+        ("# ??/??", 2.25, "2  1/4 "),
+        ("# ?/?", 5., "5    "),
+        ("?/?", 0.75, "3/4"),
+        ("# ?/8", 0.3, " 2/8"),
+        ("000-00-0000", 123456789., "123-45-6789"),
+        ("(###) ###-####", 5551234567., "(555) 123-4567"),
+        ("$#,##0_);($#,##0)", 1234., "$1,234 "),
+        ("$#,##0_);($#,##0)", -1234., "($1,234)"),
+        ("0.00;-0.00;\"zero\"", 0., "zero"),
+        ("[>100]\"big\";[<0]\"neg\";0", 150., "big"),
+        ("[>100]\"big\";[<0]\"neg\";0", -5., "neg"),
+        ("[>100]\"big\";[<0]\"neg\";0", 50., "50"),
+        ("\\$0.00", 3., "$3.00"),
+        ("0.00 \"kg\"", 3., "3.00 kg"),
+        ("*-0", 3., "3"),
+        ("0.##", 3., "3."),
+        ("0.0?", 3.5, "3.5 "),
+        ("[$€-407]#,##0.00", 1234.5, "€1,234.50"),
+        ("yyyy-mm-dd", 44259.5, "2021-03-04"),
+        ("yyyy/m/d h:mm", 44259.5, "2021/3/4 12:00"),
+        ("d-mmm-yy", 44259., "4-Mar-21"),
+        ("dddd, mmmm d", 44259., "Thursday, March 4"),
+        ("h:mm AM/PM", 0.75, "6:00 PM"),
+        ("h:mm:ss a/p", 0.25, "6:00:00 a"),
+        ("mm:ss.0", 0.000_01, "00:00.9"),
+        ("[h]:mm:ss", 1.5, "36:00:00"),
+        ("[mm]:ss", 0.5, "720:00"),
+        ("yyyy\"年\"m\"月\"d\"日\"", 44259., "2021年3月4日"),
+    ];
+    for (code, v, expected) in cases {
+        assert_eq!(
+            NumberFormatCode::parse(code).format(*v),
+            *expected,
+            "format {} with {}",
+            code,
+            v
+        );
+    }
+    let code = NumberFormatCode::parse("0.00;[Red]-0.00");
+    assert_eq!(
+        code.format_with_color(-1.),
+        FormattedValue {
+            text: "-1.00".into(),
+            color: Some(FormatColor::Red)
+        }
+    );
+    let code = NumberFormatCode::parse("0;-0;0;\"Name: \"@");
+    assert_eq!(code.format_text("Bob").text, "Name: Bob");
+    assert_eq!(
+        NumberFormatCode::parse("0.00").format_text("Bob").text,
+        "Bob"
+    );
+    let code = NumberFormatCode::parse("yyyy-mm-dd");
+    assert_eq!(code.format(60.), "1900-02-29");
+    assert_eq!(code.format(0.), "1900-01-00");
+    let code = code.with_date_system(DateSystem::Date1904);
+    assert_eq!(code.format(0.), "1904-01-01");
+    assert_eq!(code.format(42797.), "2021-03-04");
+    assert_eq!(
+        NumberFormatCode::parse("[h]:mm:ss").date_time_kind(),
+        Some(DateTimeKind::Duration)
+    );
+    assert_eq!(
+        NumberFormatCode::parse("h:mm AM/PM").date_time_kind(),
+        Some(DateTimeKind::Time)
+    );
+    assert_eq!(NumberFormatCode::parse("0.00").date_time_kind(), None);
+    assert!(NumberFormatCode::parse("[h]:mm").is_date_time());
+    assert!(!NumberFormatCode::parse("[Red]0.00").is_date_time());
+    assert!(!NumberFormatCode::parse("$#,##0_);[Red]($#,##0)").is_date_time());
+    // multi-byte literals after keyword initials
+    assert_eq!(NumberFormatCode::parse("Gé年年0").format(3.), "é年年3");
+    assert_eq!(NumberFormatCode::parse("0a月").format(3.), "3a月");
+    assert_eq!(
+        NumberFormatCode::parse("h:mm A/年").format(0.75),
+        "18:00 A/年"
+    );
+}
@@ -0,0 +1,693 @@
+//! Cell references in A1 and R1C1 styles.
+//!
+//! Rows and columns are 0-based here, `A1` is row 0 column 0, while they are 1-based in the
+//! reference text.
+//!
+//! ```rust
+//! use ooxml::document::{CellRange, CellRef, SheetRef};
+//!
+//! let cell: CellRef = "$C7".parse().unwrap();
+//! assert_eq!((cell.row, cell.col), (6, 2));
+//! assert!(cell.col_absolute && !cell.row_absolute);
+//!
+//! let range: CellRange = "A1:B2".parse().unwrap();
+//! assert!(range.contains(&"B1".parse().unwrap()));
+//!
+//! let reference: SheetRef = "'My Sheet'!A1:B2,D4".parse().unwrap();
+//! assert_eq!(reference.sheet.as_deref(), Some("My Sheet"));
+//! assert_eq!(reference.areas.len(), 2);
+//! ```
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::OoxmlError;
+
+/// Row count of a worksheet.
+pub const MAX_ROWS: u32 = 1_048_576;
+/// Column count of a worksheet, the last column is `XFD`.
+pub const MAX_COLUMNS: u32 = 16_384;
+
+fn invalid(reference: &str) -> OoxmlError {
+    OoxmlError::InvalidReference(reference.to_string())
+}
+
+/// Column index of column letters, eg. 0 for `A`, 26 for `AA`, case insensitive.
+pub fn column_index(name: &str) -> Option<u32> {
+    if name.is_empty() || name.len() > 3 {
+        return None;
+    }
+    let mut index = 0u32;
+    for c in name.chars() {
+        if !c.is_ascii_alphabetic() {
+            return None;
+        }
+        index = index * 26 + (c.to_ascii_uppercase() as u32 - 'A' as u32 + 1);
+    }
+    Some(index - 1).filter(|index| *index < MAX_COLUMNS)
+}
+
+/// Column letters of a column index, eg. `A` for 0, `AA` for 26.
+pub fn column_name(index: u32) -> String {
+    let mut name = Vec::new();
+    let mut n = index + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        name.push(b'A' + rem as u8);
+        n = (n - 1) / 26;
+    }
+    name.reverse();
+    String::from_utf8(name).expect("ascii column name")
+}
+
+/// Split an A1 reference into optional column and row parts, with their absolute markers.
+#[allow(clippy::type_complexity)]
+pub(crate) fn split_a1(s: &str) -> Option<(Option<(bool, u32)>, Option<(bool, u32)>)> {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    let mut part = |alphabetic: bool| {
+        let start = i;
+        let absolute = bytes.get(i) == Some(&b'$');
+        if absolute {
+            i += 1;
+        }
+        let digits = i;
+        while i < bytes.len()
+            && (alphabetic && bytes[i].is_ascii_alphabetic()
+                || !alphabetic && bytes[i].is_ascii_digit())
+        {
+            i += 1;
+        }
+        if digits == i {
+            i = start;
+            return None;
+        }
+        Some((absolute, &s[digits..i]))
+    };
+    let col = part(true);
+    let row = part(false);
+    if i != bytes.len() || (col.is_none() && row.is_none()) {
+        return None;
+    }
+    let col = match col {
+        Some((absolute, name)) => Some((absolute, column_index(name)?)),
+        None => None,
+    };
+    let row = match row {
+        Some((absolute, number)) => {
+            let number: u32 = number.parse().ok()?;
+            if number == 0 || number > MAX_ROWS {
+                return None;
+            }
+            Some((absolute, number - 1))
+        }
+        None => None,
+    };
+    Some((col, row))
+}
+
+/// Split a R1C1 reference into optional row and column parts, with their absolute markers.
+/// Relative parts like `R[-1]` or `C` are resolved from `origin`.
+#[allow(clippy::type_complexity)]
+fn split_r1c1(s: &str, origin: &CellRef) -> Option<(Option<(u32, bool)>, Option<(u32, bool)>)> {
+    fn part(s: &str, marker: u8, base: u32, max: u32) -> Option<(&str, Option<(u32, bool)>)> {
+        let s = match s.as_bytes().first() {
+            Some(c) if c.to_ascii_uppercase() == marker => &s[1..],
+            _ => return Some((s, None)),
+        };
+        if let Some(rest) = s.strip_prefix('[') {
+            let (offset, rest) = rest.split_once(']')?;
+            let offset: i64 = offset.parse().ok()?;
+            let index = base as i64 + offset;
+            if index < 0 || index >= max as i64 {
+                return None;
+            }
+            return Some((rest, Some((index as u32, false))));
+        }
+        let digits = s.bytes().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return Some((s, Some((base, false))));
+        }
+        let number: u32 = s[..digits].parse().ok()?;
+        if number == 0 || number > max {
+            return None;
+        }
+        Some((&s[digits..], Some((number - 1, true))))
+    }
+    let (rest, row) = part(s, b'R', origin.row, MAX_ROWS)?;
+    let (rest, col) = part(rest, b'C', origin.col, MAX_COLUMNS)?;
+    if !rest.is_empty() || (row.is_none() && col.is_none()) {
+        return None;
+    }
+    Some((row, col))
+}
+
+/// A cell reference like `B3` or `$B$3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct CellRef {
+    /// 0-based row index.
+    pub row: u32,
+    /// 0-based column index.
+    pub col: u32,
+    pub row_absolute: bool,
+    pub col_absolute: bool,
+}
+
+impl CellRef {
+    /// Relative reference by 0-based row and column.
+    pub fn new(row: u32, col: u32) -> Self {
+        Self {
+            row,
+            col,
+            ..Default::default()
+        }
+    }
+
+    /// The same cell with both absolute markers, like `$B$3`.
+    pub fn absolute(self) -> Self {
+        Self {
+            row_absolute: true,
+            col_absolute: true,
+            ..self
+        }
+    }
+
+    /// The same cell without absolute markers.
+    pub fn relative(self) -> Self {
+        Self::new(self.row, self.col)
+    }
+
+    /// Parse a R1C1 style reference, relative parts like `R[-1]C[2]` are resolved from `origin`.
+    ///
+    /// Parts with a number are absolute, eg. `R2C3` is `$C$2`.
+    pub fn parse_r1c1(s: &str, origin: &CellRef) -> Result<Self, OoxmlError> {
+        match split_r1c1(s, origin) {
+            Some((Some((row, row_absolute)), Some((col, col_absolute)))) => Ok(Self {
+                row,
+                col,
+                row_absolute,
+                col_absolute,
+            }),
+            _ => Err(invalid(s)),
+        }
+    }
+
+    /// Format in R1C1 style, relative parts are offsets from `origin`.
+    pub fn to_r1c1(&self, origin: &CellRef) -> String {
+        fn part(marker: char, index: u32, absolute: bool, base: u32) -> String {
+            match index as i64 - base as i64 {
+                _ if absolute => format!("{}{}", marker, index + 1),
+                0 => marker.to_string(),
+                offset => format!("{}[{}]", marker, offset),
+            }
+        }
+        format!(
+            "{}{}",
+            part('R', self.row, self.row_absolute, origin.row),
+            part('C', self.col, self.col_absolute, origin.col)
+        )
+    }
+}
+
+impl fmt::Display for CellRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = |absolute: bool| if absolute { "$" } else { "" };
+        write!(
+            f,
+            "{}{}{}{}",
+            marker(self.col_absolute),
+            column_name(self.col),
+            marker(self.row_absolute),
+            self.row + 1
+        )
+    }
+}
+
+impl FromStr for CellRef {
+    type Err = OoxmlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match split_a1(s) {
+            Some((Some((col_absolute, col)), Some((row_absolute, row)))) => Ok(Self {
+                row,
+                col,
+                row_absolute,
+                col_absolute,
+            }),
+            _ => Err(invalid(s)),
+        }
+    }
+}
+
+/// Shape of a range: a cell block, whole rows like `1:3` or whole columns like `A:C`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RangeKind {
+    Cells,
+    Rows,
+    Columns,
+}
+
+/// A rectangle range like `A1:C3`, corners are ordered as top-left and bottom-right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CellRange {
+    pub start: CellRef,
+    pub end: CellRef,
+    pub kind: RangeKind,
+}
+
+impl CellRange {
+    /// Cell block between two corners in any order.
+    pub fn new(a: CellRef, b: CellRef) -> Self {
+        let (top, bottom) = if a.row <= b.row { (a, b) } else { (b, a) };
+        let (left, right) = if a.col <= b.col { (a, b) } else { (b, a) };
+        Self {
+            start: CellRef {
+                row: top.row,
+                col: left.col,
+                row_absolute: top.row_absolute,
+                col_absolute: left.col_absolute,
+            },
+            end: CellRef {
+                row: bottom.row,
+                col: right.col,
+                row_absolute: bottom.row_absolute,
+                col_absolute: right.col_absolute,
+            },
+            kind: RangeKind::Cells,
+        }
+    }
+
+    /// Range of a single cell.
+    pub fn cell(cell: CellRef) -> Self {
+        Self::new(cell, cell)
+    }
+
+    /// Whole rows, 0-based and inclusive.
+    pub fn rows(first: u32, last: u32) -> Self {
+        Self {
+            kind: RangeKind::Rows,
+            ..Self::new(CellRef::new(first, 0), CellRef::new(last, MAX_COLUMNS - 1))
+        }
+    }
+
+    /// Whole columns, 0-based and inclusive.
+    pub fn columns(first: u32, last: u32) -> Self {
+        Self {
+            kind: RangeKind::Columns,
+            ..Self::new(CellRef::new(0, first), CellRef::new(MAX_ROWS - 1, last))
+        }
+    }
+
+    /// Row count.
+    pub fn height(&self) -> u32 {
+        self.end.row - self.start.row + 1
+    }
+
+    /// Column count.
+    pub fn width(&self) -> u32 {
+        self.end.col - self.start.col + 1
+    }
+
+    pub fn is_single_cell(&self) -> bool {
+        self.kind == RangeKind::Cells
+            && self.start.row == self.end.row
+            && self.start.col == self.end.col
+    }
+
+    pub fn contains(&self, cell: &CellRef) -> bool {
+        (self.start.row..=self.end.row).contains(&cell.row)
+            && (self.start.col..=self.end.col).contains(&cell.col)
+    }
+
+    /// Check if two ranges share any cell.
+    pub fn intersects(&self, other: &CellRange) -> bool {
+        self.start.row <= other.end.row
+            && other.start.row <= self.end.row
+            && self.start.col <= other.end.col
+            && other.start.col <= self.end.col
+    }
+
+    /// Iterate cells row by row.
+    pub fn cells(&self) -> impl Iterator<Item = CellRef> {
+        let (start, end) = (self.start, self.end);
+        (start.row..=end.row)
+            .flat_map(move |row| (start.col..=end.col).map(move |col| CellRef::new(row, col)))
+    }
+}
+
+impl CellRange {
+    /// Parse a R1C1 style range like `R1C1:R2C2`, whole rows like `R1:R3` or whole columns
+    /// like `C2`, relative parts are resolved from `origin`.
+    pub fn parse_r1c1(s: &str, origin: &CellRef) -> Result<Self, OoxmlError> {
+        let (a, b) = s.split_once(':').unwrap_or((s, s));
+        let range = match (split_r1c1(a, origin), split_r1c1(b, origin)) {
+            (Some((Some(r1), Some(c1))), Some((Some(r2), Some(c2)))) => Self::new(
+                CellRef {
+                    row: r1.0,
+                    col: c1.0,
+                    row_absolute: r1.1,
+                    col_absolute: c1.1,
+                },
+                CellRef {
+                    row: r2.0,
+                    col: c2.0,
+                    row_absolute: r2.1,
+                    col_absolute: c2.1,
+                },
+            ),
+            (Some((Some(r1), None)), Some((Some(r2), None))) => {
+                let mut range = Self::rows(r1.0.min(r2.0), r1.0.max(r2.0));
+                range.start.row_absolute = if r1.0 <= r2.0 { r1.1 } else { r2.1 };
+                range.end.row_absolute = if r1.0 <= r2.0 { r2.1 } else { r1.1 };
+                range
+            }
+            (Some((None, Some(c1))), Some((None, Some(c2)))) => {
+                let mut range = Self::columns(c1.0.min(c2.0), c1.0.max(c2.0));
+                range.start.col_absolute = if c1.0 <= c2.0 { c1.1 } else { c2.1 };
+                range.end.col_absolute = if c1.0 <= c2.0 { c2.1 } else { c1.1 };
+                range
+            }
+            _ => return Err(invalid(s)),
+        };
+        Ok(range)
+    }
+
+    /// Format in R1C1 style, relative parts are offsets from `origin`. Single rows and
+    /// columns are written without `:`, like `R2` or `C[1]`.
+    pub fn to_r1c1(&self, origin: &CellRef) -> String {
+        let row = |cell: &CellRef| {
+            let text = cell.to_r1c1(origin);
+            text[..text.rfind('C').expect("column part")].to_string()
+        };
+        let col = |cell: &CellRef| {
+            let text = cell.to_r1c1(origin);
+            text[text.rfind('C').expect("column part")..].to_string()
+        };
+        let (start, end) = match self.kind {
+            RangeKind::Cells => (self.start.to_r1c1(origin), self.end.to_r1c1(origin)),
+            RangeKind::Rows => (row(&self.start), row(&self.end)),
+            RangeKind::Columns => (col(&self.start), col(&self.end)),
+        };
+        if start == end {
+            start
+        } else {
+            format!("{}:{}", start, end)
+        }
+    }
+}
+
+impl fmt::Display for CellRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = |absolute: bool| if absolute { "$" } else { "" };
+        match self.kind {
+            RangeKind::Cells if self.is_single_cell() => write!(f, "{}", self.start),
+            RangeKind::Cells => write!(f, "{}:{}", self.start, self.end),
+            RangeKind::Rows => write!(
+                f,
+                "{}{}:{}{}",
+                marker(self.start.row_absolute),
+                self.start.row + 1,
+                marker(self.end.row_absolute),
+                self.end.row + 1
+            ),
+            RangeKind::Columns => write!(
+                f,
+                "{}{}:{}{}",
+                marker(self.start.col_absolute),
+                column_name(self.start.col),
+                marker(self.end.col_absolute),
+                column_name(self.end.col)
+            ),
+        }
+    }
+}
+
+impl FromStr for CellRange {
+    type Err = OoxmlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (a, b) = match s.split_once(':') {
+            Some((a, b)) => (a, b),
+            None => return Ok(Self::cell(s.parse()?)),
+        };
+        match (split_a1(a), split_a1(b)) {
+            (Some((Some(c1), Some(r1))), Some((Some(c2), Some(r2)))) => Ok(Self::new(
+                CellRef {
+                    row: r1.1,
+                    col: c1.1,
+                    row_absolute: r1.0,
+                    col_absolute: c1.0,
+                },
+                CellRef {
+                    row: r2.1,
+                    col: c2.1,
+                    row_absolute: r2.0,
+                    col_absolute: c2.0,
+                },
+            )),
+            (Some((None, Some(r1))), Some((None, Some(r2)))) => {
+                let mut range = Self::rows(r1.1.min(r2.1), r1.1.max(r2.1));
+                range.start.row_absolute = if r1.1 <= r2.1 { r1.0 } else { r2.0 };
+                range.end.row_absolute = if r1.1 <= r2.1 { r2.0 } else { r1.0 };
+                Ok(range)
+            }
+            (Some((Some(c1), None)), Some((Some(c2), None))) => {
+                let mut range = Self::columns(c1.1.min(c2.1), c1.1.max(c2.1));
+                range.start.col_absolute = if c1.1 <= c2.1 { c1.0 } else { c2.0 };
+                range.end.col_absolute = if c1.1 <= c2.1 { c2.0 } else { c1.0 };
+                Ok(range)
+            }
+            _ => Err(invalid(s)),
+        }
+    }
+}
+
+/// Quote a sheet name for references if needed, eg. `'My Sheet'`.
+pub fn quote_sheet_name(name: &str) -> String {
+    let plain = name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+        && !name.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && split_a1(name).is_none()
+        && CellRange::parse_r1c1(name, &CellRef::default()).is_err();
+    if plain && !name.is_empty() {
+        name.to_string()
+    } else {
+        format!("'{}'", name.replace('\'', "''"))
+    }
+}
+
+/// Split at separators outside of quoted sheet names.
+fn split_unquoted(s: &str, separators: &[char]) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            '\'' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if !quoted && separators.contains(&c) => parts.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+/// Split a reference into the unquoted sheet name, if any, and the rest.
+fn split_sheet(s: &str) -> Result<(Option<String>, &str), OoxmlError> {
+    let position = split_unquoted(s, &['!']);
+    if position.len() == 1 {
+        return Ok((None, s));
+    }
+    if position.len() != 2 {
+        return Err(invalid(s));
+    }
+    let (sheet, rest) = s.split_at(position[0].len());
+    let sheet = match sheet.strip_prefix('\'') {
+        Some(quoted) => quoted
+            .strip_suffix('\'')
+            .ok_or_else(|| invalid(s))?
+            .replace("''", "'"),
+        None if sheet.is_empty() => return Err(invalid(s)),
+        None => sheet.to_string(),
+    };
+    Ok((Some(sheet), &rest[1..]))
+}
+
+/// A sheet-qualified reference of one or more areas, like `'My Sheet'!A1:B2,D4`.
+///
+/// Areas are separated by commas as in formulas or by spaces as in `sqref` attributes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct SheetRef {
+    pub sheet: Option<String>,
+    pub areas: Vec<CellRange>,
+}
+
+impl SheetRef {
+    pub fn new(sheet: Option<String>, area: CellRange) -> Self {
+        Self {
+            sheet,
+            areas: vec![area],
+        }
+    }
+
+    pub fn contains(&self, cell: &CellRef) -> bool {
+        self.areas.iter().any(|area| area.contains(cell))
+    }
+
+    /// Areas separated by spaces without the sheet name, as in `sqref` attributes.
+    pub fn to_sqref(&self) -> String {
+        self.areas
+            .iter()
+            .map(|area| area.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl fmt::Display for SheetRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, area) in self.areas.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            if let Some(sheet) = &self.sheet {
+                write!(f, "{}!", quote_sheet_name(sheet))?;
+            }
+            write!(f, "{}", area)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SheetRef {
+    type Err = OoxmlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let trimmed = trimmed
+            .strip_prefix('(')
+            .and_then(|inner| inner.strip_suffix(')'))
+            .unwrap_or(trimmed);
+        let mut reference = SheetRef::default();
+        for (i, area) in split_unquoted(trimmed, &[',', ' '])
+            .iter()
+            .filter(|area| !area.is_empty())
+            .enumerate()
+        {
+            let (sheet, area) = split_sheet(area)?;
+            if i == 0 {
+                reference.sheet = sheet;
+            } else if sheet.is_some() && sheet != reference.sheet {
+                return Err(invalid(s));
+            }
+            reference.areas.push(area.parse()?);
+        }
+        if reference.areas.is_empty() {
+            return Err(invalid(s));
+        }
+        Ok(reference)
+    }
+}
+
+#[test]
+fn column_names() {
+    for (name, index) in [
+        ("A", 0),
+        ("Z", 25),
+        ("AA", 26),
+        ("AZ", 51),
+        ("BA", 52),
+        ("ZZ", 701),
+    ] {
+        assert_eq!(column_index(name), Some(index));
+        assert_eq!(column_name(index), name);
+    }
+    assert_eq!(column_index("XFD"), Some(MAX_COLUMNS - 1));
+    assert_eq!(column_index("xfd"), Some(MAX_COLUMNS - 1));
+    assert_eq!(column_index("XFE"), None);
+    assert_eq!(column_index("A1"), None);
+}
+
+#[test]
+fn cell_references() {
+    let cell: CellRef = "$AB$12".parse().unwrap();
+    assert_eq!((cell.row, cell.col), (11, 27));
+    assert!(cell.row_absolute && cell.col_absolute);
+    assert_eq!(cell.to_string(), "$AB$12");
+    assert_eq!("a1".parse::<CellRef>().unwrap(), CellRef::new(0, 0));
+    assert!("A0".parse::<CellRef>().is_err());
+    assert!("A".parse::<CellRef>().is_err());
+    assert!("1A".parse::<CellRef>().is_err());
+
+    let origin: CellRef = "C5".parse().unwrap();
+    let cell = CellRef::parse_r1c1("R[-1]C[2]", &origin).unwrap();
+    assert_eq!(cell.to_string(), "E4");
+    assert_eq!(cell.to_r1c1(&origin), "R[-1]C[2]");
+    let cell = CellRef::parse_r1c1("R2C3", &origin).unwrap();
+    assert_eq!(cell.to_string(), "$C$2");
+    assert_eq!(CellRef::parse_r1c1("RC", &origin).unwrap(), origin);
+    assert!(CellRef::parse_r1c1("R[-5]C", &origin).is_err());
+    assert!(CellRef::parse_r1c1("R2", &origin).is_err());
+    assert!(CellRef::parse_r1c1("C", &origin).is_err());
+}
+
+#[test]
+fn range_references() {
+    let range: CellRange = "B3:A1".parse().unwrap();
+    assert_eq!(range.to_string(), "A1:B3");
+    assert_eq!((range.height(), range.width()), (3, 2));
+    assert_eq!(range.cells().count(), 6);
+    let range: CellRange = "A1".parse().unwrap();
+    assert!(range.is_single_cell());
+    assert_eq!(range.to_string(), "A1");
+    let range: CellRange = "$2:5".parse().unwrap();
+    assert_eq!(range.kind, RangeKind::Rows);
+    assert_eq!(range.width(), MAX_COLUMNS);
+    assert_eq!(range.to_string(), "$2:5");
+    let range: CellRange = "B:$D".parse().unwrap();
+    assert_eq!(range.kind, RangeKind::Columns);
+    assert!(range.contains(&"C1000".parse().unwrap()));
+    assert_eq!(range.to_string(), "B:$D");
+    assert!("A1:B".parse::<CellRange>().is_err());
+
+    let origin: CellRef = "C5".parse().unwrap();
+    let range = CellRange::parse_r1c1("R1C1:R[1]C[-1]", &origin).unwrap();
+    assert_eq!(range.to_string(), "$A$1:B6");
+    assert_eq!(range.to_r1c1(&origin), "R1C1:R[1]C[-1]");
+    let range = CellRange::parse_r1c1("R1:R3", &origin).unwrap();
+    assert_eq!(range.kind, RangeKind::Rows);
+    assert_eq!(range.to_string(), "$1:$3");
+    assert_eq!(range.to_r1c1(&origin), "R1:R3");
+    let range = CellRange::parse_r1c1("C2", &origin).unwrap();
+    assert_eq!(range.kind, RangeKind::Columns);
+    assert_eq!(range.to_string(), "$B:$B");
+    assert_eq!(range.to_r1c1(&origin), "C2");
+    let range = CellRange::parse_r1c1("r:r[1]", &origin).unwrap();
+    assert_eq!(range.to_string(), "5:6");
+    assert_eq!(range.to_r1c1(&origin), "R:R[1]");
+    let range = CellRange::parse_r1c1("RC", &origin).unwrap();
+    assert!(range.is_single_cell());
+    assert!(CellRange::parse_r1c1("R1:C2", &origin).is_err());
+    assert!(CellRange::parse_r1c1("R1C1:R2", &origin).is_err());
+    assert!(CellRange::parse_r1c1("", &origin).is_err());
+
+    let reference: SheetRef = "'Bob''s Sheet'!$A$1:$B$2".parse().unwrap();
+    assert_eq!(reference.sheet.as_deref(), Some("Bob's Sheet"));
+    assert_eq!(reference.to_string(), "'Bob''s Sheet'!$A$1:$B$2");
+    let reference: SheetRef = "Sheet1!A1:B2,Sheet1!D4".parse().unwrap();
+    assert_eq!(reference.to_string(), "Sheet1!A1:B2,Sheet1!D4");
+    let reference: SheetRef = "A1:B2 D4".parse().unwrap();
+    assert_eq!(reference.sheet, None);
+    assert_eq!(reference.to_sqref(), "A1:B2 D4");
+    assert!("Sheet1!A1,Sheet2!B1".parse::<SheetRef>().is_err());
+    assert_eq!(quote_sheet_name("Data"), "Data");
+    assert_eq!(quote_sheet_name("A1"), "'A1'");
+    assert_eq!(quote_sheet_name("2020"), "'2020'");
+    for name in ["R", "c", "R1C1", "R1", "C2", "RC", "R[1]C"] {
+        assert_eq!(quote_sheet_name(name), format!("'{}'", name));
+    }
+    assert_eq!(quote_sheet_name("Rate"), "Rate");
+}
@@ -20,18 +20,135 @@ use crate::packaging::namespace::Namespaces;
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all(deserialize = "camelCase"), rename = "t")]
 pub struct Value(String);
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[serde(rename_all(deserialize = "camelCase"), rename = "si")]
+/// Color of a rich text run, see `CT_Color`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RunColor {
+    pub auto: Option<bool>,
+    pub indexed: Option<u32>,
+    /// ARGB hex like `FFFF0000`.
+    pub rgb: Option<String>,
+    pub theme: Option<u32>,
+    pub tint: Option<f64>,
+}
+
+/// Run properties `rPr` of a rich text run.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RunProperties {
+    /// Font name of `rFont`.
+    pub font: Option<String>,
+    pub charset: Option<u32>,
+    pub family: Option<u32>,
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    pub strike: Option<bool>,
+    pub color: Option<RunColor>,
+    /// Font size in points.
+    pub size: Option<f64>,
+    /// Underline style like `single` or `double`.
+    pub underline: Option<String>,
+    /// `superscript`, `subscript` or `baseline`.
+    pub vert_align: Option<String>,
+    /// `major` or `minor` theme font.
+    pub scheme: Option<String>,
+}
+
+/// A rich text run `r`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextRun {
+    pub properties: Option<RunProperties>,
+    pub text: String,
+}
+
+impl TextRun {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            properties: None,
+            text: text.into(),
+        }
+    }
+    pub fn with_properties(mut self, properties: RunProperties) -> Self {
+        self.properties = Some(properties);
+        self
+    }
+}
+
+/// Phonetic run `rPh`, the reading of base text characters from `start` to `end`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PhoneticRun {
+    /// Start index of the base text, `sb`.
+    pub start: usize,
+    /// End index of the base text, `eb`.
+    pub end: usize,
+    pub text: String,
+}
+
+/// Phonetic properties `phoneticPr`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PhoneticProperties {
+    pub font_id: usize,
+    /// `halfwidthKatakana`, `fullwidthKatakana`, `Hiragana` or `noConversion`.
+    pub kind: Option<String>,
+    /// `noControl`, `left`, `center` or `distributed`.
+    pub alignment: Option<String>,
+}
+
+/// A shared string item `si`, plain text or rich text runs, with optional phonetic data.
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct SharedString {
-    t: Option<String>,
+    /// Plain text, or the concatenation of runs.
+    text: String,
+    runs: Vec<TextRun>,
+    phonetic_runs: Vec<PhoneticRun>,
+    phonetic_properties: Option<PhoneticProperties>,
 }
+
 impl SharedString {
-    pub fn as_str(&self) -> &str {
-        match &self.t {
-            Some(s) => s.as_str(),
-            None => "",
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
         }
     }
+    /// A rich text string.
+    pub fn from_runs(runs: Vec<TextRun>) -> Self {
+        Self {
+            text: runs.iter().map(|run| run.text.as_str()).collect(),
+            runs,
+            ..Default::default()
+        }
+    }
+    pub fn with_phonetic(
+        mut self,
+        runs: Vec<PhoneticRun>,
+        properties: Option<PhoneticProperties>,
+    ) -> Self {
+        self.phonetic_runs = runs;
+        self.phonetic_properties = properties;
+        self
+    }
+    /// Plain text, rich text runs are concatenated.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+    pub fn is_rich_text(&self) -> bool {
+        !self.runs.is_empty()
+    }
+    /// Rich text runs, empty for plain text.
+    pub fn runs(&self) -> &[TextRun] {
+        &self.runs
+    }
+    pub fn phonetic_runs(&self) -> &[PhoneticRun] {
+        &self.phonetic_runs
+    }
+    pub fn phonetic_properties(&self) -> Option<&PhoneticProperties> {
+        self.phonetic_properties.as_ref()
+    }
+}
+
+impl fmt::Display for SharedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
 }
 
 // #[test]
@@ -53,24 +170,27 @@ impl SharedString {
 //     println!("{:?}", s);
 //     assert_eq!(xml, s);
 // }
-/// Custom properties
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
-#[serde(rename_all(deserialize = "camelCase"), rename = "sst")]
+/// Shared strings table part.
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct SharedStringsPart {
     count: usize,
     unique_count: usize,
-    #[serde(flatten)]
     namespaces: Namespaces,
-    #[serde(rename = "si")]
-    strings: Option<Vec<SharedString>>,
+    strings: Vec<SharedString>,
 }
 
 impl SharedStringsPart {
     pub fn get_shared_string(&self, idx: usize) -> Option<&str> {
-        self.strings
-            .as_ref()
-            .and_then(|ss| ss.get(idx))
-            .map(|ss| ss.as_str())
+        self.strings.get(idx).map(|ss| ss.as_str())
+    }
+
+    /// Shared string item with its rich text runs.
+    pub fn get(&self, idx: usize) -> Option<&SharedString> {
+        self.strings.get(idx)
+    }
+
+    pub fn strings(&self) -> &[SharedString] {
+        &self.strings
     }
 }
 
@@ -84,7 +204,184 @@ impl OpenXmlElementInfo for SharedStringsPart {
     }
 }
 
-impl OpenXmlDeserializeDefault for SharedStringsPart {}
+/// Parse an optional `val` like boolean of `<b/>` or `<b val="0"/>`.
+fn parse_bool(value: Option<String>) -> bool {
+    !matches!(value.as_deref(), Some("0") | Some("false"))
+}
+
+/// Read the value of an attribute.
+fn attribute<R: BufRead>(
+    reader: &quick_xml::Reader<R>,
+    e: &quick_xml::events::BytesStart,
+    name: &[u8],
+) -> Result<Option<String>, OoxmlError> {
+    for attr in e.attributes() {
+        let attr = attr?;
+        if attr.key == name {
+            return Ok(Some(attr.unescape_and_decode_value(reader)?));
+        }
+    }
+    Ok(None)
+}
+
+/// Read the content of a rich text element `CT_Rst` like `<si>` or comment `<text>`, the reader
+/// is right after the start of the element and stops after its end.
+pub(crate) fn read_rich_text<R: BufRead>(
+    reader: &mut quick_xml::Reader<R>,
+) -> Result<SharedString, OoxmlError> {
+    use quick_xml::events::Event;
+
+    fn parse<T: std::str::FromStr>(value: Option<String>) -> Option<T> {
+        value.and_then(|v| v.trim().parse().ok())
+    }
+
+    let mut si = SharedString::default();
+    let mut buf = Vec::new();
+    // local names of open elements, the rich text element itself is the empty root.
+    let mut path: Vec<Vec<u8>> = vec![Vec::new()];
+    loop {
+        let event = reader.read_event(&mut buf)?;
+        let (e, empty) = match &event {
+            Event::Start(e) => (Some(e), false),
+            Event::Empty(e) => (Some(e), true),
+            _ => (None, false),
+        };
+        if let Some(e) = e {
+            let parent = path.last().map(|p| p.as_slice()).unwrap_or_default();
+            match (parent, e.local_name()) {
+                (b"", b"r") => si.runs.push(TextRun::default()),
+                (b"r", b"rPr") => {
+                    if let Some(run) = si.runs.last_mut() {
+                        run.properties = Some(RunProperties::default());
+                    }
+                }
+                (b"rPr", name) => {
+                    let properties = si.runs.last_mut().and_then(|run| run.properties.as_mut());
+                    if let Some(properties) = properties {
+                        let val = attribute(reader, e, b"val")?;
+                        match name {
+                            b"rFont" => properties.font = val,
+                            b"charset" => properties.charset = parse(val),
+                            b"family" => properties.family = parse(val),
+                            b"b" => properties.bold = Some(parse_bool(val)),
+                            b"i" => properties.italic = Some(parse_bool(val)),
+                            b"strike" => properties.strike = Some(parse_bool(val)),
+                            b"sz" => properties.size = parse(val),
+                            b"u" => {
+                                properties.underline = Some(val.unwrap_or_else(|| "single".into()))
+                            }
+                            b"vertAlign" => properties.vert_align = val,
+                            b"scheme" => properties.scheme = val,
+                            b"color" => {
+                                properties.color = Some(RunColor {
+                                    auto: attribute(reader, e, b"auto")?
+                                        .map(|v| parse_bool(Some(v))),
+                                    indexed: parse(attribute(reader, e, b"indexed")?),
+                                    rgb: attribute(reader, e, b"rgb")?,
+                                    theme: parse(attribute(reader, e, b"theme")?),
+                                    tint: parse(attribute(reader, e, b"tint")?),
+                                })
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                (b"", b"rPh") => si.phonetic_runs.push(PhoneticRun {
+                    start: parse(attribute(reader, e, b"sb")?).unwrap_or_default(),
+                    end: parse(attribute(reader, e, b"eb")?).unwrap_or_default(),
+                    text: String::new(),
+                }),
+                (b"", b"phoneticPr") => {
+                    si.phonetic_properties = Some(PhoneticProperties {
+                        font_id: parse(attribute(reader, e, b"fontId")?).unwrap_or_default(),
+                        kind: attribute(reader, e, b"type")?,
+                        alignment: attribute(reader, e, b"alignment")?,
+                    });
+                }
+                _ => {}
+            }
+            if !empty {
+                path.push(e.local_name().to_vec());
+            }
+        }
+        match event {
+            Event::Text(ref e) | Event::CData(ref e)
+                if path.last().map(|p| p.as_slice()) == Some(b"t") =>
+            {
+                let text = match event {
+                    Event::CData(_) => String::from_utf8_lossy(e).into_owned(),
+                    _ => e.unescape_and_decode(reader)?,
+                };
+                let parent = path.len().checked_sub(2).map(|i| path[i].as_slice());
+                match parent {
+                    Some(b"") => si.text.push_str(&text),
+                    Some(b"r") => {
+                        if let Some(run) = si.runs.last_mut() {
+                            run.text.push_str(&text);
+                        }
+                        si.text.push_str(&text);
+                    }
+                    Some(b"rPh") => {
+                        if let Some(run) = si.phonetic_runs.last_mut() {
+                            run.text.push_str(&text);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(_) => {
+                path.pop();
+                if path.is_empty() {
+                    break;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(si)
+}
+
+impl OpenXmlDeserialize for SharedStringsPart {
+    fn from_xml_reader<R: BufRead>(reader: R) -> Result<Self, OoxmlError> {
+        use quick_xml::events::Event;
+
+        let mut reader = quick_xml::Reader::from_reader(reader);
+        reader.trim_text(false);
+
+        let mut part = SharedStringsPart::default();
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Start(ref e) | Event::Empty(ref e) if e.local_name() == b"sst" => {
+                    for attr in e.attributes() {
+                        let attr = attr?;
+                        let value = attr.unescape_and_decode_value(&reader)?;
+                        match attr.key {
+                            b"count" => part.count = value.parse().unwrap_or_default(),
+                            b"uniqueCount" => part.unique_count = value.parse().unwrap_or_default(),
+                            key if key.starts_with(b"xmlns") => part
+                                .namespaces
+                                .add_namespace(String::from_utf8_lossy(key), value),
+                            _ => {}
+                        }
+                    }
+                }
+                Event::Start(ref e) if e.local_name() == b"si" => {
+                    part.strings.push(read_rich_text(&mut reader)?);
+                }
+                Event::Empty(ref e) if e.local_name() == b"si" => {
+                    part.strings.push(SharedString::default());
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+        Ok(part)
+    }
+}
 
 impl fmt::Display for SharedStringsPart {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -97,15 +394,130 @@ impl fmt::Display for SharedStringsPart {
     }
 }
 
+/// Write a `<t>` element, preserving leading and trailing spaces.
+fn write_text<W: std::io::Write>(
+    xml: &mut quick_xml::Writer<W>,
+    text: &str,
+) -> Result<(), OoxmlError> {
+    use quick_xml::events::*;
+
+    let mut elem = BytesStart::borrowed_name(b"t");
+    if text.starts_with(char::is_whitespace) || text.ends_with(char::is_whitespace) {
+        elem.push_attribute(("xml:space", "preserve"));
+    }
+    xml.write_event(Event::Start(elem))?;
+    xml.write_event(Event::Text(BytesText::from_plain_str(text)))?;
+    xml.write_event(Event::End(BytesEnd::borrowed(b"t")))?;
+    Ok(())
+}
+
+/// Write an empty element with a `val` attribute.
+fn write_val<W: std::io::Write>(
+    xml: &mut quick_xml::Writer<W>,
+    name: &str,
+    value: Option<String>,
+) -> Result<(), OoxmlError> {
+    use quick_xml::events::*;
+
+    if let Some(value) = value {
+        let mut elem = BytesStart::borrowed_name(name.as_bytes());
+        elem.push_attribute(("val", value.as_str()));
+        xml.write_event(Event::Empty(elem))?;
+    }
+    Ok(())
+}
+
+fn write_run_properties<W: std::io::Write>(
+    xml: &mut quick_xml::Writer<W>,
+    properties: &RunProperties,
+) -> Result<(), OoxmlError> {
+    use quick_xml::events::*;
+
+    let flag = |v: Option<bool>| v.map(|v| if v { "1" } else { "0" }.to_string());
+    xml.write_event(Event::Start(BytesStart::borrowed_name(b"rPr")))?;
+    // elements in the schema order.
+    write_val(xml, "rFont", properties.font.clone())?;
+    write_val(xml, "charset", properties.charset.map(|v| v.to_string()))?;
+    write_val(xml, "family", properties.family.map(|v| v.to_string()))?;
+    write_val(xml, "b", flag(properties.bold))?;
+    write_val(xml, "i", flag(properties.italic))?;
+    write_val(xml, "strike", flag(properties.strike))?;
+    if let Some(color) = &properties.color {
+        let mut elem = BytesStart::borrowed_name(b"color");
+        if let Some(auto) = flag(color.auto) {
+            elem.push_attribute(("auto", auto.as_str()));
+        }
+        if let Some(indexed) = color.indexed {
+            elem.push_attribute(("indexed", indexed.to_string().as_str()));
+        }
+        if let Some(rgb) = &color.rgb {
+            elem.push_attribute(("rgb", rgb.as_str()));
+        }
+        if let Some(theme) = color.theme {
+            elem.push_attribute(("theme", theme.to_string().as_str()));
+        }
+        if let Some(tint) = color.tint {
+            elem.push_attribute(("tint", tint.to_string().as_str()));
+        }
+        xml.write_event(Event::Empty(elem))?;
+    }
+    write_val(xml, "sz", properties.size.map(|v| v.to_string()))?;
+    write_val(xml, "u", properties.underline.clone())?;
+    write_val(xml, "vertAlign", properties.vert_align.clone())?;
+    write_val(xml, "scheme", properties.scheme.clone())?;
+    xml.write_event(Event::End(BytesEnd::borrowed(b"rPr")))?;
+    Ok(())
+}
+
+/// Write the content of a rich text element `CT_Rst`, see [`read_rich_text`].
+pub(crate) fn write_rich_text<W: std::io::Write>(
+    xml: &mut quick_xml::Writer<W>,
+    si: &SharedString,
+) -> Result<(), OoxmlError> {
+    use quick_xml::events::*;
+
+    if si.runs.is_empty() {
+        write_text(xml, &si.text)?;
+    }
+    for run in &si.runs {
+        xml.write_event(Event::Start(BytesStart::borrowed_name(b"r")))?;
+        if let Some(properties) = &run.properties {
+            write_run_properties(xml, properties)?;
+        }
+        write_text(xml, &run.text)?;
+        xml.write_event(Event::End(BytesEnd::borrowed(b"r")))?;
+    }
+    for run in &si.phonetic_runs {
+        let mut elem = BytesStart::borrowed_name(b"rPh");
+        elem.push_attribute(("sb", run.start.to_string().as_str()));
+        elem.push_attribute(("eb", run.end.to_string().as_str()));
+        xml.write_event(Event::Start(elem))?;
+        write_text(xml, &run.text)?;
+        xml.write_event(Event::End(BytesEnd::borrowed(b"rPh")))?;
+    }
+    if let Some(properties) = &si.phonetic_properties {
+        let mut elem = BytesStart::borrowed_name(b"phoneticPr");
+        elem.push_attribute(("fontId", properties.font_id.to_string().as_str()));
+        if let Some(kind) = &properties.kind {
+            elem.push_attribute(("type", kind.as_str()));
+        }
+        if let Some(alignment) = &properties.alignment {
+            elem.push_attribute(("alignment", alignment.as_str()));
+        }
+        xml.write_event(Event::Empty(elem))?;
+    }
+    Ok(())
+}
+
 impl SharedStringsPart {
     /// Parse content types data from an xml reader.
     pub fn parse_from_xml_reader<R: BufRead>(reader: R) -> Self {
-        quick_xml::de::from_reader(reader).unwrap()
+        Self::from_xml_reader(reader).unwrap()
     }
 
     /// Parse content types data from an xml str.
     pub fn parse_from_xml_str(reader: &str) -> Self {
-        quick_xml::de::from_str(reader).unwrap()
+        Self::from_xml_str(reader).unwrap()
     }
 
     /// Save to file path.
@@ -126,12 +538,10 @@ impl SharedStringsPart {
             Some(b"UTF-8"),
             Some(b"yes"),
         )))?;
-        //quick_xml::se::to_writer(xml.inner(), self).unwrap();
 
         // 2. start types element
         let mut elem = BytesStart::borrowed_name(SHARED_STRINGS_TAG.as_bytes());
 
-        //elem.extend_attributes(vec![nsattr!(SHARED_STRINGS), nsattr!(VT)]);
         elem.extend_attributes(self.namespaces.to_xml_attributes());
         elem.extend_attributes(vec![
             Attribute {
@@ -144,10 +554,10 @@ impl SharedStringsPart {
             },
         ]);
         xml.write_event(Event::Start(elem))?;
-        for si in self.strings.as_ref().unwrap_or(&vec![]) {
+        for si in &self.strings {
             let elem = BytesStart::borrowed_name(SHARED_STRING_TAG.as_bytes());
             xml.write_event(Event::Start(elem))?;
-            quick_xml::se::to_writer(xml.inner(), &si.t)?;
+            write_rich_text(&mut xml, si)?;
             let end = BytesEnd::borrowed(SHARED_STRING_TAG.as_bytes());
             xml.write_event(Event::End(end))?;
         }
@@ -158,16 +568,47 @@ impl SharedStringsPart {
         Ok(())
     }
 }
+
 #[test]
-#[ignore]
 fn test_de() {
-    const file: &str = "examples/sharedString-missing-t/xl/sharedStrings.xml";
-    //const raw: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><sst xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" count="14" uniqueCount="5"><si><t>name</t></si><si><t>age</t></si><si><t>张三</t></si><si><t>李四</t></si><si><t>王五</t></si></sst>"#;
-    let value = SharedStringsPart::from_xml_file(file).unwrap();
-    // println!("{}", raw);
-    // let value: SharedStringsPart = quick_xml::de::from_str(raw).unwrap();
-    println!("{:?}", value);
+    const RAW: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<sst xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" count="3" uniqueCount="3">
+<si><t xml:space="preserve"> name </t></si>
+<si><r><t>plain </t></r><r><rPr><b/><i val="0"/><sz val="11"/><color rgb="FFFF0000"/><rFont val="Calibri"/><u/><vertAlign val="superscript"/></rPr><t>bold</t></r></si>
+<si><t>東京</t><rPh sb="0" eb="2"><t>トウキョウ</t></rPh><phoneticPr fontId="1" type="Hiragana"/></si>
+</sst>"#;
+    let value = SharedStringsPart::from_xml_str(RAW).unwrap();
+    assert_eq!(value.get_shared_string(0), Some(" name "));
+    assert_eq!(value.get_shared_string(1), Some("plain bold"));
+    let rich = value.get(1).unwrap();
+    assert!(rich.is_rich_text());
+    assert_eq!(rich.runs()[0], TextRun::new("plain "));
+    let properties = rich.runs()[1].properties.as_ref().unwrap();
+    assert_eq!(properties.bold, Some(true));
+    assert_eq!(properties.italic, Some(false));
+    assert_eq!(properties.size, Some(11.));
+    assert_eq!(properties.font.as_deref(), Some("Calibri"));
+    assert_eq!(properties.underline.as_deref(), Some("single"));
+    assert_eq!(properties.vert_align.as_deref(), Some("superscript"));
+    assert_eq!(
+        properties.color.as_ref().and_then(|c| c.rgb.as_deref()),
+        Some("FFFF0000")
+    );
+    let phonetic = value.get(2).unwrap();
+    assert_eq!(phonetic.as_str(), "東京");
+    assert_eq!(
+        phonetic.phonetic_runs(),
+        &[PhoneticRun {
+            start: 0,
+            end: 2,
+            text: "トウキョウ".into()
+        }]
+    );
+    assert_eq!(
+        phonetic.phonetic_properties().map(|p| p.kind.as_deref()),
+        Some(Some("Hiragana"))
+    );
+
     let display = format!("{}", value);
-    println!("{}", display);
-    // assert_eq!(raw, display);
+    assert_eq!(SharedStringsPart::from_xml_str(&display).unwrap(), value);
 }
@@ -2,7 +2,7 @@ use crate::packaging::element::*;
 use crate::packaging::namespace::Namespaces;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename = "numFmt")]
 pub struct NumberFormat {
     #[serde(rename = "numFmtId")]
@@ -235,6 +235,311 @@ pub struct SlicerStyle {
     default: String,
 }
 
+/// A color of differential formats, by rgb, theme or indexed color.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "color")]
+pub struct Color {
+    pub auto: Option<bool>,
+    /// ARGB hex like `FF9C0006`.
+    pub rgb: Option<String>,
+    pub theme: Option<u32>,
+    pub indexed: Option<u32>,
+    pub tint: Option<f64>,
+}
+
+impl Color {
+    /// Color of ARGB hex like `FF9C0006`, or RGB hex with opaque alpha.
+    pub fn rgb(rgb: &str) -> Self {
+        let rgb = rgb.trim_start_matches('#').to_uppercase();
+        Self {
+            rgb: Some(if rgb.len() == 6 {
+                format!("FF{}", rgb)
+            } else {
+                rgb
+            }),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn to_xml(&self, tag: &str) -> String {
+        let mut xml = format!("<{}", tag);
+        if let Some(auto) = self.auto {
+            xml.push_str(&format!(r#" auto="{}""#, auto as u8));
+        }
+        if let Some(rgb) = &self.rgb {
+            xml.push_str(&format!(r#" rgb="{}""#, rgb));
+        }
+        if let Some(theme) = self.theme {
+            xml.push_str(&format!(r#" theme="{}""#, theme));
+        }
+        if let Some(indexed) = self.indexed {
+            xml.push_str(&format!(r#" indexed="{}""#, indexed));
+        }
+        if let Some(tint) = self.tint {
+            xml.push_str(&format!(r#" tint="{}""#, tint));
+        }
+        xml.push_str("/>");
+        xml
+    }
+}
+
+/// A boolean font property like `<b/>`, true if `val` is not set.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct BooleanProperty {
+    pub val: Option<bool>,
+}
+
+impl BooleanProperty {
+    pub fn value(&self) -> bool {
+        self.val.unwrap_or(true)
+    }
+}
+
+/// Underline `<u>`, `single` if `val` is not set.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "u")]
+pub struct Underline {
+    pub val: Option<String>,
+}
+
+/// Font of differential formats, only set properties are applied.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "font")]
+pub struct DifferentialFont {
+    #[serde(rename = "b")]
+    pub bold: Option<BooleanProperty>,
+    #[serde(rename = "i")]
+    pub italic: Option<BooleanProperty>,
+    pub strike: Option<BooleanProperty>,
+    #[serde(rename = "u")]
+    pub underline: Option<Underline>,
+    pub color: Option<Color>,
+}
+
+/// Pattern fill of differential formats, solid fills of conditional formats use `bgColor`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "patternFill", rename_all = "camelCase")]
+pub struct DifferentialPatternFill {
+    pub pattern_type: Option<String>,
+    pub fg_color: Option<Color>,
+    pub bg_color: Option<Color>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "fill", rename_all = "camelCase")]
+pub struct DifferentialFill {
+    pub pattern_fill: Option<DifferentialPatternFill>,
+}
+
+/// A border edge like `<left>`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct BorderEdge {
+    pub style: Option<String>,
+    pub color: Option<Color>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "border")]
+pub struct DifferentialBorder {
+    pub left: Option<BorderEdge>,
+    pub right: Option<BorderEdge>,
+    pub top: Option<BorderEdge>,
+    pub bottom: Option<BorderEdge>,
+}
+
+/// A differential format `<dxf>`, the formatting of conditional formats and table styles.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "dxf", rename_all = "camelCase")]
+pub struct DifferentialFormat {
+    pub font: Option<DifferentialFont>,
+    pub num_fmt: Option<NumberFormat>,
+    pub fill: Option<DifferentialFill>,
+    pub border: Option<DifferentialBorder>,
+}
+
+impl DifferentialFormat {
+    /// Font color with a solid fill, like the presets of Excel conditional formats.
+    pub fn colored(font: &str, fill: &str) -> Self {
+        Self {
+            font: Some(DifferentialFont {
+                color: Some(Color::rgb(font)),
+                ..Default::default()
+            }),
+            fill: Some(DifferentialFill {
+                pattern_fill: Some(DifferentialPatternFill {
+                    bg_color: Some(Color::rgb(fill)),
+                    ..Default::default()
+                }),
+            }),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_xml(&self) -> String {
+        let escape =
+            |s: &str| String::from_utf8_lossy(&quick_xml::escape::escape(s.as_bytes())).to_string();
+        let mut xml = String::from("<dxf>");
+        if let Some(font) = &self.font {
+            xml.push_str("<font>");
+            for (tag, property) in [
+                ("b", &font.bold),
+                ("i", &font.italic),
+                ("strike", &font.strike),
+            ] {
+                match property.as_ref().map(|p| p.val) {
+                    Some(Some(val)) => xml.push_str(&format!(r#"<{} val="{}"/>"#, tag, val as u8)),
+                    Some(None) => xml.push_str(&format!("<{}/>", tag)),
+                    None => {}
+                }
+            }
+            match font.underline.as_ref().map(|u| u.val.as_deref()) {
+                Some(Some(val)) => xml.push_str(&format!(r#"<u val="{}"/>"#, val)),
+                Some(None) => xml.push_str("<u/>"),
+                None => {}
+            }
+            if let Some(color) = &font.color {
+                xml.push_str(&color.to_xml("color"));
+            }
+            xml.push_str("</font>");
+        }
+        if let Some(format) = &self.num_fmt {
+            xml.push_str(&format!(
+                r#"<numFmt numFmtId="{}" formatCode="{}"/>"#,
+                format.id,
+                escape(&format.code)
+            ));
+        }
+        if let Some(fill) = &self.fill {
+            xml.push_str("<fill>");
+            if let Some(pattern) = &fill.pattern_fill {
+                xml.push_str("<patternFill");
+                if let Some(pattern_type) = &pattern.pattern_type {
+                    xml.push_str(&format!(r#" patternType="{}""#, pattern_type));
+                }
+                xml.push('>');
+                if let Some(color) = &pattern.fg_color {
+                    xml.push_str(&color.to_xml("fgColor"));
+                }
+                if let Some(color) = &pattern.bg_color {
+                    xml.push_str(&color.to_xml("bgColor"));
+                }
+                xml.push_str("</patternFill>");
+            }
+            xml.push_str("</fill>");
+        }
+        if let Some(border) = &self.border {
+            xml.push_str("<border>");
+            for (tag, edge) in [
+                ("left", &border.left),
+                ("right", &border.right),
+                ("top", &border.top),
+                ("bottom", &border.bottom),
+            ] {
+                let edge = match edge {
+                    Some(edge) => edge,
+                    None => continue,
+                };
+                xml.push_str(&format!("<{}", tag));
+                if let Some(style) = &edge.style {
+                    xml.push_str(&format!(r#" style="{}""#, style));
+                }
+                match &edge.color {
+                    Some(color) => xml.push_str(&format!(">{}</{}>", color.to_xml("color"), tag)),
+                    None => xml.push_str("/>"),
+                }
+            }
+            xml.push_str("</border>");
+        }
+        xml.push_str("</dxf>");
+        xml
+    }
+}
+
+/// Differential formats `<dxfs>` of the style sheet.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "dxfs")]
+pub struct DifferentialFormats {
+    pub count: Option<usize>,
+    #[serde(rename = "dxf", default)]
+    pub dxfs: Vec<DifferentialFormat>,
+}
+
+/// Style sheet with the default font, fills, border and cell format, for workbooks without one.
+pub(crate) const DEFAULT_STYLE_SHEET: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="1"><font><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/></cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles></styleSheet>"#;
+
+/// Child elements of the style sheet in schema order.
+const STYLE_SHEET_ELEMENTS: [&str; 11] = [
+    "numFmts",
+    "fonts",
+    "fills",
+    "borders",
+    "cellStyleXfs",
+    "cellXfs",
+    "cellStyles",
+    "dxfs",
+    "tableStyles",
+    "colors",
+    "extLst",
+];
+
+/// Append a differential format to the `<dxfs>` of style sheet xml, `count` is the number of
+/// formats in it.
+pub(crate) fn append_differential_format(
+    data: &[u8],
+    count: usize,
+    dxf: &DifferentialFormat,
+) -> Result<Vec<u8>, crate::error::OoxmlError> {
+    use quick_xml::events::{BytesEnd, BytesStart, Event};
+    use std::io::Write;
+
+    let mut reader = quick_xml::Reader::from_reader(data);
+    reader.trim_text(false);
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    let mut buf = Vec::new();
+    let mut found = false;
+    let counted = |e: &BytesStart| -> Result<BytesStart<'static>, crate::error::OoxmlError> {
+        let mut elem = BytesStart::owned_name(e.name().to_vec());
+        for attr in e.attributes() {
+            let attr = attr?;
+            if attr.key != b"count" {
+                elem.push_attribute(attr);
+            }
+        }
+        elem.push_attribute(("count", (count + 1).to_string().as_str()));
+        Ok(elem)
+    };
+    loop {
+        let event = reader.read_event(&mut buf)?.into_owned();
+        buf.clear();
+        match &event {
+            Event::Eof => break,
+            Event::Start(e) if e.local_name() == b"dxfs" => {
+                found = true;
+                writer.write_event(Event::Start(counted(e)?))?;
+                continue;
+            }
+            Event::Empty(e) if e.local_name() == b"dxfs" => {
+                found = true;
+                writer.write_event(Event::Start(counted(e)?))?;
+                writer.inner().write_all(dxf.to_xml().as_bytes())?;
+                writer.write_event(Event::End(BytesEnd::owned(e.name().to_vec())))?;
+                continue;
+            }
+            Event::End(e) if e.local_name() == b"dxfs" => {
+                writer.inner().write_all(dxf.to_xml().as_bytes())?;
+            }
+            _ => {}
+        }
+        writer.write_event(event)?;
+    }
+    if found {
+        return Ok(writer.into_inner());
+    }
+    let element = format!(r#"<dxfs count="1">{}</dxfs>"#, dxf.to_xml());
+    super::worksheet::replace_child_element(data, &STYLE_SHEET_ELEMENTS, "dxfs", Some(&element))
+}
+
 /// App properties
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename = "styleSheet", rename_all = "camelCase")]
@@ -246,6 +551,7 @@ pub struct StylesPart {
     cell_xfs: Option<CellXfs>,
     // borders: Borders,
     cell_styles: Option<CellStylesPart>,
+    dxfs: Option<DifferentialFormats>,
     // ext_lst: ExtLst,
     #[serde(flatten)]
     namespaces: Namespaces,
@@ -435,14 +741,12 @@ impl StylesPart {
             NumberFormat::new(49, "@"),
         ];
 
-        if id < 50 {
-            return BUILTIN_NUMBER_FORMATS.get(id);
-        }
+        // custom formats may override the builtin ones.
         self.num_fmts
             .as_ref()
-            //.and_then(|inner| inner.num_fmt.get(id))
             .and_then(|inner| inner.num_fmt.as_ref())
             .and_then(|inner| inner.iter().find(|nf| nf.id == id))
+            .or_else(|| BUILTIN_NUMBER_FORMATS.iter().find(|nf| nf.id == id))
     }
 
     // pub fn get_number_format_xf(&self, id: usize) ->
@@ -452,6 +756,20 @@ impl StylesPart {
     pub fn get_fill(&self, id: usize) -> Option<&Fill> {
         self.fills.as_ref().and_then(|fills| fills.fills.get(id))
     }
+    /// Differential formats referred by `dxfId`.
+    pub fn differential_formats(&self) -> &[DifferentialFormat] {
+        self.dxfs.as_ref().map_or(&[], |dxfs| &dxfs.dxfs)
+    }
+    pub fn get_differential_format(&self, id: usize) -> Option<&DifferentialFormat> {
+        self.differential_formats().get(id)
+    }
+    /// Add a differential format and return its id, the xml is not changed.
+    pub(crate) fn push_differential_format(&mut self, dxf: DifferentialFormat) -> usize {
+        let dxfs = self.dxfs.get_or_insert_with(Default::default);
+        dxfs.dxfs.push(dxf);
+        dxfs.count = Some(dxfs.dxfs.len());
+        dxfs.dxfs.len() - 1
+    }
 }
 
 impl OpenXmlElementInfo for StylesPart {
@@ -483,3 +801,40 @@ fn test_de() {
     // println!("{}", display);
     // assert_eq!(raw, display);
 }
+
+#[test]
+fn differential_formats() {
+    let xml = r#"<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles><dxfs count="1"><dxf><font><b/><i val="0"/><u val="double"/><color rgb="FF9C0006"/></font><numFmt numFmtId="164" formatCode="0.0%"/><fill><patternFill><bgColor rgb="FFFFC7CE"/></patternFill></fill><border><left style="thin"><color indexed="64"/></left></border></dxf></dxfs><tableStyles count="0"/></styleSheet>"#;
+    let styles = StylesPart::from_xml_str(xml).unwrap();
+    let dxf = styles.get_differential_format(0).unwrap();
+    let font = dxf.font.as_ref().unwrap();
+    assert!(font.bold.as_ref().unwrap().value());
+    assert!(!font.italic.as_ref().unwrap().value());
+    assert_eq!(
+        font.underline.as_ref().unwrap().val.as_deref(),
+        Some("double")
+    );
+    assert_eq!(dxf.num_fmt.as_ref().unwrap().code, "0.0%");
+    let fill = dxf.fill.as_ref().unwrap().pattern_fill.as_ref().unwrap();
+    assert_eq!(fill.bg_color, Some(Color::rgb("ffc7ce")));
+    let left = dxf.border.as_ref().unwrap().left.as_ref().unwrap();
+    assert_eq!(left.color.as_ref().unwrap().indexed, Some(64));
+    let reread: DifferentialFormat = quick_xml::de::from_str(&dxf.to_xml()).unwrap();
+    assert_eq!(&reread, dxf);
+
+    let green = DifferentialFormat::colored("006100", "C6EFCE");
+    let data = append_differential_format(xml.as_bytes(), 1, &green).unwrap();
+    let styles = StylesPart::from_xml_reader(&data[..]).unwrap();
+    assert_eq!(styles.differential_formats().len(), 2);
+    assert_eq!(styles.get_differential_format(1), Some(&green));
+    assert!(String::from_utf8_lossy(&data).contains(r#"<dxfs count="2">"#));
+
+    let xml = r#"<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles><tableStyles count="0"/></styleSheet>"#;
+    let data = append_differential_format(xml.as_bytes(), 0, &green).unwrap();
+    assert!(String::from_utf8_lossy(&data).contains(r#"</cellStyles><dxfs count="1"><dxf>"#));
+    let data =
+        append_differential_format(br#"<styleSheet><dxfs count="0"/></styleSheet>"#, 0, &green)
+            .unwrap();
+    let styles = StylesPart::from_xml_reader(&data[..]).unwrap();
+    assert_eq!(styles.differential_formats(), [green]);
+}
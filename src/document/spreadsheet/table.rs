@@ -0,0 +1,523 @@
+//! Excel tables, the list objects of a worksheet, see ECMA-376 Part 1, 18.5.
+//!
+//! Each table is a part like `xl/tables/table1.xml` related from its worksheet, which lists
+//! them in `<tableParts>`.
+use serde::{Deserialize, Serialize};
+
+use crate::error::OoxmlError;
+use crate::packaging::element::*;
+
+use super::cell::CellValue;
+use super::reference::{CellRange, CellRef};
+
+/// Table style of new tables.
+pub const DEFAULT_TABLE_STYLE: &str = "TableStyleMedium2";
+
+const SPREADSHEET_NAMESPACE: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+
+/// Filter values of a column `<filterColumn>`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "filterColumn")]
+pub struct FilterColumn {
+    /// 0-based column index in the filter range.
+    pub col_id: u32,
+    pub hidden_button: Option<bool>,
+    pub filters: Option<Filters>,
+}
+
+/// Values shown by a column filter.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "filters")]
+pub struct Filters {
+    pub blank: Option<bool>,
+    #[serde(rename = "filter", default)]
+    pub filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "filter")]
+pub struct Filter {
+    pub val: String,
+}
+
+/// Auto filter `<autoFilter>` of a table.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "autoFilter")]
+pub struct AutoFilter {
+    pub r#ref: String,
+    #[serde(rename = "filterColumn", default)]
+    pub filter_columns: Vec<FilterColumn>,
+}
+
+impl AutoFilter {
+    pub fn range(&self) -> Option<CellRange> {
+        self.r#ref.parse().ok()
+    }
+}
+
+/// A table column `<tableColumn>`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "tableColumn")]
+pub struct TableColumn {
+    pub id: u32,
+    pub name: String,
+    /// Function of the totals row like `sum` or `average`.
+    pub totals_row_function: Option<String>,
+    pub totals_row_label: Option<String>,
+    /// Formula of a calculated column.
+    pub calculated_column_formula: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "tableColumns")]
+pub struct TableColumns {
+    pub count: Option<u32>,
+    #[serde(rename = "tableColumn", default)]
+    pub table_columns: Vec<TableColumn>,
+}
+
+/// Table style `<tableStyleInfo>`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "tableStyleInfo")]
+pub struct TableStyleInfo {
+    pub name: Option<String>,
+    pub show_first_column: Option<bool>,
+    pub show_last_column: Option<bool>,
+    pub show_row_stripes: Option<bool>,
+    pub show_column_stripes: Option<bool>,
+}
+
+/// A table part `<table>`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "table")]
+pub struct Table {
+    /// Table id unique in the workbook.
+    pub id: u32,
+    pub name: Option<String>,
+    /// Name of the table used in formulas, unique in the workbook.
+    pub display_name: String,
+    pub r#ref: String,
+    /// Count of header rows, 1 if not set.
+    pub header_row_count: Option<u32>,
+    /// Count of totals rows, 0 if not set.
+    pub totals_row_count: Option<u32>,
+    pub totals_row_shown: Option<bool>,
+    pub auto_filter: Option<AutoFilter>,
+    #[serde(default)]
+    pub table_columns: TableColumns,
+    pub table_style_info: Option<TableStyleInfo>,
+}
+
+impl Table {
+    /// A new table of a range with a header row of the column names, styled with the
+    /// default table style and filterable.
+    pub fn new(id: u32, name: &str, range: CellRange, columns: Vec<String>) -> Self {
+        let range = relative(range);
+        Table {
+            id,
+            name: Some(name.to_string()),
+            display_name: name.to_string(),
+            r#ref: range.to_string(),
+            totals_row_shown: Some(false),
+            auto_filter: Some(AutoFilter {
+                r#ref: range.to_string(),
+                ..Default::default()
+            }),
+            table_columns: TableColumns {
+                count: Some(columns.len() as u32),
+                table_columns: columns
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, name)| TableColumn {
+                        id: i as u32 + 1,
+                        name,
+                        ..Default::default()
+                    })
+                    .collect(),
+            },
+            table_style_info: Some(TableStyleInfo {
+                name: Some(DEFAULT_TABLE_STYLE.into()),
+                show_first_column: Some(false),
+                show_last_column: Some(false),
+                show_row_stripes: Some(true),
+                show_column_stripes: Some(false),
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// The range of the table, header and totals rows included.
+    pub fn range(&self) -> Option<CellRange> {
+        self.r#ref.parse().ok()
+    }
+
+    pub fn header_row_count(&self) -> u32 {
+        self.header_row_count.unwrap_or(1)
+    }
+
+    pub fn totals_row_count(&self) -> u32 {
+        self.totals_row_count.unwrap_or_default()
+    }
+
+    /// The range of data rows, `None` if the table has no data rows.
+    pub fn data_range(&self) -> Option<CellRange> {
+        let range = self.range()?;
+        let start = range.start.row + self.header_row_count();
+        let end = range.end.row.checked_sub(self.totals_row_count())?;
+        if start > end {
+            return None;
+        }
+        Some(CellRange::new(
+            CellRef::new(start, range.start.col),
+            CellRef::new(end, range.end.col),
+        ))
+    }
+
+    pub fn columns(&self) -> &[TableColumn] {
+        &self.table_columns.table_columns
+    }
+
+    pub fn column_names(&self) -> Vec<&str> {
+        self.columns().iter().map(|c| c.name.as_str()).collect()
+    }
+
+    /// Check if the table has the name, table names are case insensitive.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.display_name.to_lowercase() == name.to_lowercase()
+    }
+
+    /// Xml of the table part.
+    pub fn to_xml(&self) -> String {
+        let escape =
+            |s: &str| String::from_utf8_lossy(&quick_xml::escape::escape(s.as_bytes())).to_string();
+        let flag = |v: bool| if v { "1" } else { "0" };
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#);
+        xml.push_str(&format!(
+            r#"<table xmlns="{}" id="{}""#,
+            SPREADSHEET_NAMESPACE, self.id
+        ));
+        if let Some(name) = &self.name {
+            xml.push_str(&format!(r#" name="{}""#, escape(name)));
+        }
+        xml.push_str(&format!(
+            r#" displayName="{}" ref="{}""#,
+            escape(&self.display_name),
+            self.r#ref
+        ));
+        if let Some(count) = self.header_row_count {
+            xml.push_str(&format!(r#" headerRowCount="{}""#, count));
+        }
+        if let Some(count) = self.totals_row_count {
+            xml.push_str(&format!(r#" totalsRowCount="{}""#, count));
+        }
+        if let Some(shown) = self.totals_row_shown {
+            xml.push_str(&format!(r#" totalsRowShown="{}""#, flag(shown)));
+        }
+        xml.push('>');
+        if let Some(filter) = &self.auto_filter {
+            xml.push_str(&auto_filter_xml(filter));
+        }
+        xml.push_str(&format!(
+            r#"<tableColumns count="{}">"#,
+            self.columns().len()
+        ));
+        for column in self.columns() {
+            xml.push_str(&table_column_xml(column));
+        }
+        xml.push_str("</tableColumns>");
+        if let Some(style) = &self.table_style_info {
+            xml.push_str("<tableStyleInfo");
+            if let Some(name) = &style.name {
+                xml.push_str(&format!(r#" name="{}""#, escape(name)));
+            }
+            for (key, value) in [
+                ("showFirstColumn", style.show_first_column),
+                ("showLastColumn", style.show_last_column),
+                ("showRowStripes", style.show_row_stripes),
+                ("showColumnStripes", style.show_column_stripes),
+            ] {
+                if let Some(value) = value {
+                    xml.push_str(&format!(r#" {}="{}""#, key, flag(value)));
+                }
+            }
+            xml.push_str("/>");
+        }
+        xml.push_str("</table>");
+        xml
+    }
+}
+
+impl OpenXmlElementInfo for Table {
+    fn tag_name() -> &'static str {
+        "table"
+    }
+
+    fn element_type() -> OpenXmlElementType {
+        OpenXmlElementType::Root
+    }
+}
+
+impl OpenXmlDeserializeDefault for Table {}
+
+fn auto_filter_xml(filter: &AutoFilter) -> String {
+    let escape =
+        |s: &str| String::from_utf8_lossy(&quick_xml::escape::escape(s.as_bytes())).to_string();
+    if filter.filter_columns.is_empty() {
+        return format!(r#"<autoFilter ref="{}"/>"#, filter.r#ref);
+    }
+    let mut xml = format!(r#"<autoFilter ref="{}">"#, filter.r#ref);
+    for column in &filter.filter_columns {
+        xml.push_str(&format!(r#"<filterColumn colId="{}""#, column.col_id));
+        if let Some(hidden) = column.hidden_button {
+            xml.push_str(&format!(r#" hiddenButton="{}""#, hidden as u8));
+        }
+        xml.push('>');
+        if let Some(filters) = &column.filters {
+            xml.push_str("<filters");
+            if let Some(blank) = filters.blank {
+                xml.push_str(&format!(r#" blank="{}""#, blank as u8));
+            }
+            xml.push('>');
+            for filter in &filters.filters {
+                xml.push_str(&format!(r#"<filter val="{}"/>"#, escape(&filter.val)));
+            }
+            xml.push_str("</filters>");
+        }
+        xml.push_str("</filterColumn>");
+    }
+    xml.push_str("</autoFilter>");
+    xml
+}
+
+fn table_column_xml(column: &TableColumn) -> String {
+    let escape =
+        |s: &str| String::from_utf8_lossy(&quick_xml::escape::escape(s.as_bytes())).to_string();
+    let mut xml = format!(
+        r#"<tableColumn id="{}" name="{}""#,
+        column.id,
+        escape(&column.name)
+    );
+    if let Some(function) = &column.totals_row_function {
+        xml.push_str(&format!(r#" totalsRowFunction="{}""#, escape(function)));
+    }
+    if let Some(label) = &column.totals_row_label {
+        xml.push_str(&format!(r#" totalsRowLabel="{}""#, escape(label)));
+    }
+    match &column.calculated_column_formula {
+        Some(formula) => xml.push_str(&format!(
+            "><calculatedColumnFormula>{}</calculatedColumnFormula></tableColumn>",
+            escape(formula)
+        )),
+        None => xml.push_str("/>"),
+    }
+    xml
+}
+
+fn relative(range: CellRange) -> CellRange {
+    CellRange::new(range.start.relative(), range.end.relative())
+}
+
+/// Column names of header cells appended to `existing` names of a table.
+///
+/// Names of table columns must equal their header cells, so headers must be unique non-empty
+/// texts, case insensitive.
+pub(crate) fn column_names(
+    existing: &[&str],
+    headers: Vec<(CellRef, CellValue)>,
+) -> Result<Vec<String>, OoxmlError> {
+    let mut names: Vec<String> = Vec::with_capacity(headers.len());
+    for (cell, header) in headers {
+        let name = match header {
+            CellValue::String(name) if !name.trim().is_empty() => name,
+            CellValue::String(_) | CellValue::Null => {
+                return Err(OoxmlError::InvalidReference(format!(
+                    "header cell {} is empty",
+                    cell
+                )))
+            }
+            _ => {
+                return Err(OoxmlError::InvalidReference(format!(
+                    "header cell {} is not text",
+                    cell
+                )))
+            }
+        };
+        let duplicated = existing
+            .iter()
+            .copied()
+            .chain(names.iter().map(String::as_str))
+            .any(|other| other.eq_ignore_ascii_case(&name));
+        if duplicated {
+            return Err(OoxmlError::InvalidReference(format!(
+                "header {} of cell {} is duplicated",
+                name, cell
+            )));
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Resize a table part xml to a range of the same top-left cell. Columns beyond the new
+/// width are removed, `columns` are appended for new ones. Other content is kept as it is.
+pub(crate) fn resize_table_xml(
+    data: &[u8],
+    range: CellRange,
+    columns: &[TableColumn],
+) -> Result<Vec<u8>, OoxmlError> {
+    use quick_xml::events::{BytesStart, Event};
+
+    let reference = relative(range).to_string();
+    let width = range.width() as usize;
+    let mut reader = quick_xml::Reader::from_reader(data);
+    reader.trim_text(false);
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    let mut buf = Vec::new();
+    let mut skip_buf = Vec::new();
+    let mut depth = 0;
+    let mut in_columns = false;
+    let mut column_index = 0;
+    let with_attribute =
+        |e: &BytesStart, key: &[u8], value: &str| -> Result<BytesStart<'static>, OoxmlError> {
+            let mut elem = BytesStart::owned_name(e.name().to_vec());
+            for attr in e.attributes() {
+                let attr = attr?;
+                if attr.key == key {
+                    elem.push_attribute((key, value.as_bytes()));
+                } else {
+                    elem.push_attribute(attr);
+                }
+            }
+            Ok(elem)
+        };
+    loop {
+        let event = reader.read_event(&mut buf)?.into_owned();
+        buf.clear();
+        let (e, empty) = match &event {
+            Event::Start(e) => (Some(e), false),
+            Event::Empty(e) => (Some(e), true),
+            _ => (None, false),
+        };
+        if let Some(e) = e {
+            let elem = match (depth, e.local_name()) {
+                (0, b"table") | (1, b"autoFilter") => Some(with_attribute(e, b"ref", &reference)?),
+                (1, b"tableColumns") => {
+                    in_columns = true;
+                    Some(with_attribute(e, b"count", &width.to_string())?)
+                }
+                (2, b"tableColumn") if in_columns => {
+                    column_index += 1;
+                    if column_index > width {
+                        if !empty {
+                            reader.read_to_end(e.name(), &mut skip_buf)?;
+                            skip_buf.clear();
+                        }
+                        continue;
+                    }
+                    None
+                }
+                _ => None,
+            };
+            let elem = elem.unwrap_or_else(|| e.clone().into_owned());
+            if empty {
+                writer.write_event(Event::Empty(elem))?;
+            } else {
+                depth += 1;
+                writer.write_event(Event::Start(elem))?;
+            }
+            continue;
+        }
+        match &event {
+            Event::Eof => break,
+            Event::End(e) => {
+                depth -= 1;
+                if in_columns && e.local_name() == b"tableColumns" {
+                    in_columns = false;
+                    for column in columns {
+                        writer
+                            .inner()
+                            .extend_from_slice(table_column_xml(column).as_bytes());
+                    }
+                }
+            }
+            _ => {}
+        }
+        writer.write_event(event)?;
+    }
+    Ok(writer.into_inner())
+}
+
+#[test]
+fn tables() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<table xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" id="3" name="Sales" displayName="Sales" ref="B2:D6" totalsRowCount="1"><autoFilter ref="B2:D5"><filterColumn colId="0"><filters><filter val="East"/></filters></filterColumn></autoFilter><tableColumns count="3"><tableColumn id="1" name="Region" totalsRowLabel="Total"/><tableColumn id="2" name="Amount" totalsRowFunction="sum"/><tableColumn id="3" name="Tax"><calculatedColumnFormula>Sales[[#This Row],[Amount]]*0.1</calculatedColumnFormula></tableColumn></tableColumns><tableStyleInfo name="TableStyleLight9" showFirstColumn="0" showLastColumn="0" showRowStripes="1" showColumnStripes="0"/></table>"#;
+    let table = Table::from_xml_str(xml).unwrap();
+    assert_eq!(table.id, 3);
+    assert!(table.is_named("sales"));
+    assert_eq!(table.range(), "B2:D6".parse().ok());
+    assert_eq!(table.data_range(), "B3:D5".parse().ok());
+    assert_eq!(table.column_names(), ["Region", "Amount", "Tax"]);
+    assert_eq!(
+        table.columns()[1].totals_row_function.as_deref(),
+        Some("sum")
+    );
+    assert_eq!(
+        table.columns()[2].calculated_column_formula.as_deref(),
+        Some("Sales[[#This Row],[Amount]]*0.1")
+    );
+    let filter = table.auto_filter.as_ref().unwrap();
+    assert_eq!(
+        filter.filter_columns[0].filters.as_ref().unwrap().filters[0].val,
+        "East"
+    );
+    let style = table.table_style_info.as_ref().unwrap();
+    assert_eq!(style.name.as_deref(), Some("TableStyleLight9"));
+    assert_eq!(style.show_row_stripes, Some(true));
+    assert_eq!(Table::from_xml_str(&table.to_xml()).unwrap(), table);
+
+    let resized = resize_table_xml(xml.as_bytes(), "B2:C9".parse().unwrap(), &[]).unwrap();
+    let resized = Table::from_xml_str(std::str::from_utf8(&resized).unwrap()).unwrap();
+    assert_eq!(resized.r#ref, "B2:C9");
+    assert_eq!(resized.auto_filter.as_ref().unwrap().r#ref, "B2:C9");
+    assert_eq!(resized.column_names(), ["Region", "Amount"]);
+    assert_eq!(resized.table_columns.count, Some(2));
+
+    let new = TableColumn {
+        id: 4,
+        name: "Note".into(),
+        ..Default::default()
+    };
+    let resized = resize_table_xml(xml.as_bytes(), "B2:E6".parse().unwrap(), &[new]).unwrap();
+    let resized = Table::from_xml_str(std::str::from_utf8(&resized).unwrap()).unwrap();
+    assert_eq!(resized.column_names(), ["Region", "Amount", "Tax", "Note"]);
+
+    let header = |cell: &str, value: CellValue| (cell.parse().unwrap(), value);
+    assert_eq!(
+        column_names(
+            &["Region"],
+            vec![header("C2", CellValue::String("Note".into()))]
+        )
+        .unwrap(),
+        ["Note"]
+    );
+    for headers in [
+        vec![header("C2", CellValue::Null)],
+        vec![header("C2", CellValue::String(" ".into()))],
+        vec![header("C2", CellValue::Int(2021))],
+        vec![header("C2", CellValue::String("region".into()))],
+        vec![
+            header("C2", CellValue::String("Note".into())),
+            header("D2", CellValue::String("NOTE".into())),
+        ],
+    ] {
+        assert!(column_names(&["Region"], headers).is_err());
+    }
+    let table = Table::new(
+        1,
+        "People",
+        "$A$1:$B$3".parse().unwrap(),
+        vec!["Name".into(), "Age".into()],
+    );
+    assert_eq!(table.r#ref, "A1:B3");
+    assert_eq!(table.data_range(), "A2:B3".parse().ok());
+}
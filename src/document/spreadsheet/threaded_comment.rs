@@ -0,0 +1,547 @@
+//! Threaded comments of Excel 365 and the person list of their authors.
+//!
+//! Threads of a worksheet live in a part like `xl/threadedComments/threadedComment1.xml`, the
+//! authors in the workbook person list `xl/persons/person.xml`. Each thread keeps a legacy
+//! comment as a fallback for older applications, authored `tc={thread id}`.
+use std::fmt;
+use std::io::prelude::*;
+
+use chrono::NaiveDateTime;
+use sha2::{Digest, Sha256};
+
+use crate::error::OoxmlError;
+use crate::packaging::element::*;
+
+use super::comment::CommentItem;
+use super::reference::CellRef;
+use super::shared_string::SharedString;
+
+const THREADED_COMMENTS_NAMESPACE: &str =
+    "http://schemas.microsoft.com/office/spreadsheetml/2018/threadedcomments";
+const SPREADSHEET_NAMESPACE: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Text of the legacy comment before the thread.
+const LEGACY_PLACEHOLDER: &str = "[Threaded comment]\n\nYour version of Excel allows you to read this threaded comment; however, any edits to it will get removed if the file is opened in a newer version of Excel. Learn more: https://go.microsoft.com/fwlink/?linkid=870924";
+
+/// A new GUID like `{3F2504E0-4F89-41D3-9A0C-0305E82C3301}`.
+pub(crate) fn new_guid(seed: &str) -> String {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(nanos.to_le_bytes());
+    hasher.update(COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    hasher.update(seed.as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hasher.finalize()[..16]);
+    // version 4 and RFC 4122 variant.
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    format!(
+        "{{{}-{}-{}-{}-{}}}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// An author of threaded comments.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Person {
+    /// GUID of the person.
+    pub id: String,
+    pub display_name: String,
+    /// User id of the identity provider, like an email address.
+    pub user_id: Option<String>,
+    /// Identity provider like `AD`, or `None`.
+    pub provider_id: Option<String>,
+}
+
+/// A threaded comment or a reply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreadedComment {
+    /// GUID of the comment.
+    pub id: String,
+    pub author: Person,
+    pub created: Option<NaiveDateTime>,
+    pub text: String,
+}
+
+/// A thread of comments on a cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommentThread {
+    pub reference: CellRef,
+    /// The first comment, its id is the id of the thread.
+    pub comment: ThreadedComment,
+    pub replies: Vec<ThreadedComment>,
+    pub resolved: bool,
+}
+
+impl CommentThread {
+    pub fn id(&self) -> &str {
+        &self.comment.id
+    }
+}
+
+/// Person list part of a workbook.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersonsPart {
+    attributes: Vec<(String, String)>,
+    pub persons: Vec<Person>,
+}
+
+impl Default for PersonsPart {
+    fn default() -> Self {
+        Self {
+            attributes: vec![
+                ("xmlns".into(), THREADED_COMMENTS_NAMESPACE.into()),
+                ("xmlns:x".into(), SPREADSHEET_NAMESPACE.into()),
+            ],
+            persons: Vec::new(),
+        }
+    }
+}
+
+impl PersonsPart {
+    pub fn get(&self, id: &str) -> Option<&Person> {
+        self.persons.iter().find(|p| p.id == id)
+    }
+
+    /// The person of a display name, added if not exists.
+    pub fn person(&mut self, display_name: &str) -> Person {
+        if let Some(person) = self.persons.iter().find(|p| p.display_name == display_name) {
+            return person.clone();
+        }
+        let person = Person {
+            id: new_guid(display_name),
+            display_name: display_name.to_string(),
+            user_id: Some(display_name.to_string()),
+            provider_id: Some("None".into()),
+        };
+        self.persons.push(person.clone());
+        person
+    }
+
+    /// Write to an writer
+    pub fn write<W: std::io::Write>(&self, writer: W) -> Result<(), OoxmlError> {
+        use quick_xml::events::*;
+
+        let mut xml = quick_xml::Writer::new(writer);
+        xml.write_event(Event::Decl(BytesDecl::new(
+            b"1.0",
+            Some(b"UTF-8"),
+            Some(b"yes"),
+        )))?;
+        let mut elem = BytesStart::borrowed_name(b"personList");
+        for (key, value) in &self.attributes {
+            elem.push_attribute((key.as_str(), value.as_str()));
+        }
+        xml.write_event(Event::Start(elem))?;
+        for person in &self.persons {
+            let mut elem = BytesStart::borrowed_name(b"person");
+            elem.push_attribute(("displayName", person.display_name.as_str()));
+            elem.push_attribute(("id", person.id.as_str()));
+            if let Some(user_id) = &person.user_id {
+                elem.push_attribute(("userId", user_id.as_str()));
+            }
+            if let Some(provider_id) = &person.provider_id {
+                elem.push_attribute(("providerId", provider_id.as_str()));
+            }
+            xml.write_event(Event::Empty(elem))?;
+        }
+        xml.write_event(Event::End(BytesEnd::borrowed(b"personList")))?;
+        Ok(())
+    }
+}
+
+impl OpenXmlElementInfo for PersonsPart {
+    fn tag_name() -> &'static str {
+        "personList"
+    }
+
+    fn element_type() -> OpenXmlElementType {
+        OpenXmlElementType::Root
+    }
+}
+
+impl OpenXmlDeserialize for PersonsPart {
+    fn from_xml_reader<R: BufRead>(reader: R) -> Result<Self, OoxmlError> {
+        use quick_xml::events::Event;
+
+        let mut reader = quick_xml::Reader::from_reader(reader);
+        let mut part = PersonsPart {
+            attributes: Vec::new(),
+            persons: Vec::new(),
+        };
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Start(ref e) | Event::Empty(ref e) if e.local_name() == b"personList" => {
+                    for attr in e.attributes() {
+                        let attr = attr?;
+                        part.attributes.push((
+                            String::from_utf8_lossy(attr.key).into_owned(),
+                            attr.unescape_and_decode_value(&reader)?,
+                        ));
+                    }
+                }
+                Event::Start(ref e) | Event::Empty(ref e) if e.local_name() == b"person" => {
+                    let mut person = Person::default();
+                    for attr in e.attributes() {
+                        let attr = attr?;
+                        let value = attr.unescape_and_decode_value(&reader)?;
+                        match attr.key {
+                            b"displayName" => person.display_name = value,
+                            b"id" => person.id = value,
+                            b"userId" => person.user_id = Some(value),
+                            b"providerId" => person.provider_id = Some(value),
+                            _ => {}
+                        }
+                    }
+                    part.persons.push(person);
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+        Ok(part)
+    }
+}
+
+impl fmt::Display for PersonsPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut container = Vec::new();
+        self.write(&mut container).map_err(|_| fmt::Error)?;
+        f.write_str(&String::from_utf8_lossy(&container))
+    }
+}
+
+/// A `<threadedComment>` element.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ThreadedCommentItem {
+    pub reference: String,
+    pub date_time: Option<String>,
+    pub person_id: String,
+    pub id: String,
+    /// Id of the thread for replies.
+    pub parent_id: Option<String>,
+    pub done: Option<bool>,
+    pub text: String,
+}
+
+/// Threaded comments part of a worksheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreadedCommentsPart {
+    attributes: Vec<(String, String)>,
+    pub comments: Vec<ThreadedCommentItem>,
+}
+
+impl Default for ThreadedCommentsPart {
+    fn default() -> Self {
+        Self {
+            attributes: vec![
+                ("xmlns".into(), THREADED_COMMENTS_NAMESPACE.into()),
+                ("xmlns:x".into(), SPREADSHEET_NAMESPACE.into()),
+            ],
+            comments: Vec::new(),
+        }
+    }
+}
+
+impl ThreadedCommentsPart {
+    /// Threads with their replies, authors are resolved by the person list.
+    pub fn to_threads(&self, persons: &PersonsPart) -> Vec<CommentThread> {
+        let comment = |item: &ThreadedCommentItem| ThreadedComment {
+            id: item.id.clone(),
+            author: persons.get(&item.person_id).cloned().unwrap_or(Person {
+                id: item.person_id.clone(),
+                ..Default::default()
+            }),
+            created: item
+                .date_time
+                .as_ref()
+                .and_then(|dt| NaiveDateTime::parse_from_str(dt, DATE_TIME_FORMAT).ok()),
+            text: item.text.clone(),
+        };
+        self.comments
+            .iter()
+            .filter(|item| item.parent_id.is_none())
+            .filter_map(|item| {
+                Some(CommentThread {
+                    reference: item.reference.parse().ok()?,
+                    comment: comment(item),
+                    replies: self
+                        .comments
+                        .iter()
+                        .filter(|reply| reply.parent_id.as_ref() == Some(&item.id))
+                        .map(comment)
+                        .collect(),
+                    resolved: item.done.unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    /// The thread of a cell.
+    pub fn thread(&self, cell: CellRef) -> Option<&ThreadedCommentItem> {
+        let reference = cell.to_string();
+        self.comments
+            .iter()
+            .find(|item| item.parent_id.is_none() && item.reference == reference)
+    }
+
+    /// Add a comment to the thread of a cell, the thread is started if the cell has none.
+    ///
+    /// Returns the id of the thread.
+    pub fn add_comment(
+        &mut self,
+        cell: CellRef,
+        person: &Person,
+        created: NaiveDateTime,
+        text: &str,
+    ) -> String {
+        let parent_id = self.thread(cell).map(|thread| thread.id.clone());
+        let item = ThreadedCommentItem {
+            reference: cell.to_string(),
+            date_time: Some(created.format("%Y-%m-%dT%H:%M:%S%.3f").to_string()),
+            person_id: person.id.clone(),
+            id: new_guid(text),
+            parent_id: parent_id.clone(),
+            done: None,
+            text: text.to_string(),
+        };
+        let thread_id = parent_id.unwrap_or_else(|| item.id.clone());
+        // replies follow the last comment of the thread.
+        let idx = self
+            .comments
+            .iter()
+            .rposition(|c| c.id == thread_id || c.parent_id.as_ref() == Some(&thread_id))
+            .map_or(self.comments.len(), |idx| idx + 1);
+        self.comments.insert(idx, item);
+        thread_id
+    }
+
+    /// Set the resolved state of the thread of a cell, returns false if the cell has none.
+    pub fn set_resolved(&mut self, cell: CellRef, resolved: bool) -> bool {
+        let reference = cell.to_string();
+        match self
+            .comments
+            .iter_mut()
+            .find(|item| item.parent_id.is_none() && item.reference == reference)
+        {
+            Some(item) => {
+                item.done = Some(resolved);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The legacy comment of a thread as Excel writes, authored `tc={thread id}`.
+    pub fn legacy_comment(&self, thread_id: &str) -> Option<(String, CommentItem)> {
+        let thread = self.comments.iter().find(|item| item.id == thread_id)?;
+        let mut text = format!("{}\n\nComment:\n    {}", LEGACY_PLACEHOLDER, thread.text);
+        for reply in self
+            .comments
+            .iter()
+            .filter(|reply| reply.parent_id.as_deref() == Some(thread_id))
+        {
+            text.push_str("\nReply:\n    ");
+            text.push_str(&reply.text);
+        }
+        let item = CommentItem {
+            reference: thread.reference.clone(),
+            uid: Some(thread_id.to_string()),
+            text: SharedString::new(text),
+            ..Default::default()
+        };
+        Some((format!("tc={}", thread_id), item))
+    }
+
+    /// Write to an writer
+    pub fn write<W: std::io::Write>(&self, writer: W) -> Result<(), OoxmlError> {
+        use quick_xml::events::*;
+
+        let mut xml = quick_xml::Writer::new(writer);
+        xml.write_event(Event::Decl(BytesDecl::new(
+            b"1.0",
+            Some(b"UTF-8"),
+            Some(b"yes"),
+        )))?;
+        let mut elem = BytesStart::borrowed_name(b"ThreadedComments");
+        for (key, value) in &self.attributes {
+            elem.push_attribute((key.as_str(), value.as_str()));
+        }
+        xml.write_event(Event::Start(elem))?;
+        for item in &self.comments {
+            let mut elem = BytesStart::borrowed_name(b"threadedComment");
+            elem.push_attribute(("ref", item.reference.as_str()));
+            if let Some(date_time) = &item.date_time {
+                elem.push_attribute(("dT", date_time.as_str()));
+            }
+            elem.push_attribute(("personId", item.person_id.as_str()));
+            elem.push_attribute(("id", item.id.as_str()));
+            if let Some(parent_id) = &item.parent_id {
+                elem.push_attribute(("parentId", parent_id.as_str()));
+            }
+            if let Some(done) = item.done {
+                elem.push_attribute(("done", if done { "1" } else { "0" }));
+            }
+            xml.write_event(Event::Start(elem))?;
+            xml.write_event(Event::Start(BytesStart::borrowed_name(b"text")))?;
+            xml.write_event(Event::Text(BytesText::from_plain_str(&item.text)))?;
+            xml.write_event(Event::End(BytesEnd::borrowed(b"text")))?;
+            xml.write_event(Event::End(BytesEnd::borrowed(b"threadedComment")))?;
+        }
+        xml.write_event(Event::End(BytesEnd::borrowed(b"ThreadedComments")))?;
+        Ok(())
+    }
+}
+
+impl OpenXmlElementInfo for ThreadedCommentsPart {
+    fn tag_name() -> &'static str {
+        "ThreadedComments"
+    }
+
+    fn element_type() -> OpenXmlElementType {
+        OpenXmlElementType::Root
+    }
+}
+
+impl OpenXmlDeserialize for ThreadedCommentsPart {
+    fn from_xml_reader<R: BufRead>(reader: R) -> Result<Self, OoxmlError> {
+        use quick_xml::events::Event;
+
+        let mut reader = quick_xml::Reader::from_reader(reader);
+        let mut part = ThreadedCommentsPart {
+            attributes: Vec::new(),
+            comments: Vec::new(),
+        };
+        let mut buf = Vec::new();
+        let mut comment: Option<ThreadedCommentItem> = None;
+        let mut in_text = false;
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Start(ref e) | Event::Empty(ref e)
+                    if e.local_name() == b"ThreadedComments" =>
+                {
+                    for attr in e.attributes() {
+                        let attr = attr?;
+                        part.attributes.push((
+                            String::from_utf8_lossy(attr.key).into_owned(),
+                            attr.unescape_and_decode_value(&reader)?,
+                        ));
+                    }
+                }
+                Event::Start(ref e) if e.local_name() == b"threadedComment" => {
+                    let mut item = ThreadedCommentItem::default();
+                    for attr in e.attributes() {
+                        let attr = attr?;
+                        let value = attr.unescape_and_decode_value(&reader)?;
+                        match attr.key {
+                            b"ref" => item.reference = value,
+                            b"dT" => item.date_time = Some(value),
+                            b"personId" => item.person_id = value,
+                            b"id" => item.id = value,
+                            b"parentId" => item.parent_id = Some(value),
+                            b"done" => item.done = Some(value == "1" || value == "true"),
+                            _ => {}
+                        }
+                    }
+                    comment = Some(item);
+                }
+                Event::Start(ref e) if e.local_name() == b"text" => in_text = true,
+                Event::Text(ref e) if in_text => {
+                    if let Some(comment) = comment.as_mut() {
+                        comment.text.push_str(&e.unescape_and_decode(&reader)?);
+                    }
+                }
+                Event::End(ref e) if e.local_name() == b"text" => in_text = false,
+                Event::End(ref e) if e.local_name() == b"threadedComment" => {
+                    part.comments.extend(comment.take());
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+        Ok(part)
+    }
+}
+
+impl fmt::Display for ThreadedCommentsPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut container = Vec::new();
+        self.write(&mut container).map_err(|_| fmt::Error)?;
+        f.write_str(&String::from_utf8_lossy(&container))
+    }
+}
+
+#[test]
+fn threaded_comments() {
+    let persons = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<personList xmlns="http://schemas.microsoft.com/office/spreadsheetml/2018/threadedcomments" xmlns:x="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><person displayName="Ann Lee" id="{A1B2C3D4-0000-0000-0000-000000000001}" userId="ann@example.com" providerId="AD"/></personList>"#;
+    let threads = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<ThreadedComments xmlns="http://schemas.microsoft.com/office/spreadsheetml/2018/threadedcomments" xmlns:x="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><threadedComment ref="B3" dT="2023-03-01T09:30:00.50" personId="{A1B2C3D4-0000-0000-0000-000000000001}" id="{00000000-0000-0000-0000-0000000000T1}" done="1"><text>Is this right?</text></threadedComment><threadedComment ref="B3" dT="2023-03-02T10:00:00.00" personId="{A1B2C3D4-0000-0000-0000-000000000001}" id="{00000000-0000-0000-0000-0000000000R1}" parentId="{00000000-0000-0000-0000-0000000000T1}"><text>Yes &amp; fixed</text></threadedComment></ThreadedComments>"#;
+    let mut persons = PersonsPart::from_xml_str(persons).unwrap();
+    let mut part = ThreadedCommentsPart::from_xml_str(threads).unwrap();
+    let threads = part.to_threads(&persons);
+    assert_eq!(threads.len(), 1);
+    let thread = &threads[0];
+    assert_eq!(thread.reference, CellRef::new(2, 1));
+    assert_eq!(thread.id(), "{00000000-0000-0000-0000-0000000000T1}");
+    assert!(thread.resolved);
+    assert_eq!(thread.comment.author.display_name, "Ann Lee");
+    assert_eq!(
+        thread.comment.created.unwrap().to_string(),
+        "2023-03-01 09:30:00.500"
+    );
+    assert_eq!(thread.replies.len(), 1);
+    assert_eq!(thread.replies[0].text, "Yes & fixed");
+    assert_eq!(
+        PersonsPart::from_xml_str(&persons.to_string()).unwrap(),
+        persons
+    );
+    assert_eq!(
+        ThreadedCommentsPart::from_xml_str(&part.to_string()).unwrap(),
+        part
+    );
+
+    let bob = persons.person("Bob");
+    assert_eq!(persons.person("Bob"), bob);
+    assert_eq!(persons.persons.len(), 2);
+    let created = chrono::NaiveDate::from_ymd_opt(2024, 1, 2)
+        .and_then(|d| d.and_hms_opt(3, 4, 5))
+        .unwrap();
+    let thread_id = part.add_comment(CellRef::new(2, 1), &bob, created, "Thanks");
+    assert_eq!(thread_id, thread.id());
+    let new_thread = part.add_comment(CellRef::new(0, 0), &bob, created, "New");
+    assert!(part.set_resolved(CellRef::new(0, 0), true));
+    assert!(!part.set_resolved(CellRef::new(9, 9), true));
+    let threads = part.to_threads(&persons);
+    assert_eq!(threads[0].replies[1].text, "Thanks");
+    assert_eq!(threads[0].replies[1].author.display_name, "Bob");
+    assert_eq!(threads[0].replies[1].created, Some(created));
+    assert_eq!(threads[1].id(), new_thread);
+    assert!(threads[1].resolved);
+
+    let (author, legacy) = part.legacy_comment(&thread_id).unwrap();
+    assert_eq!(author, format!("tc={}", thread_id));
+    assert_eq!(legacy.reference, "B3");
+    assert!(legacy
+        .text
+        .as_str()
+        .ends_with("Comment:\n    Is this right?\nReply:\n    Yes & fixed\nReply:\n    Thanks"));
+    assert_ne!(new_guid("a"), new_guid("a"));
+}
@@ -1,11 +1,14 @@
 use std::borrow::Cow;
 
+use crate::packaging::conformance::Conformance;
 use crate::packaging::element::*;
 use crate::packaging::namespace::Namespaces;
 
 use quick_xml::events::attributes::Attribute;
 use serde::{Deserialize, Serialize};
 
+use super::cell::DateSystem;
+
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", rename = "fileVersion")]
 pub struct FileVersion {
@@ -51,6 +54,101 @@ pub struct Sheets {
     pub sheets: Vec<Sheet>,
 }
 
+/// Built-in name of the print area of a sheet.
+pub const PRINT_AREA: &str = "_xlnm.Print_Area";
+/// Built-in name of the rows and columns printed on every page of a sheet.
+pub const PRINT_TITLES: &str = "_xlnm.Print_Titles";
+
+/// A defined name `<definedName>`, see ECMA-376 Part 1, 18.2.5.
+///
+/// Names are scoped to the workbook, or to a sheet by `local_sheet_id`, the index of the
+/// sheet in `<sheets>`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "definedName")]
+pub struct DefinedName {
+    pub name: String,
+    pub local_sheet_id: Option<usize>,
+    pub hidden: Option<bool>,
+    pub comment: Option<String>,
+    /// Formula of the name without leading `=`, like `Sheet1!$A$1:$B$3`.
+    #[serde(rename = "$value", default)]
+    pub formula: String,
+}
+
+impl DefinedName {
+    pub fn new(name: impl Into<String>, formula: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            formula: formula.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden.unwrap_or_default()
+    }
+
+    /// Check if the name is a built-in one like `_xlnm.Print_Area`.
+    pub fn is_builtin(&self) -> bool {
+        self.name.starts_with("_xlnm.")
+    }
+
+    /// Check if two names are the same, names are case insensitive.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.to_lowercase() == name.to_lowercase()
+    }
+}
+
+/// Check if a text is valid as a defined name: starts with a letter, `_` or `\`, made of
+/// letters, digits, `_`, `.` and `\`, and not like an A1 cell or a R1C1 reference.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let first = match chars.next() {
+        Some(c) => c,
+        None => return false,
+    };
+    if !(first.is_alphabetic() || first == '_' || first == '\\')
+        || !chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '\\'))
+        || name.chars().count() > 255
+    {
+        return false;
+    }
+    let origin = super::reference::CellRef::new(0, 0);
+    !(name.parse::<super::reference::CellRef>().is_ok()
+        || super::reference::CellRange::parse_r1c1(name, &origin).is_ok())
+}
+
+/// Defined names `<definedNames>` of a workbook.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "definedNames")]
+pub struct DefinedNames {
+    #[serde(rename = "definedName", default)]
+    pub defined_names: Vec<DefinedName>,
+}
+
+impl DefinedNames {
+    pub fn to_xml(&self) -> String {
+        let escape =
+            |s: &str| String::from_utf8_lossy(&quick_xml::escape::escape(s.as_bytes())).to_string();
+        let mut xml = String::from("<definedNames>");
+        for name in &self.defined_names {
+            xml.push_str(&format!(r#"<definedName name="{}""#, escape(&name.name)));
+            if let Some(comment) = &name.comment {
+                xml.push_str(&format!(r#" comment="{}""#, escape(comment)));
+            }
+            if let Some(hidden) = name.hidden {
+                xml.push_str(&format!(r#" hidden="{}""#, hidden as u8));
+            }
+            if let Some(id) = name.local_sheet_id {
+                xml.push_str(&format!(r#" localSheetId="{}""#, id));
+            }
+            xml.push_str(&format!(">{}</definedName>", escape(&name.formula)));
+        }
+        xml.push_str("</definedNames>");
+        xml
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", rename = "calcPr")]
 pub struct CalcPr {
@@ -63,12 +161,24 @@ pub struct WorkbookPart {
     pub book_views: BookViews,
     pub workbook_pr: WorkbookPr,
     pub sheets: Sheets,
+    pub defined_names: Option<DefinedNames>,
     pub calc_pr: Option<CalcPr>,
     #[serde(flatten)]
     namespaces: Namespaces,
 }
 
 impl WorkbookPart {
+    /// Date system of serial date numbers in the workbook.
+    pub fn date_system(&self) -> DateSystem {
+        DateSystem::from_date1904(self.workbook_pr.date1904.unwrap_or_default())
+    }
+    /// Defined names of the workbook.
+    pub fn defined_names(&self) -> &[DefinedName] {
+        self.defined_names
+            .as_ref()
+            .map(|names| names.defined_names.as_slice())
+            .unwrap_or_default()
+    }
     pub fn sheet_names(&self) -> Vec<&str> {
         self.sheets
             .sheets
@@ -100,11 +210,121 @@ impl OpenXmlSerialize for WorkbookPart {
         quick_xml::se::to_writer(xml.inner(), &self.book_views)?;
         quick_xml::se::to_writer(xml.inner(), &self.workbook_pr)?;
         quick_xml::se::to_writer(xml.inner(), &self.sheets)?;
+        if let Some(defined_names) = &self.defined_names {
+            xml.inner().write_all(defined_names.to_xml().as_bytes())?;
+        }
         quick_xml::se::to_writer(xml.inner(), &self.calc_pr)?;
         Ok(())
     }
 }
 
+/// Child elements of `<workbook>` in schema order.
+const WORKBOOK_ELEMENTS: [&str; 21] = [
+    "fileVersion",
+    "fileSharing",
+    "workbookPr",
+    "workbookProtection",
+    "bookViews",
+    "sheets",
+    "functionGroups",
+    "externalReferences",
+    "definedNames",
+    "calcPr",
+    "oleSize",
+    "customWorkbookViews",
+    "pivotCaches",
+    "smartTagPr",
+    "smartTagTypes",
+    "webPublishing",
+    "fileRecoveryPr",
+    "webPublishObjects",
+    "extLst",
+    "AlternateContent",
+    "absPath",
+];
+
+/// Replace all `tag` children of workbook xml with `element`, or remove them for `None`.
+pub(crate) fn replace_workbook_element(
+    data: &[u8],
+    tag: &str,
+    element: Option<&str>,
+) -> Result<Vec<u8>, crate::error::OoxmlError> {
+    super::worksheet::replace_child_element(data, &WORKBOOK_ELEMENTS, tag, element)
+}
+
+/// Set or remove the `conformance="strict"` attribute of the workbook root element.
+pub(crate) fn set_workbook_conformance(
+    data: &[u8],
+    conformance: Conformance,
+) -> Result<Vec<u8>, crate::error::OoxmlError> {
+    use quick_xml::events::{BytesStart, Event};
+
+    let mut reader = quick_xml::Reader::from_reader(data);
+    reader.trim_text(false);
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    let mut buf = Vec::new();
+    let mut root = true;
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Eof => break,
+            Event::Start(e) if root => {
+                root = false;
+                let mut elem = BytesStart::owned_name(e.name().to_vec());
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    if attr.key != b"conformance" {
+                        elem.push_attribute(attr);
+                    }
+                }
+                if conformance.is_strict() {
+                    elem.push_attribute(("conformance", "strict"));
+                }
+                writer.write_event(Event::Start(elem))?;
+            }
+            event => writer.write_event(event)?,
+        }
+        buf.clear();
+    }
+    Ok(writer.into_inner())
+}
+
+#[test]
+fn defined_names() {
+    let xml = r#"<definedNames><definedName name="_xlnm.Print_Area" localSheetId="0">Sheet1!$A$1:$D$20</definedName><definedName name="Rate" hidden="1" comment="a &amp; b">0.5</definedName></definedNames>"#;
+    let names: DefinedNames = quick_xml::de::from_str(xml).unwrap();
+    assert_eq!(names.defined_names.len(), 2);
+    let area = &names.defined_names[0];
+    assert!(area.is_builtin());
+    assert_eq!(area.name, PRINT_AREA);
+    assert_eq!(area.local_sheet_id, Some(0));
+    assert_eq!(area.formula, "Sheet1!$A$1:$D$20");
+    let rate = &names.defined_names[1];
+    assert!(rate.is_hidden() && rate.is_named("RATE"));
+    assert_eq!(rate.comment.as_deref(), Some("a & b"));
+    assert_eq!(
+        quick_xml::de::from_str::<DefinedNames>(&names.to_xml()).unwrap(),
+        names
+    );
+    for name in ["Rate", "_tax", "\\path", "my.name2", "A1B"] {
+        assert!(is_valid_name(name), "{}", name);
+    }
+    for name in [
+        "",
+        "1st",
+        "A1",
+        "XFD1048576",
+        "R1C1",
+        "r",
+        "C",
+        "R2",
+        "c10",
+        "has space",
+        "a-b",
+    ] {
+        assert!(!is_valid_name(name), "{}", name);
+    }
+}
+
 #[test]
 fn serde() {
     let workbook =
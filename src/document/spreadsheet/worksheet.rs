@@ -1,15 +1,19 @@
-use super::cell::{CellType, CellValue};
+use super::calc::Value;
+use super::cell::{self, CellType, CellValue, DateSystem};
+use super::conditional_format::ConditionalFormatting;
+use super::data_validation::DataValidations;
+use super::formula::{DataTableInputs, Formula, FormulaKind};
+use super::hyperlink::SheetHyperlinks;
+use super::reference::{CellRange, CellRef};
+use crate::packaging::conformance::Conformance;
 use crate::packaging::element::*;
 use crate::packaging::namespace::Namespaces;
 
 use quick_xml::events::attributes::Attribute;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
-
-use static_init::dynamic;
-
-#[dynamic(lazy)]
-static DIMENSION_RE: regex::Regex = regex::Regex::new(r"(?P<col>[A-Z]+)(?P<row>\d+)").unwrap();
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
 
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", rename = "sheetPr")]
@@ -55,41 +59,15 @@ pub struct Dimension {
 }
 
 impl Dimension {
-    pub fn dimension(&self) -> Option<(usize, usize)> {
-        let range = self.r#ref.as_str();
-        let range: Vec<&str> = range.split_terminator(':').collect();
+    /// The used range of the worksheet.
+    pub fn range(&self) -> Option<CellRange> {
+        self.r#ref.parse().ok()
+    }
 
-        if range.len() < 2 {
-            return None;
-        }
-        let start = range[0];
-        let end = range[1];
-        //let (start, end) = range.split_once(':').expect("split at :");
-        fn rangify(range: &str) -> (usize, usize) {
-            let cap = DIMENSION_RE.captures(range).unwrap();
-            let col = cap.name("col").unwrap().as_str();
-            let row = cap
-                .name("row")
-                .unwrap()
-                .as_str()
-                .parse()
-                .unwrap_or_default();
-            fn col_to_idx(col: &str) -> usize {
-                if col.is_empty() {
-                    return 0;
-                }
-                let mut idx = 0;
-                for (i, c) in col.chars().rev().enumerate() {
-                    let c = c.to_digit(36).unwrap();
-                    idx += c * 26u32.pow(i as _);
-                }
-                return idx as usize;
-            }
-            (row, col_to_idx(col))
-        }
-        let start = rangify(start);
-        let end = rangify(end);
-        Some((end.0 - start.0 + 1, end.1 - start.1 + 1))
+    /// Row and column count of the used range.
+    pub fn dimension(&self) -> Option<(usize, usize)> {
+        self.range()
+            .map(|range| (range.height() as usize, range.width() as usize))
     }
 }
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
@@ -111,6 +89,74 @@ pub struct SheetFormatPr {
 #[serde(rename_all = "camelCase", rename = "is")]
 pub struct SheetCellIs {
     t: Option<String>,
+    /// Rich text runs.
+    #[serde(rename = "r")]
+    runs: Option<Vec<SheetCellRun>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "r")]
+pub struct SheetCellRun {
+    t: Option<String>,
+}
+
+impl SheetCellIs {
+    /// Text of the inline string, rich text runs are concatenated.
+    pub fn text(&self) -> Cow<'_, str> {
+        match (&self.t, &self.runs) {
+            (Some(t), _) => Cow::Borrowed(t),
+            (None, Some(runs)) => runs.iter().filter_map(|run| run.t.as_deref()).collect(),
+            (None, None) => Cow::Borrowed(""),
+        }
+    }
+}
+
+/// Cell formula `<f>`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "f")]
+pub struct SheetCellFormula {
+    pub t: Option<String>,
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    pub si: Option<usize>,
+    pub ca: Option<bool>,
+    pub aca: Option<bool>,
+    #[serde(rename = "dt2D")]
+    pub dt2d: Option<bool>,
+    pub dtr: Option<bool>,
+    pub del1: Option<bool>,
+    pub del2: Option<bool>,
+    pub r1: Option<String>,
+    pub r2: Option<String>,
+    pub bx: Option<bool>,
+    #[serde(rename = "$value")]
+    pub text: Option<String>,
+}
+
+impl SheetCellFormula {
+    pub fn to_formula(&self) -> Formula {
+        let kind = FormulaKind::from_type(self.t.as_deref());
+        let data_table = if kind == FormulaKind::DataTable {
+            Some(DataTableInputs {
+                two_dimensional: self.dt2d.unwrap_or_default(),
+                row_input: self.dtr.unwrap_or_default(),
+                first: self.r1.as_ref().and_then(|r| r.parse().ok()),
+                second: self.r2.as_ref().and_then(|r| r.parse().ok()),
+                first_deleted: self.del1.unwrap_or_default(),
+                second_deleted: self.del2.unwrap_or_default(),
+            })
+        } else {
+            None
+        };
+        Formula {
+            kind,
+            text: self.text.clone().unwrap_or_default(),
+            range: self.reference.as_ref().and_then(|r| r.parse().ok()),
+            shared_index: self.si,
+            always_calculate: self.ca.unwrap_or_default(),
+            data_table,
+        }
+    }
 }
 
 // #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
@@ -123,84 +169,64 @@ pub struct SheetCellIs {
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", rename = "c")]
 pub struct SheetCol {
-    /// Row expression
-    pub r: String,
+    /// Cell reference, eg. `B3`, cells without it follow the previous one.
+    pub r: Option<String>,
     /// Type
     pub t: Option<String>,
     /// Style
     pub s: Option<usize>,
     // TODO: what is SheetCellIs?
     pub is: Option<SheetCellIs>,
+    /// Formula, the cached result is in `v`.
+    pub f: Option<SheetCellFormula>,
     //#[serde(rename = "$value")]
     // pub v: String,
     pub v: Option<String>,
 }
 
 impl SheetCol {
-    pub fn as_raw_str(&self) -> &str {
+    pub fn as_raw_str(&self) -> Cow<'_, str> {
         if let Some(is) = self.is.as_ref() {
-            return is.t.as_ref().expect("inline str error");
+            is.text()
         } else if let Some(v) = self.v.as_ref() {
-            return v.as_str();
+            Cow::Borrowed(v)
         } else {
-            //dbg!(&self);
-            ""
-            //unreachable!()
+            Cow::Borrowed("")
         }
-        // self.v.as_str()
     }
     pub fn raw_value(&self) -> CellValue {
         CellValue::String(self.as_raw_str().to_string())
     }
     pub fn cell_type(&self) -> CellType {
-        if self.t.is_none() && self.v.is_none() {
+        let t = self.t.as_deref();
+        if self.v.is_none() && !(t == Some("inlineStr") && self.is.is_some()) {
             return CellType::Empty;
         }
-        match (self.t.as_ref(), self.s.as_ref()) {
-            (None, None) => CellType::Raw,
-            (Some(t), None) => match t {
-                s if s == "s" => CellType::Shared(
-                    self.v
-                        .as_ref()
-                        .expect("shared string has no id")
-                        .parse()
-                        .expect("sharedString id not valid"),
-                ),
-                n if n == "n" => CellType::Number,
-                t if t == "inlineStr" => CellType::Raw,
-                t => unimplemented!("cell type not supported: {}", t),
-            },
-            (None, Some(s)) => CellType::Styled(*s),
-            (Some(t), Some(s)) => match t {
-                t if t == "s" => CellType::Shared(
-                    self.v
-                        .as_ref()
-                        .expect("shared string has no id")
-                        .parse()
-                        .expect("sharedString id not valid"),
-                ),
-                t if t == "n" => CellType::StyledNumber(*s),
-                t if t == "inlineStr" => CellType::Raw,
-                t => unimplemented!("cell type not supported: {}", t),
+        match (t, self.s) {
+            (Some("s"), _) => match self.v.as_ref().and_then(|v| v.trim().parse().ok()) {
+                Some(id) => CellType::Shared(id),
+                None => CellType::Raw,
             },
+            (Some("b"), _) => CellType::Bool,
+            (Some("e"), _) => CellType::Error,
+            (Some("str"), _) => CellType::FormulaString,
+            (Some("inlineStr"), _) => CellType::InlineString,
+            (Some("d"), None) => CellType::Date,
+            (Some("d"), Some(s)) => CellType::StyledDate(s),
+            (Some("n"), None) => CellType::Number,
+            (Some("n"), Some(s)) => CellType::StyledNumber(s),
+            (None, None) => CellType::Raw,
+            (None, Some(s)) => CellType::Styled(s),
+            // unknown types are kept as it is.
+            (Some(_), _) => CellType::Raw,
         }
-        // if let Some(t) = self.t.as_ref() {
-        //     match t {
-        //         s if s == "s" => CellType::Shared(self.v.parse().expect("sharedString id not valid")),
-        //         n if n == "n" => CellType::Number,
-        //         t => unimplemented!("cell type not supported: {}" , t),
-        //     }
-        // } else if let Some(s) = self.s {
-        //     CellType::Styled(s)
-        // } else {
-        //     CellType::Raw
-        // }
     }
 }
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename = "row")]
 pub struct SheetRow {
-    pub r: usize,
+    /// 1-based row index, rows without it follow the previous one.
+    pub r: Option<usize>,
     #[serde(rename = "customHeight")]
     pub custom_height: Option<bool>,
     pub ht: Option<f64>,
@@ -215,6 +241,58 @@ pub struct SheetData {
     pub rows: Option<Vec<SheetRow>>,
 }
 
+/// A merged range `<mergeCell>`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename = "mergeCell")]
+pub struct MergeCell {
+    #[serde(rename = "ref")]
+    pub reference: String,
+}
+
+/// Merged ranges `<mergeCells>` of a worksheet.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename = "mergeCells")]
+pub struct MergeCells {
+    pub count: Option<usize>,
+    #[serde(rename = "mergeCell", default)]
+    pub merge_cells: Vec<MergeCell>,
+}
+
+impl MergeCells {
+    pub fn from_ranges(ranges: &[CellRange]) -> Self {
+        Self {
+            count: Some(ranges.len()),
+            merge_cells: ranges
+                .iter()
+                .map(|range| MergeCell {
+                    reference: range.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Merged ranges, invalid references are skipped.
+    pub fn ranges(&self) -> Vec<CellRange> {
+        self.merge_cells
+            .iter()
+            .filter_map(|cell| cell.reference.parse().ok())
+            .collect()
+    }
+
+    pub fn to_xml(&self) -> String {
+        let cells: String = self
+            .merge_cells
+            .iter()
+            .map(|cell| format!(r#"<mergeCell ref="{}"/>"#, cell.reference))
+            .collect();
+        format!(
+            r#"<mergeCells count="{}">{}</mergeCells>"#,
+            self.merge_cells.len(),
+            cells
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", rename = "col")]
 pub struct SheetColHeader {
@@ -255,6 +333,11 @@ pub struct WorksheetPart {
     pub sheet_format_pr: Option<SheetFormatPr>,
     pub cols: Option<SheetCols>,
     pub sheet_data: Option<SheetData>,
+    pub merge_cells: Option<MergeCells>,
+    #[serde(rename = "conditionalFormatting", default)]
+    pub conditional_formattings: Vec<ConditionalFormatting>,
+    pub data_validations: Option<DataValidations>,
+    pub hyperlinks: Option<SheetHyperlinks>,
     pub page_margins: Option<PageMargins>,
     pub header_footer: Option<HeaderFooter>,
 }
@@ -276,6 +359,153 @@ impl WorksheetPart {
             _ => None,
         }
     }
+
+    /// Merged ranges of the worksheet.
+    pub fn merged_ranges(&self) -> Vec<CellRange> {
+        self.merge_cells
+            .as_ref()
+            .map(MergeCells::ranges)
+            .unwrap_or_default()
+    }
+
+    /// Index sheet data by cell position, see [`CellGrid`].
+    pub(crate) fn cell_grid(&self) -> CellGrid {
+        let mut grid = CellGrid::default();
+        let rows = match self.sheet_data.as_ref().and_then(|data| data.rows.as_ref()) {
+            Some(rows) => rows,
+            None => return grid,
+        };
+        let mut next_row = 0;
+        for (row_idx, row) in rows.iter().enumerate() {
+            let row_number = row.r.map_or(next_row, |r| r.saturating_sub(1) as u32);
+            next_row = row_number + 1;
+            let mut next_col = 0;
+            for (col_idx, col) in row.cols.iter().flatten().enumerate() {
+                let col_number = col
+                    .r
+                    .as_ref()
+                    .and_then(|r| r.parse::<CellRef>().ok())
+                    .map_or(next_col, |r| r.col);
+                next_col = col_number + 1;
+                grid.cells
+                    .insert((row_number, col_number), (row_idx, col_idx));
+                let master = col.f.as_ref().and_then(|f| {
+                    f.si.filter(|_| !f.text.as_deref().unwrap_or_default().is_empty())
+                });
+                if let Some(si) = master {
+                    grid.shared_masters
+                        .entry(si)
+                        .or_insert_with(|| CellRef::new(row_number, col_number));
+                }
+            }
+        }
+        grid
+    }
+
+    /// Cell at row and cell index of sheet data.
+    pub(crate) fn cell_at(&self, (row, col): (usize, usize)) -> Option<&SheetCol> {
+        let data = self.sheet_data.as_ref()?;
+        data.rows
+            .as_ref()
+            .and_then(|rows| rows.get(row))
+            .and_then(|row| row.cols.as_ref().and_then(|cols| cols.get(col)))
+    }
+
+    /// Concrete formula of a cell, shared formulas are expanded from their master cells.
+    pub(crate) fn formula(&self, grid: &CellGrid, cell: CellRef) -> Option<Formula> {
+        let formula = self
+            .cell_at(grid.get(cell.row, cell.col)?)?
+            .f
+            .as_ref()?
+            .to_formula();
+        let si = match formula.shared_index {
+            Some(si) if formula.kind == FormulaKind::Shared && formula.text.is_empty() => si,
+            _ => return Some(formula),
+        };
+        let anchor = grid.shared_masters.get(&si)?;
+        let master = self
+            .cell_at(grid.get(anchor.row, anchor.col)?)?
+            .f
+            .as_ref()?;
+        let rows = cell.row as i64 - anchor.row as i64;
+        let cols = cell.col as i64 - anchor.col as i64;
+        Some(Formula {
+            range: None,
+            ..master.to_formula().shifted(rows, cols)
+        })
+    }
+
+    /// Formulas of all cells, shared formulas are expanded to their concrete forms.
+    pub(crate) fn formulas(&self, grid: &CellGrid) -> BTreeMap<CellRef, Formula> {
+        let cells: Vec<(CellRef, Formula)> = grid
+            .cells
+            .iter()
+            .filter_map(|((row, col), position)| {
+                let f = self.cell_at(*position)?.f.as_ref()?;
+                Some((CellRef::new(*row, *col), f.to_formula()))
+            })
+            .collect();
+        let masters: HashMap<usize, (CellRef, &Formula)> = cells
+            .iter()
+            .filter(|(_, f)| f.is_shared_master())
+            .filter_map(|(cell, f)| Some((f.shared_index?, (*cell, f))))
+            .collect();
+        cells
+            .iter()
+            .map(|(cell, formula)| {
+                let master = formula
+                    .shared_index
+                    .filter(|_| formula.kind == FormulaKind::Shared && formula.text.is_empty())
+                    .and_then(|si| masters.get(&si));
+                let formula = match master {
+                    Some((anchor, master)) => Formula {
+                        range: None,
+                        ..master.shifted(
+                            cell.row as i64 - anchor.row as i64,
+                            cell.col as i64 - anchor.col as i64,
+                        )
+                    },
+                    None => formula.clone(),
+                };
+                (*cell, formula)
+            })
+            .collect()
+    }
+}
+
+/// Sheet data positions of cells, by 0-based row and column.
+///
+/// Excel omits blank rows and cells, so the n-th `<row>` is not always the n-th row, rows and
+/// cells are placed by their `r` attribute instead.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct CellGrid {
+    cells: BTreeMap<(u32, u32), (usize, usize)>,
+    /// Master cells of shared formula groups by `si`, which hold the formula text.
+    shared_masters: HashMap<usize, CellRef>,
+}
+
+impl CellGrid {
+    /// Row and cell index in sheet data of a cell.
+    pub fn get(&self, row: u32, col: u32) -> Option<(usize, usize)> {
+        self.cells.get(&(row, col)).copied()
+    }
+
+    /// Positions of all the cells, by rows.
+    pub fn cells(&self) -> impl Iterator<Item = CellRef> + '_ {
+        self.cells.keys().map(|(row, col)| CellRef::new(*row, *col))
+    }
+
+    /// Bounds of all the cells.
+    pub fn range(&self) -> Option<CellRange> {
+        let (first_row, _) = self.cells.keys().next()?;
+        let (last_row, _) = self.cells.keys().next_back()?;
+        let first_col = self.cells.keys().map(|(_, col)| *col).min()?;
+        let last_col = self.cells.keys().map(|(_, col)| *col).max()?;
+        Some(CellRange::new(
+            CellRef::new(*first_row, first_col),
+            CellRef::new(*last_row, last_col),
+        ))
+    }
 }
 
 impl OpenXmlElementInfo for WorksheetPart {
@@ -301,6 +531,20 @@ impl OpenXmlSerialize for WorksheetPart {
         quick_xml::se::to_writer(xml.inner(), &self.dimension)?;
         quick_xml::se::to_writer(xml.inner(), &self.sheet_format_pr)?;
         quick_xml::se::to_writer(xml.inner(), &self.sheet_data)?;
+        if let Some(merge_cells) = &self.merge_cells {
+            xml.inner().write_all(merge_cells.to_xml().as_bytes())?;
+        }
+        for conditional_formatting in &self.conditional_formattings {
+            xml.inner()
+                .write_all(conditional_formatting.to_xml().as_bytes())?;
+        }
+        if let Some(data_validations) = &self.data_validations {
+            xml.inner()
+                .write_all(data_validations.to_xml().as_bytes())?;
+        }
+        if let Some(hyperlinks) = &self.hyperlinks {
+            xml.inner().write_all(hyperlinks.to_xml().as_bytes())?;
+        }
         quick_xml::se::to_writer(xml.inner(), &self.page_margins)?;
         quick_xml::se::to_writer(xml.inner(), &self.header_footer)?;
 
@@ -308,6 +552,409 @@ impl OpenXmlSerialize for WorksheetPart {
     }
 }
 
+/// Convert date cells of worksheet xml between serial numbers and strict ISO 8601 `t="d"` values.
+///
+/// To strict, numeric cells with a date style get `t="d"`; to transitional, `t="d"` cells get
+/// their serial numbers back in the date system of the workbook. Returns `None` if no cell is
+/// changed.
+pub(crate) fn convert_date_cells(
+    data: &[u8],
+    conformance: Conformance,
+    date_system: DateSystem,
+    is_date_style: impl Fn(usize) -> bool,
+) -> Result<Option<Vec<u8>>, crate::error::OoxmlError> {
+    use quick_xml::events::{BytesStart, BytesText, Event};
+
+    let mut reader = quick_xml::Reader::from_reader(data);
+    reader.trim_text(false);
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    let mut buf = Vec::new();
+    let mut changed = false;
+    // buffered events of the converting cell.
+    let mut cell: Option<Vec<Event<'static>>> = None;
+    loop {
+        let event = reader.read_event(&mut buf)?.into_owned();
+        buf.clear();
+        match event {
+            Event::Eof => break,
+            Event::Start(ref e) if e.name() == b"c" => {
+                let mut t = None;
+                let mut s = None;
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    match attr.key {
+                        b"t" => t = Some(attr.value.into_owned()),
+                        b"s" => {
+                            s = std::str::from_utf8(&attr.value)
+                                .ok()
+                                .and_then(|s| s.parse().ok())
+                        }
+                        _ => {}
+                    }
+                }
+                let convert = match conformance {
+                    Conformance::Strict => {
+                        matches!(t.as_deref(), None | Some(b"n")) && s.is_some_and(&is_date_style)
+                    }
+                    Conformance::Transitional => t.as_deref() == Some(b"d"),
+                };
+                if convert {
+                    cell = Some(vec![event]);
+                } else {
+                    writer.write_event(event)?;
+                }
+            }
+            Event::End(ref e) if e.name() == b"c" && cell.is_some() => {
+                let mut events = cell.take().expect("checked cell");
+                events.push(event);
+                // the text event right after `<v>`
+                let value = events.windows(2).position(|w| {
+                    matches!(&w[0], Event::Start(v) if v.name() == b"v")
+                        && matches!(&w[1], Event::Text(_))
+                });
+                let converted = value.and_then(|i| {
+                    let text = match &events[i + 1] {
+                        Event::Text(text) => text.unescaped().ok()?,
+                        _ => return None,
+                    };
+                    let text = std::str::from_utf8(&text).ok()?;
+                    match conformance {
+                        Conformance::Strict => {
+                            let datetime =
+                                date_system.serial_to_datetime(text.trim().parse().ok()?)?;
+                            Some((i + 1, cell::format_iso_datetime(&datetime)))
+                        }
+                        Conformance::Transitional => {
+                            let datetime = cell::parse_iso_datetime(text)?;
+                            Some((i + 1, date_system.datetime_to_serial(&datetime).to_string()))
+                        }
+                    }
+                });
+                if let Some((i, text)) = converted {
+                    let start = match &events[0] {
+                        Event::Start(e) => e,
+                        _ => unreachable!(),
+                    };
+                    let mut elem = BytesStart::owned_name(b"c".to_vec());
+                    for attr in start.attributes() {
+                        let attr = attr?;
+                        if attr.key != b"t" {
+                            elem.push_attribute(attr);
+                        }
+                    }
+                    if conformance.is_strict() {
+                        elem.push_attribute(("t", "d"));
+                    }
+                    events[0] = Event::Start(elem);
+                    events[i] = Event::Text(BytesText::from_plain_str(&text).into_owned());
+                    changed = true;
+                }
+                for event in events {
+                    writer.write_event(event)?;
+                }
+            }
+            event => match cell.as_mut() {
+                Some(events) => events.push(event),
+                None => writer.write_event(event)?,
+            },
+        }
+    }
+    if changed {
+        Ok(Some(writer.into_inner()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Child elements of `<worksheet>` in schema order, see ECMA-376 Part 1, 18.3.1.99.
+const WORKSHEET_ELEMENTS: [&str; 39] = [
+    "sheetPr",
+    "dimension",
+    "sheetViews",
+    "sheetFormatPr",
+    "cols",
+    "sheetData",
+    "sheetCalcPr",
+    "sheetProtection",
+    "protectedRanges",
+    "scenarios",
+    "autoFilter",
+    "sortState",
+    "dataConsolidate",
+    "customSheetViews",
+    "mergeCells",
+    "phoneticPr",
+    "conditionalFormatting",
+    "dataValidations",
+    "hyperlinks",
+    "printOptions",
+    "pageMargins",
+    "pageSetup",
+    "headerFooter",
+    "rowBreaks",
+    "colBreaks",
+    "customProperties",
+    "cellWatches",
+    "ignoredErrors",
+    "smartTags",
+    "drawing",
+    "legacyDrawing",
+    "legacyDrawingHF",
+    "drawingHF",
+    "picture",
+    "oleObjects",
+    "controls",
+    "webPublishItems",
+    "tableParts",
+    "extLst",
+];
+
+/// Replace all `tag` children of worksheet xml with `element`, or remove them for `None`.
+///
+/// The element takes the place of the first replaced one, or its schema position if the
+/// worksheet has none.
+pub(crate) fn replace_worksheet_element(
+    data: &[u8],
+    tag: &str,
+    element: Option<&str>,
+) -> Result<Vec<u8>, crate::error::OoxmlError> {
+    replace_child_element(data, &WORKSHEET_ELEMENTS, tag, element)
+}
+
+/// Replace all `tag` children of the root element with `element`, or remove them for `None`,
+/// `elements` are the child names of the root in schema order.
+pub(crate) fn replace_child_element(
+    data: &[u8],
+    elements: &[&str],
+    tag: &str,
+    element: Option<&str>,
+) -> Result<Vec<u8>, crate::error::OoxmlError> {
+    use quick_xml::events::Event;
+
+    let order = |name: &[u8]| elements.iter().position(|e| e.as_bytes() == name);
+    let target = order(tag.as_bytes());
+    let mut reader = quick_xml::Reader::from_reader(data);
+    reader.trim_text(false);
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    let mut buf = Vec::new();
+    let mut skip_buf = Vec::new();
+    let mut depth = 0;
+    let mut element = element;
+    loop {
+        let event = reader.read_event(&mut buf)?.into_owned();
+        buf.clear();
+        match &event {
+            Event::Eof => break,
+            Event::Start(e) | Event::Empty(e) if depth == 1 => {
+                let name = e.local_name();
+                let replaced = name == tag.as_bytes();
+                if replaced || order(name) > target {
+                    if let Some(element) = element.take() {
+                        writer.inner().write_all(element.as_bytes())?;
+                    }
+                }
+                if replaced {
+                    if let Event::Start(e) = &event {
+                        reader.read_to_end(e.name(), &mut skip_buf)?;
+                        skip_buf.clear();
+                    }
+                    continue;
+                }
+                if let Event::Start(_) = event {
+                    depth += 1;
+                }
+            }
+            Event::Start(_) => depth += 1,
+            Event::End(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(element) = element.take() {
+                        writer.inner().write_all(element.as_bytes())?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        writer.write_event(event)?;
+    }
+    Ok(writer.into_inner())
+}
+
+/// Rewrite formulas of worksheet xml: cell formulas `<f>`, and formulas of conditional
+/// formatting and data validations. Returns `None` if no formula is changed.
+pub(crate) fn rewrite_formulas(
+    data: &[u8],
+    rewrite: impl Fn(&str) -> Option<String>,
+) -> Result<Option<Vec<u8>>, crate::error::OoxmlError> {
+    use quick_xml::events::{BytesText, Event};
+
+    let mut reader = quick_xml::Reader::from_reader(data);
+    reader.trim_text(false);
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    let mut buf = Vec::new();
+    let mut changed = false;
+    let mut in_formula = false;
+    loop {
+        let event = reader.read_event(&mut buf)?.into_owned();
+        buf.clear();
+        match event {
+            Event::Eof => break,
+            Event::Start(ref e) => {
+                in_formula = matches!(
+                    e.local_name(),
+                    b"f" | b"formula" | b"formula1" | b"formula2"
+                );
+                writer.write_event(event)?;
+            }
+            Event::Text(ref text) if in_formula => {
+                let formula = reader.decode(&text.unescaped()?)?.to_string();
+                match rewrite(&formula) {
+                    Some(formula) => {
+                        writer.write_event(Event::Text(BytesText::from_plain_str(&formula)))?;
+                        changed = true;
+                    }
+                    None => writer.write_event(event)?,
+                }
+            }
+            event => {
+                in_formula = false;
+                writer.write_event(event)?;
+            }
+        }
+    }
+    if changed {
+        Ok(Some(writer.into_inner()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Write calculated values into `<v>` of worksheet xml cells, types in `t` are updated and
+/// empty values remove `<v>`. Cells not in sheet data are not added. Returns `None` if no
+/// cell is changed.
+pub(crate) fn set_cell_values(
+    data: &[u8],
+    values: &BTreeMap<CellRef, Value>,
+) -> Result<Option<Vec<u8>>, crate::error::OoxmlError> {
+    use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+
+    let mut reader = quick_xml::Reader::from_reader(data);
+    reader.trim_text(false);
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    let mut buf = Vec::new();
+    let mut changed = false;
+    let (mut next_row, mut row, mut next_col) = (0u32, 0u32, 0u32);
+    // the updating cell, its start element and child events.
+    let mut cell: Option<(BytesStart<'static>, &Value, Vec<Event<'static>>)> = None;
+    // whether events are inside the `<v>` being replaced.
+    let mut in_value = false;
+    let attribute = |e: &BytesStart, key: &[u8]| -> Option<String> {
+        e.attributes()
+            .flatten()
+            .find(|attr| attr.key == key)
+            .and_then(|attr| String::from_utf8(attr.value.into_owned()).ok())
+    };
+    loop {
+        let event = reader.read_event(&mut buf)?.into_owned();
+        buf.clear();
+        match event {
+            Event::Eof => break,
+            Event::Start(ref e) | Event::Empty(ref e) if e.name() == b"row" => {
+                row = attribute(e, b"r")
+                    .and_then(|r| r.parse::<u32>().ok())
+                    .map_or(next_row, |r| r.saturating_sub(1));
+                next_row = row + 1;
+                next_col = 0;
+                writer.write_event(event)?;
+            }
+            Event::Start(ref e) | Event::Empty(ref e) if e.name() == b"c" => {
+                let col = attribute(e, b"r")
+                    .and_then(|r| r.parse::<CellRef>().ok())
+                    .map_or(next_col, |r| r.col);
+                next_col = col + 1;
+                match values.get(&CellRef::new(row, col)) {
+                    Some(value) if matches!(event, Event::Start(_)) => {
+                        cell = Some((e.clone().into_owned(), value, Vec::new()));
+                    }
+                    Some(value) => {
+                        write_cell(&mut writer, e, value, Vec::new())?;
+                        changed = true;
+                    }
+                    None => writer.write_event(event)?,
+                }
+            }
+            Event::End(ref e) if e.name() == b"c" && cell.is_some() => {
+                let (start, value, children) = cell.take().expect("checked cell");
+                write_cell(&mut writer, &start, value, children)?;
+                changed = true;
+            }
+            Event::Start(ref e) if e.name() == b"v" && cell.is_some() => in_value = true,
+            Event::Empty(ref e) if e.name() == b"v" && cell.is_some() => {}
+            Event::End(ref e) if e.name() == b"v" && in_value => in_value = false,
+            _ if in_value => {}
+            event => match cell.as_mut() {
+                Some((_, _, children)) => children.push(event),
+                None => writer.write_event(event)?,
+            },
+        }
+    }
+
+    /// Write a cell with the value inserted after its formula.
+    fn write_cell(
+        writer: &mut quick_xml::Writer<Vec<u8>>,
+        start: &BytesStart,
+        value: &Value,
+        children: Vec<Event<'static>>,
+    ) -> Result<(), crate::error::OoxmlError> {
+        let (t, text) = match value.scalar() {
+            Value::Empty => (None, None),
+            Value::Number(v) => (None, Some(v.to_string())),
+            Value::Text(s) => (Some("str"), Some(s)),
+            Value::Bool(v) => (Some("b"), Some((v as u8).to_string())),
+            Value::Error(e) => (Some("e"), Some(e.to_string())),
+            Value::Array(_) => unreachable!("scalar value"),
+        };
+        let mut elem = BytesStart::owned_name(b"c".to_vec());
+        for attr in start.attributes() {
+            let attr = attr?;
+            if attr.key != b"t" {
+                elem.push_attribute(attr);
+            }
+        }
+        if let Some(t) = t {
+            elem.push_attribute(("t", t));
+        }
+        writer.write_event(Event::Start(elem))?;
+        // `<v>` follows `<f>` and precedes `<is>` and `<extLst>`.
+        let position = children
+            .iter()
+            .position(|e| {
+                matches!(e, Event::Start(e) | Event::Empty(e) if e.name() == b"is" || e.name() == b"extLst")
+            })
+            .unwrap_or(children.len());
+        let mut children = children.into_iter();
+        for event in children.by_ref().take(position) {
+            writer.write_event(event)?;
+        }
+        if let Some(text) = text {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"v")))?;
+            writer.write_event(Event::Text(BytesText::from_plain_str(&text)))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"v")))?;
+        }
+        for event in children {
+            writer.write_event(event)?;
+        }
+        writer.write_event(Event::End(BytesEnd::borrowed(b"c")))?;
+        Ok(())
+    }
+
+    if changed {
+        Ok(Some(writer.into_inner()))
+    } else {
+        Ok(None)
+    }
+}
+
 #[test]
 fn serde() {
     // let xml = include_str!("../../../tests/inline-str/xl/worksheets/sheet1.xml");
@@ -340,3 +987,222 @@ fn cell() {
     let c: SheetCol = quick_xml::de::from_str(xml).unwrap();
     println!("{:?}", c);
 }
+
+#[test]
+fn date_cells() {
+    let transitional = r#"<sheetData><row r="1"><c r="A1" s="1"><v>44259.5</v></c><c r="B1" s="2"><v>3</v></c></row></sheetData>"#;
+    let strict = r#"<sheetData><row r="1"><c r="A1" s="1" t="d"><v>2021-03-04T12:00:00</v></c><c r="B1" s="2"><v>3</v></c></row></sheetData>"#;
+    let converted = convert_date_cells(
+        transitional.as_bytes(),
+        Conformance::Strict,
+        DateSystem::Date1900,
+        |s| s == 1,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(String::from_utf8_lossy(&converted), strict);
+    let converted = convert_date_cells(
+        strict.as_bytes(),
+        Conformance::Transitional,
+        DateSystem::Date1900,
+        |_| false,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(String::from_utf8_lossy(&converted), transitional);
+    assert!(convert_date_cells(
+        transitional.as_bytes(),
+        Conformance::Transitional,
+        DateSystem::Date1900,
+        |_| true,
+    )
+    .unwrap()
+    .is_none());
+}
+
+#[test]
+fn date1904_cells() {
+    // 2021-03-04T12:00:00 is 44259.5 in the 1900 system and 42797.5 in the 1904 system.
+    let transitional =
+        r#"<sheetData><row r="1"><c r="A1" s="1"><v>42797.5</v></c></row></sheetData>"#;
+    let strict = r#"<sheetData><row r="1"><c r="A1" s="1" t="d"><v>2021-03-04T12:00:00</v></c></row></sheetData>"#;
+    let converted = convert_date_cells(
+        transitional.as_bytes(),
+        Conformance::Strict,
+        DateSystem::Date1904,
+        |s| s == 1,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(String::from_utf8_lossy(&converted), strict);
+    let converted = convert_date_cells(
+        &converted,
+        Conformance::Transitional,
+        DateSystem::Date1904,
+        |_| false,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(String::from_utf8_lossy(&converted), transitional);
+}
+
+#[test]
+fn sparse_cell_grid() {
+    let xml = r#"<worksheet><sheetData>
+        <row r="2"><c r="B2"><v>1</v></c><c r="D2"><v>2</v></c></row>
+        <row r="5"><c r="A5"><v>3</v></c><c><v>4</v></c></row>
+        <row><c r="C6"><v>5</v></c></row>
+    </sheetData></worksheet>"#;
+    let part = WorksheetPart::from_xml_reader(xml.as_bytes()).unwrap();
+    let grid = part.cell_grid();
+    assert_eq!(grid.get(1, 1), Some((0, 0)));
+    assert_eq!(grid.get(1, 3), Some((0, 1)));
+    assert_eq!(grid.get(1, 2), None);
+    assert_eq!(grid.get(4, 0), Some((1, 0)));
+    assert_eq!(grid.get(4, 1), Some((1, 1)));
+    assert_eq!(grid.get(5, 2), Some((2, 0)));
+    assert_eq!(grid.range().unwrap().to_string(), "A2:D6");
+}
+
+#[test]
+fn typed_cells() {
+    let xml = r#"<worksheet><sheetData><row r="1">
+        <c r="A1" t="b"><v>1</v></c>
+        <c r="B1" t="e"><v>#REF!</v></c>
+        <c r="C1" t="str"><v>text</v></c>
+        <c r="D1" t="inlineStr"><is><r><t>rich</t></r><r><rPr><b/></rPr><t>text</t></r></is></c>
+        <c r="E1" t="inlineStr"><is><t>plain</t></is></c>
+        <c r="F1" t="s"/>
+        <c r="G1" t="x"><v>?</v></c>
+    </row></sheetData></worksheet>"#;
+    let part = WorksheetPart::from_xml_reader(xml.as_bytes()).unwrap();
+    let cells = part.sheet_data.unwrap().rows.unwrap()[0]
+        .cols
+        .clone()
+        .unwrap();
+    let types: Vec<CellType> = cells.iter().map(|c| c.cell_type()).collect();
+    assert_eq!(
+        types,
+        vec![
+            CellType::Bool,
+            CellType::Error,
+            CellType::FormulaString,
+            CellType::InlineString,
+            CellType::InlineString,
+            CellType::Empty,
+            CellType::Raw
+        ]
+    );
+    assert_eq!(cells[3].as_raw_str(), "richtext");
+    assert_eq!(cells[4].as_raw_str(), "plain");
+}
+
+#[test]
+fn shared_formulas() {
+    let xml = r#"<worksheet><sheetData>
+        <row r="1"><c r="A1"><v>1</v></c><c r="B1"><f t="shared" ref="B1:B3" si="0">A1*2+$A$1</f><v>3</v></c></row>
+        <row r="2"><c r="A2"><v>2</v></c><c r="B2"><f t="shared" si="0"/><v>5</v></c></row>
+        <row r="3"><c r="A3"><v>3</v></c><c r="B3"><f t="shared" si="0"/><v>7</v></c>
+            <c r="C3"><f t="array" ref="C3:C4">A1:A2*2</f><v>2</v></c></row>
+        <row r="5"><c r="A5"><f>SUM(A1:A3)</f><v>6</v></c></row>
+    </sheetData></worksheet>"#;
+    let part = WorksheetPart::from_xml_reader(xml.as_bytes()).unwrap();
+    let grid = part.cell_grid();
+    assert_eq!(grid.shared_masters.len(), 1);
+    assert_eq!(grid.shared_masters[&0], "B1".parse().unwrap());
+    let formulas = part.formulas(&grid);
+    let text = |cell: &str| formulas[&cell.parse::<CellRef>().unwrap()].text.clone();
+    assert_eq!(formulas.len(), 5);
+    assert_eq!(text("B1"), "A1*2+$A$1");
+    assert_eq!(text("B2"), "A2*2+$A$1");
+    assert_eq!(text("B3"), "A3*2+$A$1");
+    assert_eq!(text("A5"), "SUM(A1:A3)");
+    let array = &formulas[&"C3".parse::<CellRef>().unwrap()];
+    assert_eq!(array.kind, FormulaKind::Array);
+    assert_eq!(array.range, "C3:C4".parse().ok());
+    assert_eq!(
+        part.formula(&grid, "B3".parse().unwrap()).map(|f| f.text),
+        Some("A3*2+$A$1".into())
+    );
+}
+
+#[test]
+fn cell_values() {
+    let xml = r#"<sheetData><row r="1"><c r="A1"><v>1</v></c><c r="B1" s="2"><f>A1*2</f><v>0</v></c><c r="C1" t="n"><f>"x"&amp;A1</f></c></row><row><c r="A2"><f>1/0</f><v>1</v></c><c r="B2"/><c r="C2"><f>C3</f><v>9</v></c></row></sheetData>"#;
+    let values: BTreeMap<CellRef, Value> = [
+        ("B1", Value::Number(2.)),
+        ("C1", Value::from("x<1")),
+        ("A2", Value::Error(cell::CellError::Div0)),
+        ("B2", Value::Bool(true)),
+        ("C2", Value::Empty),
+    ]
+    .iter()
+    .map(|(cell, value)| (cell.parse().unwrap(), value.clone()))
+    .collect();
+    let updated = set_cell_values(xml.as_bytes(), &values).unwrap().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&updated),
+        r#"<sheetData><row r="1"><c r="A1"><v>1</v></c><c r="B1" s="2"><f>A1*2</f><v>2</v></c><c r="C1" t="str"><f>"x"&amp;A1</f><v>x&lt;1</v></c></row><row><c r="A2" t="e"><f>1/0</f><v>#DIV/0!</v></c><c r="B2" t="b"><v>1</v></c><c r="C2"><f>C3</f></c></row></sheetData>"#
+    );
+    assert!(set_cell_values(xml.as_bytes(), &BTreeMap::new())
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn merge_cells() {
+    let xml = r#"<worksheet><sheetData/><mergeCells count="2"><mergeCell ref="A1:B2"/><mergeCell ref="D1:D3"/></mergeCells><pageMargins left="0.7"/></worksheet>"#;
+    let part = WorksheetPart::from_xml_reader(xml.as_bytes()).unwrap();
+    let ranges = part.merged_ranges();
+    assert_eq!(
+        ranges,
+        vec!["A1:B2".parse().unwrap(), "D1:D3".parse().unwrap()]
+    );
+
+    let merged = MergeCells::from_ranges(&ranges[1..]).to_xml();
+    let replaced = replace_worksheet_element(xml.as_bytes(), "mergeCells", Some(&merged)).unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&replaced),
+        r#"<worksheet><sheetData/><mergeCells count="1"><mergeCell ref="D1:D3"/></mergeCells><pageMargins left="0.7"/></worksheet>"#
+    );
+    let removed = replace_worksheet_element(xml.as_bytes(), "mergeCells", None).unwrap();
+    let removed = String::from_utf8_lossy(&removed).to_string();
+    assert_eq!(
+        removed,
+        r#"<worksheet><sheetData/><pageMargins left="0.7"/></worksheet>"#
+    );
+    let inserted =
+        replace_worksheet_element(removed.as_bytes(), "mergeCells", Some(&merged)).unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&replaced),
+        String::from_utf8_lossy(&inserted)
+    );
+    let appended = replace_worksheet_element(
+        b"<worksheet><sheetData/></worksheet>",
+        "mergeCells",
+        Some(&merged),
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&appended),
+        r#"<worksheet><sheetData/><mergeCells count="1"><mergeCell ref="D1:D3"/></mergeCells></worksheet>"#
+    );
+}
+
+#[test]
+fn rewrite_worksheet_formulas() {
+    let xml = r#"<worksheet><sheetData><row r="1"><c r="A1"><f>Rate&amp;&quot;Rate&quot;</f><v>1</v></c><c r="B1"><v>2</v></c></row></sheetData><dataValidations count="1"><dataValidation type="list" sqref="C1"><formula1>Rate</formula1></dataValidation></dataValidations></worksheet>"#;
+    let rewritten = rewrite_formulas(xml.as_bytes(), |formula| {
+        Some(formula.replacen("Rate", "Tax", 1))
+    })
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&rewritten),
+        xml.replace("<f>Rate", "<f>Tax")
+            .replace("<formula1>Rate", "<formula1>Tax")
+    );
+    assert!(rewrite_formulas(xml.as_bytes(), |_| None)
+        .unwrap()
+        .is_none());
+}
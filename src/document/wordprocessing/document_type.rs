@@ -0,0 +1,45 @@
+#[derive(Debug, Clone, Copy, Default)]
+pub enum WordprocessingDocumentType {
+    /// Word Document (*.docx).
+    #[default]
+    Document,
+    /// Word Template (*.dotx).
+    Template,
+    /// Word Macro-Enabled Document (*.docm).
+    MacroEnabledDocument,
+    /// Word Macro-Enabled Template (*.dotm).
+    MacroEnabledTemplate,
+}
+
+const DOCUMENT_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml";
+const TEMPLATE_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.template.main+xml";
+const MACRO_ENABLED_DOCUMENT_CONTENT_TYPE: &str =
+    "application/vnd.ms-word.document.macroEnabled.main+xml";
+const MACRO_ENABLED_TEMPLATE_CONTENT_TYPE: &str =
+    "application/vnd.ms-word.template.macroEnabledTemplate.main+xml";
+impl WordprocessingDocumentType {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            WordprocessingDocumentType::Document => DOCUMENT_CONTENT_TYPE,
+            WordprocessingDocumentType::Template => TEMPLATE_CONTENT_TYPE,
+            WordprocessingDocumentType::MacroEnabledDocument => MACRO_ENABLED_DOCUMENT_CONTENT_TYPE,
+            WordprocessingDocumentType::MacroEnabledTemplate => MACRO_ENABLED_TEMPLATE_CONTENT_TYPE,
+        }
+    }
+    /// Document type of the main part content type, `None` if it's not a word processing document.
+    pub fn try_from_content_type(content_type: &str) -> Option<Self> {
+        match content_type {
+            DOCUMENT_CONTENT_TYPE => Some(WordprocessingDocumentType::Document),
+            TEMPLATE_CONTENT_TYPE => Some(WordprocessingDocumentType::Template),
+            MACRO_ENABLED_DOCUMENT_CONTENT_TYPE => {
+                Some(WordprocessingDocumentType::MacroEnabledDocument)
+            }
+            MACRO_ENABLED_TEMPLATE_CONTENT_TYPE => {
+                Some(WordprocessingDocumentType::MacroEnabledTemplate)
+            }
+            _ => None,
+        }
+    }
+}
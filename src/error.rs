@@ -14,6 +14,16 @@ pub enum OoxmlError {
     XmlDeError(#[from] quick_xml::de::DeError),
     #[error("No content type in package")]
     PackageContentTypeError,
+    #[error("No relationships in package")]
+    PackageRelationshipError,
+    #[error("part not found: {0}")]
+    PartNotFound(String),
+    #[error("invalid cell reference: {0}")]
+    InvalidReference(String),
+    #[error("digital signature error: {0}")]
+    SignatureError(String),
+    #[error("invalid formula: {0}")]
+    FormulaError(String),
     #[error("unknown data store error")]
     Unknown,
 }
@@ -9,3 +9,15 @@ mod drawing;
 mod math;
 
 pub mod document;
+
+/// Open any OpenXML file, dispatched into a [`document::Document`] by its main part.
+///
+/// ```rust
+/// match ooxml::open("examples/docx-demo/rust-docx-rs.docx").unwrap() {
+///     ooxml::document::Document::Wordprocessing(docx) => println!("{:?}", docx.document_type()),
+///     _ => unreachable!(),
+/// }
+/// ```
+pub fn open<P: AsRef<std::path::Path>>(path: P) -> error::Result<document::Document> {
+    document::Document::open(path)
+}
@@ -0,0 +1,213 @@
+//! Conformance class of a package, transitional or strict (ISO/IEC 29500 Strict).
+//!
+//! Strict packages use `http://purl.oclc.org/ooxml/...` namespaces and relationship types. They
+//! are normalized to the transitional ones when loading, so the rest of the crate only deals
+//! with transitional uris, and converted back when saving in strict form.
+use std::borrow::Cow;
+
+use crate::packaging::relationship::RelationshipType;
+
+const STRICT_URI_PREFIX: &str = "http://purl.oclc.org/ooxml/";
+const TRANSITIONAL_URI_PREFIX: &str = "http://schemas.openxmlformats.org/";
+
+/// Namespaces as (strict, transitional) pairs.
+const NAMESPACES: &[(&str, &str)] = &[
+    (
+        "http://purl.oclc.org/ooxml/spreadsheetml/main",
+        "http://schemas.openxmlformats.org/spreadsheetml/2006/main",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/wordprocessingml/main",
+        "http://schemas.openxmlformats.org/wordprocessingml/2006/main",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/presentationml/main",
+        "http://schemas.openxmlformats.org/presentationml/2006/main",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/officeDocument/relationships",
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/officeDocument/extendedProperties",
+        "http://schemas.openxmlformats.org/officeDocument/2006/extended-properties",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/officeDocument/customProperties",
+        "http://schemas.openxmlformats.org/officeDocument/2006/custom-properties",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/officeDocument/docPropsVTypes",
+        "http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/officeDocument/sharedTypes",
+        "http://schemas.openxmlformats.org/officeDocument/2006/sharedTypes",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/officeDocument/customXml",
+        "http://schemas.openxmlformats.org/officeDocument/2006/customXml",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/officeDocument/bibliography",
+        "http://schemas.openxmlformats.org/officeDocument/2006/bibliography",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/officeDocument/math",
+        "http://schemas.openxmlformats.org/officeDocument/2006/math",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/schemaLibrary/main",
+        "http://schemas.openxmlformats.org/schemaLibrary/2006/main",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/drawingml/main",
+        "http://schemas.openxmlformats.org/drawingml/2006/main",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/drawingml/chart",
+        "http://schemas.openxmlformats.org/drawingml/2006/chart",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/drawingml/chartDrawing",
+        "http://schemas.openxmlformats.org/drawingml/2006/chartDrawing",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/drawingml/diagram",
+        "http://schemas.openxmlformats.org/drawingml/2006/diagram",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/drawingml/picture",
+        "http://schemas.openxmlformats.org/drawingml/2006/picture",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/drawingml/lockedCanvas",
+        "http://schemas.openxmlformats.org/drawingml/2006/lockedCanvas",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/drawingml/compatibility",
+        "http://schemas.openxmlformats.org/drawingml/2006/compatibility",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/drawingml/spreadsheetDrawing",
+        "http://schemas.openxmlformats.org/drawingml/2006/spreadsheetDrawing",
+    ),
+    (
+        "http://purl.oclc.org/ooxml/drawingml/wordprocessingDrawing",
+        "http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing",
+    ),
+];
+
+/// Conformance class of a package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Conformance {
+    /// ECMA-376 transitional, the form most office applications write.
+    #[default]
+    Transitional,
+    /// ISO/IEC 29500 strict.
+    Strict,
+}
+
+impl Conformance {
+    pub fn is_strict(&self) -> bool {
+        *self == Conformance::Strict
+    }
+}
+
+/// Replace quoted uris starting with `prefix` in xml data, `None` if nothing changed.
+fn map_quoted_uris(
+    data: &[u8],
+    prefix: &str,
+    map: impl Fn(&str) -> Option<Cow<'static, str>>,
+) -> Option<Vec<u8>> {
+    let prefix = prefix.as_bytes();
+    let mut output = Vec::new();
+    let mut copied = 0;
+    let mut from = 0;
+    while let Some(pos) = find(&data[from..], prefix).map(|pos| pos + from) {
+        from = pos + prefix.len();
+        let quote = match pos.checked_sub(1).map(|i| data[i]) {
+            Some(quote @ b'"') | Some(quote @ b'\'') => quote,
+            _ => continue,
+        };
+        let end = match data[pos..].iter().position(|b| *b == quote) {
+            Some(len) => pos + len,
+            None => break,
+        };
+        let uri = match std::str::from_utf8(&data[pos..end]) {
+            Ok(uri) => uri,
+            Err(_) => continue,
+        };
+        if let Some(mapped) = map(uri) {
+            output.extend_from_slice(&data[copied..pos]);
+            output.extend_from_slice(mapped.as_bytes());
+            copied = end;
+        }
+        from = end;
+    }
+    if copied == 0 {
+        return None;
+    }
+    output.extend_from_slice(&data[copied..]);
+    Some(output)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Map strict namespaces and relationship types in xml data to the transitional ones.
+///
+/// Returns `None` if there's no strict uri.
+pub fn to_transitional(data: &[u8]) -> Option<Vec<u8>> {
+    map_quoted_uris(data, STRICT_URI_PREFIX, |uri| {
+        if let Some((_, transitional)) = NAMESPACES.iter().find(|(strict, _)| *strict == uri) {
+            return Some(Cow::Borrowed(*transitional));
+        }
+        match RelationshipType::from_uri(uri) {
+            RelationshipType::Other(_) => None,
+            t => Some(Cow::Owned(t.as_uri().to_string())),
+        }
+    })
+}
+
+/// Map transitional namespaces and relationship types in xml data to the strict ones.
+///
+/// Returns `None` if there's no transitional uri with a strict form.
+pub fn to_strict(data: &[u8]) -> Option<Vec<u8>> {
+    map_quoted_uris(data, TRANSITIONAL_URI_PREFIX, |uri| {
+        if let Some((strict, _)) = NAMESPACES
+            .iter()
+            .find(|(_, transitional)| *transitional == uri)
+        {
+            return Some(Cow::Borrowed(*strict));
+        }
+        let t = RelationshipType::from_uri(uri);
+        if t.as_strict_uri() != uri {
+            Some(Cow::Owned(t.as_strict_uri().to_string()))
+        } else {
+            None
+        }
+    })
+}
+
+#[test]
+fn strict_to_transitional() {
+    let strict = r#"<worksheet xmlns="http://purl.oclc.org/ooxml/spreadsheetml/main" xmlns:r='http://purl.oclc.org/ooxml/officeDocument/relationships'><hyperlink r:id="rId1"/></worksheet>"#;
+    let transitional = r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r='http://schemas.openxmlformats.org/officeDocument/2006/relationships'><hyperlink r:id="rId1"/></worksheet>"#;
+    let converted = to_transitional(strict.as_bytes()).unwrap();
+    assert_eq!(String::from_utf8_lossy(&converted), transitional);
+    let converted = to_strict(transitional.as_bytes()).unwrap();
+    assert_eq!(String::from_utf8_lossy(&converted), strict);
+    assert!(to_transitional(transitional.as_bytes()).is_none());
+
+    let rels = r#"<Relationship Id="rId1" Type="http://purl.oclc.org/ooxml/officeDocument/relationships/extendedProperties" Target="docProps/app.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="docProps/core.xml"/>"#;
+    let converted = to_transitional(rels.as_bytes()).unwrap();
+    let converted = String::from_utf8(converted).unwrap();
+    assert!(converted.contains(
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties"
+    ));
+    assert_eq!(to_strict(converted.as_bytes()).unwrap(), rels.as_bytes());
+}
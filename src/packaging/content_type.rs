@@ -2,13 +2,230 @@ use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
+use std::str::FromStr;
 
 use linked_hash_map::LinkedHashMap;
 use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
 
 use crate::error::OoxmlError;
 
-pub type ContentType = String;
+macro_rules! content_types {
+    ($($(#[$meta:meta])* $variant:ident => $mime:literal,)*) => {
+        /// Content type of a part, as declared in `[Content_Types].xml`.
+        ///
+        /// Unknown content types are kept in [`ContentType::Other`].
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum ContentType {
+            $($(#[$meta])* $variant,)*
+            /// Any other content type.
+            Other(String),
+        }
+
+        impl ContentType {
+            /// Parse from content type string.
+            pub fn from_mime_str(s: &str) -> Self {
+                match s {
+                    $($mime => ContentType::$variant,)*
+                    s => ContentType::Other(s.to_string()),
+                }
+            }
+
+            /// The content type string.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(ContentType::$variant => $mime,)*
+                    ContentType::Other(s) => s.as_str(),
+                }
+            }
+        }
+    };
+}
+
+content_types! {
+    // package
+    Relationships => "application/vnd.openxmlformats-package.relationships+xml",
+    CoreProperties => "application/vnd.openxmlformats-package.core-properties+xml",
+    DigitalSignatureOrigin => "application/vnd.openxmlformats-package.digital-signature-origin",
+    DigitalSignatureXmlSignature => "application/vnd.openxmlformats-package.digital-signature-xmlsignature+xml",
+    DigitalSignatureCertificate => "application/vnd.openxmlformats-package.digital-signature-certificate",
+    // shared
+    ExtendedProperties => "application/vnd.openxmlformats-officedocument.extended-properties+xml",
+    CustomProperties => "application/vnd.openxmlformats-officedocument.custom-properties+xml",
+    CustomXmlProperties => "application/vnd.openxmlformats-officedocument.customXmlProperties+xml",
+    Theme => "application/vnd.openxmlformats-officedocument.theme+xml",
+    ThemeOverride => "application/vnd.openxmlformats-officedocument.themeOverride+xml",
+    Drawing => "application/vnd.openxmlformats-officedocument.drawing+xml",
+    VmlDrawing => "application/vnd.openxmlformats-officedocument.vmlDrawing",
+    Chart => "application/vnd.openxmlformats-officedocument.drawingml.chart+xml",
+    ChartUserShapes => "application/vnd.openxmlformats-officedocument.drawingml.chartshapes+xml",
+    DiagramColors => "application/vnd.openxmlformats-officedocument.drawingml.diagramColors+xml",
+    DiagramData => "application/vnd.openxmlformats-officedocument.drawingml.diagramData+xml",
+    DiagramLayout => "application/vnd.openxmlformats-officedocument.drawingml.diagramLayout+xml",
+    DiagramStyle => "application/vnd.openxmlformats-officedocument.drawingml.diagramStyle+xml",
+    OleObject => "application/vnd.openxmlformats-officedocument.oleObject",
+    ObfuscatedFont => "application/vnd.openxmlformats-officedocument.obfuscatedFont",
+    Font => "application/x-fontdata",
+    ActiveX => "application/vnd.ms-office.activeX+xml",
+    VbaProject => "application/vnd.ms-office.vbaProject",
+    Xml => "application/xml",
+    Png => "image/png",
+    Jpeg => "image/jpeg",
+    Gif => "image/gif",
+    Bmp => "image/bmp",
+    Tiff => "image/tiff",
+    Emf => "image/x-emf",
+    Wmf => "image/x-wmf",
+    Svg => "image/svg+xml",
+    // spreadsheetml
+    SpreadsheetWorkbook => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml",
+    SpreadsheetTemplate => "application/vnd.openxmlformats-officedocument.spreadsheetml.template.main+xml",
+    SpreadsheetMacroEnabledWorkbook => "application/vnd.ms-excel.sheet.macroEnabled.main+xml",
+    SpreadsheetMacroEnabledTemplate => "application/vnd.ms-excel.template.macroEnabled.main+xml",
+    SpreadsheetAddIn => "application/vnd.ms-excel.addin.macroEnabled.main+xml",
+    Worksheet => "application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml",
+    Chartsheet => "application/vnd.openxmlformats-officedocument.spreadsheetml.chartsheet+xml",
+    Dialogsheet => "application/vnd.openxmlformats-officedocument.spreadsheetml.dialogsheet+xml",
+    SharedStrings => "application/vnd.openxmlformats-officedocument.spreadsheetml.sharedStrings+xml",
+    SpreadsheetStyles => "application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml",
+    CalculationChain => "application/vnd.openxmlformats-officedocument.spreadsheetml.calcChain+xml",
+    SpreadsheetComments => "application/vnd.openxmlformats-officedocument.spreadsheetml.comments+xml",
+    Table => "application/vnd.openxmlformats-officedocument.spreadsheetml.table+xml",
+    TableSingleCells => "application/vnd.openxmlformats-officedocument.spreadsheetml.tableSingleCells+xml",
+    PivotTable => "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotTable+xml",
+    PivotCacheDefinition => "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotCacheDefinition+xml",
+    PivotCacheRecords => "application/vnd.openxmlformats-officedocument.spreadsheetml.pivotCacheRecords+xml",
+    QueryTable => "application/vnd.openxmlformats-officedocument.spreadsheetml.queryTable+xml",
+    ExternalLink => "application/vnd.openxmlformats-officedocument.spreadsheetml.externalLink+xml",
+    Connections => "application/vnd.openxmlformats-officedocument.spreadsheetml.connections+xml",
+    RevisionHeaders => "application/vnd.openxmlformats-officedocument.spreadsheetml.revisionHeaders+xml",
+    RevisionLog => "application/vnd.openxmlformats-officedocument.spreadsheetml.revisionLog+xml",
+    UserNames => "application/vnd.openxmlformats-officedocument.spreadsheetml.userNames+xml",
+    VolatileDependencies => "application/vnd.openxmlformats-officedocument.spreadsheetml.volatileDependencies+xml",
+    SheetMetadata => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheetMetadata+xml",
+    SpreadsheetPrinterSettings => "application/vnd.openxmlformats-officedocument.spreadsheetml.printerSettings",
+    /// Microsoft Office extension: Excel 365 threaded comments.
+    ThreadedComments => "application/vnd.ms-excel.threadedcomments+xml",
+    /// Microsoft Office extension: persons of threaded comments.
+    Person => "application/vnd.ms-excel.person+xml",
+    // wordprocessingml
+    WordprocessingDocument => "application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml",
+    WordprocessingTemplate => "application/vnd.openxmlformats-officedocument.wordprocessingml.template.main+xml",
+    WordprocessingMacroEnabledDocument => "application/vnd.ms-word.document.macroEnabled.main+xml",
+    WordprocessingMacroEnabledTemplate => "application/vnd.ms-word.template.macroEnabledTemplate.main+xml",
+    WordprocessingStyles => "application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml",
+    WordprocessingComments => "application/vnd.openxmlformats-officedocument.wordprocessingml.comments+xml",
+    Numbering => "application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml",
+    Settings => "application/vnd.openxmlformats-officedocument.wordprocessingml.settings+xml",
+    FontTable => "application/vnd.openxmlformats-officedocument.wordprocessingml.fontTable+xml",
+    WebSettings => "application/vnd.openxmlformats-officedocument.wordprocessingml.webSettings+xml",
+    Footnotes => "application/vnd.openxmlformats-officedocument.wordprocessingml.footnotes+xml",
+    Endnotes => "application/vnd.openxmlformats-officedocument.wordprocessingml.endnotes+xml",
+    Header => "application/vnd.openxmlformats-officedocument.wordprocessingml.header+xml",
+    Footer => "application/vnd.openxmlformats-officedocument.wordprocessingml.footer+xml",
+    GlossaryDocument => "application/vnd.openxmlformats-officedocument.wordprocessingml.document.glossary+xml",
+    WordprocessingPrinterSettings => "application/vnd.openxmlformats-officedocument.wordprocessingml.printerSettings",
+    // presentationml
+    Presentation => "application/vnd.openxmlformats-officedocument.presentationml.presentation.main+xml",
+    PresentationTemplate => "application/vnd.openxmlformats-officedocument.presentationml.template.main+xml",
+    Slideshow => "application/vnd.openxmlformats-officedocument.presentationml.slideshow.main+xml",
+    PresentationMacroEnabled => "application/vnd.ms-powerpoint.presentation.macroEnabled.main+xml",
+    PresentationMacroEnabledTemplate => "application/vnd.ms-powerpoint.template.macroEnabled.main+xml",
+    SlideshowMacroEnabled => "application/vnd.ms-powerpoint.slideshow.macroEnabled.main+xml",
+    PresentationAddIn => "application/vnd.ms-powerpoint.addin.macroEnabled.main+xml",
+    Slide => "application/vnd.openxmlformats-officedocument.presentationml.slide+xml",
+    SlideLayout => "application/vnd.openxmlformats-officedocument.presentationml.slideLayout+xml",
+    SlideMaster => "application/vnd.openxmlformats-officedocument.presentationml.slideMaster+xml",
+    NotesSlide => "application/vnd.openxmlformats-officedocument.presentationml.notesSlide+xml",
+    NotesMaster => "application/vnd.openxmlformats-officedocument.presentationml.notesMaster+xml",
+    HandoutMaster => "application/vnd.openxmlformats-officedocument.presentationml.handoutMaster+xml",
+    PresentationProperties => "application/vnd.openxmlformats-officedocument.presentationml.presProps+xml",
+    ViewProperties => "application/vnd.openxmlformats-officedocument.presentationml.viewProps+xml",
+    TableStyles => "application/vnd.openxmlformats-officedocument.presentationml.tableStyles+xml",
+    CommentAuthors => "application/vnd.openxmlformats-officedocument.presentationml.commentAuthors+xml",
+    PresentationComments => "application/vnd.openxmlformats-officedocument.presentationml.comments+xml",
+    Tags => "application/vnd.openxmlformats-officedocument.presentationml.tags+xml",
+    SlideSyncData => "application/vnd.openxmlformats-officedocument.presentationml.slideUpdateInfo+xml",
+}
+
+impl ContentType {
+    /// Default content type of a file extension, used by `Default` entries.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let content_type = match extension.to_ascii_lowercase().as_str() {
+            "rels" => ContentType::Relationships,
+            "xml" => ContentType::Xml,
+            "vml" => ContentType::VmlDrawing,
+            "png" => ContentType::Png,
+            "jpg" | "jpeg" | "jpe" => ContentType::Jpeg,
+            "gif" => ContentType::Gif,
+            "bmp" => ContentType::Bmp,
+            "tif" | "tiff" => ContentType::Tiff,
+            "emf" => ContentType::Emf,
+            "wmf" => ContentType::Wmf,
+            "svg" => ContentType::Svg,
+            _ => return None,
+        };
+        Some(content_type)
+    }
+
+    /// Parse as a mime type, `None` if it's not a valid mime.
+    pub fn to_mime(&self) -> Option<mime::Mime> {
+        self.as_str().parse().ok()
+    }
+
+    /// Check if the content type is xml, eg. `application/xml` or `*+xml`.
+    pub fn is_xml(&self) -> bool {
+        let s = self.as_str();
+        s.ends_with("+xml") || s == "application/xml" || s == "text/xml"
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ContentType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from_mime_str(s))
+    }
+}
+
+impl From<&str> for ContentType {
+    fn from(s: &str) -> Self {
+        Self::from_mime_str(s)
+    }
+}
+
+impl From<String> for ContentType {
+    fn from(s: String) -> Self {
+        Self::from_mime_str(&s)
+    }
+}
+
+impl From<&ContentType> for ContentType {
+    fn from(s: &ContentType) -> Self {
+        s.clone()
+    }
+}
+
+impl serde::Serialize for ContentType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ContentType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from_mime_str(&s))
+    }
+}
 
 #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
@@ -155,7 +372,19 @@ impl ContentTypes {
 
     /// follow OpenXML SDK function definitions, but not implemented.
     pub fn add_content_type() {}
-    pub fn get_content_type() {}
+
+    /// Get content type of a part, the override entry first, then the default by extension.
+    pub fn get_content_type(&self, part_name: &str) -> Option<&ContentType> {
+        let part_name = format!("/{}", part_name.trim_start_matches('/'));
+        if let Some(content_type) = self.overrides.get(&part_name) {
+            return Some(content_type);
+        }
+        let (_, extension) = part_name.rsplit_once('.')?;
+        self.defaults
+            .iter()
+            .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+            .map(|(_, content_type)| content_type)
+    }
     pub fn delete_content_type(&mut self, _content_type: &ContentType) {}
 
     /// Save to file path.
@@ -196,7 +425,7 @@ impl ContentTypes {
                     },
                     Attribute {
                         key: CONTENT_TYPE_ATTRIBUTE_NAME.as_bytes(),
-                        value: value.as_bytes().into(),
+                        value: value.as_str().as_bytes().into(),
                     },
                 ]),
             ))?;
@@ -212,7 +441,7 @@ impl ContentTypes {
                     },
                     Attribute {
                         key: CONTENT_TYPE_ATTRIBUTE_NAME.as_bytes(),
-                        value: value.as_bytes().into(),
+                        value: value.as_str().as_bytes().into(),
                     },
                 ]),
             ))?;
@@ -231,6 +460,12 @@ impl ContentTypes {
         self.overrides.insert(part_name, content_type);
     }
 
+    /// Delete the override entry of a part, returns `true` if it exists.
+    pub fn delete_override_element(&mut self, part_name: &str) -> bool {
+        let part_name = format!("/{}", part_name.trim_start_matches('/'));
+        self.overrides.remove(&part_name).is_some()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.defaults.is_empty() && self.overrides.is_empty()
     }
@@ -245,3 +480,27 @@ fn test_de() {
     let display = format!("{}", content_types);
     assert_eq!(raw, display);
 }
+
+#[test]
+fn test_content_type_catalogue() {
+    let raw = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/><Override PartName="/xl/charts/style1.xml" ContentType="application/vnd.ms-office.chartstyle+xml"/></Types>"#;
+    let types = ContentTypes::parse_from_xml_str(raw);
+    assert_eq!(
+        types.get_content_type("xl/styles.xml"),
+        Some(&ContentType::SpreadsheetStyles)
+    );
+    assert_eq!(
+        types.get_content_type("xl/charts/style1.xml"),
+        Some(&ContentType::Other(
+            "application/vnd.ms-office.chartstyle+xml".into()
+        ))
+    );
+    assert_eq!(
+        types.get_content_type("xl/workbook.xml"),
+        Some(&ContentType::Xml)
+    );
+    assert_eq!(raw, format!("{}", types));
+    assert_eq!(ContentType::from_extension("PNG"), Some(ContentType::Png));
+    assert_eq!(ContentType::Png.to_mime().unwrap().type_(), mime::IMAGE);
+    assert!(ContentType::SharedStrings.is_xml());
+}
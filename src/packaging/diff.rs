@@ -0,0 +1,630 @@
+//! Compare two packages part by part.
+//!
+//! Xml parts are compared in a canonical form: attributes sorted by name, whitespace-only text
+//! between elements dropped and one tag per line, so that reformatting or reordering attributes
+//! does not count as a change.
+//!
+//! ```no_run
+//! use ooxml::packaging::OpenXmlPackage;
+//!
+//! let old = OpenXmlPackage::open("v1.xlsx").unwrap();
+//! let new = OpenXmlPackage::open("v2.xlsx").unwrap();
+//! let diff = old.diff(&new).unwrap();
+//! if !diff.is_empty() {
+//!     println!("{}", diff);
+//! }
+//! ```
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::error::OoxmlError;
+use crate::packaging::content_type::{ContentType, CONTENT_TYPES_FILE};
+use crate::packaging::package::OpenXmlPackage;
+use crate::packaging::relationship::{Relationship, Relationships, RELATIONSHIPS_FILE};
+use crate::packaging::signature::c14n::{self, Element, Node};
+
+/// Lines of context around changes in the text report.
+const CONTEXT_LINES: usize = 3;
+
+/// A line of a part diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+impl DiffLine {
+    pub fn is_change(&self) -> bool {
+        !matches!(self, DiffLine::Same(_))
+    }
+}
+
+impl fmt::Display for DiffLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffLine::Same(line) => write!(f, " {}", line),
+            DiffLine::Removed(line) => write!(f, "-{}", line),
+            DiffLine::Added(line) => write!(f, "+{}", line),
+        }
+    }
+}
+
+/// A part changed between the packages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartDiff {
+    pub uri: String,
+    /// Canonical xml line diff, `None` for binary parts.
+    pub lines: Option<Vec<DiffLine>>,
+}
+
+/// A part whose content type changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentTypeChange {
+    pub uri: String,
+    pub old: Option<ContentType>,
+    pub new: Option<ContentType>,
+}
+
+/// A relationship added, removed or changed, by its source part (empty for the package root).
+#[derive(Debug, Clone, PartialEq)]
+pub enum RelationshipChange {
+    Added {
+        source: String,
+        relationship: Relationship,
+    },
+    Removed {
+        source: String,
+        relationship: Relationship,
+    },
+    Changed {
+        source: String,
+        old: Relationship,
+        new: Relationship,
+    },
+}
+
+/// Differences between two packages.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PackageDiff {
+    /// Parts only in the new package.
+    pub added: Vec<String>,
+    /// Parts only in the old package.
+    pub removed: Vec<String>,
+    /// Parts in both packages with different content.
+    pub changed: Vec<PartDiff>,
+    pub content_types: Vec<ContentTypeChange>,
+    pub relationships: Vec<RelationshipChange>,
+}
+
+impl PackageDiff {
+    /// Compare the `old` package to the `new` one.
+    pub fn new(old: &OpenXmlPackage, new: &OpenXmlPackage) -> Result<Self, OoxmlError> {
+        let mut diff = PackageDiff::default();
+        let old_parts = part_names(old);
+        let new_parts = part_names(new);
+        diff.removed = old_parts.difference(&new_parts).cloned().collect();
+        diff.added = new_parts.difference(&old_parts).cloned().collect();
+        for uri in old_parts.union(&new_parts) {
+            let old_type = old_parts
+                .contains(uri)
+                .then(|| old.get_part_content_type(uri))
+                .flatten();
+            let new_type = new_parts
+                .contains(uri)
+                .then(|| new.get_part_content_type(uri))
+                .flatten();
+            if old_type != new_type {
+                diff.content_types.push(ContentTypeChange {
+                    uri: uri.clone(),
+                    old: old_type.cloned(),
+                    new: new_type.cloned(),
+                });
+            }
+        }
+        for uri in old_parts.intersection(&new_parts) {
+            let old_data = old.stored_entry_bytes(uri)?.unwrap_or_default();
+            let new_data = new.stored_entry_bytes(uri)?.unwrap_or_default();
+            if old_data == new_data {
+                continue;
+            }
+            let lines = if old.is_xml_entry(uri) && new.is_xml_entry(uri) {
+                match (canonical_lines(&old_data), canonical_lines(&new_data)) {
+                    (Some(a), Some(b)) if a == b => continue,
+                    (Some(a), Some(b)) => Some(diff_lines(&a, &b)),
+                    _ => None,
+                }
+            } else {
+                None
+            };
+            diff.changed.push(PartDiff {
+                uri: uri.clone(),
+                lines,
+            });
+        }
+
+        let sources: BTreeSet<String> = old
+            .entry_names()
+            .into_iter()
+            .chain(new.entry_names())
+            .filter_map(relationships_source)
+            .collect();
+        for source in sources {
+            let old_relationships = package_relationships(old, &source);
+            let new_relationships = package_relationships(new, &source);
+            for relationship in old_relationships.iter() {
+                match new_relationships.get_relationship_by_id(relationship.id()) {
+                    None => diff.relationships.push(RelationshipChange::Removed {
+                        source: source.clone(),
+                        relationship: relationship.clone(),
+                    }),
+                    Some(other) if other != relationship => {
+                        diff.relationships.push(RelationshipChange::Changed {
+                            source: source.clone(),
+                            old: relationship.clone(),
+                            new: other.clone(),
+                        })
+                    }
+                    _ => {}
+                }
+            }
+            for relationship in new_relationships.iter() {
+                if !old_relationships.contains(relationship.id()) {
+                    diff.relationships.push(RelationshipChange::Added {
+                        source: source.clone(),
+                        relationship: relationship.clone(),
+                    });
+                }
+            }
+        }
+        Ok(diff)
+    }
+
+    /// No differences at all.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.content_types.is_empty()
+            && self.relationships.is_empty()
+    }
+}
+
+fn write_relationship(f: &mut fmt::Formatter<'_>, relationship: &Relationship) -> fmt::Result {
+    write!(
+        f,
+        "{} {} -> {}",
+        relationship.id(),
+        relationship.r#type(),
+        relationship.target()
+    )?;
+    if let Some(mode) = relationship.target_mode() {
+        write!(f, " ({})", mode)?;
+    }
+    Ok(())
+}
+
+fn source_name(source: &str) -> &str {
+    if source.is_empty() {
+        "/"
+    } else {
+        source
+    }
+}
+
+/// A plain text report, like `diff -u` over canonical xml.
+impl fmt::Display for PackageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for uri in &self.added {
+            writeln!(f, "added: {}", uri)?;
+        }
+        for uri in &self.removed {
+            writeln!(f, "removed: {}", uri)?;
+        }
+        for change in &self.content_types {
+            let name =
+                |c: &Option<ContentType>| c.as_ref().map_or("none", |c| c.as_str()).to_string();
+            writeln!(
+                f,
+                "content type: {}: {} -> {}",
+                change.uri,
+                name(&change.old),
+                name(&change.new)
+            )?;
+        }
+        for change in &self.relationships {
+            match change {
+                RelationshipChange::Added {
+                    source,
+                    relationship,
+                } => {
+                    write!(f, "relationship added: {}: ", source_name(source))?;
+                    write_relationship(f, relationship)?;
+                }
+                RelationshipChange::Removed {
+                    source,
+                    relationship,
+                } => {
+                    write!(f, "relationship removed: {}: ", source_name(source))?;
+                    write_relationship(f, relationship)?;
+                }
+                RelationshipChange::Changed { source, old, new } => {
+                    write!(f, "relationship changed: {}: ", source_name(source))?;
+                    write_relationship(f, old)?;
+                    write!(f, " => ")?;
+                    write_relationship(f, new)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        for part in &self.changed {
+            let lines = match &part.lines {
+                Some(lines) => lines,
+                None => {
+                    writeln!(f, "binary part changed: {}", part.uri)?;
+                    continue;
+                }
+            };
+            writeln!(f, "--- a/{}", part.uri)?;
+            writeln!(f, "+++ b/{}", part.uri)?;
+            let mut near = vec![false; lines.len()];
+            for (i, _) in lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| line.is_change())
+            {
+                let end = (i + CONTEXT_LINES + 1).min(lines.len());
+                near[i.saturating_sub(CONTEXT_LINES)..end].fill(true);
+            }
+            let mut last = None;
+            for (i, line) in lines.iter().enumerate() {
+                if !near[i] {
+                    continue;
+                }
+                if last.is_some_and(|last| last + 1 != i) {
+                    writeln!(f, "@@")?;
+                }
+                writeln!(f, "{}", line)?;
+                last = Some(i);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OpenXmlPackage {
+    /// Compare to another package, `self` is the old one.
+    pub fn diff(&self, other: &OpenXmlPackage) -> Result<PackageDiff, OoxmlError> {
+        PackageDiff::new(self, other)
+    }
+}
+
+/// Parts of a package, relationships parts and content types are compared on their own.
+fn part_names(package: &OpenXmlPackage) -> BTreeSet<String> {
+    package
+        .entry_names()
+        .into_iter()
+        .filter(|name| !name.ends_with('/') && *name != CONTENT_TYPES_FILE)
+        .filter(|name| relationships_source(name).is_none())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Source part of a relationships part, eg. `xl/workbook.xml` for `xl/_rels/workbook.xml.rels`.
+fn relationships_source(uri: &str) -> Option<String> {
+    if uri == RELATIONSHIPS_FILE {
+        return Some(String::new());
+    }
+    let name = uri.strip_suffix(".rels")?;
+    match name.rsplit_once("_rels/")? {
+        ("", name) => Some(name.to_string()),
+        (dir, name) if dir.ends_with('/') => Some(format!("{}{}", dir, name)),
+        _ => None,
+    }
+}
+
+fn package_relationships(package: &OpenXmlPackage, source: &str) -> Relationships {
+    if source.is_empty() {
+        package.relationships().clone()
+    } else {
+        package.get_part_relationships(source).unwrap_or_default()
+    }
+}
+
+/// Render xml as canonical lines, `None` if it's not well-formed.
+fn canonical_lines(data: &[u8]) -> Option<Vec<String>> {
+    let root = c14n::parse(data).ok()?;
+    let mut lines = Vec::new();
+    write_lines(&root, 0, &mut lines);
+    Some(lines)
+}
+
+fn start_tag(element: &Element) -> String {
+    let mut attributes: Vec<&(String, String)> = element.attributes.iter().collect();
+    attributes.sort();
+    let mut tag = format!("<{}", element.name);
+    for (key, value) in attributes {
+        tag.push(' ');
+        tag.push_str(key);
+        tag.push_str("=\"");
+        c14n::escape_attribute(value, &mut tag);
+        tag.push('"');
+    }
+    tag
+}
+
+fn write_lines(element: &Element, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    let tag = start_tag(element);
+    let children: Vec<&Node> = element
+        .children
+        .iter()
+        .filter(|node| !matches!(node, Node::Text(text) if text.trim().is_empty()))
+        .collect();
+    match children.as_slice() {
+        [] => lines.push(format!("{}{}/>", indent, tag)),
+        [Node::Text(text)] => {
+            let mut line = format!("{}{}>", indent, tag);
+            c14n::escape_text(text, &mut line);
+            line.push_str(&format!("</{}>", element.name));
+            lines.push(line);
+        }
+        children => {
+            lines.push(format!("{}{}>", indent, tag));
+            let inner = "  ".repeat(depth + 1);
+            for child in children {
+                match child {
+                    Node::Element(child) => write_lines(child, depth + 1, lines),
+                    Node::Text(text) => {
+                        let mut line = inner.clone();
+                        c14n::escape_text(text, &mut line);
+                        lines.push(line);
+                    }
+                    Node::Comment(comment) => lines.push(format!("{}<!--{}-->", inner, comment)),
+                    Node::ProcessingInstruction(pi) => lines.push(format!("{}<?{}?>", inner, pi)),
+                }
+            }
+            lines.push(format!("{}</{}>", indent, element.name));
+        }
+    }
+}
+
+/// Shortest line edit script with the linear space Myers algorithm, which splits the lines
+/// at the middle snake of an edit path and diffs both halves, keeping memory in O(n + m).
+fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffLine> {
+    let max = (old.len() + new.len()).div_ceil(2) + 1;
+    let mut forward = Diagonals::new(max);
+    let mut backward = Diagonals::new(max);
+    let mut script = Vec::with_capacity(old.len().max(new.len()));
+    diff_range(old, new, &mut forward, &mut backward, &mut script);
+    script
+}
+
+/// Furthest x of edit paths by diagonal `k = x - y`, for `k` in `-max..=max`.
+struct Diagonals {
+    offset: isize,
+    v: Vec<usize>,
+}
+
+impl Diagonals {
+    fn new(max: usize) -> Self {
+        Self {
+            offset: max as isize + 1,
+            v: vec![0; 2 * max + 3],
+        }
+    }
+}
+
+impl std::ops::Index<isize> for Diagonals {
+    type Output = usize;
+
+    fn index(&self, k: isize) -> &usize {
+        &self.v[(k + self.offset) as usize]
+    }
+}
+
+impl std::ops::IndexMut<isize> for Diagonals {
+    fn index_mut(&mut self, k: isize) -> &mut usize {
+        &mut self.v[(k + self.offset) as usize]
+    }
+}
+
+fn diff_range(
+    old: &[String],
+    new: &[String],
+    forward: &mut Diagonals,
+    backward: &mut Diagonals,
+    script: &mut Vec<DiffLine>,
+) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    script.extend(old[..prefix].iter().cloned().map(DiffLine::Same));
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old, common) = old.split_at(old.len() - suffix);
+    let new = &new[..new.len() - suffix];
+    if old.is_empty() {
+        script.extend(new.iter().cloned().map(DiffLine::Added));
+    } else if new.is_empty() {
+        script.extend(old.iter().cloned().map(DiffLine::Removed));
+    } else {
+        let (x, y) = middle_snake(old, new, forward, backward);
+        diff_range(&old[..x], &new[..y], forward, backward, script);
+        diff_range(&old[x..], &new[y..], forward, backward, script);
+    }
+    script.extend(common.iter().cloned().map(DiffLine::Same));
+}
+
+/// A point on the middle snake of a shortest edit path of lines without common prefix and
+/// suffix, it's neither the start nor the end of the path.
+fn middle_snake(
+    old: &[String],
+    new: &[String],
+    forward: &mut Diagonals,
+    backward: &mut Diagonals,
+) -> (usize, usize) {
+    let (n, m) = (old.len(), new.len());
+    let delta = n as isize - m as isize;
+    let odd = delta & 1 == 1;
+    forward[1] = 0;
+    backward[1] = 0;
+    let max = (n + m).div_ceil(2) as isize;
+    for d in 0..=max {
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && forward[k - 1] < forward[k + 1]) {
+                forward[k + 1]
+            } else {
+                forward[k - 1] + 1
+            };
+            let mut y = (x as isize - k) as usize;
+            let start = (x, y);
+            while x < n && y < m && old[x] == new[y] {
+                x += 1;
+                y += 1;
+            }
+            forward[k] = x;
+            if odd && (k - delta).abs() < d && forward[k] + backward[delta - k] >= n {
+                return start;
+            }
+            k += 2;
+        }
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && backward[k - 1] < backward[k + 1]) {
+                backward[k + 1]
+            } else {
+                backward[k - 1] + 1
+            };
+            let mut y = (x as isize - k) as usize;
+            while x < n && y < m && old[n - x - 1] == new[m - y - 1] {
+                x += 1;
+                y += 1;
+            }
+            backward[k] = x;
+            if !odd && (k - delta).abs() <= d && backward[k] + forward[delta - k] >= n {
+                return (n - x, m - y);
+            }
+            k += 2;
+        }
+    }
+    unreachable!("edit paths meet within (n + m) / 2 steps")
+}
+
+#[test]
+fn canonical_xml_lines() {
+    let a = br#"<?xml version="1.0"?>
+<root b="2" a="1">
+    <item>text</item>
+    <empty></empty>
+</root>"#;
+    let b = br#"<root a="1" b="2"><item>text</item><empty/></root>"#;
+    assert_eq!(canonical_lines(a), canonical_lines(b));
+    assert_eq!(
+        canonical_lines(b).unwrap(),
+        vec![
+            r#"<root a="1" b="2">"#,
+            "  <item>text</item>",
+            "  <empty/>",
+            "</root>"
+        ]
+    );
+
+    let old: Vec<String> = ["a", "b", "c", "d"].iter().map(|s| s.to_string()).collect();
+    let new: Vec<String> = ["a", "c", "d", "e"].iter().map(|s| s.to_string()).collect();
+    assert_eq!(
+        diff_lines(&old, &new),
+        vec![
+            DiffLine::Same("a".into()),
+            DiffLine::Removed("b".into()),
+            DiffLine::Same("c".into()),
+            DiffLine::Same("d".into()),
+            DiffLine::Added("e".into()),
+        ]
+    );
+    // every third line changed, edit scripts keep both sides in order
+    let old: Vec<String> = (0..3000).map(|i| i.to_string()).collect();
+    let new: Vec<String> = (0..3000)
+        .map(|i| {
+            if i % 3 == 0 {
+                format!("{}'", i)
+            } else {
+                i.to_string()
+            }
+        })
+        .collect();
+    let script = diff_lines(&old, &new);
+    assert_eq!(script.iter().filter(|l| l.is_change()).count(), 2000);
+    let side = |added: bool| -> Vec<String> {
+        script
+            .iter()
+            .filter_map(|line| match line {
+                DiffLine::Same(s) => Some(s.clone()),
+                DiffLine::Added(s) if added => Some(s.clone()),
+                DiffLine::Removed(s) if !added => Some(s.clone()),
+                _ => None,
+            })
+            .collect()
+    };
+    assert_eq!(side(false), old);
+    assert_eq!(side(true), new);
+
+    assert_eq!(relationships_source("_rels/.rels").as_deref(), Some(""));
+    assert_eq!(
+        relationships_source("xl/_rels/workbook.xml.rels").as_deref(),
+        Some("xl/workbook.xml")
+    );
+}
+
+#[test]
+fn package_diff() {
+    let old = OpenXmlPackage::open("examples/excel-demo/demo.xlsx").unwrap();
+    let mut new = old.clone();
+    assert!(old.diff(&new).unwrap().is_empty());
+
+    // reformatting is not a change
+    let workbook = new.stored_entry_bytes("xl/workbook.xml").unwrap().unwrap();
+    let reformatted = String::from_utf8(workbook).unwrap().replace("><", ">\n  <");
+    new.set_part_data("xl/workbook.xml", reformatted);
+    assert!(old.diff(&new).unwrap().is_empty());
+
+    new.create_part_core_with_data("docProps/thumbnail.png", &ContentType::Png, b"\x89PNG")
+        .unwrap();
+    new.create_relationship(
+        crate::packaging::relationship::RelationshipType::Thumbnail,
+        "docProps/thumbnail.png",
+    );
+    let styles = new.stored_entry_bytes("xl/styles.xml").unwrap().unwrap();
+    let styles = String::from_utf8(styles)
+        .unwrap()
+        .replacen("<numFmts", "<numFmts x=\"1\"", 1);
+    new.set_part_data("xl/styles.xml", styles);
+
+    let diff = old.diff(&new).unwrap();
+    assert_eq!(diff.added, vec!["docProps/thumbnail.png".to_string()]);
+    assert!(diff.removed.is_empty());
+    assert_eq!(
+        diff.content_types,
+        vec![ContentTypeChange {
+            uri: "docProps/thumbnail.png".into(),
+            old: None,
+            new: Some(ContentType::Png),
+        }]
+    );
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(diff.changed[0].uri, "xl/styles.xml");
+    let lines = diff.changed[0].lines.as_ref().unwrap();
+    assert_eq!(lines.iter().filter(|l| l.is_change()).count(), 2);
+    assert!(matches!(
+        &diff.relationships[..],
+        [RelationshipChange::Added { source, .. }] if source.is_empty()
+    ));
+    let report = diff.to_string();
+    assert!(report.contains("added: docProps/thumbnail.png"));
+    assert!(report.contains("content type: docProps/thumbnail.png: none -> image/png"));
+    assert!(report.contains("+++ b/xl/styles.xml"));
+}
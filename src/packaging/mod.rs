@@ -1,12 +1,15 @@
 pub mod app_property;
+pub mod conformance;
 pub mod content_type;
 pub mod custom_property;
+pub mod diff;
 pub mod element;
 pub mod namespace;
 pub mod package;
 pub mod part;
 pub mod property;
 pub mod relationship;
+pub mod signature;
 pub mod variant;
 // pub mod xml;
 // pub mod zip;
@@ -1,15 +1,20 @@
 use crate::error::OoxmlError;
 use crate::packaging::app_property::AppProperties;
+use crate::packaging::conformance::{self, Conformance};
 use crate::packaging::content_type::{ContentType, ContentTypes};
 use crate::packaging::custom_property::CustomProperties;
 use crate::packaging::element::*;
-use crate::packaging::part::OpenXmlPart;
+use crate::packaging::part::{OpenXmlPart, PartRegistry, TypedPart};
 use crate::packaging::property::Properties;
 pub use crate::packaging::relationship::Relationships;
 
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::prelude::*;
+use std::io::Cursor;
 use std::path::Path;
+use std::sync::Arc;
 
 use zip::ZipArchive;
 
@@ -19,19 +24,43 @@ use crate::packaging::app_property::APP_PROPERTIES_URI;
 use crate::packaging::content_type::CONTENT_TYPES_FILE;
 use crate::packaging::custom_property::CUSTOM_PROPERTIES_URI;
 use crate::packaging::property::CORE_PROPERTIES_URI;
-use crate::packaging::relationship::RELATIONSHIPS_FILE;
+use crate::packaging::relationship::{
+    relationships_uri, resolve_target, Relationship, RelationshipType, RELATIONSHIPS_FILE,
+};
 
 /// A common OpenXML package manager, compatible with any [OpenXML Package Convertion]()
 ///
-/// FIXME(@zitsen): A dirty dict representing data change should be added.
+/// Packages opened from zip keep the source archive, entries not changed since loading are
+/// copied to the output as raw compressed data, see [`OpenXmlPackage::is_dirty`].
+///
+/// Open and save without changes is lossless: every part, relationship and content type is
+/// kept, entries are written in the source order and the archive comment is preserved.
+/// Per-entry zip comments are not kept.
+///
+/// Strict packages are normalized to transitional namespaces when loading, see
+/// [`OpenXmlPackage::conformance`].
 #[derive(Debug, Clone, Default)]
 pub struct OpenXmlPackage {
     content_types: ContentTypes,
     relationships: Relationships,
-    app_properties: AppProperties,
+    app_properties: Option<AppProperties>,
     properties: Properties,
     custom_properties: Option<CustomProperties>,
     parts: LinkedHashMap<String, OpenXmlPart>,
+    /// Parts parsed into user-defined types, see [`PartRegistry`].
+    typed_parts: LinkedHashMap<String, Box<dyn TypedPart>>,
+    /// The source zip archive, if opened from zip.
+    ///
+    /// Clones share the archive data and the parsed central directory.
+    source: Option<ZipArchive<Cursor<Arc<[u8]>>>>,
+    /// Entry names in loading order, directory entries end with `/`.
+    entries: Vec<String>,
+    /// Zip archive comment.
+    comment: Vec<u8>,
+    /// Entry names changed since loading.
+    dirty: HashSet<String>,
+    /// Conformance class to write xml parts in.
+    conformance: Conformance,
 }
 
 impl OpenXmlPackage {
@@ -41,55 +70,194 @@ impl OpenXmlPackage {
         Self::from_reader(file)
     }
     /// Parse OpenXML package from reader.
-    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self, OoxmlError> {
-        let mut zip = ZipArchive::new(reader)?;
-        let mut package = OpenXmlPackage::default();
-        let mut content_types_id = None;
+    pub fn from_reader<R: Read + Seek>(mut reader: R) -> Result<Self, OoxmlError> {
+        let mut source = Vec::new();
+        reader.read_to_end(&mut source)?;
+        let mut zip = ZipArchive::new(Cursor::new(Arc::from(source)))?;
+        let mut package = OpenXmlPackage {
+            comment: zip.comment().to_vec(),
+            ..Default::default()
+        };
         for i in 0..zip.len() {
             let mut file = zip.by_index(i)?;
-            // skip directory entries seems ok.
+            let filename = file.name().to_string();
+            // keep directory entries for the entry order only.
             if file.is_dir() {
+                package.entries.push(filename);
                 continue;
             }
-            let filename = file.name().to_string();
-            if filename == CONTENT_TYPES_FILE {
-                content_types_id = Some(i);
-                let mut xml = String::new();
-                file.read_to_string(&mut xml)?;
-                package.content_types = ContentTypes::parse_from_xml_str(&xml);
-                continue;
-            } else if filename == RELATIONSHIPS_FILE {
-                let mut xml = String::new();
-                file.read_to_string(&mut xml)?;
-                package.relationships = Relationships::parse_from_xml_str(&xml);
-                continue;
-            } else if filename == CORE_PROPERTIES_URI {
-                let mut xml = String::new();
-                file.read_to_string(&mut xml)?;
-                package.properties = Properties::parse_from_xml_str(&xml);
-                continue;
-            } else if filename == CUSTOM_PROPERTIES_URI {
-                let mut xml = String::new();
-                file.read_to_string(&mut xml)?;
-                package.custom_properties = Some(CustomProperties::parse_from_xml_str(&xml));
-            } else if filename == APP_PROPERTIES_URI {
-                let mut xml = String::new();
-                file.read_to_string(&mut xml)?;
-                package.app_properties = OpenXmlDeserialize::from_xml_str(&xml)?;
-            } else {
-                let uri = std::path::PathBuf::from(&filename);
-                let part = OpenXmlPart::from_reader(uri, &mut file)?;
-                package.parts.insert(filename, part);
-            }
-        }
-        if content_types_id.is_none() {
-            return Err(OoxmlError::PackageContentTypeError);
+            package.load_entry(filename, &mut file)?;
         }
-        assert!(package.has_content_types());
-        assert!(package.has_relationships());
+        package.source = Some(zip);
+        package.check()
+    }
+
+    /// Open a OpenXML file path, parse parts registered in `registry` into their types.
+    pub fn open_with_registry<P: AsRef<Path>>(
+        path: P,
+        registry: &PartRegistry,
+    ) -> Result<Self, OoxmlError> {
+        let mut package = Self::open(path)?;
+        package.parse_typed_parts(registry)?;
         Ok(package)
     }
 
+    /// Parse OpenXML package from reader, parse parts registered in `registry` into their types.
+    pub fn from_reader_with_registry<R: Read + Seek>(
+        reader: R,
+        registry: &PartRegistry,
+    ) -> Result<Self, OoxmlError> {
+        let mut package = Self::from_reader(reader)?;
+        package.parse_typed_parts(registry)?;
+        Ok(package)
+    }
+
+    /// Parse loaded parts with the types registered by content type or relationship type.
+    pub fn parse_typed_parts(&mut self, registry: &PartRegistry) -> Result<(), OoxmlError> {
+        if registry.is_empty() {
+            return Ok(());
+        }
+        // relationship types targeting each internal part.
+        let mut targeted: std::collections::HashMap<String, Vec<RelationshipType>> =
+            Default::default();
+        let mut add_targets = |source: &str, relationships: &Relationships| {
+            for relationship in relationships.iter().filter(|r| !r.is_external()) {
+                targeted
+                    .entry(resolve_target(source, relationship.target()))
+                    .or_default()
+                    .push(relationship.relationship_type());
+            }
+        };
+        add_targets("", &self.relationships);
+        for (name, part) in self.parts.iter() {
+            if let Some((dir, file)) = name.rsplit_once("_rels/") {
+                if let Some(source) = file.strip_suffix(".rels") {
+                    let relationships = Relationships::parse_from_xml_reader(part.as_part_bytes());
+                    add_targets(&format!("{}{}", dir, source), &relationships);
+                }
+            }
+        }
+        for (name, part) in self.parts.iter() {
+            let relationship_types = targeted.get(name).map(Vec::as_slice).unwrap_or_default();
+            let content_type = self.content_types.get_content_type(name);
+            if let Some(typed) =
+                registry.parse(content_type, relationship_types, part.as_part_bytes())
+            {
+                self.typed_parts.insert(name.clone(), typed?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Open an unzipped package directory, which contains `[Content_Types].xml` and `_rels`.
+    ///
+    /// Files are loaded as zip entries named by their path relative to the directory.
+    pub fn open_dir<P: AsRef<Path>>(path: P) -> Result<Self, OoxmlError> {
+        fn walk(dir: &Path, files: &mut Vec<std::path::PathBuf>) -> std::io::Result<()> {
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    walk(&path, files)?;
+                } else {
+                    files.push(path);
+                }
+            }
+            Ok(())
+        }
+        let root = path.as_ref();
+        let mut files = Vec::new();
+        walk(root, &mut files)?;
+        files.sort();
+
+        let mut package = OpenXmlPackage::default();
+        for file in files {
+            let filename = file
+                .strip_prefix(root)
+                .expect("walk in package directory")
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            package.load_entry(filename, File::open(&file)?)?;
+        }
+        package.check()
+    }
+
+    /// Load a zip entry or a file of unzipped package.
+    fn load_entry<R: Read>(&mut self, filename: String, mut reader: R) -> Result<(), OoxmlError> {
+        self.entries.push(filename.clone());
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if self.is_xml_entry(&filename) {
+            if let Some(transitional) = conformance::to_transitional(&data) {
+                self.conformance = Conformance::Strict;
+                data = transitional;
+            }
+        }
+        let xml = || String::from_utf8_lossy(&data);
+        if filename == CONTENT_TYPES_FILE {
+            self.content_types = ContentTypes::parse_from_xml_str(&xml());
+        } else if filename == RELATIONSHIPS_FILE {
+            self.relationships = Relationships::parse_from_xml_str(&xml());
+        } else if filename == CORE_PROPERTIES_URI {
+            self.properties = Properties::parse_from_xml_str(&xml());
+        } else if filename == CUSTOM_PROPERTIES_URI {
+            self.custom_properties = Some(CustomProperties::parse_from_xml_str(&xml()));
+        } else if filename == APP_PROPERTIES_URI {
+            self.app_properties = Some(OpenXmlDeserialize::from_xml_str(&xml())?);
+        } else {
+            let uri = std::path::PathBuf::from(&filename);
+            let part = OpenXmlPart::from_reader(uri, data.as_slice())?;
+            self.parts.insert(filename, part);
+        }
+        Ok(())
+    }
+
+    /// Xml entries are normalized by conformance, by the extension or the content type.
+    pub(crate) fn is_xml_entry(&self, name: &str) -> bool {
+        let extension = name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase());
+        matches!(
+            extension.as_deref(),
+            Some("xml") | Some("rels") | Some("vml")
+        ) || self
+            .content_types
+            .get_content_type(name)
+            .is_some_and(|content_type| content_type.is_xml())
+    }
+
+    /// Conformance class of the package, it's strict if any part used strict uris when loading.
+    pub fn conformance(&self) -> Conformance {
+        self.conformance
+    }
+
+    /// Change the conformance class to save in, all the xml entries will be written again.
+    pub fn set_conformance(&mut self, conformance: Conformance) {
+        if self.conformance == conformance {
+            return;
+        }
+        self.conformance = conformance;
+        let names: Vec<String> = self
+            .entry_names()
+            .into_iter()
+            .filter(|name| self.is_xml_entry(name))
+            .map(|name| name.to_string())
+            .collect();
+        self.dirty.extend(names);
+    }
+
+    /// Check the required components after loading.
+    fn check(self) -> Result<Self, OoxmlError> {
+        if !self.has_content_types() {
+            return Err(OoxmlError::PackageContentTypeError);
+        }
+        if !self.has_relationships() {
+            return Err(OoxmlError::PackageRelationshipError);
+        }
+        Ok(self)
+    }
+
     /// Save as file, write zip package for office document.
     pub fn save_as<P: AsRef<Path>>(&self, path: P) -> Result<(), OoxmlError> {
         let mut file = File::create(path)?;
@@ -97,28 +265,159 @@ impl OpenXmlPackage {
         Ok(())
     }
 
+    /// Write zip package, unchanged entries are raw copied from the source archive with their
+    /// original compression method and CRC, changed entries are deflated.
     pub fn write<W: Write + Seek>(&self, writer: W) -> Result<(), OoxmlError> {
         let mut zip = zip::ZipWriter::new(writer);
         let options =
-            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
-        zip.start_file(CONTENT_TYPES_FILE, options)?;
-        self.content_types.write(&mut zip)?;
-        zip.start_file(RELATIONSHIPS_FILE, options)?;
-        self.relationships.write(&mut zip)?;
-        zip.start_file(CORE_PROPERTIES_URI, options)?;
-        self.properties.write(&mut zip)?;
-        if let Some(custom_properties) = &self.custom_properties {
-            zip.start_file(CORE_PROPERTIES_URI, options)?;
-            custom_properties.write(&mut zip)?;
-        }
-        for (path, part) in self.parts.iter() {
-            zip.start_file(path, options)?;
-            zip.write(part.as_part_bytes())?;
-        }
-        zip.flush()?;
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        let mut source = self.source.clone();
+        for name in self.entry_names() {
+            if !self.is_dirty(name) {
+                if let Some(Ok(file)) = source.as_mut().map(|source| source.by_name(name)) {
+                    zip.raw_copy_file(file)?;
+                    continue;
+                }
+            }
+            if name.ends_with('/') {
+                zip.add_directory(name, options)?;
+                continue;
+            }
+            zip.start_file(name, options)?;
+            zip.write_all(&self.entry_bytes(name)?)?;
+        }
+        if !self.comment.is_empty() {
+            zip.set_comment(String::from_utf8_lossy(&self.comment));
+        }
+        zip.finish()?;
         Ok(())
     }
 
+    /// Save as an unzipped package directory, the counterpart of [`OpenXmlPackage::open_dir`].
+    pub fn save_to_dir<P: AsRef<Path>>(&self, path: P) -> Result<(), OoxmlError> {
+        let root = path.as_ref();
+        for name in self.entry_names() {
+            let path = root.join(name);
+            if name.ends_with('/') {
+                std::fs::create_dir_all(path)?;
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, self.entry_bytes(name)?)?;
+        }
+        Ok(())
+    }
+
+    /// Names of all the package entries in writing order.
+    ///
+    /// Entries keep the loading order, the required or newly created ones are added.
+    pub(crate) fn entry_names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        for required in [CONTENT_TYPES_FILE, RELATIONSHIPS_FILE] {
+            if !self.entries.iter().any(|name| name == required) {
+                names.push(required);
+            }
+        }
+        names.extend(
+            self.entries
+                .iter()
+                .map(|name| name.as_str())
+                .filter(|name| self.has_entry(name)),
+        );
+        if !self.entries.iter().any(|name| name == CORE_PROPERTIES_URI)
+            && self.is_dirty(CORE_PROPERTIES_URI)
+        {
+            names.push(CORE_PROPERTIES_URI);
+        }
+        names
+    }
+
+    /// Entry data as it will be written, unchanged entries are read from the source archive.
+    ///
+    /// Returns `None` if there's no such entry.
+    pub(crate) fn stored_entry_bytes(&self, name: &str) -> Result<Option<Vec<u8>>, OoxmlError> {
+        let written = match name {
+            name if name.ends_with('/') => false,
+            CORE_PROPERTIES_URI => {
+                self.is_dirty(name) || self.entries.iter().any(|entry| entry == name)
+            }
+            name => self.has_entry(name),
+        };
+        if !written {
+            return Ok(None);
+        }
+        if let (false, Some(source)) = (self.is_dirty(name), &self.source) {
+            let mut zip = source.clone();
+            let mut data = Vec::new();
+            let found = match zip.by_name(name) {
+                Ok(mut file) => file.read_to_end(&mut data).map(|_| true)?,
+                Err(_) => false,
+            };
+            if found {
+                return Ok(Some(data));
+            }
+        }
+        Ok(Some(self.entry_bytes(name)?.into_owned()))
+    }
+
+    /// Check if the entry exists in the package.
+    fn has_entry(&self, name: &str) -> bool {
+        match name {
+            name if name.ends_with('/') => true,
+            CONTENT_TYPES_FILE | RELATIONSHIPS_FILE | CORE_PROPERTIES_URI => true,
+            APP_PROPERTIES_URI => self.app_properties.is_some(),
+            CUSTOM_PROPERTIES_URI => self.custom_properties.is_some(),
+            name => self.parts.contains_key(name),
+        }
+    }
+
+    /// Serialize a package entry by name.
+    fn entry_bytes(&self, name: &str) -> Result<Cow<'_, [u8]>, OoxmlError> {
+        let data = self.transitional_entry_bytes(name)?;
+        if self.conformance.is_strict() && self.is_xml_entry(name) {
+            if let Some(strict) = conformance::to_strict(&data) {
+                return Ok(Cow::Owned(strict));
+            }
+        }
+        Ok(data)
+    }
+
+    /// Serialize a package entry by name in transitional form.
+    fn transitional_entry_bytes(&self, name: &str) -> Result<Cow<'_, [u8]>, OoxmlError> {
+        let mut buffer = Vec::new();
+        match name {
+            CONTENT_TYPES_FILE => self.content_types.write(&mut buffer)?,
+            RELATIONSHIPS_FILE => self.relationships.write(&mut buffer)?,
+            CORE_PROPERTIES_URI => self.properties.write(&mut buffer)?,
+            APP_PROPERTIES_URI if self.app_properties.is_some() => {
+                buffer = self
+                    .app_properties
+                    .as_ref()
+                    .expect("checked app properties")
+                    .to_xml_bytes()?
+            }
+            CUSTOM_PROPERTIES_URI if self.custom_properties.is_some() => self
+                .custom_properties
+                .as_ref()
+                .expect("checked custom properties")
+                .write(&mut buffer)?,
+            name if self.typed_parts.contains_key(name) => {
+                buffer = self.typed_parts[name].to_part_bytes()?;
+            }
+            name => {
+                return Ok(Cow::Borrowed(
+                    self.parts
+                        .get(name)
+                        .map(|part| part.as_part_bytes())
+                        .unwrap_or_default(),
+                ))
+            }
+        }
+        Ok(Cow::Owned(buffer))
+    }
+
     pub fn has_content_types(&self) -> bool {
         !self.content_types.is_empty()
     }
@@ -127,9 +426,36 @@ impl OpenXmlPackage {
         !self.relationships.is_empty()
     }
 
-    /// A part is dirty if data has been changed.
-    pub fn is_dirty(&self, _uri: &str) -> bool {
-        unimplemented!()
+    /// Package-level relationships, aka. `_rels/.rels`.
+    pub fn relationships(&self) -> &Relationships {
+        &self.relationships
+    }
+
+    /// Content types of the package, aka. `[Content_Types].xml`.
+    pub fn content_types(&self) -> &ContentTypes {
+        &self.content_types
+    }
+
+    /// Get the main document part uri by the root `officeDocument` relationship.
+    pub fn main_part_uri(&self) -> Option<String> {
+        self.relationships
+            .get_relationship_by_type(&RelationshipType::OfficeDocument)
+            .map(|r| resolve_target("", r.target()))
+    }
+
+    /// Get the content type of a part by uri.
+    pub fn get_part_content_type(&self, uri: &str) -> Option<&ContentType> {
+        self.content_types.get_content_type(uri)
+    }
+
+    /// A part is dirty if data has been changed since loading.
+    pub fn is_dirty(&self, uri: &str) -> bool {
+        self.dirty.contains(uri.trim_start_matches('/'))
+    }
+
+    /// Mark a part as changed, so it will be serialized again when saving.
+    pub fn mark_dirty(&mut self, uri: &str) {
+        self.dirty.insert(uri.trim_start_matches('/').to_string());
     }
 
     /// Get OpenXML `Part` by uri.
@@ -137,17 +463,158 @@ impl OpenXmlPackage {
         self.parts.get(uri)
     }
 
+    /// Get mutable OpenXML `Part` by uri, the part is marked dirty.
+    ///
+    /// The raw data wins over the typed part: a typed part of the uri is dropped, call
+    /// [`OpenXmlPackage::flush`] before to keep its changes.
+    pub fn get_part_mut(&mut self, uri: &str) -> Option<&mut OpenXmlPart> {
+        if self.parts.contains_key(uri) {
+            self.typed_parts.remove(uri);
+            self.mark_dirty(uri);
+        }
+        self.parts.get_mut(uri)
+    }
+
+    /// Replace the data of an existing part, returns `false` if the part does not exist.
+    pub fn set_part_data(&mut self, uri: &str, data: impl Into<Vec<u8>>) -> bool {
+        match self.get_part_mut(uri) {
+            Some(part) => {
+                part.set_part_bytes(data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get a part parsed into the registered type `T`.
+    pub fn typed_part<T: TypedPart>(&self, uri: &str) -> Option<&T> {
+        self.typed_parts
+            .get(uri)
+            .and_then(|part| part.as_any().downcast_ref())
+    }
+
+    /// Get a mutable typed part, the part is marked dirty and serialized again when saving.
+    pub fn typed_part_mut<T: TypedPart>(&mut self, uri: &str) -> Option<&mut T> {
+        if self.typed_parts.contains_key(uri) {
+            self.mark_dirty(uri);
+        }
+        self.typed_parts
+            .get_mut(uri)
+            .and_then(|part| part.as_any_mut().downcast_mut())
+    }
+
+    /// Add a new typed part, or replace the existing one.
+    pub fn insert_typed_part<T: TypedPart>(
+        &mut self,
+        uri: &str,
+        content_type: &ContentType,
+        part: T,
+    ) -> Result<(), OoxmlError> {
+        self.create_part_core_with_data(uri, content_type, &part.to_part_bytes()?)?;
+        self.typed_parts.insert(uri.into(), Box::new(part));
+        Ok(())
+    }
+
+    /// Core properties, aka. `docProps/core.xml`.
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+
+    /// Mutable core properties, the part is marked dirty.
+    pub fn properties_mut(&mut self) -> &mut Properties {
+        self.mark_dirty(CORE_PROPERTIES_URI);
+        &mut self.properties
+    }
+
+    /// Extended (application) properties, aka. `docProps/app.xml`.
+    pub fn app_properties(&self) -> Option<&AppProperties> {
+        self.app_properties.as_ref()
+    }
+
+    /// Mutable extended properties, the part is marked dirty.
+    pub fn app_properties_mut(&mut self) -> Option<&mut AppProperties> {
+        self.mark_dirty(APP_PROPERTIES_URI);
+        self.app_properties.as_mut()
+    }
+
+    /// Custom properties, aka. `docProps/custom.xml`.
+    pub fn custom_properties(&self) -> Option<&CustomProperties> {
+        self.custom_properties.as_ref()
+    }
+
+    /// Mutable custom properties, the part is marked dirty.
+    pub fn custom_properties_mut(&mut self) -> Option<&mut CustomProperties> {
+        self.mark_dirty(CUSTOM_PROPERTIES_URI);
+        self.custom_properties.as_mut()
+    }
+
     pub fn create_part() {}
 
-    pub fn flush() {}
+    /// Serialize the changed typed parts to their raw part data.
+    pub fn flush(&mut self) -> Result<(), OoxmlError> {
+        for (name, typed) in self.typed_parts.iter() {
+            if self.dirty.contains(name) {
+                if let Some(part) = self.parts.get_mut(name) {
+                    part.set_part_bytes(typed.to_part_bytes()?);
+                }
+            }
+        }
+        Ok(())
+    }
 
-    pub fn create_relationship() {}
+    /// Add a package-level relationship to a part, returns the relationship id.
+    pub fn create_relationship(
+        &mut self,
+        relationship_type: impl Into<RelationshipType>,
+        target: &str,
+    ) -> String {
+        let id = self.relationships.next_id();
+        self.relationships.add_relationship(Relationship::new(
+            id.clone(),
+            relationship_type,
+            target,
+        ));
+        self.mark_dirty(RELATIONSHIPS_FILE);
+        id
+    }
 
     pub fn delete_relationship() {}
 
     pub fn get_relationships() {}
 
-    pub fn get_relationships_by_type(_relationship_type: String) {}
+    /// Get package-level relationships of the relationship type.
+    pub fn get_relationships_by_type<'a>(
+        &'a self,
+        relationship_type: &'a RelationshipType,
+    ) -> impl Iterator<Item = &'a Relationship> + 'a {
+        self.relationships
+            .get_relationships_by_type(relationship_type)
+    }
+
+    /// Parse the relationships of a part, `None` if the part has no relationships.
+    pub fn get_part_relationships(&self, uri: &str) -> Option<Relationships> {
+        let part = self.get_part(&relationships_uri(uri))?;
+        Some(Relationships::parse_from_xml_reader(part.as_part_bytes()))
+    }
+
+    /// Find the first part related from the source part by the relationship type, eg. the
+    /// styles part of a workbook.
+    pub fn find_related_part_uri(
+        &self,
+        source_uri: &str,
+        relationship_type: &RelationshipType,
+    ) -> Option<String> {
+        let relationships = if source_uri.trim_start_matches('/').is_empty() {
+            self.relationships.clone()
+        } else {
+            self.get_part_relationships(source_uri)?
+        };
+        let uri = relationships
+            .get_relationships_by_type(relationship_type)
+            .find(|r| !r.is_external())
+            .map(|r| resolve_target(source_uri, r.target()));
+        uri
+    }
 
     pub fn relationship_exist(&self, id: &str) -> bool {
         self.relationships.contains(id)
@@ -155,8 +622,11 @@ impl OpenXmlPackage {
 
     pub fn create_part_core(&mut self, uri: &str, content_type: &ContentType) {
         let part = OpenXmlPart::new_with_content_type(uri, content_type);
-        self.parts.insert(uri.into(), part);
-        // if content type is not exist, add to content types.
+        if self.parts.insert(uri.into(), part).is_none() {
+            self.entries.push(uri.into());
+        }
+        self.mark_dirty(uri);
+        self.ensure_content_type(uri, content_type);
     }
     pub fn create_part_core_with_data(
         &mut self,
@@ -165,24 +635,55 @@ impl OpenXmlPackage {
         data: &[u8],
     ) -> Result<(), OoxmlError> {
         let part = OpenXmlPart::new(uri, content_type, data)?;
-        self.parts.insert(uri.into(), part);
-        // if content type is not exist, add to content types.
+        if self.parts.insert(uri.into(), part).is_none() {
+            self.entries.push(uri.into());
+        }
+        self.typed_parts.remove(uri);
+        self.mark_dirty(uri);
+        self.ensure_content_type(uri, content_type);
         Ok(())
     }
 
+    /// Add an override content type for the part if it does not resolve to `content_type`.
+    fn ensure_content_type(&mut self, uri: &str, content_type: &ContentType) {
+        if self.content_types.get_content_type(uri) != Some(content_type) {
+            self.content_types.add_override_element(
+                format!("/{}", uri.trim_start_matches('/')),
+                content_type.clone(),
+            );
+            self.mark_dirty(CONTENT_TYPES_FILE);
+        }
+    }
+
     /// Delete the part corresponding to the uri specified.
     ///
     /// Delete the content type for this part if it was specified as an override.
     pub fn delete_part_core(&mut self, uri: &str) {
-        let part = self.parts.remove(uri);
-        if let Some(part) = part {
-            if let Some(content_type) = part.content_type() {
-                self.content_types.delete_content_type(content_type);
+        if self.parts.remove(uri).is_some() {
+            self.typed_parts.remove(uri);
+            self.entries.retain(|name| name != uri);
+            self.mark_dirty(uri);
+            if self.content_types.delete_override_element(uri) {
+                self.mark_dirty(CONTENT_TYPES_FILE);
             }
         }
     }
 }
 
+#[test]
+fn missing_relationships() {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(CONTENT_TYPES_FILE, zip::write::FileOptions::default())
+        .unwrap();
+    zip.write_all(br#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="xml" ContentType="application/xml"/></Types>"#)
+        .unwrap();
+    let bytes = zip.finish().unwrap().into_inner();
+    assert!(matches!(
+        OpenXmlPackage::from_reader(Cursor::new(bytes)),
+        Err(OoxmlError::PackageRelationshipError)
+    ));
+}
+
 #[test]
 fn open_and_save() {
     let package = OpenXmlPackage::open("examples/excel-demo/demo.xlsx").unwrap();
@@ -193,3 +694,254 @@ fn open_and_save() {
     package.save_as("tests/write-back.docx").unwrap();
     // std::fs::remove_file("tests/write-back.xlsx").unwrap();
 }
+
+/// Open and save every example package, then compare entries, content types and relationships.
+#[test]
+fn lossless_round_trip() {
+    let corpus = [
+        "examples/excel-demo/demo.xlsx",
+        "examples/issue-12/demo.xlsx",
+        "examples/simple-spreadsheet/data-image-demo.xlsx",
+        "examples/docx-demo/rust-docx-rs.docx",
+    ];
+    fn entries(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut zip = ZipArchive::new(Cursor::new(data)).unwrap();
+        (0..zip.len())
+            .map(|i| {
+                let mut file = zip.by_index(i).unwrap();
+                let mut content = Vec::new();
+                file.read_to_end(&mut content).unwrap();
+                (file.name().to_string(), content)
+            })
+            .collect()
+    }
+    for path in corpus {
+        let source = std::fs::read(path).unwrap();
+        let package = OpenXmlPackage::from_reader(Cursor::new(&source)).unwrap();
+        let mut output = Cursor::new(Vec::new());
+        package.write(&mut output).unwrap();
+        assert_eq!(entries(&source), entries(output.get_ref()), "{}", path);
+
+        // serialize all the package level parts again.
+        let mut package = package;
+        package.properties_mut();
+        package.app_properties_mut();
+        package.custom_properties_mut();
+        package.mark_dirty(CONTENT_TYPES_FILE);
+        package.mark_dirty(RELATIONSHIPS_FILE);
+        let mut output = Cursor::new(Vec::new());
+        package.write(&mut output).unwrap();
+        output.set_position(0);
+        let reloaded = OpenXmlPackage::from_reader(output).unwrap();
+        assert_eq!(reloaded.content_types, package.content_types, "{}", path);
+        assert_eq!(reloaded.relationships, package.relationships, "{}", path);
+        assert_eq!(reloaded.entries, package.entries, "{}", path);
+        assert_eq!(
+            reloaded.app_properties.is_some(),
+            package.app_properties.is_some()
+        );
+        for (name, part) in package.parts.iter() {
+            assert_eq!(
+                reloaded.get_part(name).unwrap().as_part_bytes(),
+                part.as_part_bytes()
+            );
+        }
+    }
+}
+
+#[test]
+fn typed_parts_registry() {
+    use crate::packaging::namespace::Namespaces;
+    use quick_xml::events::attributes::Attribute;
+
+    #[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+    #[serde(rename = "addin")]
+    struct AddinPart {
+        name: String,
+        version: usize,
+    }
+    impl OpenXmlElementInfo for AddinPart {
+        fn tag_name() -> &'static str {
+            "addin"
+        }
+        fn element_type() -> OpenXmlElementType {
+            OpenXmlElementType::Root
+        }
+    }
+    impl OpenXmlDeserializeDefault for AddinPart {}
+    impl OpenXmlSerialize for AddinPart {
+        fn attributes(&self) -> Option<Vec<Attribute<'_>>> {
+            Some(vec![
+                Attribute {
+                    key: b"name",
+                    value: self.name.as_bytes().into(),
+                },
+                Attribute {
+                    key: b"version",
+                    value: self.version.to_string().into_bytes().into(),
+                },
+            ])
+        }
+        fn namespaces(&self) -> Option<Cow<'_, Namespaces>> {
+            None
+        }
+        fn write_inner<W: Write>(&self, _writer: W) -> crate::error::Result<()> {
+            Ok(())
+        }
+    }
+    const ADDIN_CONTENT_TYPE: &str = "application/vnd.example.addin+xml";
+    const ADDIN_RELATIONSHIP_TYPE: &str = "http://example.com/relationships/addin";
+
+    let mut package = OpenXmlPackage::open("examples/excel-demo/demo.xlsx").unwrap();
+    let addin = AddinPart {
+        name: "calc".into(),
+        version: 1,
+    };
+    package
+        .insert_typed_part(
+            "addin/addin1.xml",
+            &ADDIN_CONTENT_TYPE.into(),
+            addin.clone(),
+        )
+        .unwrap();
+    package
+        .create_part_core_with_data(
+            "addin/addin2.xml",
+            &"application/xml".into(),
+            br#"<addin name="linked" version="2"></addin>"#,
+        )
+        .unwrap();
+    package.create_relationship(ADDIN_RELATIONSHIP_TYPE, "addin/addin2.xml");
+    let mut output = Cursor::new(Vec::new());
+    package.write(&mut output).unwrap();
+
+    let mut registry = PartRegistry::new();
+    registry
+        .register_content_type::<AddinPart>(ADDIN_CONTENT_TYPE)
+        .register_relationship_type::<AddinPart>(ADDIN_RELATIONSHIP_TYPE);
+    output.set_position(0);
+    let mut package = OpenXmlPackage::from_reader_with_registry(output, &registry).unwrap();
+    assert_eq!(
+        package.typed_part::<AddinPart>("addin/addin1.xml"),
+        Some(&addin)
+    );
+    assert_eq!(
+        package
+            .typed_part::<AddinPart>("addin/addin2.xml")
+            .map(|part| part.version),
+        Some(2)
+    );
+    assert!(package.typed_part::<AddinPart>("xl/workbook.xml").is_none());
+
+    package
+        .typed_part_mut::<AddinPart>("addin/addin1.xml")
+        .unwrap()
+        .version = 3;
+    let mut output = Cursor::new(Vec::new());
+    package.write(&mut output).unwrap();
+    output.set_position(0);
+    let mut package = OpenXmlPackage::from_reader_with_registry(output, &registry).unwrap();
+    assert_eq!(
+        package
+            .typed_part::<AddinPart>("addin/addin1.xml")
+            .map(|part| part.version),
+        Some(3)
+    );
+
+    // raw edits drop the typed part instead of being overwritten by it.
+    package.set_part_data(
+        "addin/addin1.xml",
+        &br#"<addin name="raw" version="4"></addin>"#[..],
+    );
+    assert!(package
+        .typed_part::<AddinPart>("addin/addin1.xml")
+        .is_none());
+    let mut output = Cursor::new(Vec::new());
+    package.write(&mut output).unwrap();
+    output.set_position(0);
+    let package = OpenXmlPackage::from_reader_with_registry(output, &registry).unwrap();
+    assert_eq!(
+        package
+            .typed_part::<AddinPart>("addin/addin1.xml")
+            .map(|part| part.name.as_str()),
+        Some("raw")
+    );
+}
+
+#[test]
+fn raw_copy_unchanged_entries() {
+    let source = std::fs::read("examples/excel-demo/demo.xlsx").unwrap();
+    let mut package = OpenXmlPackage::from_reader(Cursor::new(&source)).unwrap();
+    assert!(!package.is_dirty("xl/sharedStrings.xml"));
+    assert!(package.set_part_data("xl/sharedStrings.xml", b"<sst/>".to_vec()));
+    assert!(package.is_dirty("xl/sharedStrings.xml"));
+
+    let mut output = Cursor::new(Vec::new());
+    package.write(&mut output).unwrap();
+    let mut source = ZipArchive::new(Cursor::new(source)).unwrap();
+    let mut output = ZipArchive::new(output).unwrap();
+    for name in [
+        "xl/styles.xml",
+        "xl/media/image1.png",
+        "xl/worksheets/sheet1.xml",
+    ] {
+        let origin = source.by_name(name).unwrap();
+        let (method, crc32, size) = (
+            origin.compression(),
+            origin.crc32(),
+            origin.compressed_size(),
+        );
+        drop(origin);
+        let copied = output.by_name(name).unwrap();
+        assert_eq!(copied.compression(), method);
+        assert_eq!(copied.crc32(), crc32);
+        assert_eq!(copied.compressed_size(), size);
+    }
+    let mut changed = String::new();
+    output
+        .by_name("xl/sharedStrings.xml")
+        .unwrap()
+        .read_to_string(&mut changed)
+        .unwrap();
+    assert_eq!(changed, "<sst/>");
+}
+
+#[test]
+fn open_dir_and_save_to_dir() {
+    let package = OpenXmlPackage::open_dir("examples/simple-spreadsheet").unwrap();
+    assert!(package.get_part("xl/workbook.xml").is_some());
+    assert!(package.get_part("xl/worksheets/sheet2.xml").is_some());
+    assert_eq!(package.main_part_uri().as_deref(), Some("xl/workbook.xml"));
+
+    let dir = std::env::temp_dir().join("ooxml-save-to-dir");
+    let _ = std::fs::remove_dir_all(&dir);
+    package.save_to_dir(&dir).unwrap();
+    assert!(dir.join("[Content_Types].xml").is_file());
+    assert!(dir.join("_rels/.rels").is_file());
+
+    let reloaded = OpenXmlPackage::open_dir(&dir).unwrap();
+    assert_eq!(
+        reloaded
+            .get_part("xl/workbook.xml")
+            .unwrap()
+            .as_part_bytes(),
+        package.get_part("xl/workbook.xml").unwrap().as_part_bytes()
+    );
+
+    // build a real package from the directory.
+    let mut zip = std::io::Cursor::new(Vec::new());
+    reloaded.write(&mut zip).unwrap();
+    zip.set_position(0);
+    let zipped = OpenXmlPackage::from_reader(zip).unwrap();
+    assert_eq!(
+        zipped
+            .get_part("xl/sharedStrings.xml")
+            .unwrap()
+            .as_part_bytes(),
+        package
+            .get_part("xl/sharedStrings.xml")
+            .unwrap()
+            .as_part_bytes()
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
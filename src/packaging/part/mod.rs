@@ -1,5 +1,8 @@
 mod container;
 mod pair;
+mod registry;
+
+pub use registry::{PartRegistry, TypedPart};
 
 use crate::error::OoxmlError;
 use crate::packaging::content_type::ContentType;
@@ -44,7 +47,7 @@ impl OpenXmlPart {
 
     pub fn new<S: Into<PathBuf>, C: Into<ContentType>, R: Read>(
         uri: S,
-        _content_type: C,
+        content_type: C,
         mut reader: R,
     ) -> Result<Self, OoxmlError> {
         let mut raw = Cursor::new(Vec::new());
@@ -52,7 +55,7 @@ impl OpenXmlPart {
         let part = Self {
             raw,
             uri: uri.into(),
-            ..Default::default()
+            content_type: Some(content_type.into()),
         };
         Ok(part)
     }
@@ -61,6 +64,11 @@ impl OpenXmlPart {
         self.raw.get_ref()
     }
 
+    /// Replace the part data.
+    pub fn set_part_bytes(&mut self, data: impl Into<Vec<u8>>) {
+        self.raw = Cursor::new(data.into());
+    }
+
     pub fn content_type(&self) -> &Option<ContentType> {
         &self.content_type
     }
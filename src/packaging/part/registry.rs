@@ -0,0 +1,124 @@
+//! Registry of user-defined part types.
+//!
+//! Applications register their own part types by content type or relationship type, then the
+//! package parses the matched parts into these types on load and serializes them on save.
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::error::OoxmlError;
+use crate::packaging::content_type::ContentType;
+use crate::packaging::element::{OpenXmlDeserialize, OpenXmlSerialize};
+use crate::packaging::relationship::RelationshipType;
+
+/// A type-erased typed part.
+///
+/// It's implemented for any type with both [`OpenXmlDeserialize`] and [`OpenXmlSerialize`].
+pub trait TypedPart: Any + fmt::Debug {
+    /// Serialize the part to xml bytes.
+    fn to_part_bytes(&self) -> Result<Vec<u8>, OoxmlError>;
+    fn clone_box(&self) -> Box<dyn TypedPart>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T> TypedPart for T
+where
+    T: OpenXmlSerialize + OpenXmlDeserialize + Clone + fmt::Debug + 'static,
+{
+    fn to_part_bytes(&self) -> Result<Vec<u8>, OoxmlError> {
+        self.to_xml_bytes()
+    }
+    fn clone_box(&self) -> Box<dyn TypedPart> {
+        Box::new(self.clone())
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Clone for Box<dyn TypedPart> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+type PartParser = fn(&[u8]) -> Result<Box<dyn TypedPart>, OoxmlError>;
+
+fn parse_part<T>(data: &[u8]) -> Result<Box<dyn TypedPart>, OoxmlError>
+where
+    T: OpenXmlSerialize + OpenXmlDeserialize + Clone + fmt::Debug + 'static,
+{
+    Ok(Box::new(T::from_xml_reader(data)?))
+}
+
+/// Part types registered by content type or relationship type.
+///
+/// Content type is checked first when a part matches both.
+#[derive(Clone, Default)]
+pub struct PartRegistry {
+    content_types: HashMap<ContentType, PartParser>,
+    relationship_types: HashMap<RelationshipType, PartParser>,
+}
+
+impl fmt::Debug for PartRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PartRegistry")
+            .field("content_types", &self.content_types.keys())
+            .field("relationship_types", &self.relationship_types.keys())
+            .finish()
+    }
+}
+
+impl PartRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register part type `T` for parts of the content type.
+    pub fn register_content_type<T>(&mut self, content_type: impl Into<ContentType>) -> &mut Self
+    where
+        T: OpenXmlSerialize + OpenXmlDeserialize + Clone + fmt::Debug + 'static,
+    {
+        self.content_types
+            .insert(content_type.into(), parse_part::<T>);
+        self
+    }
+
+    /// Register part type `T` for parts targeted by relationships of the relationship type.
+    pub fn register_relationship_type<T>(
+        &mut self,
+        relationship_type: impl Into<RelationshipType>,
+    ) -> &mut Self
+    where
+        T: OpenXmlSerialize + OpenXmlDeserialize + Clone + fmt::Debug + 'static,
+    {
+        self.relationship_types
+            .insert(relationship_type.into(), parse_part::<T>);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.content_types.is_empty() && self.relationship_types.is_empty()
+    }
+
+    /// Parse a part with the registered type, `None` if no type matches.
+    pub(crate) fn parse(
+        &self,
+        content_type: Option<&ContentType>,
+        relationship_types: &[RelationshipType],
+        data: &[u8],
+    ) -> Option<Result<Box<dyn TypedPart>, OoxmlError>> {
+        content_type
+            .and_then(|content_type| self.content_types.get(content_type))
+            .or_else(|| {
+                relationship_types
+                    .iter()
+                    .find_map(|r| self.relationship_types.get(r))
+            })
+            .map(|parse| parse(data))
+    }
+}
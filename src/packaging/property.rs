@@ -1,5 +1,4 @@
 use crate::error::OoxmlError;
-use crate::packaging::content_type::ContentType;
 
 use serde::{Deserialize, Serialize};
 
@@ -44,7 +43,7 @@ pub const PROPERTY_VERSION_TAG: &str = "cp:version";
 pub struct Properties {
     pub category: Option<String>,
     pub content_status: Option<String>,
-    pub content_type: Option<ContentType>,
+    pub content_type: Option<String>,
     pub created: Option<DateTime>,
     pub creator: Option<String>,
     pub description: Option<String>,
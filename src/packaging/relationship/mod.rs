@@ -1,6 +1,10 @@
 mod reference;
+mod relationship_type;
 
 pub use reference::ReferenceRelationship;
+pub use relationship_type::{
+    RelationshipType, STRICT_RELATIONSHIP_PREFIX, TRANSITIONAL_RELATIONSHIP_PREFIX,
+};
 pub struct RelationshipId(String);
 pub struct ExternalRelationship(ReferenceRelationship);
 pub struct DataPartReferenceRelationship(ReferenceRelationship);
@@ -37,18 +41,55 @@ pub struct Relationship {
     id: String,
     r#type: String,
     target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_mode: Option<String>,
 }
 
 impl Relationship {
+    pub fn new(
+        id: impl Into<String>,
+        relationship_type: impl Into<RelationshipType>,
+        target: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            r#type: relationship_type.into().as_uri().to_string(),
+            target: target.into(),
+            target_mode: None,
+        }
+    }
+    /// New relationship to a target outside the package, eg. a hyperlink url.
+    pub fn new_external(
+        id: impl Into<String>,
+        relationship_type: impl Into<RelationshipType>,
+        target: impl Into<String>,
+    ) -> Self {
+        Self {
+            target_mode: Some("External".into()),
+            ..Self::new(id, relationship_type, target)
+        }
+    }
     pub fn id(&self) -> &str {
         &self.id
     }
+    /// Relationship type uri as it is in the relationships part.
     pub fn r#type(&self) -> &str {
         &self.r#type
     }
+    /// Typed relationship type, strict uris are parsed as the transitional ones.
+    pub fn relationship_type(&self) -> RelationshipType {
+        RelationshipType::from_uri(&self.r#type)
+    }
     pub fn target(&self) -> &str {
         &self.target
     }
+    /// Target mode, `External` for targets outside the package.
+    pub fn target_mode(&self) -> Option<&str> {
+        self.target_mode.as_deref()
+    }
+    pub fn is_external(&self) -> bool {
+        self.target_mode.as_deref() == Some("External")
+    }
 }
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct Relationships {
@@ -185,6 +226,167 @@ impl Relationships {
     pub fn contains(&self, id: &str) -> bool {
         self.relationships.contains_key(id)
     }
+
+    /// Get the first relationship of the relationship type.
+    pub fn get_relationship_by_type(
+        &self,
+        relationship_type: &RelationshipType,
+    ) -> Option<&Relationship> {
+        self.iter()
+            .find(|r| r.relationship_type() == *relationship_type)
+    }
+
+    /// Get all relationships of the relationship type.
+    pub fn get_relationships_by_type<'a>(
+        &'a self,
+        relationship_type: &'a RelationshipType,
+    ) -> impl Iterator<Item = &'a Relationship> + 'a {
+        self.iter()
+            .filter(move |r| r.relationship_type() == *relationship_type)
+    }
+
+    /// Get an unused relationship id, like `rId3`.
+    pub fn next_id(&self) -> String {
+        (1..)
+            .map(|i| format!("rId{}", i))
+            .find(|id| !self.contains(id))
+            .expect("relationship id overflow")
+    }
+
+    /// Delete a relationship by id, returns the deleted one.
+    pub fn delete_relationship(&mut self, id: &str) -> Option<Relationship> {
+        self.relationships.remove(id)
+    }
+
+    /// Iterate relationships in loading order.
+    pub fn iter(&self) -> impl Iterator<Item = &Relationship> {
+        self.relationships.values()
+    }
+}
+
+/// Get the relationships part uri of a part, eg. `xl/_rels/workbook.xml.rels` for `xl/workbook.xml`.
+///
+/// The package root (empty uri or `/`) gives `_rels/.rels`.
+pub fn relationships_uri(part_uri: &str) -> String {
+    let part_uri = part_uri.trim_start_matches('/');
+    match part_uri.rsplit_once('/') {
+        _ if part_uri.is_empty() => RELATIONSHIPS_FILE.to_string(),
+        Some((dir, name)) => format!("{}/_rels/{}.rels", dir, name),
+        None => format!("_rels/{}.rels", part_uri),
+    }
+}
+
+/// Resolve a relationship target against its source part, returns the part uri without leading `/`.
+///
+/// Targets are relative to the folder of the source part unless they start with `/`.
+pub fn resolve_target(source_uri: &str, target: &str) -> String {
+    let mut segments: Vec<&str> = if target.starts_with('/') {
+        Vec::new()
+    } else {
+        let source_uri = source_uri.trim_start_matches('/');
+        match source_uri.rsplit_once('/') {
+            Some((dir, _)) => dir.split('/').collect(),
+            None => Vec::new(),
+        }
+    };
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Relative target of a part from the source part, the reverse of [`resolve_target`].
+pub fn relative_target(source_uri: &str, part_uri: &str) -> String {
+    let source_uri = source_uri.trim_start_matches('/');
+    let part_uri = part_uri.trim_start_matches('/');
+    let dir: Vec<&str> = match source_uri.rsplit_once('/') {
+        Some((dir, _)) => dir.split('/').collect(),
+        None => Vec::new(),
+    };
+    let segments: Vec<&str> = part_uri.split('/').collect();
+    let common = dir
+        .iter()
+        .zip(&segments[..segments.len() - 1])
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut target: Vec<&str> = vec![".."; dir.len() - common];
+    target.extend(&segments[common..]);
+    target.join("/")
+}
+
+#[test]
+fn test_relative_target() {
+    let source = "xl/worksheets/sheet1.xml";
+    for (part, target) in [
+        ("xl/comments1.xml", "../comments1.xml"),
+        ("xl/worksheets/sheet2.xml", "sheet2.xml"),
+        ("xl/drawings/vmlDrawing1.vml", "../drawings/vmlDrawing1.vml"),
+        ("docProps/app.xml", "../../docProps/app.xml"),
+    ] {
+        assert_eq!(relative_target(source, part), target);
+        assert_eq!(resolve_target(source, target), part);
+    }
+    assert_eq!(
+        relative_target("xl/workbook.xml", "xl/styles.xml"),
+        "styles.xml"
+    );
+}
+
+#[test]
+fn test_target_mode() {
+    let raw = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://github.com/zitsen/ooxml-rs" TargetMode="External"/></Relationships>"#;
+    let relationships = Relationships::parse_from_xml_str(raw);
+    assert!(relationships
+        .get_relationship_by_id("rId1")
+        .unwrap()
+        .is_external());
+    assert_eq!(raw, format!("{}", relationships));
+}
+
+#[test]
+fn test_relationship_type() {
+    let raw = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://purl.oclc.org/ooxml/officeDocument/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://purl.oclc.org/ooxml/officeDocument/relationships/styles" Target="styles.xml"/><Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet2.xml"/></Relationships>"#;
+    let relationships = Relationships::parse_from_xml_str(raw);
+    let styles = relationships
+        .get_relationship_by_type(&RelationshipType::Styles)
+        .unwrap();
+    assert_eq!(styles.target(), "styles.xml");
+    assert_eq!(
+        relationships
+            .get_relationships_by_type(&RelationshipType::Worksheet)
+            .count(),
+        2
+    );
+    // strict uri is kept as it is.
+    assert_eq!(raw, format!("{}", relationships));
+}
+
+#[test]
+fn test_resolve_target() {
+    assert_eq!(resolve_target("", "xl/workbook.xml"), "xl/workbook.xml");
+    assert_eq!(
+        resolve_target("xl/workbook.xml", "worksheets/sheet1.xml"),
+        "xl/worksheets/sheet1.xml"
+    );
+    assert_eq!(
+        resolve_target("xl/workbook.xml", "/xl/worksheets/sheet1.xml"),
+        "xl/worksheets/sheet1.xml"
+    );
+    assert_eq!(
+        resolve_target("xl/worksheets/sheet1.xml", "../drawings/drawing1.xml"),
+        "xl/drawings/drawing1.xml"
+    );
+    assert_eq!(relationships_uri(""), "_rels/.rels");
+    assert_eq!(
+        relationships_uri("xl/workbook.xml"),
+        "xl/_rels/workbook.xml.rels"
+    );
 }
 
 #[test]
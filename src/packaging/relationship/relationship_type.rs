@@ -0,0 +1,257 @@
+//! Relationship types defined by ECMA-376, in both transitional and strict form.
+use std::fmt;
+use std::str::FromStr;
+
+/// Prefix of transitional office document relationship types.
+pub const TRANSITIONAL_RELATIONSHIP_PREFIX: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/";
+/// Prefix of strict office document relationship types.
+pub const STRICT_RELATIONSHIP_PREFIX: &str =
+    "http://purl.oclc.org/ooxml/officeDocument/relationships/";
+
+macro_rules! relationship_types {
+    (
+        office {
+            $($(#[$office_meta:meta])* $office:ident => $name:literal $(| $strict_name:literal)?,)*
+        }
+        package {
+            $($(#[$package_meta:meta])* $package:ident => $package_uri:literal,)*
+        }
+    ) => {
+        /// Relationship type, parsed from the transitional or strict uri.
+        ///
+        /// Unknown uris are kept in [`RelationshipType::Other`].
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum RelationshipType {
+            $($(#[$office_meta])* $office,)*
+            $($(#[$package_meta])* $package,)*
+            /// Any other relationship type uri.
+            Other(String),
+        }
+
+        impl RelationshipType {
+            /// Parse relationship type from uri, transitional or strict.
+            pub fn from_uri(uri: &str) -> Self {
+                if let Some(name) = uri.strip_prefix(TRANSITIONAL_RELATIONSHIP_PREFIX) {
+                    match name {
+                        $($name => return RelationshipType::$office,)*
+                        _ => {}
+                    }
+                }
+                if let Some(name) = uri.strip_prefix(STRICT_RELATIONSHIP_PREFIX) {
+                    match name {
+                        $(relationship_types!(@strict $name $(, $strict_name)?) => {
+                            return RelationshipType::$office
+                        })*
+                        _ => {}
+                    }
+                }
+                match uri {
+                    $($package_uri => RelationshipType::$package,)*
+                    uri => RelationshipType::Other(uri.to_string()),
+                }
+            }
+
+            /// The transitional uri.
+            pub fn as_uri(&self) -> &str {
+                match self {
+                    $(RelationshipType::$office => {
+                        concat!("http://schemas.openxmlformats.org/officeDocument/2006/relationships/", $name)
+                    })*
+                    $(RelationshipType::$package => $package_uri,)*
+                    RelationshipType::Other(uri) => uri.as_str(),
+                }
+            }
+
+            /// The strict uri, it's the same as transitional for package relationships and
+            /// unknown types.
+            pub fn as_strict_uri(&self) -> &str {
+                match self {
+                    $(RelationshipType::$office => concat!(
+                        "http://purl.oclc.org/ooxml/officeDocument/relationships/",
+                        relationship_types!(@strict $name $(, $strict_name)?)
+                    ),)*
+                    other => other.as_uri(),
+                }
+            }
+        }
+    };
+    (@strict $name:literal) => { $name };
+    (@strict $name:literal, $strict_name:literal) => { $strict_name };
+}
+
+relationship_types! {
+    office {
+        /// Main document part of a package, eg. workbook, document and presentation.
+        OfficeDocument => "officeDocument",
+        /// Extended (application) properties.
+        ExtendedProperties => "extended-properties" | "extendedProperties",
+        /// Custom file properties.
+        CustomProperties => "custom-properties" | "customProperties",
+        AlternativeFormatImport => "aFChunk",
+        AttachedTemplate => "attachedTemplate",
+        Audio => "audio",
+        CalculationChain => "calcChain",
+        Chart => "chart",
+        Chartsheet => "chartsheet",
+        ChartUserShapes => "chartUserShapes",
+        CommentAuthors => "commentAuthors",
+        Comments => "comments",
+        Connections => "connections",
+        Control => "control",
+        CustomProperty => "customProperty",
+        CustomXml => "customXml",
+        CustomXmlProperties => "customXmlProps",
+        DiagramColors => "diagramColors",
+        DiagramData => "diagramData",
+        DiagramLayout => "diagramLayout",
+        DiagramStyle => "diagramQuickStyle",
+        Dialogsheet => "dialogsheet",
+        Drawing => "drawing",
+        Endnotes => "endnotes",
+        ExternalLink => "externalLink",
+        ExternalLinkPath => "externalLinkPath",
+        Font => "font",
+        FontTable => "fontTable",
+        Footer => "footer",
+        Footnotes => "footnotes",
+        Frame => "frame",
+        GlossaryDocument => "glossaryDocument",
+        HandoutMaster => "handoutMaster",
+        Header => "header",
+        Hyperlink => "hyperlink",
+        Image => "image",
+        MailMergeHeaderSource => "mailMergeHeaderSource",
+        MailMergeRecipientData => "recipientData",
+        MailMergeSource => "mailMergeSource",
+        Movie => "video",
+        NotesMaster => "notesMaster",
+        NotesSlide => "notesSlide",
+        Numbering => "numbering",
+        OleObject => "oleObject",
+        Package => "package",
+        PivotCacheDefinition => "pivotCacheDefinition",
+        PivotCacheRecords => "pivotCacheRecords",
+        PivotTable => "pivotTable",
+        PresentationProperties => "presProps",
+        PrinterSettings => "printerSettings",
+        QueryTable => "queryTable",
+        RevisionHeaders => "revisionHeaders",
+        RevisionLog => "revisionLog",
+        Settings => "settings",
+        SharedStrings => "sharedStrings",
+        SheetMetadata => "sheetMetadata",
+        Slide => "slide",
+        SlideLayout => "slideLayout",
+        SlideMaster => "slideMaster",
+        SlideSyncData => "slideUpdateInfo",
+        Styles => "styles",
+        SubDocument => "subDocument",
+        Table => "table",
+        TableSingleCells => "tableSingleCells",
+        TableStyles => "tableStyles",
+        Tags => "tags",
+        Theme => "theme",
+        ThemeOverride => "themeOverride",
+        UserNames => "usernames",
+        ViewProperties => "viewProps",
+        VmlDrawing => "vmlDrawing",
+        VolatileDependencies => "volatileDependencies",
+        WebSettings => "webSettings",
+        Worksheet => "worksheet",
+        WorksheetSource => "worksheetSource",
+        XlMacrosheet => "xlMacrosheet",
+        XlIntlMacrosheet => "xlIntlMacrosheet",
+        XmlMaps => "xmlMaps",
+    }
+    package {
+        /// Core file properties.
+        CoreProperties => "http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties",
+        Thumbnail => "http://schemas.openxmlformats.org/package/2006/relationships/metadata/thumbnail",
+        DigitalSignatureOrigin => "http://schemas.openxmlformats.org/package/2006/relationships/digital-signature/origin",
+        DigitalSignature => "http://schemas.openxmlformats.org/package/2006/relationships/digital-signature/signature",
+        DigitalSignatureCertificate => "http://schemas.openxmlformats.org/package/2006/relationships/digital-signature/certificate",
+        /// Microsoft Office extension: VBA project.
+        VbaProject => "http://schemas.microsoft.com/office/2006/relationships/vbaProject",
+        /// Microsoft Office extension: Excel 365 threaded comments.
+        ThreadedComment => "http://schemas.microsoft.com/office/2017/10/relationships/threadedComment",
+        /// Microsoft Office extension: persons of threaded comments.
+        Person => "http://schemas.microsoft.com/office/2017/10/relationships/person",
+        /// Microsoft Office extension: word comments extended.
+        CommentsExtended => "http://schemas.microsoft.com/office/2011/relationships/commentsExtended",
+        /// Microsoft Office extension: styles with effects.
+        StylesWithEffects => "http://schemas.microsoft.com/office/2007/relationships/stylesWithEffects",
+        /// Microsoft Office extension: diagram drawing.
+        DiagramDrawing => "http://schemas.microsoft.com/office/2007/relationships/diagramDrawing",
+        /// Microsoft Office extension: slicer.
+        Slicer => "http://schemas.microsoft.com/office/2007/relationships/slicer",
+        /// Microsoft Office extension: slicer cache.
+        SlicerCache => "http://schemas.microsoft.com/office/2007/relationships/slicerCache",
+    }
+}
+
+impl RelationshipType {
+    /// Check if the uri is a strict office document relationship type.
+    pub fn is_strict_uri(uri: &str) -> bool {
+        uri.starts_with(STRICT_RELATIONSHIP_PREFIX)
+    }
+}
+
+impl fmt::Display for RelationshipType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_uri())
+    }
+}
+
+impl FromStr for RelationshipType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from_uri(s))
+    }
+}
+
+impl From<&str> for RelationshipType {
+    fn from(uri: &str) -> Self {
+        Self::from_uri(uri)
+    }
+}
+
+impl From<String> for RelationshipType {
+    fn from(uri: String) -> Self {
+        Self::from_uri(&uri)
+    }
+}
+
+#[test]
+fn relationship_type_uri() {
+    let styles = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles";
+    assert_eq!(RelationshipType::from_uri(styles), RelationshipType::Styles);
+    assert_eq!(RelationshipType::Styles.as_uri(), styles);
+    assert_eq!(
+        RelationshipType::from_uri(
+            "http://purl.oclc.org/ooxml/officeDocument/relationships/styles"
+        ),
+        RelationshipType::Styles
+    );
+    assert_eq!(
+        RelationshipType::ExtendedProperties.as_strict_uri(),
+        "http://purl.oclc.org/ooxml/officeDocument/relationships/extendedProperties"
+    );
+    assert_eq!(
+        RelationshipType::from_uri(
+            "http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties"
+        ),
+        RelationshipType::ExtendedProperties
+    );
+    assert_eq!(
+        RelationshipType::CoreProperties.as_strict_uri(),
+        RelationshipType::CoreProperties.as_uri()
+    );
+    let other = "http://example.com/relationships/addin";
+    assert_eq!(
+        RelationshipType::from_uri(other),
+        RelationshipType::Other(other.into())
+    );
+    assert_eq!(RelationshipType::from_uri(other).to_string(), other);
+}
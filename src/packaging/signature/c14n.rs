@@ -0,0 +1,444 @@
+//! A minimal xml tree for XML-DSig, with canonical xml serialization.
+//!
+//! Both inclusive [C14N 1.0](https://www.w3.org/TR/xml-c14n) and
+//! [exclusive C14N](https://www.w3.org/TR/xml-exc-c14n/) are supported, with or without comments.
+use std::collections::{BTreeMap, BTreeSet};
+
+use quick_xml::events::Event;
+
+use crate::error::OoxmlError;
+
+const XML_NAMESPACE_URI: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// In-scope namespaces, prefix (empty for default) to uri.
+pub(crate) type Namespaces = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Node {
+    Element(Element),
+    Text(String),
+    Comment(String),
+    ProcessingInstruction(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct Element {
+    /// Qualified name as it is, eg. `mdssi:SignatureTime`.
+    pub name: String,
+    /// Attributes in document order, namespace declarations included.
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_child(mut self, child: Element) -> Self {
+        self.children.push(Node::Element(child));
+        self
+    }
+
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.children.push(Node::Text(text.into()));
+        self
+    }
+
+    /// Local name without prefix.
+    pub fn local_name(&self) -> &str {
+        local_name(&self.name)
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Child elements.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// First child element by local name.
+    pub fn child(&self, local: &str) -> Option<&Element> {
+        self.elements().find(|e| e.local_name() == local)
+    }
+
+    /// Child elements by local name.
+    pub fn children_named<'a>(&'a self, local: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements().filter(move |e| e.local_name() == local)
+    }
+
+    /// Concatenated text content of the direct children.
+    pub fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Namespaces declared by the element itself.
+    fn declared_namespaces(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes.iter().filter_map(|(key, value)| {
+            if key == "xmlns" {
+                Some(("", value.as_str()))
+            } else {
+                key.strip_prefix("xmlns:").map(|p| (p, value.as_str()))
+            }
+        })
+    }
+
+    /// Find the first element in the tree with the `Id` attribute, and the namespaces in scope
+    /// from its ancestors.
+    pub fn find_by_id(&self, id: &str) -> Option<(&Element, Namespaces)> {
+        self.find_with_scope(
+            &|e: &Element| e.attribute("Id") == Some(id),
+            &Namespaces::new(),
+        )
+    }
+
+    pub(crate) fn find_with_scope<'a>(
+        &'a self,
+        predicate: &dyn Fn(&Element) -> bool,
+        scope: &Namespaces,
+    ) -> Option<(&'a Element, Namespaces)> {
+        if predicate(self) {
+            return Some((self, scope.clone()));
+        }
+        let mut scope = scope.clone();
+        for (prefix, uri) in self.declared_namespaces() {
+            scope.insert(prefix.to_string(), uri.to_string());
+        }
+        self.elements()
+            .find_map(|child| child.find_with_scope(predicate, &scope))
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, local)| local)
+}
+
+fn prefix(name: &str) -> &str {
+    name.split_once(':').map_or("", |(prefix, _)| prefix)
+}
+
+/// Parse xml data into an element tree, the xml declaration and doctype are dropped.
+pub(crate) fn parse(data: &[u8]) -> Result<Element, OoxmlError> {
+    let mut reader = quick_xml::Reader::from_reader(data);
+    reader.trim_text(false);
+    let mut buf = Vec::new();
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root = None;
+    fn push_node(stack: &mut [Element], node: Node) {
+        if let Some(parent) = stack.last_mut() {
+            match (parent.children.last_mut(), node) {
+                (Some(Node::Text(last)), Node::Text(text)) => last.push_str(&text),
+                (_, node) => parent.children.push(node),
+            }
+        }
+    }
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(e) => stack.push(element_from(&e)?),
+            Event::Empty(e) => {
+                let element = element_from(&e)?;
+                if stack.is_empty() {
+                    root = Some(element);
+                } else {
+                    push_node(&mut stack, Node::Element(element));
+                }
+            }
+            Event::End(_) => {
+                let element = stack.pop().expect("checked end name");
+                if stack.is_empty() {
+                    root = Some(element);
+                } else {
+                    push_node(&mut stack, Node::Element(element));
+                }
+            }
+            Event::Text(e) => {
+                let text = String::from_utf8_lossy(&e.unescaped()?).replace("\r\n", "\n");
+                push_node(&mut stack, Node::Text(text));
+            }
+            Event::CData(e) => {
+                let text = String::from_utf8_lossy(&e).replace("\r\n", "\n");
+                push_node(&mut stack, Node::Text(text));
+            }
+            Event::Comment(e) => {
+                push_node(
+                    &mut stack,
+                    Node::Comment(String::from_utf8_lossy(&e).into()),
+                );
+            }
+            Event::PI(e) => {
+                let pi = String::from_utf8_lossy(&e).into();
+                push_node(&mut stack, Node::ProcessingInstruction(pi));
+            }
+            Event::Decl(_) | Event::DocType(_) => {}
+            Event::Eof => break,
+        }
+        buf.clear();
+    }
+    root.ok_or_else(|| OoxmlError::SignatureError("no root element".into()))
+}
+
+fn element_from(e: &quick_xml::events::BytesStart) -> Result<Element, OoxmlError> {
+    let mut element = Element::new(String::from_utf8_lossy(e.name()));
+    for attr in e.attributes() {
+        let attr = attr?;
+        // attribute value normalization of xml parsers.
+        let raw: Vec<u8> = attr
+            .value
+            .iter()
+            .map(|b| match b {
+                b'\t' | b'\n' | b'\r' => b' ',
+                b => *b,
+            })
+            .collect();
+        let value = quick_xml::escape::unescape(&raw).map_err(quick_xml::Error::EscapeError)?;
+        element.attributes.push((
+            String::from_utf8_lossy(attr.key).into(),
+            String::from_utf8_lossy(&value).into(),
+        ));
+    }
+    Ok(element)
+}
+
+/// Canonicalization algorithms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Canonicalization {
+    Inclusive,
+    InclusiveWithComments,
+    Exclusive,
+    ExclusiveWithComments,
+}
+
+impl Canonicalization {
+    pub fn from_algorithm(algorithm: &str) -> Option<Self> {
+        match algorithm {
+            "http://www.w3.org/TR/2001/REC-xml-c14n-20010315" => Some(Self::Inclusive),
+            "http://www.w3.org/TR/2001/REC-xml-c14n-20010315#WithComments" => {
+                Some(Self::InclusiveWithComments)
+            }
+            "http://www.w3.org/2001/10/xml-exc-c14n#" => Some(Self::Exclusive),
+            "http://www.w3.org/2001/10/xml-exc-c14n#WithComments" => {
+                Some(Self::ExclusiveWithComments)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            Self::Inclusive => "http://www.w3.org/TR/2001/REC-xml-c14n-20010315",
+            Self::InclusiveWithComments => {
+                "http://www.w3.org/TR/2001/REC-xml-c14n-20010315#WithComments"
+            }
+            Self::Exclusive => "http://www.w3.org/2001/10/xml-exc-c14n#",
+            Self::ExclusiveWithComments => "http://www.w3.org/2001/10/xml-exc-c14n#WithComments",
+        }
+    }
+
+    fn is_exclusive(&self) -> bool {
+        matches!(self, Self::Exclusive | Self::ExclusiveWithComments)
+    }
+
+    fn with_comments(&self) -> bool {
+        matches!(
+            self,
+            Self::InclusiveWithComments | Self::ExclusiveWithComments
+        )
+    }
+}
+
+/// Canonicalize an element subtree, `scope` is the namespaces in scope from its ancestors.
+pub(crate) fn canonicalize(
+    element: &Element,
+    scope: &Namespaces,
+    method: Canonicalization,
+) -> Vec<u8> {
+    let mut output = String::new();
+    write_element(element, scope, &Namespaces::new(), method, &mut output);
+    output.into_bytes()
+}
+
+fn write_element(
+    element: &Element,
+    parent_scope: &Namespaces,
+    rendered: &Namespaces,
+    method: Canonicalization,
+    output: &mut String,
+) {
+    let mut scope = parent_scope.clone();
+    for (prefix, uri) in element.declared_namespaces() {
+        scope.insert(prefix.to_string(), uri.to_string());
+    }
+    let attributes: Vec<&(String, String)> = element
+        .attributes
+        .iter()
+        .filter(|(key, _)| key != "xmlns" && !key.starts_with("xmlns:"))
+        .collect();
+
+    let candidates: BTreeSet<&str> = if method.is_exclusive() {
+        std::iter::once(prefix(&element.name))
+            .chain(
+                attributes
+                    .iter()
+                    .map(|(key, _)| prefix(key))
+                    .filter(|p| !p.is_empty()),
+            )
+            .collect()
+    } else {
+        scope.keys().map(|p| p.as_str()).collect()
+    };
+    let mut namespaces = Namespaces::new();
+    for prefix in candidates.into_iter().filter(|p| *p != "xml") {
+        let uri = scope.get(prefix).map_or("", |uri| uri.as_str());
+        let current = rendered.get(prefix).map_or("", |uri| uri.as_str());
+        if uri != current {
+            namespaces.insert(prefix.to_string(), uri.to_string());
+        }
+    }
+    let mut rendered = rendered.clone();
+    rendered.extend(namespaces.clone());
+
+    output.push('<');
+    output.push_str(&element.name);
+    for (prefix, uri) in &namespaces {
+        if prefix.is_empty() {
+            output.push_str(" xmlns=\"");
+        } else {
+            output.push_str(" xmlns:");
+            output.push_str(prefix);
+            output.push_str("=\"");
+        }
+        escape_attribute(uri, output);
+        output.push('"');
+    }
+    let mut attributes: Vec<(&str, &str, &str, &str)> = attributes
+        .iter()
+        .map(|(key, value)| {
+            let uri = match prefix(key) {
+                "" => "",
+                "xml" => XML_NAMESPACE_URI,
+                prefix => scope.get(prefix).map_or("", |uri| uri.as_str()),
+            };
+            (uri, local_name(key), key.as_str(), value.as_str())
+        })
+        .collect();
+    attributes.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+    for (_, _, key, value) in attributes {
+        output.push(' ');
+        output.push_str(key);
+        output.push_str("=\"");
+        escape_attribute(value, output);
+        output.push('"');
+    }
+    output.push('>');
+    for node in &element.children {
+        match node {
+            Node::Element(child) => write_element(child, &scope, &rendered, method, output),
+            Node::Text(text) => escape_text(text, output),
+            Node::Comment(comment) if method.with_comments() => {
+                output.push_str("<!--");
+                output.push_str(comment);
+                output.push_str("-->");
+            }
+            Node::Comment(_) => {}
+            Node::ProcessingInstruction(pi) => {
+                output.push_str("<?");
+                output.push_str(pi);
+                output.push_str("?>");
+            }
+        }
+    }
+    output.push_str("</");
+    output.push_str(&element.name);
+    output.push('>');
+}
+
+pub(crate) fn escape_attribute(value: &str, output: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '"' => output.push_str("&quot;"),
+            '\t' => output.push_str("&#x9;"),
+            '\n' => output.push_str("&#xA;"),
+            '\r' => output.push_str("&#xD;"),
+            c => output.push(c),
+        }
+    }
+}
+
+pub(crate) fn escape_text(text: &str, output: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '\r' => output.push_str("&#xD;"),
+            c => output.push(c),
+        }
+    }
+}
+
+#[test]
+fn canonical_xml() {
+    // examples from the C14N 1.0 spec, section 3.3 and 3.5.
+    let xml = r#"<?xml version="1.0"?>
+<doc xmlns:b="http://www.ietf.org" xmlns:a="http://www.w3.org" xmlns="http://example.org">
+   <e1   />
+   <e5 a:attr="out" b:attr="sorted" attr2="all" attr="I'm"
+       xmlns:b="http://www.ietf.org"
+       xmlns:a="http://www.w3.org"
+       xmlns="http://example.org"/>
+   <e6 xmlns="" xmlns:a="http://www.w3.org">
+       <e7 xmlns="http://www.ietf.org"><e8 xmlns="">&amp;&lt;&#x20;</e8></e7>
+   </e6>
+</doc>"#;
+    let root = parse(xml.as_bytes()).unwrap();
+    let c14n = canonicalize(&root, &Namespaces::new(), Canonicalization::Inclusive);
+    assert_eq!(
+        String::from_utf8(c14n).unwrap(),
+        r#"<doc xmlns="http://example.org" xmlns:a="http://www.w3.org" xmlns:b="http://www.ietf.org">
+   <e1></e1>
+   <e5 attr="I'm" attr2="all" b:attr="sorted" a:attr="out"></e5>
+   <e6 xmlns="">
+       <e7 xmlns="http://www.ietf.org"><e8 xmlns="">&amp;&lt; </e8></e7>
+   </e6>
+</doc>"#
+    );
+
+    // a subtree inherits the namespaces in scope, or only the utilized ones if exclusive.
+    let (e7, scope) = root
+        .find_with_scope(&|e: &Element| e.name == "e7", &Namespaces::new())
+        .unwrap();
+    let c14n = canonicalize(e7, &scope, Canonicalization::Inclusive);
+    assert_eq!(
+        String::from_utf8(c14n).unwrap(),
+        r#"<e7 xmlns="http://www.ietf.org" xmlns:a="http://www.w3.org" xmlns:b="http://www.ietf.org"><e8 xmlns="">&amp;&lt; </e8></e7>"#
+    );
+    let c14n = canonicalize(e7, &scope, Canonicalization::Exclusive);
+    assert_eq!(
+        String::from_utf8(c14n).unwrap(),
+        r#"<e7 xmlns="http://www.ietf.org"><e8 xmlns="">&amp;&lt; </e8></e7>"#
+    );
+}
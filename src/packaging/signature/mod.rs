@@ -0,0 +1,827 @@
+//! OPC digital signatures, see ECMA-376 Part 2, chapter 12.
+//!
+//! A signed package has a signature origin part (`_xmlsignatures/origin.sigs`) related from the
+//! package root, and XML-DSig signature parts (eg. `_xmlsignatures/sig1.xml`) related from the
+//! origin. Each signature has a manifest of part references with their digests, relationships
+//! parts are referenced through the OPC relationship transform so that only the selected
+//! relationships are signed.
+//!
+//! Only RSA keys are supported, which is what office applications use.
+pub(crate) mod c14n;
+
+use base64::Engine;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use x509_cert::der::{Decode, DecodePem, Encode};
+
+use self::c14n::{Canonicalization, Element, Namespaces};
+use crate::error::OoxmlError;
+use crate::packaging::content_type::ContentType;
+use crate::packaging::package::OpenXmlPackage;
+use crate::packaging::relationship::{
+    relationships_uri, resolve_target, Relationship, RelationshipType, Relationships,
+};
+
+/// Default uri of the signature origin part.
+pub const SIGNATURE_ORIGIN_URI: &str = "_xmlsignatures/origin.sigs";
+
+const XMLDSIG_NAMESPACE_URI: &str = "http://www.w3.org/2000/09/xmldsig#";
+const DIGITAL_SIGNATURE_NAMESPACE_URI: &str =
+    "http://schemas.openxmlformats.org/package/2006/digital-signature";
+const RELATIONSHIPS_NAMESPACE_URI: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships";
+const RELATIONSHIP_TRANSFORM: &str =
+    "http://schemas.openxmlformats.org/package/2006/RelationshipTransform";
+const OBJECT_REFERENCE_TYPE: &str = "http://www.w3.org/2000/09/xmldsig#Object";
+const PACKAGE_OBJECT_ID: &str = "idPackageObject";
+const PACKAGE_SIGNATURE_ID: &str = "idPackageSignature";
+
+/// Digest algorithms of XML-DSig references.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DigestMethod {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestMethod {
+    fn from_algorithm(algorithm: &str) -> Option<Self> {
+        match algorithm {
+            "http://www.w3.org/2000/09/xmldsig#sha1" => Some(Self::Sha1),
+            "http://www.w3.org/2001/04/xmlenc#sha256" => Some(Self::Sha256),
+            "http://www.w3.org/2001/04/xmldsig-more#sha384" => Some(Self::Sha384),
+            "http://www.w3.org/2001/04/xmlenc#sha512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    fn algorithm(&self) -> &'static str {
+        match self {
+            Self::Sha1 => "http://www.w3.org/2000/09/xmldsig#sha1",
+            Self::Sha256 => "http://www.w3.org/2001/04/xmlenc#sha256",
+            Self::Sha384 => "http://www.w3.org/2001/04/xmldsig-more#sha384",
+            Self::Sha512 => "http://www.w3.org/2001/04/xmlenc#sha512",
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha1 => Sha1::digest(data).to_vec(),
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Sha384 => Sha384::digest(data).to_vec(),
+            Self::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    fn pkcs1v15(&self) -> Pkcs1v15Sign {
+        match self {
+            Self::Sha1 => Pkcs1v15Sign::new::<Sha1>(),
+            Self::Sha256 => Pkcs1v15Sign::new::<Sha256>(),
+            Self::Sha384 => Pkcs1v15Sign::new::<Sha384>(),
+            Self::Sha512 => Pkcs1v15Sign::new::<Sha512>(),
+        }
+    }
+}
+
+/// Digest method of RSA signature methods.
+fn rsa_signature_method(algorithm: &str) -> Option<DigestMethod> {
+    match algorithm {
+        "http://www.w3.org/2000/09/xmldsig#rsa-sha1" => Some(DigestMethod::Sha1),
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256" => Some(DigestMethod::Sha256),
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha384" => Some(DigestMethod::Sha384),
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512" => Some(DigestMethod::Sha512),
+        _ => None,
+    }
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text: String = text.split_whitespace().collect();
+    base64::engine::general_purpose::STANDARD.decode(text).ok()
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(data)
+}
+
+/// A part referenced by the manifest of a signature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartReference {
+    /// Part uri without leading `/`.
+    pub part_uri: String,
+    /// Content type in the reference uri query.
+    pub content_type: Option<String>,
+    /// Relationship ids selected by the relationship transform, relationships parts only.
+    pub relationship_ids: Vec<String>,
+    /// Relationship types selected by the relationship transform, relationships parts only.
+    pub relationship_types: Vec<String>,
+}
+
+impl PartReference {
+    fn from_element(reference: &Element) -> Option<Self> {
+        let uri = reference.attribute("URI")?;
+        if uri.starts_with('#') {
+            return None;
+        }
+        let (path, query) = match uri.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (uri, None),
+        };
+        let content_type = query.and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("ContentType="))
+                .map(|s| s.to_string())
+        });
+        let mut relationship_ids = Vec::new();
+        let mut relationship_types = Vec::new();
+        for transform in transforms(reference) {
+            if transform.attribute("Algorithm") != Some(RELATIONSHIP_TRANSFORM) {
+                continue;
+            }
+            for selector in transform.elements() {
+                match selector.local_name() {
+                    "RelationshipReference" => relationship_ids
+                        .extend(selector.attribute("SourceId").map(|id| id.to_string())),
+                    "RelationshipsGroupReference" => relationship_types
+                        .extend(selector.attribute("SourceType").map(|t| t.to_string())),
+                    _ => {}
+                }
+            }
+        }
+        Some(Self {
+            part_uri: path.trim_start_matches('/').to_string(),
+            content_type,
+            relationship_ids,
+            relationship_types,
+        })
+    }
+}
+
+fn transforms(reference: &Element) -> impl Iterator<Item = &Element> {
+    reference
+        .child("Transforms")
+        .into_iter()
+        .flat_map(|transforms| transforms.children_named("Transform"))
+}
+
+/// Why a signature does not verify.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationFailure {
+    /// No X.509 certificate in the signature or related to it.
+    MissingCertificate,
+    /// The algorithm of signature method, digest method or transform is not supported.
+    UnsupportedAlgorithm(String),
+    /// The reference target does not exist, by the reference uri.
+    MissingReference(String),
+    /// The reference target has been changed since signing, by the reference uri.
+    DigestMismatch(String),
+    /// The signature value does not match the signed info.
+    InvalidSignatureValue,
+}
+
+/// Verification result of a signature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureVerification {
+    /// Uri of the signature part.
+    pub uri: String,
+    /// Parts covered by the signature.
+    pub signed_parts: Vec<String>,
+    pub failures: Vec<VerificationFailure>,
+}
+
+impl SignatureVerification {
+    pub fn is_valid(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// An XML-DSig signature part of a package.
+#[derive(Debug, Clone)]
+pub struct PackageSignature {
+    uri: String,
+    root: Element,
+    certificate: Option<Vec<u8>>,
+}
+
+impl PackageSignature {
+    fn parse(uri: String, data: &[u8], certificate: Option<Vec<u8>>) -> Result<Self, OoxmlError> {
+        let root = c14n::parse(data)?;
+        let certificate = root
+            .child("KeyInfo")
+            .and_then(|key_info| key_info.child("X509Data"))
+            .and_then(|data| data.child("X509Certificate"))
+            .and_then(|certificate| decode_base64(&certificate.text()))
+            .or(certificate);
+        Ok(Self {
+            uri,
+            root,
+            certificate,
+        })
+    }
+
+    /// Uri of the signature part, eg. `_xmlsignatures/sig1.xml`.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// DER encoded X.509 certificate of the signer.
+    pub fn certificate_der(&self) -> Option<&[u8]> {
+        self.certificate.as_deref()
+    }
+
+    /// Subject of the signer certificate, eg. `CN=John Smith`.
+    pub fn signer(&self) -> Option<String> {
+        let certificate = x509_cert::Certificate::from_der(self.certificate_der()?).ok()?;
+        Some(certificate.tbs_certificate.subject.to_string())
+    }
+
+    /// Signing time as it is in the signature properties.
+    pub fn signature_time(&self) -> Option<String> {
+        self.objects()
+            .flat_map(|object| object.children_named("SignatureProperties"))
+            .flat_map(|properties| properties.children_named("SignatureProperty"))
+            .flat_map(|property| property.children_named("SignatureTime"))
+            .find_map(|time| time.child("Value"))
+            .map(|value| value.text())
+    }
+
+    fn objects(&self) -> impl Iterator<Item = &Element> {
+        self.root.children_named("Object")
+    }
+
+    fn manifest_references(&self) -> impl Iterator<Item = &Element> {
+        self.objects()
+            .flat_map(|object| object.children_named("Manifest"))
+            .flat_map(|manifest| manifest.children_named("Reference"))
+    }
+
+    /// Part references in the signature manifest.
+    pub fn references(&self) -> Vec<PartReference> {
+        self.manifest_references()
+            .filter_map(PartReference::from_element)
+            .collect()
+    }
+
+    /// Uris of the parts covered by the signature.
+    pub fn signed_parts(&self) -> Vec<String> {
+        let mut parts: Vec<String> = Vec::new();
+        for reference in self.references() {
+            if !parts.contains(&reference.part_uri) {
+                parts.push(reference.part_uri);
+            }
+        }
+        parts
+    }
+
+    /// Verify the signature value and every reference digest against the package.
+    pub fn verify(&self, package: &OpenXmlPackage) -> Result<SignatureVerification, OoxmlError> {
+        let mut failures = Vec::new();
+        let signed_info = self
+            .root
+            .child("SignedInfo")
+            .ok_or_else(|| OoxmlError::SignatureError("no SignedInfo".into()))?;
+        for reference in signed_info
+            .children_named("Reference")
+            .chain(self.manifest_references())
+        {
+            if let Some(failure) = self.check_reference(package, reference)? {
+                failures.push(failure);
+            }
+        }
+        if let Some(failure) = self.check_signature_value(signed_info) {
+            failures.push(failure);
+        }
+        Ok(SignatureVerification {
+            uri: self.uri.clone(),
+            signed_parts: self.signed_parts(),
+            failures,
+        })
+    }
+
+    fn check_signature_value(&self, signed_info: &Element) -> Option<VerificationFailure> {
+        let algorithm = signed_info
+            .child("SignatureMethod")
+            .and_then(|method| method.attribute("Algorithm"))
+            .unwrap_or_default();
+        let digest_method = match rsa_signature_method(algorithm) {
+            Some(method) => method,
+            None => return Some(VerificationFailure::UnsupportedAlgorithm(algorithm.into())),
+        };
+        let algorithm = signed_info
+            .child("CanonicalizationMethod")
+            .and_then(|method| method.attribute("Algorithm"))
+            .unwrap_or_default();
+        let method = match Canonicalization::from_algorithm(algorithm) {
+            Some(method) => method,
+            None => return Some(VerificationFailure::UnsupportedAlgorithm(algorithm.into())),
+        };
+        let public_key = match self.certificate_der().and_then(public_key) {
+            Some(key) => key,
+            None => return Some(VerificationFailure::MissingCertificate),
+        };
+        let (signed_info, scope) = self
+            .root
+            .find_by_element(signed_info)
+            .expect("signed info in the signature");
+        let hashed = digest_method.digest(&c14n::canonicalize(signed_info, &scope, method));
+        let signature = self
+            .root
+            .child("SignatureValue")
+            .and_then(|value| decode_base64(&value.text()))
+            .unwrap_or_default();
+        match public_key.verify(digest_method.pkcs1v15(), &hashed, &signature) {
+            Ok(()) => None,
+            Err(_) => Some(VerificationFailure::InvalidSignatureValue),
+        }
+    }
+
+    /// Check the digest of a reference, returns the failure if any.
+    fn check_reference(
+        &self,
+        package: &OpenXmlPackage,
+        reference: &Element,
+    ) -> Result<Option<VerificationFailure>, OoxmlError> {
+        let uri = reference.attribute("URI").unwrap_or_default();
+        let algorithm = reference
+            .child("DigestMethod")
+            .and_then(|method| method.attribute("Algorithm"))
+            .unwrap_or_default();
+        let digest_method = match DigestMethod::from_algorithm(algorithm) {
+            Some(method) => method,
+            None => {
+                return Ok(Some(VerificationFailure::UnsupportedAlgorithm(
+                    algorithm.into(),
+                )))
+            }
+        };
+        let octets = match uri.strip_prefix('#') {
+            Some(id) => self.root.find_by_id(id).map(|(element, scope)| {
+                transform_element(element.clone(), scope, transforms(reference))
+            }),
+            None => {
+                let part_uri = uri.split('?').next().unwrap_or_default();
+                package
+                    .stored_entry_bytes(part_uri.trim_start_matches('/'))?
+                    .map(|data| transform_part(&data, transforms(reference)))
+            }
+        };
+        let octets = match octets {
+            Some(Ok(octets)) => octets,
+            Some(Err(algorithm)) => {
+                return Ok(Some(VerificationFailure::UnsupportedAlgorithm(algorithm)))
+            }
+            None => return Ok(Some(VerificationFailure::MissingReference(uri.into()))),
+        };
+        let expected = reference
+            .child("DigestValue")
+            .and_then(|value| decode_base64(&value.text()));
+        if expected.as_deref() == Some(digest_method.digest(&octets).as_slice()) {
+            Ok(None)
+        } else {
+            Ok(Some(VerificationFailure::DigestMismatch(uri.into())))
+        }
+    }
+}
+
+impl Element {
+    /// Find a descendant (or self) element by identity, with its ancestors' namespaces.
+    fn find_by_element(&self, target: &Element) -> Option<(&Element, Namespaces)> {
+        self.find_with_scope(&|e: &Element| std::ptr::eq(e, target), &Namespaces::new())
+    }
+}
+
+fn public_key(certificate: &[u8]) -> Option<RsaPublicKey> {
+    let certificate = x509_cert::Certificate::from_der(certificate).ok()?;
+    let spki = certificate
+        .tbs_certificate
+        .subject_public_key_info
+        .to_der()
+        .ok()?;
+    RsaPublicKey::from_public_key_der(&spki).ok()
+}
+
+/// Apply transforms to an element reference, returns the unsupported algorithm if any.
+fn transform_element<'a>(
+    element: Element,
+    scope: Namespaces,
+    transforms: impl Iterator<Item = &'a Element>,
+) -> Result<Vec<u8>, String> {
+    let mut method = Canonicalization::Inclusive;
+    for transform in transforms {
+        let algorithm = transform.attribute("Algorithm").unwrap_or_default();
+        method =
+            Canonicalization::from_algorithm(algorithm).ok_or_else(|| algorithm.to_string())?;
+    }
+    Ok(c14n::canonicalize(&element, &scope, method))
+}
+
+/// Apply transforms to part data, parts without transforms are digested as it is.
+fn transform_part<'a>(
+    data: &[u8],
+    transforms: impl Iterator<Item = &'a Element>,
+) -> Result<Vec<u8>, String> {
+    let mut octets = data.to_vec();
+    let mut tree: Option<Element> = None;
+    for transform in transforms {
+        let algorithm = transform.attribute("Algorithm").unwrap_or_default();
+        let current = match tree.take() {
+            Some(tree) => tree,
+            None => c14n::parse(&octets).map_err(|_| algorithm.to_string())?,
+        };
+        if algorithm == RELATIONSHIP_TRANSFORM {
+            tree = Some(relationship_transform(&current, transform));
+        } else {
+            let method =
+                Canonicalization::from_algorithm(algorithm).ok_or_else(|| algorithm.to_string())?;
+            octets = c14n::canonicalize(&current, &Namespaces::new(), method);
+        }
+    }
+    if let Some(tree) = tree {
+        octets = c14n::canonicalize(&tree, &Namespaces::new(), Canonicalization::Inclusive);
+    }
+    Ok(octets)
+}
+
+/// The OPC relationship transform, ECMA-376 Part 2, 13.2.4.24.
+///
+/// Keeps the relationships selected by id or type, sorted by id, with `TargetMode` defaulting
+/// to `Internal`, and drops everything else between them.
+fn relationship_transform(relationships: &Element, transform: &Element) -> Element {
+    let selectors: Vec<(&str, &str)> = transform
+        .elements()
+        .filter_map(|selector| match selector.local_name() {
+            "RelationshipReference" => selector.attribute("SourceId").map(|id| ("Id", id)),
+            "RelationshipsGroupReference" => selector.attribute("SourceType").map(|t| ("Type", t)),
+            _ => None,
+        })
+        .collect();
+    let mut selected: Vec<&Element> = relationships
+        .children_named("Relationship")
+        .filter(|r| {
+            selectors
+                .iter()
+                .any(|(key, value)| r.attribute(key) == Some(*value))
+        })
+        .collect();
+    selected.sort_by(|a, b| a.attribute("Id").cmp(&b.attribute("Id")));
+    let mut output =
+        Element::new("Relationships").with_attribute("xmlns", RELATIONSHIPS_NAMESPACE_URI);
+    for relationship in selected {
+        let mut element = Element::new("Relationship");
+        element.attributes = relationship.attributes.clone();
+        if relationship.attribute("TargetMode").is_none() {
+            element = element.with_attribute("TargetMode", "Internal");
+        }
+        output = output.with_child(element);
+    }
+    output
+}
+
+/// RSA private key and X.509 certificate to sign packages with.
+#[derive(Clone)]
+pub struct SigningKey {
+    key: RsaPrivateKey,
+    certificate: Vec<u8>,
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKey").finish_non_exhaustive()
+    }
+}
+
+impl SigningKey {
+    /// New signing key with DER encoded certificate.
+    pub fn new(key: RsaPrivateKey, certificate_der: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key,
+            certificate: certificate_der.into(),
+        }
+    }
+
+    /// Load PEM encoded PKCS#8 or PKCS#1 private key and X.509 certificate.
+    pub fn from_pem(key_pem: &str, certificate_pem: &str) -> Result<Self, OoxmlError> {
+        use rsa::pkcs1::DecodeRsaPrivateKey;
+        let key = RsaPrivateKey::from_pkcs8_pem(key_pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(key_pem))
+            .map_err(|e| OoxmlError::SignatureError(e.to_string()))?;
+        let certificate = x509_cert::Certificate::from_pem(certificate_pem)
+            .and_then(|certificate| certificate.to_der())
+            .map_err(|e| OoxmlError::SignatureError(e.to_string()))?;
+        Ok(Self::new(key, certificate))
+    }
+}
+
+/// Relationship types of the signature parts, not signed themselves.
+fn is_signature_relationship(relationship: &Relationship) -> bool {
+    matches!(
+        relationship.relationship_type(),
+        RelationshipType::DigitalSignatureOrigin
+            | RelationshipType::DigitalSignature
+            | RelationshipType::DigitalSignatureCertificate
+    )
+}
+
+impl OpenXmlPackage {
+    /// Uri of the signature origin part, if the package is signed.
+    fn signature_origin_uri(&self) -> Option<String> {
+        self.find_related_part_uri("", &RelationshipType::DigitalSignatureOrigin)
+    }
+
+    /// List the digital signatures of the package.
+    pub fn signatures(&self) -> Result<Vec<PackageSignature>, OoxmlError> {
+        let origin = match self.signature_origin_uri() {
+            Some(origin) => origin,
+            None => return Ok(Vec::new()),
+        };
+        let relationships = self.get_part_relationships(&origin).unwrap_or_default();
+        let mut signatures = Vec::new();
+        for relationship in
+            relationships.get_relationships_by_type(&RelationshipType::DigitalSignature)
+        {
+            let uri = resolve_target(&origin, relationship.target());
+            let data = match self.stored_entry_bytes(&uri)? {
+                Some(data) => data,
+                None => continue,
+            };
+            let certificate = match self
+                .find_related_part_uri(&uri, &RelationshipType::DigitalSignatureCertificate)
+            {
+                Some(certificate) => self.stored_entry_bytes(&certificate)?,
+                None => None,
+            };
+            signatures.push(PackageSignature::parse(uri, &data, certificate)?);
+        }
+        Ok(signatures)
+    }
+
+    /// Verify every digital signature of the package offline.
+    pub fn verify_signatures(&self) -> Result<Vec<SignatureVerification>, OoxmlError> {
+        self.signatures()?
+            .iter()
+            .map(|signature| signature.verify(self))
+            .collect()
+    }
+
+    /// Sign every part of the package, returns the uri of the new signature part.
+    ///
+    /// Changing the package after signing invalidates the signature.
+    pub fn sign(&mut self, key: &SigningKey) -> Result<String, OoxmlError> {
+        let origin = match self.signature_origin_uri() {
+            Some(origin) => origin,
+            None => {
+                self.create_part_core_with_data(
+                    SIGNATURE_ORIGIN_URI,
+                    &ContentType::DigitalSignatureOrigin,
+                    &[],
+                )?;
+                self.create_relationship(
+                    RelationshipType::DigitalSignatureOrigin,
+                    SIGNATURE_ORIGIN_URI,
+                );
+                SIGNATURE_ORIGIN_URI.to_string()
+            }
+        };
+        let origin_relationships_uri = relationships_uri(&origin);
+        let mut excluded = vec![origin.clone(), origin_relationships_uri.clone()];
+        for signature in self.signatures()? {
+            excluded.push(relationships_uri(signature.uri()));
+            excluded.push(signature.uri);
+        }
+        let directory = origin.rsplit_once('/').map_or("", |(dir, _)| dir);
+        let signature_uri = (1..)
+            .map(|i| match directory {
+                "" => format!("sig{}.xml", i),
+                dir => format!("{}/sig{}.xml", dir, i),
+            })
+            .find(|uri| self.get_part(uri).is_none())
+            .expect("signature part uri");
+
+        // 1. manifest of part references.
+        let names: Vec<String> = self
+            .entry_names()
+            .into_iter()
+            .filter(|name| !name.ends_with('/'))
+            .filter(|name| *name != crate::packaging::content_type::CONTENT_TYPES_FILE)
+            .filter(|name| !excluded.iter().any(|e| e == name))
+            .map(|name| name.to_string())
+            .collect();
+        let mut manifest = Element::new("Manifest");
+        for name in names {
+            let content_type = match self.get_part_content_type(&name) {
+                Some(content_type) => content_type.to_string(),
+                None => continue,
+            };
+            let data = match self.stored_entry_bytes(&name)? {
+                Some(data) => data,
+                None => continue,
+            };
+            let mut reference = Element::new("Reference")
+                .with_attribute("URI", format!("/{}?ContentType={}", name, content_type));
+            let octets = if name.ends_with(".rels") {
+                let relationships = Relationships::parse_from_xml_reader(data.as_slice());
+                let mut transform =
+                    Element::new("Transform").with_attribute("Algorithm", RELATIONSHIP_TRANSFORM);
+                for relationship in relationships
+                    .iter()
+                    .filter(|r| !is_signature_relationship(r))
+                {
+                    transform = transform.with_child(
+                        Element::new("mdssi:RelationshipReference")
+                            .with_attribute("xmlns:mdssi", DIGITAL_SIGNATURE_NAMESPACE_URI)
+                            .with_attribute("SourceId", relationship.id()),
+                    );
+                }
+                if transform.elements().next().is_none() {
+                    continue;
+                }
+                let c14n = Element::new("Transform")
+                    .with_attribute("Algorithm", Canonicalization::Inclusive.algorithm());
+                let transforms = Element::new("Transforms")
+                    .with_child(transform)
+                    .with_child(c14n);
+                let octets = transform_part(&data, transforms.elements())
+                    .map_err(OoxmlError::SignatureError)?;
+                reference = reference.with_child(transforms);
+                octets
+            } else {
+                data
+            };
+            manifest = manifest.with_child(
+                reference
+                    .with_child(
+                        Element::new("DigestMethod")
+                            .with_attribute("Algorithm", DigestMethod::Sha256.algorithm()),
+                    )
+                    .with_child(
+                        Element::new("DigestValue")
+                            .with_text(encode_base64(&DigestMethod::Sha256.digest(&octets))),
+                    ),
+            );
+        }
+
+        // 2. package object with the manifest and signing time.
+        let time = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let object = Element::new("Object")
+            .with_attribute("Id", PACKAGE_OBJECT_ID)
+            .with_child(manifest)
+            .with_child(
+                Element::new("SignatureProperties").with_child(
+                    Element::new("SignatureProperty")
+                        .with_attribute("Id", "idSignatureTime")
+                        .with_attribute("Target", format!("#{}", PACKAGE_SIGNATURE_ID))
+                        .with_child(
+                            Element::new("mdssi:SignatureTime")
+                                .with_attribute("xmlns:mdssi", DIGITAL_SIGNATURE_NAMESPACE_URI)
+                                .with_child(
+                                    Element::new("mdssi:Format")
+                                        .with_text("YYYY-MM-DDThh:mm:ssTZD"),
+                                )
+                                .with_child(Element::new("mdssi:Value").with_text(time)),
+                        ),
+                ),
+            );
+
+        // 3. signed info and signature value.
+        let mut scope = Namespaces::new();
+        scope.insert(String::new(), XMLDSIG_NAMESPACE_URI.to_string());
+        let object_digest = DigestMethod::Sha256.digest(&c14n::canonicalize(
+            &object,
+            &scope,
+            Canonicalization::Inclusive,
+        ));
+        let signed_info = Element::new("SignedInfo")
+            .with_child(
+                Element::new("CanonicalizationMethod")
+                    .with_attribute("Algorithm", Canonicalization::Inclusive.algorithm()),
+            )
+            .with_child(Element::new("SignatureMethod").with_attribute(
+                "Algorithm",
+                "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256",
+            ))
+            .with_child(
+                Element::new("Reference")
+                    .with_attribute("Type", OBJECT_REFERENCE_TYPE)
+                    .with_attribute("URI", format!("#{}", PACKAGE_OBJECT_ID))
+                    .with_child(
+                        Element::new("DigestMethod")
+                            .with_attribute("Algorithm", DigestMethod::Sha256.algorithm()),
+                    )
+                    .with_child(
+                        Element::new("DigestValue").with_text(encode_base64(&object_digest)),
+                    ),
+            );
+        let hashed = DigestMethod::Sha256.digest(&c14n::canonicalize(
+            &signed_info,
+            &scope,
+            Canonicalization::Inclusive,
+        ));
+        let signature_value = key
+            .key
+            .sign(DigestMethod::Sha256.pkcs1v15(), &hashed)
+            .map_err(|e| OoxmlError::SignatureError(e.to_string()))?;
+        let signature = Element::new("Signature")
+            .with_attribute("xmlns", XMLDSIG_NAMESPACE_URI)
+            .with_attribute("Id", PACKAGE_SIGNATURE_ID)
+            .with_child(signed_info)
+            .with_child(Element::new("SignatureValue").with_text(encode_base64(&signature_value)))
+            .with_child(
+                Element::new("KeyInfo").with_child(Element::new("X509Data").with_child(
+                    Element::new("X509Certificate").with_text(encode_base64(&key.certificate)),
+                )),
+            )
+            .with_child(object);
+
+        // 4. signature part, related from the origin.
+        let mut data = br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#.to_vec();
+        data.extend(c14n::canonicalize(
+            &signature,
+            &Namespaces::new(),
+            Canonicalization::Inclusive,
+        ));
+        self.create_part_core_with_data(
+            &signature_uri,
+            &ContentType::DigitalSignatureXmlSignature,
+            &data,
+        )?;
+        let mut relationships = self.get_part_relationships(&origin).unwrap_or_default();
+        let target = signature_uri
+            .strip_prefix(&format!("{}/", directory))
+            .unwrap_or(&signature_uri);
+        relationships.add_relationship(Relationship::new(
+            relationships.next_id(),
+            RelationshipType::DigitalSignature,
+            target,
+        ));
+        let mut data = Vec::new();
+        relationships.write(&mut data)?;
+        self.create_part_core_with_data(
+            &origin_relationships_uri,
+            &ContentType::Relationships,
+            &data,
+        )?;
+        Ok(signature_uri)
+    }
+}
+
+#[test]
+fn sign_and_verify() {
+    let key = SigningKey::from_pem(
+        include_str!("../../../examples/signature/key.pem"),
+        include_str!("../../../examples/signature/cert.pem"),
+    )
+    .unwrap();
+    let mut package = OpenXmlPackage::open("examples/excel-demo/demo.xlsx").unwrap();
+    assert!(package.signatures().unwrap().is_empty());
+    let uri = package.sign(&key).unwrap();
+    assert_eq!(uri, "_xmlsignatures/sig1.xml");
+
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    package.write(&mut buffer).unwrap();
+    buffer.set_position(0);
+    let mut package = OpenXmlPackage::from_reader(buffer).unwrap();
+    let signatures = package.signatures().unwrap();
+    assert_eq!(signatures.len(), 1);
+    assert_eq!(
+        signatures[0].signer().as_deref(),
+        Some("CN=ooxml-rs test signer")
+    );
+    assert!(signatures[0].signature_time().is_some());
+    let signed_parts = signatures[0].signed_parts();
+    assert!(signed_parts.contains(&"xl/workbook.xml".to_string()));
+    assert!(signed_parts.contains(&"_rels/.rels".to_string()));
+    assert!(!signed_parts.contains(&SIGNATURE_ORIGIN_URI.to_string()));
+    let verification = package.verify_signatures().unwrap();
+    assert!(verification[0].is_valid(), "{:?}", verification[0]);
+
+    // changing a signed part breaks the signature
+    package.set_part_data("xl/workbook.xml", b"<workbook/>".to_vec());
+    let verification = package.verify_signatures().unwrap();
+    assert_eq!(
+        verification[0].failures,
+        vec![VerificationFailure::DigestMismatch(
+            "/xl/workbook.xml?ContentType=application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml".into()
+        )]
+    );
+}
+
+#[test]
+fn relationship_transform_output() {
+    let rels = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+    <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>
+    <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com" TargetMode="External"/>
+    <Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/theme" Target="theme/theme1.xml"/>
+</Relationships>"#;
+    let transforms = c14n::parse(
+        br#"<Transforms><Transform Algorithm="http://schemas.openxmlformats.org/package/2006/RelationshipTransform"><mdssi:RelationshipReference xmlns:mdssi="http://schemas.openxmlformats.org/package/2006/digital-signature" SourceId="rId2"/><mdssi:RelationshipsGroupReference xmlns:mdssi="http://schemas.openxmlformats.org/package/2006/digital-signature" SourceType="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink"/></Transform><Transform Algorithm="http://www.w3.org/TR/2001/REC-xml-c14n-20010315"/></Transforms>"#,
+    )
+    .unwrap();
+    let octets = transform_part(rels.as_bytes(), transforms.elements()).unwrap();
+    assert_eq!(
+        String::from_utf8(octets).unwrap(),
+        r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Target="https://example.com" TargetMode="External" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink"></Relationship><Relationship Id="rId2" Target="styles.xml" TargetMode="Internal" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles"></Relationship></Relationships>"#
+    );
+}